@@ -0,0 +1,226 @@
+//! Collecting the authors and maintainers of many packages, for `authors`.
+//!
+//! People are deduplicated by email address, or by name if they have none,
+//! so one report lists everyone to contact for a dependency set and what they work on.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::python_pkg::{Metadata, PackageName};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    Author,
+    Maintainer,
+}
+
+/// A name and/or email address from core metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contact {
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+/// Someone listed in the metadata of one or more packages
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct Person {
+    pub name: Option<String>,
+    pub emails: BTreeSet<String>,
+    /// Their roles by package name
+    pub packages: BTreeMap<String, BTreeSet<Role>>,
+}
+
+/// Everyone listed in the metadata of a set of packages, sorted by name
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct AuthorsReport {
+    people: Vec<Person>,
+}
+
+impl AuthorsReport {
+    pub fn new(packages: impl IntoIterator<Item = (PackageName, Metadata)>) -> Self {
+        let mut people = Vec::<Person>::new();
+        for (package, metadata) in packages {
+            for (role, contact) in contacts(&metadata) {
+                let i = find(&people, &contact).unwrap_or_else(|| {
+                    people.push(Person::default());
+                    people.len() - 1
+                });
+                let person = &mut people[i];
+                if person.name.is_none() {
+                    person.name = contact.name;
+                }
+                person
+                    .emails
+                    .extend(contact.email.map(|e| e.to_lowercase()));
+                person
+                    .packages
+                    .entry(package.original().to_owned())
+                    .or_default()
+                    .insert(role);
+            }
+        }
+        people.sort_by_cached_key(|p| {
+            let key = p.name.as_ref().or(p.emails.first());
+            key.map(|k| k.to_lowercase())
+        });
+        Self { people }
+    }
+}
+
+/// The same person by email address, or by name if either has no email address
+fn find(people: &[Person], contact: &Contact) -> Option<usize> {
+    let same_name = |person: &Person| {
+        let (Some(a), Some(b)) = (&person.name, &contact.name) else {
+            return false;
+        };
+        a.to_lowercase() == b.to_lowercase()
+    };
+    if let Some(email) = &contact.email {
+        let by_email = people
+            .iter()
+            .position(|p| p.emails.contains(&email.to_lowercase()));
+        by_email.or_else(|| {
+            people
+                .iter()
+                .position(|p| p.emails.is_empty() && same_name(p))
+        })
+    } else {
+        people.iter().position(same_name)
+    }
+}
+
+/// The authors and maintainers listed in `metadata`
+pub fn contacts(metadata: &Metadata) -> Vec<(Role, Contact)> {
+    let mut contacts = Vec::new();
+    for (role, name_field, email_field) in [
+        (Role::Author, "Author", "Author-email"),
+        (Role::Maintainer, "Maintainer", "Maintainer-email"),
+    ] {
+        let name = metadata
+            .get(name_field)
+            .map(str::trim)
+            .filter(|n| !n.is_empty());
+        let mut emails = metadata
+            .get(email_field)
+            .map(parse_addresses)
+            .unwrap_or_default();
+        // `Author: Jane` with `Author-email: jane@example.com`
+        if let ([contact], Some(name)) = (emails.as_mut_slice(), name)
+            && contact.name.is_none()
+        {
+            contact.name = Some(name.to_owned());
+        } else if let Some(name) = name
+            && !emails.iter().any(|c| c.name.as_deref() == Some(name))
+        {
+            emails.push(Contact {
+                name: Some(name.to_owned()),
+                email: None,
+            });
+        }
+        contacts.extend(emails.into_iter().map(|contact| (role, contact)));
+    }
+    contacts
+}
+
+/// Parse a list of addresses like `Jane <jane@example.com>, "Doe, John" <john@example.com>, bob@example.com`
+fn parse_addresses(field: &str) -> Vec<Contact> {
+    let mut items = Vec::new();
+    let (mut start, mut quoted, mut bracketed) = (0, false, false);
+    for (i, c) in field.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '<' => bracketed = true,
+            '>' => bracketed = false,
+            ',' if !quoted && !bracketed => {
+                items.push(&field[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&field[start..]);
+    items
+        .into_iter()
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| match item.split_once('<') {
+            Some((name, email)) => {
+                let name = name.trim().trim_matches('"').trim();
+                Contact {
+                    name: (!name.is_empty()).then(|| name.to_owned()),
+                    email: Some(email.trim_end_matches('>').trim().to_owned()),
+                }
+            }
+            None if item.contains('@') => Contact {
+                name: None,
+                email: Some(item.to_owned()),
+            },
+            None => Contact {
+                name: Some(item.to_owned()),
+                email: None,
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(fields: &[(&str, &str)]) -> Metadata {
+        let mut metadata = Metadata::default();
+        for (key, value) in fields {
+            metadata.push(*key, *value);
+        }
+        metadata
+    }
+
+    #[test]
+    fn test_parse_addresses() {
+        let contacts = parse_addresses(
+            r#"Jane <jane@example.com>, "Doe, John" <john@example.com>, bob@x.org"#,
+        );
+        let contacts = contacts
+            .iter()
+            .map(|c| (c.name.as_deref(), c.email.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            contacts,
+            [
+                (Some("Jane"), Some("jane@example.com")),
+                (Some("Doe, John"), Some("john@example.com")),
+                (None, Some("bob@x.org")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let foo = metadata(&[("Author", "Jane Doe"), ("Author-email", "jane@example.com")]);
+        let bar = metadata(&[
+            ("Author-email", "Jane Doe <JANE@example.com>"),
+            ("Maintainer", "jane doe"),
+        ]);
+        let baz = metadata(&[("Maintainer-email", "Bob <bob@example.com>")]);
+        let names = ["foo", "bar", "baz"].map(|n| n.parse::<PackageName>().unwrap());
+        let report = AuthorsReport::new(names.into_iter().zip([foo, bar, baz]));
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!([
+                {
+                    "name": "Bob",
+                    "emails": ["bob@example.com"],
+                    "packages": {"baz": ["maintainer"]},
+                },
+                {
+                    "name": "Jane Doe",
+                    "emails": ["jane@example.com"],
+                    "packages": {"bar": ["author", "maintainer"], "foo": ["author"]},
+                },
+            ])
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Whether a release matching a requirement can be installed on a target, and how large it is,
+//! for the `assert` and `assert-size` commands.
+
+use std::str::FromStr as _;
+
+use eyre::{Error, OptionExt as _, Result};
+use futures::future::try_join_all;
+use reqwest::header::CONTENT_LENGTH;
+
+use crate::LazyZipClient;
+use crate::python_pkg::{Dependency, SdistFilename, Target, WheelFilename};
+use crate::simple_repo_api::{self, Yanking};
+
+/// What counts as installable
+#[derive(Debug, Clone, Default)]
+pub struct Criteria {
+    pub target: Target,
+    /// Only accept wheels, not sdists that would need to be built
+    pub has_wheel: bool,
+}
+
+/// Find the newest non-yanked file matching `dep` that can be installed on the target,
+/// preferring wheels over sdists of the same release
+pub async fn find_installable(
+    client: &LazyZipClient,
+    dep: &Dependency,
+    criteria: &Criteria,
+) -> Result<Option<simple_repo_api::File>> {
+    let best = candidates(client, dep, criteria)
+        .await?
+        .into_iter()
+        .max_by(|(l, _), (r, _)| l.cmp(r));
+    Ok(best.map(|(_, file)| file))
+}
+
+/// Find the largest of the files [`find_installable`] could pick, and its size.
+/// Without a platform in the target, that’s the largest wheel of the release for any platform.
+pub async fn largest_installable(
+    client: &LazyZipClient,
+    dep: &Dependency,
+    criteria: &Criteria,
+) -> Result<Option<(simple_repo_api::File, u64)>> {
+    let candidates = candidates(client, dep, criteria).await?;
+    let Some(best) = candidates.iter().map(|(key, _)| key).max().cloned() else {
+        return Ok(None);
+    };
+    let files =
+        candidates
+            .into_iter()
+            .filter(|(key, _)| *key == best)
+            .map(|(_, file)| async move {
+                let size = file_size(client, &file).await?;
+                Ok::<_, Error>((file, size))
+            });
+    let sized = try_join_all(files).await?;
+    Ok(sized.into_iter().max_by_key(|(_, size)| *size))
+}
+
+/// A file’s size from the index (PEP 700), or from a `HEAD` request if the index doesn’t list it
+pub async fn file_size(client: &LazyZipClient, file: &simple_repo_api::File) -> Result<u64> {
+    if let Some(size) = file.size {
+        return Ok(size);
+    }
+    let url = client.rewrite_url(file.url.clone())?;
+    let response = client.http().head(url).send().await?.error_for_status()?;
+    // `Response::content_length` is the body’s, which is empty for `HEAD`
+    response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|len| len.to_str().ok()?.parse().ok())
+        .ok_or_eyre(format!("No size listed or served for {}", file.filename))
+}
+
+/// Non-yanked files matching `dep` installable on the target, by version and whether they’re wheels
+async fn candidates(
+    client: &LazyZipClient,
+    dep: &Dependency,
+    criteria: &Criteria,
+) -> Result<Vec<((pep440_rs::Version, bool), simple_repo_api::File)>> {
+    let dep = &client.constrain(dep);
+    let project = client.find_project(dep.name()).await?;
+    let candidates = project
+        .files
+        .into_iter()
+        .filter(|file| matches!(file.yanked, Yanking::NotYanked))
+        .filter(|file| supports_python(criteria, file))
+        .filter_map(|file| {
+            let (version, is_wheel) = match WheelFilename::from_str(&file.filename) {
+                Ok(wheel) => {
+                    let tags = wheel.tags().ok()?;
+                    criteria
+                        .target
+                        .supports(&tags)
+                        .then_some((wheel.version, true))?
+                }
+                Err(_) if !criteria.has_wheel => {
+                    (SdistFilename::from_str(&file.filename).ok()?.version, false)
+                }
+                Err(_) => return None,
+            };
+            let matches = dep
+                .version_spec()
+                .is_none_or(|version_spec| version_spec.contains(&version));
+            matches.then_some(((version, is_wheel), file))
+        })
+        .collect();
+    Ok(candidates)
+}
+
+/// Whether the file’s `Requires-Python` allows the target version. Invalid ones are ignored.
+fn supports_python(criteria: &Criteria, file: &simple_repo_api::File) -> bool {
+    let (Some((major, minor)), Some(requires_python)) =
+        (criteria.target.python, &file.requires_python)
+    else {
+        return true;
+    };
+    let Ok(specifiers) = pep440_rs::VersionSpecifiers::from_str(requires_python) else {
+        return true;
+    };
+    specifiers.contains(&pep440_rs::Version::new([major, minor]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockIndex, MockWheel};
+
+    #[tokio::test]
+    async fn test_find_installable() {
+        let index = MockIndex::start([
+            MockWheel::new("foo", "1.0"),
+            MockWheel::new("foo", "2.0").requires_python(">=3.14"),
+            MockWheel::new("foo", "3.0").yanked(None),
+        ])
+        .await
+        .unwrap();
+        let client = index.client();
+        let criteria = Criteria {
+            target: Target {
+                python: Some((3, 13)),
+                platform: Some("manylinux_2_28_x86_64".to_owned()),
+            },
+            has_wheel: true,
+        };
+        let file = find_installable(&client, &"foo".parse().unwrap(), &criteria)
+            .await
+            .unwrap();
+        assert_eq!(file.unwrap().filename, "foo-1.0-py3-none-any.whl");
+        let file = find_installable(&client, &"foo>=2".parse().unwrap(), &criteria)
+            .await
+            .unwrap();
+        assert!(file.is_none());
+
+        let (file, size) = largest_installable(&client, &"foo".parse().unwrap(), &criteria)
+            .await
+            .unwrap()
+            .unwrap();
+        let served = client.http().get(file.url.clone()).send().await.unwrap();
+        assert_eq!(size, served.bytes().await.unwrap().len() as u64);
+    }
+}
@@ -0,0 +1,122 @@
+//! Comparing lazily reading a wheel’s metadata to downloading the whole wheel.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use eyre::{Result, bail};
+use http::Extensions;
+use reqwest::{Method, Request, Response, Url};
+use reqwest_middleware::{Middleware, Next};
+use serde::Serialize;
+
+use crate::error::Failure;
+use crate::simple_repo_api::split_hash_fragment;
+use crate::wheel::{self, find_wheel, is_metadata, pkg_metadata};
+use crate::{LazyZipClient, PkgLoc};
+
+/// Middleware counting requests and the bytes of their response bodies
+#[derive(Debug, Clone, Default)]
+pub struct RequestStats(Arc<Counters>);
+
+#[derive(Debug, Default)]
+struct Counters {
+    requests: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl RequestStats {
+    pub fn requests(&self) -> u64 {
+        self.0.requests.load(Ordering::Relaxed)
+    }
+
+    /// Bytes according to the `Content-Length` of responses, which is all range responses have
+    pub fn bytes(&self) -> u64 {
+        self.0.bytes.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for RequestStats {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        // HEAD responses announce a length, but have no body
+        let has_body = req.method() != Method::HEAD;
+        let response = next.run(req, extensions).await?;
+        self.0.requests.fetch_add(1, Ordering::Relaxed);
+        if has_body {
+            let len = response.content_length().unwrap_or_default();
+            self.0.bytes.fetch_add(len, Ordering::Relaxed);
+        }
+        Ok(response)
+    }
+}
+
+/// Cost of reading a wheel’s metadata one way
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Measurement {
+    pub seconds: f64,
+    pub requests: u64,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Report {
+    pub url: Url,
+    /// Reading `METADATA` using range requests
+    pub lazy: Measurement,
+    /// Downloading the whole wheel, then reading `METADATA`
+    pub full: Measurement,
+}
+
+/// Read a remote wheel’s metadata lazily and by downloading it fully, measuring both
+#[tracing::instrument(skip(client), fields(pkg_loc = %pkg_loc))]
+pub async fn bench(client: &LazyZipClient, pkg_loc: PkgLoc) -> Result<Report> {
+    let url = match pkg_loc {
+        PkgLoc::Dependency(dep) => find_wheel(client, &dep).await?.url,
+        PkgLoc::Url(url) => split_hash_fragment(url).0,
+        PkgLoc::Path(path) => bail!("can only benchmark remote wheels, not {}", path.display()),
+    };
+    let lazy = measure(client, async |client| {
+        pkg_metadata(client, PkgLoc::Url(url.clone())).await?;
+        Ok(())
+    })
+    .await?;
+    let full = measure(client, async |client| {
+        let bytes = client
+            .http()
+            .get(client.rewrite_url(url.clone())?)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let reader = Box::new(futures::io::Cursor::new(bytes.to_vec()));
+        let mut zip_reader = wheel::zip_reader(reader).await?;
+        let idx = wheel::find_entry(&mut zip_reader, is_metadata)
+            .ok_or_else(|| Failure::entry_missing("wheel has no METADATA"))?;
+        wheel::read_entry(&mut zip_reader, idx, &mut String::new()).await?;
+        Ok(())
+    })
+    .await?;
+    Ok(Report { url, lazy, full })
+}
+
+async fn measure(
+    client: &LazyZipClient,
+    f: impl AsyncFnOnce(&LazyZipClient) -> Result<()>,
+) -> Result<Measurement> {
+    let stats = RequestStats::default();
+    let client = client.clone().with_middleware(stats.clone());
+    let start = Instant::now();
+    f(&client).await?;
+    Ok(Measurement {
+        seconds: start.elapsed().as_secs_f64(),
+        requests: stats.requests(),
+        bytes: stats.bytes(),
+    })
+}
@@ -0,0 +1,188 @@
+//! A content-addressed store of fully read zip entries, shared across packages and runs.
+//!
+//! Blobs are stored by their SHA-256, so identical license texts or metadata of different
+//! packages and versions are stored once. They’re found by the SHA-256 the index lists for the file
+//! and the entry’s path, so a stored entry needs no range requests.
+//! A file’s entries can’t be confused with another file’s, unlike when looking them up by a CRC
+//! anyone publishing a package can make collide. Files without a SHA-256, like local ones, aren’t stored.
+//! The CRC and size the zip’s central directory records for the entry are checked on every read,
+//! and so is the blob’s SHA-256. Corrupt blobs are removed, so the entry is read from the zip and stored again.
+
+use std::path::{Path, PathBuf};
+
+use eyre::{Context as _, ContextCompat as _, Result};
+use sha2::{Digest as _, Sha256};
+
+use crate::cache_integrity::remove_corrupt;
+use crate::hashes::hex;
+use crate::result_cache::user_cache_dir;
+
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    dir: PathBuf,
+}
+
+impl BlobStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `blobs` in the user’s cache directory, if there is one
+    pub fn in_user_cache() -> Option<Self> {
+        Some(Self::new(user_cache_dir()?.join("blobs")))
+    }
+
+    fn blob_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join("sha256").join(sha256)
+    }
+
+    /// Where the SHA-256 of the entry `path` of the file with the SHA-256 `file_sha256` is stored.
+    /// `None` if `file_sha256` isn’t one, as it comes from an index.
+    fn index_path(&self, file_sha256: &str, path: &str) -> Option<PathBuf> {
+        let is_sha256 =
+            file_sha256.len() == 64 && file_sha256.bytes().all(|b| b.is_ascii_hexdigit());
+        is_sha256.then(|| {
+            self.dir
+                .join("entries")
+                .join(file_sha256.to_ascii_lowercase())
+                .join(hex(&Sha256::digest(path)))
+        })
+    }
+
+    /// The content of the entry `path` of the file with the SHA-256 `file_sha256`, if stored.
+    /// `crc32` and `size` are what the zip records for the entry.
+    pub fn get(&self, file_sha256: &str, path: &str, crc32: u32, size: u64) -> Option<Vec<u8>> {
+        let index_path = self.index_path(file_sha256, path)?;
+        let sha256 = std::fs::read_to_string(&index_path).ok()?;
+        let blob_path = self.blob_path(sha256.trim());
+        let content = std::fs::read(&blob_path).ok()?;
+        if !self.is_valid(&blob_path, &content) {
+            remove_corrupt(&self.dir, &blob_path);
+            return None;
+        }
+        // An intact blob for another entry, so the index is wrong
+        if content.len() as u64 != size || crc32fast::hash(&content) != crc32 {
+            remove_corrupt(&self.dir, &index_path);
+            return None;
+        }
+        Some(content)
+    }
+
+    /// If a file in the store holds what its path says:
+    /// a blob with the SHA-256 it’s named after, or the SHA-256 of a blob
+    pub(crate) fn is_valid(&self, path: &Path, content: &[u8]) -> bool {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            return false;
+        };
+        if path.starts_with(self.dir.join("sha256")) {
+            name == hex(&Sha256::digest(content))
+        } else {
+            content.len() == 64 && content.iter().all(u8::is_ascii_hexdigit)
+        }
+    }
+
+    /// Store the content of the entry `path` of the file with the SHA-256 `file_sha256`,
+    /// returning the content’s SHA-256
+    pub fn put(&self, file_sha256: &str, path: &str, content: &[u8]) -> Result<String> {
+        let index_path = self
+            .index_path(file_sha256, path)
+            .ok_or_else(|| eyre::eyre!("Invalid SHA-256 {file_sha256:?}"))?;
+        let sha256 = hex(&Sha256::digest(content));
+        let blob_path = self.blob_path(&sha256);
+        if !blob_path.exists() {
+            write_atomic(&blob_path, content)?;
+        }
+        write_atomic(&index_path, sha256.as_bytes())?;
+        Ok(sha256)
+    }
+}
+
+/// Replace the file at `path` with `content` via a temporary file,
+/// so readers never see a partially written file.
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let tmp = tmp_path(path)?;
+    std::fs::write(&tmp, content).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// A hidden file next to `path` to write to before replacing it, with the process ID so concurrent runs don’t share it
+pub fn tmp_path(path: &Path) -> Result<PathBuf> {
+    let file_name = path.file_name().context("path has no file name")?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    Ok(path.with_file_name(tmp_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHEEL: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+    const OTHER_WHEEL: &str = "fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210";
+
+    #[test]
+    fn test_put_get() {
+        let dir = std::env::temp_dir().join(format!("pypi-lazyzip-blobs-{}", std::process::id()));
+        let store = BlobStore::new(&dir);
+        let license = b"MIT License\n";
+        let crc32 = crc32fast::hash(license);
+        let size = license.len() as u64;
+        assert_eq!(store.get(WHEEL, "LICENSE", crc32, size), None);
+
+        let sha256 = store.put(WHEEL, "LICENSE", license).unwrap();
+        assert_eq!(store.put(OTHER_WHEEL, "LICENSE", license).unwrap(), sha256);
+        assert_eq!(
+            store.get(WHEEL, "LICENSE", crc32, size).as_deref(),
+            Some(&license[..])
+        );
+        assert_eq!(std::fs::read_dir(dir.join("sha256")).unwrap().count(), 1);
+
+        // A corrupt blob isn’t used, but removed and stored again
+        std::fs::write(store.blob_path(&sha256), b"BSD License\n").unwrap();
+        assert_eq!(store.get(WHEEL, "LICENSE", crc32, size), None);
+        assert!(!store.blob_path(&sha256).exists());
+        store.put(WHEEL, "LICENSE", license).unwrap();
+        assert_eq!(
+            store.get(WHEEL, "LICENSE", crc32, size).as_deref(),
+            Some(&license[..])
+        );
+
+        // SHA-256s from an index can’t escape the store
+        assert!(store.put("../../etc", "LICENSE", license).is_err());
+        assert_eq!(store.get("../../etc", "LICENSE", crc32, size), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_crc_collision() {
+        let dir = std::env::temp_dir().join(format!(
+            "pypi-lazyzip-blobs-collision-{}",
+            std::process::id()
+        ));
+        let store = BlobStore::new(&dir);
+        let (ours, theirs) = (b"plumless", b"buckeroo");
+        let crc32 = crc32fast::hash(ours);
+        assert_eq!(crc32fast::hash(theirs), crc32);
+
+        // Another file’s entry with the same CRC and size isn’t returned for ours
+        store.put(OTHER_WHEEL, "foo/__init__.py", theirs).unwrap();
+        assert_eq!(store.get(WHEEL, "foo/__init__.py", crc32, 8), None);
+        store.put(WHEEL, "foo/__init__.py", ours).unwrap();
+        assert_eq!(
+            store.get(WHEEL, "foo/__init__.py", crc32, 8).as_deref(),
+            Some(&ours[..])
+        );
+        assert_eq!(
+            store
+                .get(OTHER_WHEEL, "foo/__init__.py", crc32, 8)
+                .as_deref(),
+            Some(&theirs[..])
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
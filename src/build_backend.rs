@@ -0,0 +1,240 @@
+//! Which tool built a package, for `build-backend`.
+//!
+//! Wheels name the tool that built them in `WHEEL`’s `Generator` field,
+//! and sdists name their build backend in `pyproject.toml`, or run `setup.py` if they have none.
+
+use std::str::FromStr as _;
+
+use eyre::Result;
+use serde::Serialize;
+
+use crate::python_pkg::{Metadata, PackageName};
+use crate::sdist::{self, has_suffix};
+use crate::wheel::{extract, is_wheel_file};
+use crate::{LazyZipClient, PkgLoc};
+
+/// The build backend family, covering its build backend module and wheel generator names
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    Setuptools,
+    Hatch,
+    Flit,
+    Poetry,
+    Pdm,
+    Maturin,
+    ScikitBuild,
+    MesonPython,
+    Uv,
+    Other,
+}
+
+impl Backend {
+    /// From `build-system.build-backend`, like `hatchling.build`
+    pub fn from_build_backend(build_backend: &str) -> Self {
+        let module = build_backend.split([':', '.']).next().unwrap_or_default();
+        match module {
+            "setuptools" => Self::Setuptools,
+            "hatchling" => Self::Hatch,
+            "flit_core" | "flit" => Self::Flit,
+            "poetry" => Self::Poetry,
+            "pdm" => Self::Pdm,
+            "maturin" => Self::Maturin,
+            "scikit_build_core" => Self::ScikitBuild,
+            "mesonpy" => Self::MesonPython,
+            "uv_build" => Self::Uv,
+            _ => Self::Other,
+        }
+    }
+
+    /// From the tool name in `WHEEL`’s `Generator`, like `hatchling`
+    pub fn from_generator(tool: &str) -> Self {
+        match tool.to_ascii_lowercase().as_str() {
+            "bdist_wheel" | "setuptools" => Self::Setuptools,
+            "hatchling" | "hatch" => Self::Hatch,
+            "flit" | "flit_core" => Self::Flit,
+            "poetry" | "poetry-core" | "poetry_core" => Self::Poetry,
+            "pdm" | "pdm-backend" | "pdm-pep517" => Self::Pdm,
+            "maturin" => Self::Maturin,
+            "scikit-build-core" | "skbuild" => Self::ScikitBuild,
+            "meson" | "meson-python" | "mesonpy" => Self::MesonPython,
+            "uv" | "uv_build" | "uv-build" => Self::Uv,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// What the wheel’s `Generator` says
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct WheelBuild {
+    /// As given, like `bdist_wheel (0.43.0)`
+    pub generator: String,
+    pub tool: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl WheelBuild {
+    /// Split a `Generator` into tool name and version, which may be parenthesized
+    pub fn parse(generator: &str) -> Self {
+        let (tool, version) = generator
+            .trim()
+            .split_once(char::is_whitespace)
+            .unwrap_or((generator.trim(), ""));
+        let version = version.trim().trim_start_matches('(').trim_end_matches(')');
+        Self {
+            generator: generator.to_owned(),
+            tool: tool.to_owned(),
+            version: (!version.is_empty()).then(|| version.to_owned()),
+        }
+    }
+}
+
+/// What the sdist’s `pyproject.toml` says
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SdistBuild {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_backend: Option<String>,
+    pub build_requires: Vec<String>,
+    /// Without a build backend, installers run `setup.py` via `setuptools.build_meta:__legacy__`
+    pub legacy: bool,
+}
+
+/// How a package was built
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// From the sdist’s build backend if there is one, or the wheel’s generator
+    pub backend: Option<Backend>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wheel: Option<WheelBuild>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sdist: Option<SdistBuild>,
+}
+
+impl BuildInfo {
+    pub fn new(wheel: Option<WheelBuild>, sdist: Option<SdistBuild>) -> Self {
+        let from_sdist = sdist.as_ref().map(|sdist| {
+            sdist
+                .build_backend
+                .as_deref()
+                .map_or(Backend::Setuptools, Backend::from_build_backend)
+        });
+        let from_wheel = wheel.as_ref().map(|w| Backend::from_generator(&w.tool));
+        Self {
+            backend: from_sdist.or(from_wheel),
+            wheel,
+            sdist,
+        }
+    }
+}
+
+/// Detect how a package was built. For requirements, both the wheel and the sdist are inspected if available.
+#[tracing::instrument(skip(client), fields(pkg_loc = %pkg_loc))]
+pub async fn detect(client: &LazyZipClient, pkg_loc: PkgLoc) -> Result<(PackageName, BuildInfo)> {
+    let is_wheel = match &pkg_loc {
+        PkgLoc::Dependency(dep) => {
+            let (wheel, sdist) = futures::join!(
+                wheel_build(client, PkgLoc::Dependency(dep.clone())),
+                sdist_build(client, PkgLoc::Dependency(dep.clone())),
+            );
+            let (wheel, sdist) = match (wheel, sdist) {
+                (Err(e), Err(_)) => return Err(e),
+                (wheel, sdist) => (
+                    log_missing(wheel, "wheel").flatten(),
+                    log_missing(sdist, "sdist"),
+                ),
+            };
+            return Ok((dep.name().clone(), BuildInfo::new(wheel, sdist)));
+        }
+        PkgLoc::Url(url) => has_suffix(url.path(), ".whl"),
+        PkgLoc::Path(path) => has_suffix(&path.to_string_lossy(), ".whl"),
+    };
+    if is_wheel {
+        let (name, wheel) = wheel_build(client, pkg_loc).await?;
+        Ok((name, BuildInfo::new(wheel, None)))
+    } else {
+        let (name, sdist) = sdist_build(client, pkg_loc).await?;
+        Ok((name, BuildInfo::new(None, Some(sdist))))
+    }
+}
+
+/// The inspected value, or `None` if there’s no such file or it couldn’t be read
+fn log_missing<T>(result: Result<(PackageName, T)>, kind: &str) -> Option<T> {
+    result
+        .inspect_err(|e| tracing::debug!("No {kind} inspected: {e:#}"))
+        .ok()
+        .map(|(_, value)| value)
+}
+
+async fn wheel_build(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+) -> Result<(PackageName, Option<WheelBuild>)> {
+    let (name, content) = extract(client, pkg_loc, is_wheel_file).await?;
+    let generator = content
+        .as_deref()
+        .map(Metadata::from_str)
+        .transpose()?
+        .and_then(|wheel| wheel.get("Generator").map(WheelBuild::parse));
+    Ok((name, generator))
+}
+
+async fn sdist_build(client: &LazyZipClient, pkg_loc: PkgLoc) -> Result<(PackageName, SdistBuild)> {
+    let (name, info) = sdist::inspect(client, pkg_loc).await?;
+    let sdist = SdistBuild {
+        legacy: info.build_backend.is_none(),
+        build_backend: info.build_backend,
+        build_requires: info.build_requires,
+    };
+    Ok((name, sdist))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wheel_build_parse() {
+        let build = WheelBuild::parse("bdist_wheel (0.43.0)");
+        assert_eq!(
+            (build.tool.as_str(), build.version.as_deref()),
+            ("bdist_wheel", Some("0.43.0"))
+        );
+        let build = WheelBuild::parse("hatchling 1.25.0");
+        assert_eq!(
+            (build.tool.as_str(), build.version.as_deref()),
+            ("hatchling", Some("1.25.0"))
+        );
+        assert_eq!(WheelBuild::parse("custom").version, None);
+    }
+
+    #[test]
+    fn test_backend() {
+        assert_eq!(
+            Backend::from_build_backend("setuptools.build_meta:__legacy__"),
+            Backend::Setuptools
+        );
+        assert_eq!(
+            Backend::from_build_backend("poetry.core.masonry.api"),
+            Backend::Poetry
+        );
+        assert_eq!(Backend::from_build_backend("mesonpy"), Backend::MesonPython);
+        assert_eq!(Backend::from_generator("maturin"), Backend::Maturin);
+
+        let sdist = SdistBuild {
+            build_backend: None,
+            build_requires: Vec::new(),
+            legacy: true,
+        };
+        let wheel = WheelBuild::parse("hatchling 1.25.0");
+        assert_eq!(
+            BuildInfo::new(Some(wheel.clone()), Some(sdist)).backend,
+            Some(Backend::Setuptools)
+        );
+        assert_eq!(
+            BuildInfo::new(Some(wheel), None).backend,
+            Some(Backend::Hatch)
+        );
+        assert_eq!(BuildInfo::new(None, None).backend, None);
+    }
+}
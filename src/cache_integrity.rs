@@ -0,0 +1,217 @@
+//! Checksummed cache entries, and statistics about the ones found corrupt.
+//!
+//! Cached documents start with a line holding the SHA-256 of the rest, checked on every read.
+//! Corrupt entries, e.g. from disk errors or interrupted writes on file systems without atomic renames,
+//! are removed and count as missing, so they’re fetched again and replaced instead of being parsed.
+//! Each cache directory counts the entries removed that way in its `corrupt-entries` file.
+
+use std::path::{Path, PathBuf};
+
+use eyre::Result;
+use serde::Serialize;
+use sha2::{Digest as _, Sha256};
+
+use crate::blob_store::{BlobStore, write_atomic};
+use crate::hashes::hex;
+use crate::result_cache::user_cache_dir;
+
+const PREFIX: &[u8] = b"sha256:";
+
+/// Name of the file counting removed corrupt entries in each cache directory
+const CORRUPT_COUNT: &str = "corrupt-entries";
+
+/// Write `content` to `path` with its checksum, replacing the file only once it’s fully written
+pub(crate) fn write_checked(path: &Path, content: &[u8]) -> Result<()> {
+    let mut checked = [PREFIX, hex(&Sha256::digest(content)).as_bytes(), b"\n"].concat();
+    checked.extend_from_slice(content);
+    write_atomic(path, &checked)
+}
+
+/// The content of an entry written by [`write_checked`] in the cache in `cache_dir`, if it exists.
+/// Corrupt entries are removed and counted, and read as missing.
+pub(crate) fn read_checked(cache_dir: &Path, path: &Path) -> Option<Vec<u8>> {
+    let checked = std::fs::read(path).ok()?;
+    if !checked.starts_with(PREFIX) {
+        tracing::debug!("Ignoring cache entry without checksum {}", path.display());
+        return None;
+    }
+    let content = verify(&checked);
+    if content.is_none() {
+        remove_corrupt(cache_dir, path);
+    }
+    content.map(<[u8]>::to_vec)
+}
+
+/// The content after a valid checksum line
+fn verify(checked: &[u8]) -> Option<&[u8]> {
+    let (header, content) = checked.split_at(checked.iter().position(|&b| b == b'\n')?);
+    let sha256 = header.strip_prefix(PREFIX)?;
+    let content = &content[1..];
+    (sha256 == hex(&Sha256::digest(content)).as_bytes()).then_some(content)
+}
+
+/// Remove a corrupt entry of the cache in `cache_dir`, so it’s replaced, and count it
+pub(crate) fn remove_corrupt(cache_dir: &Path, path: &Path) {
+    tracing::warn!("Removing corrupt cache entry {}", path.display());
+    if let Err(e) = std::fs::remove_file(path) {
+        tracing::warn!("Failed to remove {}: {e}", path.display());
+    }
+    let count_path = cache_dir.join(CORRUPT_COUNT);
+    let count = corrupt_count(cache_dir) + 1;
+    if let Err(e) = write_atomic(&count_path, count.to_string().as_bytes()) {
+        tracing::debug!("Failed to count corrupt cache entries: {e:#}");
+    }
+}
+
+/// How many corrupt entries were removed from the cache in `cache_dir`
+fn corrupt_count(cache_dir: &Path) -> u64 {
+    std::fs::read_to_string(cache_dir.join(CORRUPT_COUNT))
+        .ok()
+        .and_then(|count| count.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Size and health of one cache directory
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct CacheStats {
+    pub name: &'static str,
+    /// The cache’s directory, or file for single-file caches
+    pub path: PathBuf,
+    pub entries: u64,
+    pub bytes: u64,
+    /// Corrupt entries removed so far, including ones found by verifying
+    pub corrupt_removed: u64,
+    /// Corrupt entries found by verifying all entries, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub corrupt_found: Option<u64>,
+}
+
+/// Stats of the caches in the user’s cache directory, verifying every entry if `verify` is set
+pub fn info(verify: bool) -> Result<Vec<CacheStats>> {
+    let Some(root) = user_cache_dir() else {
+        eyre::bail!("No cache directory, set `XDG_CACHE_HOME` or `HOME`");
+    };
+    Ok([
+        ("blobs", Kind::Blobs),
+        ("results", Kind::Checked),
+        ("index-roots", Kind::Checked),
+        ("prefetch-hints.json", Kind::Checked),
+    ]
+    .into_iter()
+    .map(|(name, kind)| stats(name, &root.join(name), kind, verify))
+    .collect())
+}
+
+/// How a cache’s entries are checked
+#[derive(Clone, Copy)]
+enum Kind {
+    /// Written by [`write_checked`]
+    Checked,
+    /// Content-addressed by [`BlobStore`]
+    Blobs,
+}
+
+/// Stats of a cache directory, or of a cache that is a single file, counted in the directory containing it
+fn stats(name: &'static str, path: &Path, kind: Kind, verify: bool) -> CacheStats {
+    let (dir, files) = if path.is_file() {
+        (path.parent().unwrap_or(path), vec![path.to_owned()])
+    } else {
+        (path, files(path))
+    };
+    let mut entries = 0;
+    let mut bytes = 0;
+    let mut corrupt = 0;
+    for path in files {
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        if verify && !is_valid(kind, dir, &path) {
+            remove_corrupt(dir, &path);
+            corrupt += 1;
+            continue;
+        }
+        entries += 1;
+        bytes += metadata.len();
+    }
+    CacheStats {
+        name,
+        path: path.to_owned(),
+        entries,
+        bytes,
+        corrupt_removed: corrupt_count(dir),
+        corrupt_found: verify.then_some(corrupt),
+    }
+}
+
+fn is_valid(kind: Kind, dir: &Path, path: &Path) -> bool {
+    let Ok(content) = std::fs::read(path) else {
+        return false;
+    };
+    match kind {
+        // Entries from before checksums were added are replaced when written next
+        Kind::Checked => !content.starts_with(PREFIX) || verify(&content).is_some(),
+        Kind::Blobs => BlobStore::new(dir).is_valid(path, &content),
+    }
+}
+
+/// Entries in `dir` and its subdirectories, skipping the counter and partially written files
+fn files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(self::files(&path));
+        } else if path.file_name().is_some_and(|name| name != CORRUPT_COUNT)
+            && path.extension().is_none_or(|ext| ext != "tmp")
+        {
+            files.push(path);
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_checked() {
+        let dir = std::env::temp_dir().join(format!("pypi-lazyzip-checked-{}", std::process::id()));
+        let path = dir.join("entry.json");
+        assert_eq!(read_checked(&dir, &path), None);
+        write_checked(&path, b"[\"foo\"]").unwrap();
+        assert_eq!(
+            read_checked(&dir, &path).as_deref(),
+            Some(&b"[\"foo\"]"[..])
+        );
+
+        // A flipped bit is detected, and the entry removed
+        let mut corrupt = std::fs::read(&path).unwrap();
+        *corrupt.last_mut().unwrap() ^= 1;
+        std::fs::write(&path, corrupt).unwrap();
+        assert_eq!(read_checked(&dir, &path), None);
+        assert!(!path.exists());
+        assert_eq!(corrupt_count(&dir), 1);
+
+        // Entries without a checksum are ignored, but not corrupt
+        std::fs::write(&path, b"[\"foo\"]").unwrap();
+        assert_eq!(read_checked(&dir, &path), None);
+        write_checked(&dir.join("other.json"), b"[]").unwrap();
+        std::fs::write(dir.join("broken.json"), b"sha256:abc\n[]").unwrap();
+        let stats = stats("test", &dir, Kind::Checked, true);
+        assert_eq!((stats.entries, stats.corrupt_found), (2, Some(1)));
+        assert_eq!(stats.corrupt_removed, 2);
+        assert!(!dir.join("broken.json").exists());
+
+        // Single-file caches count corrupt entries in their directory
+        let file = dir.join("hints.json");
+        std::fs::write(&file, b"sha256:abc\n{}").unwrap();
+        let stats = super::stats("hints.json", &file, Kind::Checked, true);
+        assert_eq!((stats.entries, stats.corrupt_found), (0, Some(1)));
+        assert_eq!(stats.corrupt_removed, 3);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,123 @@
+use crate::project_urls::normalize_label;
+use crate::python_pkg::Metadata;
+
+/// File stems commonly used for changelogs, in order of preference
+const STEMS: &[&str] = &["changelog", "changes", "news", "history", "releasenotes"];
+
+/// Project-URL labels commonly used for changelogs, normalized as in
+/// [the spec](https://packaging.python.org/en/latest/specifications/well-known-project-urls/#label-normalization)
+const URL_LABELS: &[&str] = &[
+    "changelog",
+    "changes",
+    "releasenotes",
+    "history",
+    "whatsnew",
+];
+
+/// Extensions of text files that could be changelogs
+const EXTENSIONS: &[&str] = &["", "md", "markdown", "rst", "txt"];
+
+/// Rank a file path as a changelog candidate, lower is better.
+pub fn rank(path: &str) -> Option<usize> {
+    let file_name = path.rsplit('/').next()?;
+    let (stem, ext) = file_name.split_once('.').unwrap_or((file_name, ""));
+    if !EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()) {
+        return None;
+    }
+    let stem = stem.replace(['-', '_'], "").to_ascii_lowercase();
+    STEMS.iter().position(|s| *s == stem)
+}
+
+/// Find the changelog URL in `Project-URL` metadata
+pub fn url(metadata: &Metadata) -> Option<&str> {
+    metadata.get_all("Project-URL").find_map(|entry| {
+        let (label, url) = entry.split_once(',')?;
+        URL_LABELS
+            .contains(&normalize_label(label).as_str())
+            .then_some(url.trim())
+    })
+}
+
+/// Find the section of a changelog about `version`.
+///
+/// Sections start with a Markdown or reStructuredText heading mentioning the version
+/// and end at the next heading of the same or a higher level.
+pub fn section<'a>(changelog: &'a str, version: &str) -> Option<&'a str> {
+    let lines = changelog.split_inclusive('\n').collect::<Vec<_>>();
+    let headings = (0..lines.len())
+        .filter_map(|i| heading_level(&lines, i).map(|level| (i, level)))
+        .collect::<Vec<_>>();
+    let (pos, &(start, level)) = headings
+        .iter()
+        .enumerate()
+        .find(|(_, (i, _))| mentions_version(lines[*i], version))?;
+    let end = headings[pos + 1..]
+        .iter()
+        .find(|(_, l)| *l <= level)
+        .map_or(lines.len(), |(i, _)| *i);
+    let offset = |i: usize| lines[..i].iter().map(|l| l.len()).sum::<usize>();
+    Some(changelog[offset(start)..offset(end)].trim_end())
+}
+
+/// The level of a heading starting at line `i`, lower is more important.
+/// reStructuredText headings have no inherent levels, so they’re ranked by underline character.
+fn heading_level(lines: &[&str], i: usize) -> Option<usize> {
+    let line = lines[i].trim_end();
+    if let Some(rest) = line.strip_prefix('#') {
+        let hashes = 1 + rest.chars().take_while(|c| *c == '#').count();
+        return rest[hashes - 1..].starts_with(' ').then_some(hashes);
+    }
+    let underline = lines.get(i + 1)?.trim_end();
+    let c = underline.chars().next()?;
+    let is_underline = !line.is_empty()
+        && underline.len() >= 3
+        && "=-~^*+#".contains(c)
+        && underline.chars().all(|u| u == c);
+    is_underline.then(|| "=-~^*+#".find(c).unwrap_or_default() + 1)
+}
+
+/// If `line` contains `version` not directly surrounded by other version characters
+fn mentions_version(line: &str, version: &str) -> bool {
+    line.match_indices(version).any(|(i, _)| {
+        let before = line[..i].chars().next_back();
+        let after = line[i + version.len()..].chars().next();
+        !before.is_some_and(|c| c.is_ascii_digit() || c == '.')
+            && !after.is_some_and(|c| c.is_ascii_alphanumeric() || c == '.')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank() {
+        assert_eq!(rank("CHANGELOG.md"), Some(0));
+        assert_eq!(rank("foo-1.0.dist-info/NEWS.rst"), Some(2));
+        assert_eq!(rank("release_notes.txt"), Some(4));
+        assert_eq!(rank("foo/CHANGES"), Some(1));
+        assert_eq!(rank("foo/history.py"), None);
+        assert_eq!(rank("README.md"), None);
+    }
+
+    #[test]
+    fn test_section_markdown() {
+        let md = "# Changelog\n\n## v1.10.0\n\n- new\n\n### Fixes\n\n- fix\n\n## 1.1.0\n\n- old\n";
+        assert_eq!(
+            section(md, "1.10.0"),
+            Some("## v1.10.0\n\n- new\n\n### Fixes\n\n- fix")
+        );
+        assert_eq!(section(md, "1.1.0"), Some("## 1.1.0\n\n- old"));
+        assert_eq!(section(md, "1.0"), None);
+    }
+
+    #[test]
+    fn test_section_rst() {
+        let rst =
+            "Changes\n=======\n\n2.0 (2024-01-01)\n----------------\n\n* b\n\n1.0\n---\n\n* a\n";
+        assert_eq!(
+            section(rst, "2.0"),
+            Some("2.0 (2024-01-01)\n----------------\n\n* b")
+        );
+    }
+}
@@ -1,41 +1,913 @@
-use std::{path::PathBuf, str::FromStr};
+use std::io::IsTerminal as _;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::str::FromStr as _;
+use std::sync::Arc;
+use std::time::Duration;
 
-use color_eyre::eyre::Error;
+use color_eyre::eyre::{ContextCompat as _, Result, bail};
+use pypi_lazyzip::blob_store::BlobStore;
+use pypi_lazyzip::client::{IndexMatch, Quarantined, WheelPreference};
+use pypi_lazyzip::constraints::Constraints;
+use pypi_lazyzip::dep_graph::{GraphFormat, Traversal};
+use pypi_lazyzip::dns::{FamilyResolver, IpFamily};
+use pypi_lazyzip::download_script::ScriptFormat;
+use pypi_lazyzip::entry_query::{EntryQuery, parse_size};
+use pypi_lazyzip::fixtures::{Recorder, Replayer};
+use pypi_lazyzip::hash_pins::HashPins;
+use pypi_lazyzip::lockfile::Group;
+use pypi_lazyzip::overrides::Overrides;
+use pypi_lazyzip::prefetch_hints::PrefetchHints;
+use pypi_lazyzip::python_pkg::{Dependency, PackageName, parse_requirement, parse_specifiers};
+use pypi_lazyzip::retry::RetryPolicy;
+use pypi_lazyzip::signing::CommandSigner;
+use pypi_lazyzip::simple_repo_api::{ApiVersion, PYPI};
+use pypi_lazyzip::snapshot::Query;
+use pypi_lazyzip::suggestions::NameSuggester;
+use pypi_lazyzip::template::Template;
+use pypi_lazyzip::wheel_matrix::MatrixFormat;
+use pypi_lazyzip::{LazyZipClient, PkgLoc};
+use reqwest::Url;
 
-use crate::python_pkg::Dependency;
+use crate::output::{Dest, Output, WarningsMode};
 
-#[derive(Debug, Clone)]
-pub enum PkgLoc {
-    Dependency(Dependency),
-    Path(PathBuf),
+/// Read files and metadata from Python packages on indexes without downloading them
+#[derive(clap::Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub client: ClientArgs,
+    #[command(flatten)]
+    pub selection: SelectionArgs,
+    #[cfg(feature = "tuf")]
+    #[command(flatten)]
+    pub tuf: TufArgs,
+    #[command(flatten)]
+    pub input: InputArgs,
+    #[command(flatten)]
+    pub state: StateArgs,
+    #[command(flatten)]
+    pub output: OutputArgs,
+    /// Packages to extract top-level module names from
+    pub pkg_locs: Vec<PkgLoc>,
+}
+
+impl Cli {
+    /// Directories that files are written to, given the options
+    pub fn writable_dirs(&self) -> Vec<PathBuf> {
+        let parent = |path: &PathBuf| match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_owned(),
+            _ => PathBuf::from("."),
+        };
+        let files = [
+            &self.client.pin_hashes,
+            &self.state.state,
+            &self.state.merge_into,
+            &self.output.output,
+            &self.output.metrics,
+        ];
+        self.client
+            .record
+            .iter()
+            .cloned()
+            .chain(files.into_iter().flatten().map(parent))
+            .collect()
+    }
 }
 
-impl std::fmt::Display for PkgLoc {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            PkgLoc::Dependency(dep) => {
-                dep.name().fmt(f)?;
-                dep.version_spec().map(|vs| vs.fmt(f)).transpose()?;
-                Ok(())
+#[derive(clap::Args)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct ClientArgs {
+    /// Base URL of a simple repository API to search for packages.
+    /// Can be given multiple times, preferring earlier ones.
+    #[arg(long = "index-url", value_name = "URL", global = true, default_value = PYPI)]
+    pub index_urls: Vec<Url>,
+    /// Require index pages to have at least this simple API version,
+    /// failing clearly if an index lacks fields it introduced, e.g. file sizes from 1.1
+    #[arg(long, value_enum, value_name = "VERSION", global = true)]
+    pub simple_api_version: Option<ApiVersion>,
+    /// Ignore files uploaded after this time, or after this day (UTC) for dates like `2023-06-01`,
+    /// and files the index lists without upload time (PEP 700)
+    #[arg(long, value_name = "TIME", global = true, value_parser = parse_time)]
+    pub exclude_newer: Option<jiff::Timestamp>,
+    /// Query indexes as they were at this time, e.g. to reproduce an old resolution.
+    /// Replaces `{snapshot}` in `--index-url`s, like `https://mirror.example/{snapshot}/simple/`,
+    /// and implies `--exclude-newer` with the same time unless given
+    #[arg(long, value_name = "TIME", global = true, value_parser = parse_snapshot)]
+    pub index_snapshot: Option<(String, jiff::Timestamp)>,
+    /// Suggest similar names for projects that aren’t found, from the indexes’ project listings,
+    /// and offer near misses on other indexes when asking which index to use.
+    /// Listings are cached for a day, as they’re large
+    #[arg(long, global = true)]
+    pub suggest_names: bool,
+    /// Never prompt, e.g. pick the first index if multiple ones offer a package
+    #[arg(long, global = true)]
+    pub no_interaction: bool,
+    /// Pin the hashes of used files in this file on first use, and warn if they change later
+    #[arg(long, value_name = "PATH", global = true)]
+    pub pin_hashes: Option<PathBuf>,
+    /// Don’t reuse or store fully read entries like `METADATA` in the user’s cache directory
+    #[arg(long, global = true)]
+    pub no_blob_store: bool,
+    /// Don’t size the first request for wheels by what was read from earlier versions
+    #[arg(long, global = true)]
+    pub no_prefetch_hints: bool,
+    /// Maximum number of redirects to follow per request
+    #[arg(long, value_name = "N", global = true, default_value_t = 10)]
+    pub max_redirects: usize,
+    /// Fetch files whose URL starts with FROM from TO instead, e.g. from a caching proxy.
+    /// Can be given multiple times, the first matching one is used.
+    #[arg(long = "rewrite-url", value_name = "FROM=TO", global = true, value_parser = parse_url_rewrite)]
+    pub url_rewrites: Vec<(Url, Url)>,
+    /// Only connect via IPv4, short for `--ip-family ipv4`
+    #[arg(long, global = true, group = "ip_family")]
+    pub ipv4: bool,
+    /// Only connect via IPv6, short for `--ip-family ipv6`
+    #[arg(long, global = true, group = "ip_family")]
+    pub ipv6: bool,
+    /// Which IP address families to connect to, e.g. `prefer-ipv4` if IPv6 routes are slow or broken
+    #[arg(
+        long = "ip-family",
+        value_enum,
+        default_value_t,
+        global = true,
+        group = "ip_family"
+    )]
+    pub family: IpFamily,
+    /// Maximum number of concurrent connections per host, e.g. higher for an internal mirror
+    #[arg(long, value_name = "N", global = true)]
+    pub max_connections_per_host: Option<NonZeroUsize>,
+    /// Fetch large files that are read fully, e.g. by `cat`, in up to this many parallel range requests
+    #[arg(long, value_name = "N", global = true, default_value = "4")]
+    pub parallel_ranges: NonZeroUsize,
+    /// Close idle connections after this many seconds
+    #[arg(long, value_name = "SECS", global = true, default_value_t = 90)]
+    pub pool_idle_timeout: u64,
+    /// Send TCP keepalive probes after connections were idle for this many seconds
+    #[arg(long, value_name = "SECS", global = true)]
+    pub tcp_keepalive: Option<u64>,
+    /// Give up on requests that take longer than this many seconds in total
+    #[arg(long, value_name = "SECS", global = true)]
+    pub timeout: Option<u64>,
+    /// Give up on connecting after this many seconds
+    #[arg(long, value_name = "SECS", global = true)]
+    pub connect_timeout: Option<u64>,
+    /// Retry failed connections, timeouts and `429` and `5xx` responses this many times,
+    /// waiting longer before each retry
+    #[arg(long, value_name = "N", global = true, default_value_t = 0)]
+    pub retries: u32,
+    /// Save all HTTP responses to this directory, to replay them later with `--replay`
+    #[arg(long, value_name = "DIR", global = true)]
+    pub record: Option<PathBuf>,
+    /// Answer all HTTP requests with responses saved by `--record`, without network access
+    #[arg(long, value_name = "DIR", global = true, conflicts_with = "record")]
+    pub replay: Option<PathBuf>,
+    /// Run this shell command to sign every request, e.g. for gateways requiring AWS `SigV4` or HMAC headers.
+    /// It gets `PYPI_LAZYZIP_METHOD`, `PYPI_LAZYZIP_URL` and the headers as `Name: value` lines on stdin,
+    /// and headers it prints that way are added
+    #[arg(long, value_name = "CMD", global = true)]
+    pub sign_cmd: Option<String>,
+    /// Send all requests through this Unix socket, e.g. a local forwarding proxy.
+    /// HTTPS connections are still encrypted end to end.
+    #[cfg(unix)]
+    #[arg(long, value_name = "PATH", global = true, conflicts_with_all = ["ipv4", "ipv6", "family"])]
+    pub unix_socket: Option<PathBuf>,
+}
+
+/// Options for which files to use for packages
+#[derive(clap::Args)]
+pub struct SelectionArgs {
+    /// Use `.egg` and `.zip` files for projects or versions without wheels
+    #[arg(long, global = true)]
+    pub allow_legacy_formats: bool,
+    /// Merge the files of projects offered by multiple indexes instead of choosing one index.
+    /// Files with the same name are taken from the first `--index-url` offering them.
+    #[arg(long, global = true)]
+    pub merge_indexes: bool,
+    /// Patch packages’ requirements and `Requires-Python` as given in this TOML file
+    /// when analyzing dependencies (`requires`, `deps`, `requires-python`, `check-compat`),
+    /// e.g. to model known-bad upstream metadata
+    #[arg(long = "override", value_name = "PATH", global = true)]
+    pub overrides: Option<PathBuf>,
+    /// Only choose package versions allowed by this pip-style constraints file (repeatable).
+    /// Constraints cap versions without adding packages
+    #[arg(short = 'c', long = "constraint", value_name = "PATH", global = true)]
+    pub constraints: Vec<PathBuf>,
+    /// Only look a package up on this index, and constrain its versions if given (repeatable),
+    /// e.g. `internal-lib==1.2@https://internal.example/simple/`
+    #[arg(long = "pkg", value_name = "REQ@URL", global = true, value_parser = parse_pinned_index)]
+    pub pinned_indexes: Vec<(Dependency, Url)>,
+    /// What to do about projects the index marks as quarantined (PEP 792), e.g. for containing malware.
+    /// Archived and deprecated projects are reported as warnings
+    #[arg(long, value_enum, default_value_t, global = true)]
+    pub quarantined: Quarantined,
+    /// Which wheel to choose when the newest version has several, e.g. both `py3-none-any` and platform wheels.
+    /// By default, the last one the index lists is used
+    #[arg(long, value_enum, global = true)]
+    pub prefer: Option<WheelPreference>,
+}
+
+impl SelectionArgs {
+    pub fn apply(self, mut client: LazyZipClient) -> Result<LazyZipClient> {
+        client = client.with_quarantined(self.quarantined);
+        if let Some(preference) = self.prefer {
+            client = client.with_wheel_preference(preference);
+        }
+        if self.allow_legacy_formats {
+            client = client.with_legacy_formats();
+        }
+        if self.merge_indexes {
+            client = client.with_merged_indexes();
+        }
+        if let Some(path) = &self.overrides {
+            client = client.with_overrides(Overrides::load(path)?);
+        }
+        if !self.constraints.is_empty() || !self.pinned_indexes.is_empty() {
+            let mut constraints = Constraints::default();
+            for path in &self.constraints {
+                constraints.load(path)?;
             }
-            PkgLoc::Path(path) => path.display().fmt(f),
+            for (dep, _) in &self.pinned_indexes {
+                if let Some(version_spec) = dep.version_spec() {
+                    constraints.add(dep.name().clone(), version_spec);
+                }
+            }
+            client = client.with_constraints(constraints);
+        }
+        for (dep, index) in self.pinned_indexes {
+            client = client.with_pinned_index(dep.name().clone(), index);
         }
+        Ok(client)
     }
 }
 
-impl FromStr for PkgLoc {
-    type Err = Error;
+impl ClientArgs {
+    fn ip_family(&self) -> IpFamily {
+        match (self.ipv4, self.ipv6) {
+            (true, _) => IpFamily::Ipv4,
+            (_, true) => IpFamily::Ipv6,
+            _ => self.family,
+        }
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Ok(dep) = Dependency::from_str(s) {
-            Ok(PkgLoc::Dependency(dep))
-        } else {
-            Ok(PkgLoc::Path(PathBuf::from(s)))
+    /// Create a client, prompting for ambiguous choices if `can_prompt`,
+    /// unless disabled or not on a terminal
+    pub fn into_client(self, can_prompt: bool) -> Result<LazyZipClient> {
+        let mut http = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::limited(self.max_redirects))
+            .pool_idle_timeout(Duration::from_secs(self.pool_idle_timeout))
+            .tcp_keepalive(self.tcp_keepalive.map(Duration::from_secs));
+        let ip_family = self.ip_family();
+        if ip_family != IpFamily::Any {
+            http = http.dns_resolver(Arc::new(FamilyResolver(ip_family)));
+        }
+        #[cfg(unix)]
+        if let Some(path) = self.unix_socket.clone() {
+            http = http.unix_socket(path);
+        }
+        let index_urls = match &self.index_snapshot {
+            Some((snapshot, _)) => self
+                .index_urls
+                .iter()
+                .map(|url| {
+                    let url = url
+                        .as_str()
+                        .replace("%7Bsnapshot%7D", snapshot)
+                        .replace("{snapshot}", snapshot);
+                    Url::parse(&url)
+                })
+                .collect::<Result<_, _>>()?,
+            None => self.index_urls,
+        };
+        let mut options = LazyZipClient::builder().http(http).index_urls(index_urls);
+        if let Some(secs) = self.timeout {
+            options = options.timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.connect_timeout {
+            options = options.connect_timeout(Duration::from_secs(secs));
+        }
+        if self.retries > 0 {
+            options = options.retries(RetryPolicy::with_max_retries(self.retries));
         }
+        if let Some(max) = self.max_connections_per_host {
+            options = options.max_connections_per_host(max.get());
+        }
+        if !self.no_blob_store
+            && let Some(blob_store) = BlobStore::in_user_cache()
+        {
+            options = options.blob_store(blob_store);
+        }
+        if !self.no_prefetch_hints
+            && let Some(prefetch_hints) = PrefetchHints::in_user_cache()
+        {
+            options = options.prefetch_hints(prefetch_hints);
+        }
+        let mut client = options
+            .build()?
+            .with_parallel_ranges(self.parallel_ranges.get());
+        let snapshot_time = self.index_snapshot.map(|(_, time)| time);
+        if let Some(time) = self.exclude_newer.or(snapshot_time) {
+            client = client.with_exclude_newer(time);
+        }
+        if let Some(api_version) = self.simple_api_version {
+            client = client.with_api_version(api_version);
+        }
+        if let Some(dir) = &self.record {
+            client = client.with_middleware(Recorder::new(dir)?);
+        }
+        if let Some(dir) = &self.replay {
+            client = client.with_middleware(Replayer::new(dir));
+        }
+        for (from, to) in &self.url_rewrites {
+            client = client.with_url_rewrite(from, to);
+        }
+        if self.suggest_names {
+            client = client.with_name_suggestions(NameSuggester::in_user_cache());
+        }
+        if let Some(command) = &self.sign_cmd {
+            client = client.with_signer(CommandSigner::new(command));
+        }
+        if let Some(path) = &self.pin_hashes {
+            client = client.with_hash_pins(HashPins::load(path)?);
+        }
+        let is_interactive = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+        if self.no_interaction || !can_prompt || !is_interactive {
+            return Ok(client);
+        }
+        Ok(client.with_index_chooser(choose_index))
     }
 }
 
-#[derive(clap::Parser)]
-pub struct Cli {
+/// Verification of files from indexes against TUF metadata (PEP 458)
+#[cfg(feature = "tuf")]
+#[derive(clap::Args)]
+pub struct TufArgs {
+    /// Trusted TUF root metadata. Enables verifying files from indexes against TUF metadata.
+    #[arg(
+        long = "tuf-root",
+        value_name = "PATH",
+        global = true,
+        requires = "metadata_url"
+    )]
+    pub root: Option<PathBuf>,
+    /// Base URL of the TUF metadata (`timestamp.json` etc.)
+    #[arg(long = "tuf-metadata-url", value_name = "URL", global = true)]
+    pub metadata_url: Option<Url>,
+    /// Base URL TUF target paths are relative to
+    #[arg(
+        long = "tuf-targets-url",
+        value_name = "URL",
+        global = true,
+        default_value = "https://files.pythonhosted.org/"
+    )]
+    pub targets_url: Url,
+}
+
+#[cfg(feature = "tuf")]
+impl TufArgs {
+    /// Fetch and verify TUF metadata if enabled, and make `client` verify files against it
+    pub async fn apply(self, client: LazyZipClient) -> Result<LazyZipClient> {
+        use color_eyre::eyre::Context as _;
+        use pypi_lazyzip::tuf::TufRepository;
+
+        let Some(root_path) = self.root else {
+            return Ok(client);
+        };
+        let root = std::fs::read(&root_path)
+            .with_context(|| format!("Failed to read TUF root {}", root_path.display()))?;
+        let metadata_url = self
+            .metadata_url
+            .context("--tuf-metadata-url is required")?;
+        let versions = pypi_lazyzip::tuf::versions_path(&metadata_url);
+        let repo = TufRepository::load(
+            client.http().clone(),
+            metadata_url,
+            &root,
+            versions.as_deref(),
+        )
+        .await
+        .context("Failed to verify TUF metadata")?;
+        Ok(client.with_tuf(repo, self.targets_url))
+    }
+}
+
+/// Additional packages for commands processing many (top-level, `metadata`, `requires`, `list`, …)
+#[derive(clap::Args)]
+pub struct InputArgs {
+    /// Also process the projects in a top-pypi-packages JSON dump, at a URL or in a file.
+    /// See <https://hugovk.github.io/top-pypi-packages/>
+    #[arg(long, value_name = "URL_OR_FILE", global = true)]
+    pub from_top_packages: Option<String>,
+    /// Only take this many of the most downloaded projects from the top packages list
+    #[arg(long, value_name = "N", global = true)]
+    pub top: Option<usize>,
+    /// Also process the releases pinned by a `uv.lock`, `poetry.lock`, `Pipfile.lock`, or requirements file.
+    /// Locked wheels are checked against their hashes where the lock file has their URLs.
+    /// The requirements of a `Pipfile` or the `pip:` section of a conda `environment.yml` are processed unpinned
+    #[arg(long, value_name = "PATH", global = true)]
+    pub lockfile: Option<PathBuf>,
+    /// Pipenv package categories to take from `--lockfile` or for `verify-lock`
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "default",
+        global = true
+    )]
+    pub group: Vec<Group>,
+}
+
+/// Options for commands producing one result per package (top-level, `metadata`, `requires`, `list`)
+#[derive(clap::Args)]
+pub struct StateArgs {
+    /// Record completed packages and their results in this file while running.
+    /// Fails if it exists, unless `--resume` is given
+    #[arg(long, value_name = "PATH", global = true)]
+    pub state: Option<PathBuf>,
+    /// Skip packages completed according to `--state`, merging their results into the output
+    #[arg(long, global = true, requires = "state")]
+    pub resume: bool,
+    /// Update the results in this file from an earlier run,
+    /// only re-fetching packages whose file changed since. Results for other packages are kept
+    #[arg(
+        long,
+        value_name = "PATH",
+        global = true,
+        conflicts_with_all = ["state", "output", "fields", "template"]
+    )]
+    pub merge_into: Option<PathBuf>,
+    /// Don’t reuse or store results for files with a known SHA-256 in the user’s cache directory
+    #[arg(long, global = true)]
+    pub no_result_cache: bool,
+}
+
+#[derive(clap::Args)]
+pub struct OutputArgs {
+    /// Write results to this file instead of stdout, replacing it only once they’re complete
+    #[arg(long, short, value_name = "PATH", global = true)]
+    pub output: Option<PathBuf>,
+    /// Append results to `--output` as newline-delimited JSON, one line per package if possible
+    #[arg(long, global = true, requires = "output")]
+    pub append: bool,
+    /// How to report caveats about results, like skipped yanked releases
+    #[arg(long, value_enum, default_value_t, global = true)]
+    pub warnings: WarningsMode,
+    /// Only keep these fields of JSON results, e.g. `name,version`
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',', global = true)]
+    pub fields: Option<Vec<String>>,
+    /// Render results as text, e.g. `'{{name}}=={{version}}'`, one line per package.
+    /// Filters like `{{top_level | join(",")}}` are documented in the README
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        global = true,
+        conflicts_with = "append"
+    )]
+    pub template: Option<Template>,
+    /// Log every fetched byte range and print an access map per remote file to stderr
+    #[arg(long, global = true)]
+    pub trace_ranges: bool,
+    /// Log which file was chosen for every package, and the index it came from
+    #[arg(long, global = true)]
+    pub explain: bool,
+    /// Write request counts, bytes, latencies and cache hits to this file in the Prometheus text format
+    #[arg(long, value_name = "PATH", global = true)]
+    pub metrics: Option<PathBuf>,
+}
+
+impl OutputArgs {
+    pub fn output(&self) -> Output {
+        let dest = match &self.output {
+            None => Dest::Stdout,
+            Some(path) if self.append => Dest::Append(path.clone()),
+            Some(path) => Dest::File(path.clone()),
+        };
+        Output {
+            dest,
+            warnings: self.warnings,
+            fields: self.fields.clone(),
+            template: self.template.clone(),
+        }
+    }
+}
+
+/// Parse an RFC 3339 timestamp, or a date meaning the end of that day in UTC
+fn parse_time(time: &str) -> Result<jiff::Timestamp> {
+    if let Ok(date) = jiff::civil::Date::from_str(time) {
+        return Ok(date
+            .tomorrow()?
+            .to_zoned(jiff::tz::TimeZone::UTC)?
+            .timestamp());
+    }
+    Ok(jiff::Timestamp::from_str(time)?)
+}
+
+/// Parse a time for `--index-snapshot`, keeping it as given to substitute it in URLs
+fn parse_snapshot(time: &str) -> Result<(String, jiff::Timestamp)> {
+    Ok((time.to_owned(), parse_time(time)?))
+}
+
+fn parse_rate(rate: &str) -> Result<f64> {
+    let rate: f64 = rate.parse()?;
+    if !(rate.is_finite() && rate > 0.) {
+        bail!("expected a positive number");
+    }
+    Ok(rate)
+}
+
+fn parse_url_rewrite(rewrite: &str) -> Result<(Url, Url)> {
+    let (from, to) = rewrite.split_once('=').context("expected FROM=TO")?;
+    Ok((Url::parse(from)?, Url::parse(to)?))
+}
+
+/// Parse `REQ@URL`, splitting at the first `@` as requirements can’t contain one
+fn parse_pinned_index(pinned: &str) -> Result<(Dependency, Url)> {
+    let (req, url) = pinned.split_once('@').context("expected REQ@URL")?;
+    Ok((Dependency::from_str(req.trim())?, Url::parse(url.trim())?))
+}
+
+/// Let the user pick one of multiple indexes offering a package, or of projects easily confused with it
+fn choose_index(name: &PackageName, matches: &[IndexMatch]) -> Option<usize> {
+    let items = matches
+        .iter()
+        .map(|m| {
+            let files = m.project.files.len();
+            format!("{} from {} ({files} files)", m.project.name, m.index)
+        })
+        .collect::<Vec<_>>();
+    let near_misses = matches
+        .iter()
+        .any(|m| PackageName::from_str(&m.project.name).ok().as_ref() != Some(name));
+    let prompt = if near_misses {
+        format!(
+            "{} is offered by multiple indexes, some under similar names",
+            name.original()
+        )
+    } else {
+        format!("{} is offered by multiple indexes", name.original())
+    };
+    dialoguer::FuzzySelect::new()
+        .with_prompt(prompt)
+        .items(&items)
+        .default(0)
+        .interact_opt()
+        .inspect_err(|e| tracing::warn!("Failed to prompt for index: {e}"))
+        .ok()
+        .flatten()
+}
+
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Print the top-level module names of packages, also done when no subcommand is given
+    TopLevel(PkgArgs),
+    /// Print the core metadata of packages
+    Metadata(PkgArgs),
+    /// Print the requirements (`Requires-Dist`) of packages
+    Requires(PkgArgs),
+    /// List the files in packages
+    List {
+        #[command(flatten)]
+        pkgs: PkgArgs,
+        /// Only list files matching a query, like `'(glob:*.pyi or name:py.typed) and max-size:1MB'`.
+        /// Terms are `glob:PATTERN`, `name:NAME`, `max-size:SIZE`, `min-size:SIZE`, `stored` and `dir`,
+        /// combined with `and`, `or`, `not` and parentheses
+        #[arg(long)]
+        query: Option<EntryQuery>,
+    },
+    /// Print every extra packages declare, with the requirements it activates,
+    /// and extras their requirements use without declaring them
+    Extras(PkgArgs),
+    /// Print the homepage, repository, documentation, changelog, issue tracker and funding URLs of packages
+    Urls(PkgArgs),
+    /// Recursively collect the requirements of packages into a dependency graph.
+    /// Every package is resolved once, using the first requirement encountered for it.
+    Deps {
+        #[command(flatten)]
+        pkgs: PkgArgs,
+        #[arg(long, value_enum, default_value_t)]
+        format: GraphFormat,
+        /// Instead of the graph, print a script downloading the resolved wheels and checking their hashes,
+        /// e.g. to download them on another machine for an air-gapped environment
+        #[arg(
+            long,
+            value_enum,
+            value_name = "FORMAT",
+            num_args = 0..=1,
+            default_missing_value = "sh",
+            conflicts_with = "format"
+        )]
+        emit_download_script: Option<ScriptFormat>,
+        #[command(flatten)]
+        traversal: TraversalArgs,
+    },
+    /// Print the changelog section for a package’s version, or the link to its changelog
+    Changelog {
+        #[command(flatten)]
+        pkg: PkgArg,
+        /// Print the whole changelog instead of just the section for the package version
+        #[arg(long)]
+        full: bool,
+    },
+    /// Interactively browse a package’s files, metadata and requirements
+    #[cfg(feature = "tui")]
+    Tui(PkgArg),
+    /// Compare the requirements of two releases, e.g. `deps-diff pkg==1.0 pkg==2.0`
+    DepsDiff { old: PkgLoc, new: PkgLoc },
+    /// Copy the `*.dist-info` directories (`METADATA`, `RECORD`, entry points, licenses, …) of wheels
+    /// into `DEST/{name}/`, as a lightweight metadata mirror for offline resolvers and audits
+    MirrorMetadata {
+        #[command(flatten)]
+        pkgs: PkgArgs,
+        /// Also mirror the packages in this requirements file
+        #[arg(short = 'r', long = "requirement", value_name = "PATH")]
+        requirement_files: Vec<PathBuf>,
+        /// Directory to write the tree to
+        #[arg(long, value_name = "DIR")]
+        dest: PathBuf,
+    },
+    /// Write a static simple-API index (PEP 503 HTML and PEP 691 JSON) to `DEST` listing the wheels packages
+    /// and their requirements resolve to, to serve a curated subset of an index from any static file server
+    IndexGen {
+        #[command(flatten)]
+        pkgs: PkgArgs,
+        /// Also index the packages in this requirements file
+        #[arg(short = 'r', long = "requirement", value_name = "PATH")]
+        requirement_files: Vec<PathBuf>,
+        /// Directory to write the index to
+        #[arg(long, value_name = "DIR")]
+        dest: PathBuf,
+        /// Link to files under this URL instead of their original URLs, e.g. `../../files/` for a local mirror
+        /// downloaded with `deps --emit-download-script`. Relative URLs are resolved from the project pages.
+        #[arg(long, value_name = "URL")]
+        files_url: Option<String>,
+        #[command(flatten)]
+        traversal: TraversalArgs,
+    },
+    /// Print the licenses of packages and their requirements, from `License-Expression` or guessed
+    /// from the `License` field and classifiers. Fails if `--allow` or `--deny` reject a license.
+    Licenses {
+        #[command(flatten)]
+        pkgs: PkgArgs,
+        /// Also check the packages in this requirements file
+        #[arg(short = 'r', long = "requirement", value_name = "PATH")]
+        requirement_files: Vec<PathBuf>,
+        /// Print counts per license and the packages with unknown licenses instead of each package’s license
+        #[arg(long)]
+        summary: bool,
+        /// Only accept these SPDX licenses, rejecting unknown ones (repeatable or comma-separated)
+        #[arg(long, value_name = "SPDX-ID", value_delimiter = ',')]
+        allow: Vec<String>,
+        /// Reject these SPDX licenses (repeatable or comma-separated)
+        #[arg(long, value_name = "SPDX-ID", value_delimiter = ',')]
+        deny: Vec<String>,
+        #[command(flatten)]
+        traversal: TraversalArgs,
+    },
+    /// Print a digest of wheels’ contents from their central directories (entry names, sizes and CRCs),
+    /// which is equal for wheels with equal contents regardless of timestamps, compression and entry order
+    Fingerprint(PkgArgs),
+    /// Compare the contents of two wheels by their central directories, without downloading any entries,
+    /// e.g. `fingerprint-diff foo-1.0-py3-none-any.whl https://example.com/foo-1.0-py3-none-any.whl`
+    FingerprintDiff { old: PkgLoc, new: PkgLoc },
+    /// Validate package names and print their normalized forms, as in PEP 503
+    Normalize {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// Report the Python versions supported by all given packages
+    RequiresPython {
+        #[command(flatten)]
+        pkgs: PkgArgs,
+        /// Flag packages that don’t support this interpreter version
+        #[arg(long)]
+        python_version: Option<pep440_rs::Version>,
+    },
+    /// Flag packages that look deprecated or renamed, with the successor they suggest
+    DeprecationCheck(PkgArgs),
+    /// Report the authors and maintainers of all given packages, deduplicated by email address,
+    /// with the packages each of them works on
+    Authors(PkgArgs),
+    /// Inventory the non-Python files each wheel would install by extension and directory,
+    /// flagging compiled code in pure wheels, model weights and files of 50 MiB or more
+    DataFiles(PkgArgs),
+    /// Find dependencies wheels bundle instead of requiring, from modules in directories like `_vendor/`
+    /// and well-known packages nested in other packages, e.g. for vulnerability impact analysis
+    VendoredScan(PkgArgs),
+    /// List the commands all given packages would install from their `console_scripts` and `gui_scripts`
+    /// entry points, with the `module:function` each calls, flagging commands more than one package installs
+    ConsoleScripts(PkgArgs),
+    /// Check a wheel’s `WHEEL` file version and tags, `Root-Is-Purelib` against its platform tags,
+    /// that `METADATA` and `RECORD` exist and match the filename, and that `RECORD` lists exactly the wheel’s files
+    /// with their sizes. Only reads the wheel’s directory and metadata files, so hashes aren’t verified.
+    /// Fails if there are problems
+    LintWheel(PkgArg),
+    /// Compare reading a remote wheel’s metadata lazily to downloading the whole wheel
+    Bench(PkgArg),
+    /// Check connectivity to the indexes, range request support, proxy and clock settings,
+    /// and that output directories are writable
+    Doctor {
+        /// Package to look up on every index, whose files are used to check range requests
+        #[arg(default_value = "pip")]
+        package: PackageName,
+    },
+    /// Report the build backend of packages and its version, from the wheel’s `WHEEL` `Generator`
+    /// and the sdist’s `pyproject.toml`, flagging sdists without a build backend that run `setup.py`
+    BuildBackend(PkgArgs),
+    /// Print the core metadata (`PKG-INFO`), build backend and build requirements of source distributions
+    Sdist(PkgArgs),
+    /// Crawl the most downloaded projects at a limited rate, e.g. for bulk datasets.
+    /// Failed queries are recorded in the results, and `--state` makes long crawls resumable.
+    /// `--top` defaults to 1000 projects, and `--from-top-packages` to the latest dump
+    Snapshot {
+        /// What to find out about every project
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "top-level")]
+        query: Vec<Query>,
+        /// Projects to start per second
+        #[arg(long, default_value_t = 5.0, value_parser = parse_rate)]
+        rate: f64,
+    },
+    /// Print a file from a package as is, streaming it instead of reading it whole
+    Cat {
+        #[command(flatten)]
+        pkg: PkgArg,
+        /// Path of the file in the package, e.g. `foo/py.typed`
+        path: String,
+    },
+    /// Exit successfully only if a release matching a requirement can be installed on a target,
+    /// e.g. as a CI gate for upgrades: `assert 'numpy>=2' --python-version 3.13 --has-wheel --platform manylinux_2_28_x86_64`
+    Assert {
+        /// E.g. `numpy>=2`
+        requirement: Dependency,
+        /// Python version to install for, e.g. `3.13`
+        #[arg(long)]
+        python_version: Option<pep440_rs::Version>,
+        /// Platform tag to install on, e.g. `manylinux_2_28_x86_64`, `macosx_14_0_arm64` or `win_amd64`
+        #[arg(long)]
+        platform: Option<String>,
+        /// Require a compatible wheel, instead of accepting sdists that would need to be built
+        #[arg(long)]
+        has_wheel: bool,
+    },
+    /// Exit successfully only if the file that would be installed for a requirement fits a size budget,
+    /// e.g. as a CI gate: `assert-size 'torch<3' --max-compressed 900MB --platform manylinux_2_28_x86_64`.
+    /// Uses the size the index lists, or a `HEAD` request. Without `--platform`, the largest wheel is checked
+    AssertSize {
+        /// E.g. `torch<3`
+        requirement: Dependency,
+        /// Largest acceptable file size, like `900MB` or `1.5GiB`
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        max_compressed: u64,
+        /// Python version to install for, e.g. `3.13`
+        #[arg(long)]
+        python_version: Option<pep440_rs::Version>,
+        /// Platform tag to install on, e.g. `manylinux_2_28_x86_64`
+        #[arg(long)]
+        platform: Option<String>,
+        /// Only consider wheels, instead of also sdists if a release has no compatible wheel
+        #[arg(long)]
+        has_wheel: bool,
+    },
+    /// Show which releases ship wheels for which Python versions and platforms,
+    /// from the tags in their filenames
+    WheelMatrix {
+        /// E.g. `numpy` or `numpy>=2`
+        requirement: Dependency,
+        #[arg(long, value_enum, default_value_t)]
+        format: MatrixFormat,
+    },
+    /// Check that the files pinned by a lock file are still on the index and not yanked,
+    /// e.g. as a scheduled job guarding against removals and republished files.
+    /// Fails if any drifted
+    VerifyLock {
+        /// A `uv.lock`, `poetry.lock`, or requirements file
+        lock_file: PathBuf,
+        /// Also check that the files’ hashes match the locked ones
+        #[arg(long)]
+        hashes: bool,
+    },
+    /// Probe every file of the newest release matching a requirement with a `HEAD` request,
+    /// checking that it’s served, with the size the index lists, and with range request support.
+    /// Fails if any file has problems, e.g. as a health check for mirrors
+    CheckFiles {
+        /// E.g. `numpy` or `numpy==2.1.0`
+        requirement: Dependency,
+    },
+    /// Poll a project’s file list and print changes as JSON Patch (RFC 6902) lines, until interrupted.
+    /// The document maps file names to their URL, hashes, size, upload time, `Requires-Python` and yanked status,
+    /// and the first patch adds all files
+    Watch {
+        package: PackageName,
+        /// Seconds between polls
+        #[arg(long, value_name = "SECS", default_value_t = 300)]
+        interval: u64,
+    },
+    /// Find requirements that can’t be satisfied together, from the given ones and the requirements
+    /// of their newest matching releases, without resolving the whole dependency tree.
+    /// Fails if any conflict
+    CheckCompat {
+        /// Requirements like `requests>=2.30`
+        #[arg(value_parser = parse_requirement)]
+        requirements: Vec<pep508_rs::Requirement>,
+        /// Also check the requirements in this requirements file
+        #[arg(short = 'r', long = "requirement", value_name = "PATH")]
+        requirement_files: Vec<PathBuf>,
+    },
+    /// Inspect the user’s cache directory
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// Print a shell completion script, e.g. `pypi-lazyzip completions bash > /etc/bash_completion.d/pypi-lazyzip`
+    Completions { shell: clap_complete::Shell },
+    /// Print the man page, or write one for every subcommand into a directory
+    Man {
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum CacheCommand {
+    /// Print the entries and size of each cache, and how many corrupt entries were removed from it
+    Info {
+        /// Check every entry now, removing corrupt ones
+        #[arg(long)]
+        verify: bool,
+    },
+}
+
+/// How far `deps` follows requirements
+#[derive(clap::Args)]
+pub struct TraversalArgs {
+    /// Only follow requirements up to this many levels deep
+    #[arg(long, conflicts_with_all = ["no_deps", "only_deps"])]
+    max_depth: Option<usize>,
+    /// Only resolve the given packages, short for `--max-depth 0`
+    #[arg(long, conflicts_with = "only_deps")]
+    no_deps: bool,
+    /// Only follow the given packages’ own requirements, short for `--max-depth 1`
+    #[arg(long)]
+    only_deps: bool,
+    /// Don’t follow the requirements of extras, like `bar` in `foo[bar]`
+    #[arg(long)]
+    no_extras: bool,
+    /// Don’t resolve or follow required packages whose names match this pattern, like `boto*` (repeatable)
+    #[arg(long, value_name = "PATTERN")]
+    exclude: Vec<String>,
+}
+
+impl From<TraversalArgs> for Traversal {
+    fn from(args: TraversalArgs) -> Self {
+        let max_depth = match (args.no_deps, args.only_deps) {
+            (true, _) => Some(0),
+            (_, true) => Some(1),
+            _ => args.max_depth,
+        };
+        Traversal {
+            max_depth,
+            extras: !args.no_extras,
+            exclude: args.exclude,
+        }
+    }
+}
+
+#[derive(clap::Args)]
+pub struct PkgArgs {
     pub pkg_locs: Vec<PkgLoc>,
+    /// Only consider releases matching this version specifier, e.g. `==3.2.25`
+    #[arg(long = "version", value_name = "SPEC", value_parser = parse_specifiers)]
+    pub version_spec: Option<pep440_rs::VersionSpecifiers>,
+}
+
+impl PkgArgs {
+    /// The package locations, with `--version` applied to all dependencies
+    pub fn into_pkg_locs(self) -> Vec<PkgLoc> {
+        let Some(version_spec) = self.version_spec else {
+            return self.pkg_locs;
+        };
+        self.pkg_locs
+            .into_iter()
+            .map(|pkg_loc| pkg_loc.constrain(&version_spec))
+            .collect()
+    }
+}
+
+#[derive(clap::Args)]
+pub struct PkgArg {
+    pub pkg_loc: PkgLoc,
+    /// Only consider releases matching this version specifier, e.g. `==3.2.25`
+    #[arg(long = "version", value_name = "SPEC", value_parser = parse_specifiers)]
+    pub version_spec: Option<pep440_rs::VersionSpecifiers>,
+}
+
+impl PkgArg {
+    /// The package location, with `--version` applied if it’s a dependency
+    pub fn into_pkg_loc(self) -> PkgLoc {
+        match self.version_spec {
+            Some(version_spec) => self.pkg_loc.constrain(&version_spec),
+            None => self.pkg_loc,
+        }
+    }
 }
@@ -0,0 +1,1187 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr as _;
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use eyre::{Result, bail};
+use futures::future::try_join_all;
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+use reqwest_middleware::{ClientWithMiddleware, Middleware};
+use tokio::sync::{OnceCell, OwnedSemaphorePermit, Semaphore};
+
+use crate::blob_store::BlobStore;
+use crate::constraints::Constraints;
+use crate::error::Failure;
+use crate::hash_pins::HashPins;
+use crate::metrics::Metrics;
+use crate::overrides::Overrides;
+use crate::prefetch_hints::PrefetchHints;
+use crate::python_pkg::{Dependency, Metadata, PackageName, Target, WheelFilename};
+use crate::signing::{RequestSigner, Signing};
+use crate::simple_repo_api::{self, ApiVersion, Project, ProjectStatus, Status};
+use crate::suggestions::NameSuggester;
+use crate::transport::{HttpTransport, Transported};
+use crate::warnings::{self, Warning};
+use crate::wheel::ZipCell;
+
+/// Picks one of multiple indexes offering a project, by position. `None` picks the first.
+pub type IndexChooser = dyn Fn(&PackageName, &[IndexMatch]) -> Option<usize> + Send + Sync;
+
+/// A project as offered by one index
+#[derive(Debug)]
+pub struct IndexMatch {
+    pub index: Url,
+    pub project: Project,
+}
+
+/// Shared handle for talking to package indexes. Cheap to clone.
+#[derive(Clone)]
+pub struct LazyZipClient {
+    http: ClientWithMiddleware,
+    indexes: Arc<[Url]>,
+    chooser: Option<Arc<IndexChooser>>,
+    /// Indexes picked for projects offered by multiple indexes,
+    /// so the chooser is only asked once per project, even by concurrent lookups
+    chosen: Arc<Mutex<HashMap<PackageName, ChosenCell>>>,
+    /// Indexes pinned for projects, which are only looked up there
+    pinned: Arc<HashMap<PackageName, Url>>,
+    /// Projects fetched during this run, by index and name, so concurrent lookups share one request
+    projects: Arc<Mutex<HashMap<(Url, PackageName), ProjectCell>>>,
+    /// Remote zips currently open, by URL, so tasks reading the same file share it
+    zips: Arc<Mutex<HashMap<Url, Weak<ZipCell>>>>,
+    hash_pins: Option<Arc<Mutex<HashPins>>>,
+    /// Limits of concurrent connections per host
+    host_limits: Option<Arc<HostLimits>>,
+    /// Concurrent range requests to fetch a large entry with
+    parallel_ranges: usize,
+    /// URL prefixes of files and their replacements
+    url_rewrites: Arc<[(String, String)]>,
+    /// Fall back to `.egg` and `.zip` files for projects without wheels
+    legacy_formats: bool,
+    /// Merge the files of projects offered by multiple indexes instead of choosing one index
+    merge_indexes: bool,
+    /// What to do about projects the index quarantined
+    quarantined: Quarantined,
+    /// Tie-breaking between wheels of the same version; `None` keeps the index’s order
+    wheel_preference: Option<WheelPreference>,
+    /// Only wheels installable on this are chosen
+    target: Option<Target>,
+    /// Minimum simple API version to accept from indexes
+    api_version: Option<ApiVersion>,
+    /// Ignore files uploaded after this time
+    exclude_newer: Option<jiff::Timestamp>,
+    /// Suggests similar names for projects that weren’t found
+    suggester: Option<Arc<NameSuggester>>,
+    /// Patches of metadata used for dependency analysis
+    overrides: Option<Arc<Overrides>>,
+    /// Caps on the versions chosen for packages
+    constraints: Arc<Constraints>,
+    /// Store of fully read zip entries, to reuse them across packages and runs
+    blob_store: Option<Arc<BlobStore>>,
+    /// How much of the end of packages’ wheels to fetch at first, updated while reading them
+    prefetch_hints: Option<Arc<Mutex<PrefetchHints>>>,
+    metrics: Option<Metrics>,
+    #[cfg(feature = "tuf")]
+    tuf: Option<Arc<TufTargets>>,
+}
+
+/// What to do about projects an index marks as quarantined, e.g. for containing malware
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Quarantined {
+    /// Use whatever files are still offered, and report the status as a warning
+    #[default]
+    Warn,
+    /// Fail looking the project up
+    Fail,
+}
+
+/// Which wheel to choose when the newest version has several
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WheelPreference {
+    /// Wheels for any platform, like `py3-none-any`
+    Pure,
+    /// Wheels built for a specific platform, e.g. with compiled extensions
+    Platform,
+    /// The smallest wheel the index lists a size for (PEP 700), regardless of platform.
+    /// Fetches the fewest bytes when only metadata is read, which is the same in all of a version’s wheels
+    Smallest,
+}
+
+/// A project’s page on one index, fetched once. `None` if the index doesn’t have it.
+type ProjectCell = Arc<OnceCell<Option<Project>>>;
+
+/// The index and project picked for a name, set by the first lookup that finds several
+type ChosenCell = Arc<OnceCell<Choice>>;
+
+/// A project picked among several offered for a name, which is a near miss if it’s not that name
+#[derive(Debug, Clone)]
+struct Choice {
+    index: Url,
+    name: PackageName,
+}
+
+/// Semaphores limiting concurrent connections, by host
+#[derive(Debug)]
+struct HostLimits {
+    max: usize,
+    semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+/// TUF metadata and the base URL its target paths are relative to
+#[cfg(feature = "tuf")]
+#[derive(Debug)]
+struct TufTargets {
+    repo: crate::tuf::TufRepository,
+    base: Url,
+}
+
+#[cfg(feature = "tuf")]
+impl TufTargets {
+    /// Fetch a project’s HTML page and check it against TUF metadata before using it.
+    /// Pages are signed as `index.html` in their directory relative to the index’s parent,
+    /// e.g. `simple/foo/index.html` for `https://pypi.org/simple/` (PEP 458).
+    async fn fetch_project(
+        &self,
+        http: &ClientWithMiddleware,
+        index: &Url,
+        name: &PackageName,
+        required: Option<ApiVersion>,
+    ) -> Result<Option<Project>> {
+        use eyre::WrapErr as _;
+
+        let Some(response) =
+            simple_repo_api::get_project_page(http, index, name, simple_repo_api::ACCEPT_HTML)
+                .await?
+        else {
+            return Ok(None);
+        };
+        let page = response.url().clone();
+        let base = index.join("..")?;
+        let Some(dir) = page.as_str().strip_prefix(base.as_str()) else {
+            bail!("{page} is outside of {base}, which TUF target paths of pages are relative to");
+        };
+        let path = format!("{}/index.html", dir.trim_end_matches('/'));
+        let html = response.text().await?;
+        self.repo
+            .check_target(&path, html.as_bytes())
+            .await
+            .wrap_err_with(|| Failure::index(format!("Unverified page for {name} on {index}")))?;
+        tracing::debug!(%name, path, "verified page against TUF metadata");
+        let project = simple_repo_api::parse_project_html(&html, &page, name, required)
+            .wrap_err_with(|| Failure::index(format!("Unusable page for {name} on {index}")))?;
+        Ok(Some(project))
+    }
+}
+
+impl LazyZipClient {
+    /// Create a client querying `indexes` in order of preference.
+    /// Index URLs get a trailing slash if they lack one.
+    pub fn new(
+        http: impl Into<ClientWithMiddleware>,
+        indexes: impl IntoIterator<Item = Url>,
+    ) -> Self {
+        let indexes = indexes.into_iter().map(with_trailing_slash).collect();
+        Self {
+            http: http.into(),
+            indexes,
+            chooser: None,
+            chosen: Arc::default(),
+            pinned: Arc::default(),
+            projects: Arc::default(),
+            zips: Arc::default(),
+            hash_pins: None,
+            host_limits: None,
+            parallel_ranges: 1,
+            url_rewrites: Arc::new([]),
+            legacy_formats: false,
+            merge_indexes: false,
+            quarantined: Quarantined::default(),
+            wheel_preference: None,
+            target: None,
+            api_version: None,
+            exclude_newer: None,
+            suggester: None,
+            overrides: None,
+            constraints: Arc::default(),
+            blob_store: None,
+            prefetch_hints: None,
+            metrics: None,
+            #[cfg(feature = "tuf")]
+            tuf: None,
+        }
+    }
+
+    /// Decide which index to use when a project is offered by multiple ones,
+    /// instead of picking the first. It’s asked once per project, while concurrent lookups wait for it.
+    ///
+    /// Projects are matched by normalized name, so `Foo_Bar` on one index and `foo-bar` on another are offered as one.
+    /// With [name suggestions](Self::with_name_suggestions), near misses like `foobar` on indexes without `foo-bar`
+    /// are offered too, as they’re easily confused with it.
+    #[must_use]
+    pub fn with_index_chooser(
+        mut self,
+        chooser: impl Fn(&PackageName, &[IndexMatch]) -> Option<usize> + Send + Sync + 'static,
+    ) -> Self {
+        self.chooser = Some(Arc::new(chooser));
+        self
+    }
+
+    /// Only look `name` up on `index`, which doesn’t need to be one of the client’s indexes
+    #[must_use]
+    pub fn with_pinned_index(mut self, name: PackageName, index: Url) -> Self {
+        Arc::make_mut(&mut self.pinned).insert(name, with_trailing_slash(index));
+        self
+    }
+
+    /// Pin the hashes of files on first use, and warn if they change later
+    #[must_use]
+    pub fn with_hash_pins(mut self, hash_pins: HashPins) -> Self {
+        self.hash_pins = Some(Arc::new(Mutex::new(hash_pins)));
+        self
+    }
+
+    /// Limit concurrent connections per host, e.g. to go easy on public indexes.
+    /// Lazily read files count as one connection while they’re open.
+    #[must_use]
+    pub fn with_max_connections_per_host(mut self, max: usize) -> Self {
+        self.host_limits = Some(Arc::new(HostLimits {
+            max,
+            semaphores: Mutex::default(),
+        }));
+        self
+    }
+
+    /// Fetch large entries that are read fully, like with [`SharedZipReader::prefetch_entry`](crate::wheel::SharedZipReader::prefetch_entry),
+    /// in up to `parts` parallel range requests.
+    /// They count as one connection, plus as many more as the host limit allows at the time.
+    #[must_use]
+    pub fn with_parallel_ranges(mut self, parts: usize) -> Self {
+        self.parallel_ranges = parts;
+        self
+    }
+
+    pub(crate) fn parallel_ranges(&self) -> usize {
+        self.parallel_ranges
+    }
+
+    /// Wait until another connection to `url`’s host is allowed, if connections are limited.
+    /// The connection counts until the permit is dropped.
+    pub async fn host_permit(&self, url: &Url) -> Option<OwnedSemaphorePermit> {
+        // Semaphores are never closed
+        self.host_semaphore(url)?.acquire_owned().await.ok()
+    }
+
+    /// Up to `max` more connections to `url`’s host, as many as are allowed without waiting.
+    /// `None` if connections aren’t limited.
+    pub(crate) fn try_host_permits(
+        &self,
+        url: &Url,
+        max: usize,
+    ) -> Option<Vec<OwnedSemaphorePermit>> {
+        let semaphore = self.host_semaphore(url)?;
+        Some(
+            std::iter::from_fn(|| semaphore.clone().try_acquire_owned().ok())
+                .take(max)
+                .collect(),
+        )
+    }
+
+    fn host_semaphore(&self, url: &Url) -> Option<Arc<Semaphore>> {
+        let limits = self.host_limits.as_ref()?;
+        let semaphore = limits
+            .semaphores
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(url.host_str().unwrap_or_default().to_owned())
+            .or_insert_with(|| Arc::new(Semaphore::new(limits.max)))
+            .clone();
+        Some(semaphore)
+    }
+
+    /// Fetch a project from one index, waiting for a connection permit.
+    /// Its files are marked as coming from `index`.
+    /// Results are cached, failures are retried on the next call.
+    async fn fetch_project(&self, index: &Url, name: &PackageName) -> Result<Option<Project>> {
+        let cell = self
+            .projects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry((index.clone(), name.clone()))
+            .or_default()
+            .clone();
+        let project = cell
+            .get_or_try_init(|| async {
+                let _permit = self.host_permit(index).await;
+                self.load_project(index, name).await
+            })
+            .await?;
+        Ok(project.clone())
+    }
+
+    /// Fetch a project from one index, bypassing the cache and connection limits
+    async fn load_project(&self, index: &Url, name: &PackageName) -> Result<Option<Project>> {
+        #[cfg(feature = "tuf")]
+        let mut project = match &self.tuf {
+            Some(tuf) => {
+                tuf.fetch_project(&self.http, index, name, self.api_version)
+                    .await?
+            }
+            None => {
+                simple_repo_api::fetch_project(&self.http, index, name, self.api_version).await?
+            }
+        };
+        #[cfg(not(feature = "tuf"))]
+        let mut project =
+            simple_repo_api::fetch_project(&self.http, index, name, self.api_version).await?;
+        for file in project.iter_mut().flat_map(|p| &mut p.files) {
+            file.index = Some(index.clone());
+        }
+        if let (Some(project), Some(cutoff)) = (&mut project, self.exclude_newer) {
+            exclude_newer(project, cutoff);
+        }
+        Ok(project)
+    }
+
+    /// Drop the cached pages of a project, so the next lookup fetches it again
+    pub(crate) fn forget_project(&self, name: &PackageName) {
+        self.projects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|(_, cached), _| cached != name);
+    }
+
+    /// A fresh URL for a wheel listed by an index, fetching its project page again,
+    /// e.g. because the listed URL was pre-signed and expired. Other URLs are returned as they are.
+    ///
+    /// This is called while reading `url`, whose reader holds a connection permit for `url`’s host.
+    /// Registries often serve pages and files from the same host, so pages there are fetched under that permit
+    /// instead of waiting for another one, which might never be free.
+    pub(crate) async fn refresh_file_url(&self, url: &Url) -> Result<Url> {
+        let Some(filename) = url
+            .path_segments()
+            .and_then(Iterator::last)
+            .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+        else {
+            return Ok(url.clone());
+        };
+        let Ok(whl) = WheelFilename::from_str(&filename) else {
+            return Ok(url.clone());
+        };
+        let indexes = self
+            .projects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys()
+            .filter(|(_, name)| *name == whl.name)
+            .map(|(index, _)| index.clone())
+            .collect::<Vec<_>>();
+        self.forget_project(&whl.name);
+        for index in indexes {
+            let _permit = if index.host_str() == url.host_str() {
+                None
+            } else {
+                self.host_permit(&index).await
+            };
+            let project = self.load_project(&index, &whl.name).await?;
+            self.projects
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(
+                    (index.clone(), whl.name.clone()),
+                    Arc::new(OnceCell::new_with(Some(project.clone()))),
+                );
+            let file = project
+                .and_then(|project| project.files.into_iter().find(|f| f.filename == filename));
+            if let Some(file) = file {
+                return self.rewrite_url(file.url);
+            }
+        }
+        Ok(url.clone())
+    }
+
+    /// The zip for a remote file, shared with other tasks while any of them has it open
+    pub(crate) fn zip_cell(&self, url: &Url) -> Arc<ZipCell> {
+        let mut zips = self.zips.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(cell) = zips.get(url).and_then(Weak::upgrade) {
+            return cell;
+        }
+        zips.retain(|_, cell| cell.strong_count() > 0);
+        let cell = Arc::default();
+        zips.insert(url.clone(), Arc::downgrade(&cell));
+        cell
+    }
+
+    /// Fetch files starting with `from` from `to` instead, e.g. from a caching proxy.
+    /// The first matching rewrite is applied.
+    #[must_use]
+    pub fn with_url_rewrite(mut self, from: &Url, to: &Url) -> Self {
+        self.url_rewrites = self
+            .url_rewrites
+            .iter()
+            .cloned()
+            .chain([(from.to_string(), to.to_string())])
+            .collect();
+        self
+    }
+
+    /// Apply the first matching URL rewrite to a file URL
+    pub fn rewrite_url(&self, url: Url) -> Result<Url> {
+        let Some((from, to)) = self
+            .url_rewrites
+            .iter()
+            .find(|(from, _)| url.as_str().starts_with(from.as_str()))
+        else {
+            return Ok(url);
+        };
+        let rewritten = format!("{to}{}", &url.as_str()[from.len()..]);
+        tracing::debug!(%url, rewritten, "rewrote URL");
+        Ok(Url::parse(&rewritten)?)
+    }
+
+    /// Merge the files of projects offered by multiple indexes, instead of choosing one index.
+    /// Files with the same name are taken from the index preferred first.
+    #[must_use]
+    pub fn with_merged_indexes(mut self) -> Self {
+        self.merge_indexes = true;
+        self
+    }
+
+    /// Use `.egg` and `.zip` files for projects or versions without wheels,
+    /// reading their `PKG-INFO` and `requires.txt` instead of `METADATA`
+    #[must_use]
+    pub fn with_legacy_formats(mut self) -> Self {
+        self.legacy_formats = true;
+        self
+    }
+
+    pub fn legacy_formats(&self) -> bool {
+        self.legacy_formats
+    }
+
+    /// Choose what to do about quarantined projects, instead of warning
+    #[must_use]
+    pub fn with_quarantined(mut self, quarantined: Quarantined) -> Self {
+        self.quarantined = quarantined;
+        self
+    }
+
+    /// Prefer pure-Python, platform or the smallest wheels among the newest version’s wheels,
+    /// instead of the last one the index lists
+    #[must_use]
+    pub fn with_wheel_preference(mut self, preference: WheelPreference) -> Self {
+        self.wheel_preference = Some(preference);
+        self
+    }
+
+    pub fn wheel_preference(&self) -> Option<WheelPreference> {
+        self.wheel_preference
+    }
+
+    /// Only choose wheels installable on `target`, e.g. a Python version and platform to inspect wheels for
+    #[must_use]
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    pub fn target(&self) -> Option<&Target> {
+        self.target.as_ref()
+    }
+
+    /// Reject index pages older than `api_version`, e.g. ones lacking file sizes
+    #[must_use]
+    pub fn with_api_version(mut self, api_version: ApiVersion) -> Self {
+        self.api_version = Some(api_version);
+        self
+    }
+
+    /// Ignore files uploaded after `time`, and ones without an upload time (PEP 700),
+    /// e.g. to reproduce an old resolution
+    #[must_use]
+    pub fn with_exclude_newer(mut self, time: jiff::Timestamp) -> Self {
+        self.exclude_newer = Some(time);
+        self
+    }
+
+    /// Suggest existing names in errors about projects that weren’t found,
+    /// from the root listings of the indexes
+    #[must_use]
+    pub fn with_name_suggestions(mut self, suggester: NameSuggester) -> Self {
+        self.suggester = Some(Arc::new(suggester));
+        self
+    }
+
+    /// Patch the requirements and `Requires-Python` of packages when analyzing dependencies
+    #[must_use]
+    pub fn with_overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = Some(Arc::new(overrides));
+        self
+    }
+
+    pub fn overrides(&self) -> Option<&Overrides> {
+        self.overrides.as_deref()
+    }
+
+    /// Only choose releases of packages allowed by `constraints`
+    #[must_use]
+    pub fn with_constraints(mut self, constraints: Constraints) -> Self {
+        self.constraints = Arc::new(constraints);
+        self
+    }
+
+    pub fn constraints(&self) -> &Constraints {
+        &self.constraints
+    }
+
+    /// Restrict a dependency to the versions allowed by the constraints
+    pub fn constrain(&self, dep: &Dependency) -> Dependency {
+        self.constraints.apply(dep)
+    }
+
+    /// Reuse zip entries read before from `blob_store`, and add newly read ones to it
+    #[must_use]
+    pub fn with_blob_store(mut self, blob_store: BlobStore) -> Self {
+        self.blob_store = Some(Arc::new(blob_store));
+        self
+    }
+
+    pub fn blob_store(&self) -> Option<&BlobStore> {
+        self.blob_store.as_deref()
+    }
+
+    /// Size the first request for wheels by how much of earlier versions’ ends was read,
+    /// and record that for wheels read now
+    #[must_use]
+    pub fn with_prefetch_hints(mut self, prefetch_hints: PrefetchHints) -> Self {
+        self.prefetch_hints = Some(Arc::new(Mutex::new(prefetch_hints)));
+        self
+    }
+
+    pub(crate) fn prefetch_hints(&self) -> Option<&Arc<Mutex<PrefetchHints>>> {
+        self.prefetch_hints.as_ref()
+    }
+
+    /// Save updated prefetch hints, if enabled
+    pub fn save_prefetch_hints(&self) -> Result<()> {
+        match &self.prefetch_hints {
+            Some(hints) => hints.lock().unwrap_or_else(PoisonError::into_inner).save(),
+            None => Ok(()),
+        }
+    }
+
+    /// Collect metrics of requests and cache lookups
+    #[must_use]
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics.clone());
+        self.with_middleware(metrics)
+    }
+
+    /// Count a lookup in a local cache, if collecting metrics
+    pub fn record_cache_lookup(&self, cache: &'static str, hit: bool) {
+        if let Some(metrics) = &self.metrics {
+            metrics.cache_lookup(cache, hit);
+        }
+    }
+
+    /// Apply the overrides to metadata read for dependency analysis, if any
+    pub fn apply_overrides(&self, metadata: &mut Metadata) {
+        if let Some(overrides) = &self.overrides {
+            overrides.apply(metadata);
+        }
+    }
+
+    /// Only use files from indexes that are signed in `repo`’s TUF metadata.
+    /// Target paths are the file URLs relative to `targets_url`.
+    #[cfg(feature = "tuf")]
+    #[must_use]
+    pub fn with_tuf(mut self, repo: crate::tuf::TufRepository, mut targets_url: Url) -> Self {
+        if !targets_url.path().ends_with('/') {
+            targets_url.set_path(&format!("{}/", targets_url.path()));
+        }
+        self.tuf = Some(Arc::new(TufTargets {
+            repo,
+            base: targets_url,
+        }));
+        self
+    }
+
+    /// Check a file the index listed against TUF metadata, if configured.
+    /// Its hashes are replaced by the signed ones, so they’re used for verifying downloads.
+    #[cfg(feature = "tuf")]
+    pub async fn verify_tuf_target(
+        &self,
+        mut file: simple_repo_api::File,
+    ) -> Result<simple_repo_api::File> {
+        let Some(tuf) = &self.tuf else {
+            return Ok(file);
+        };
+        let Some(path) = file.url.as_str().strip_prefix(tuf.base.as_str()) else {
+            bail!("{} is outside of the TUF targets at {}", file.url, tuf.base);
+        };
+        let Some(target) = tuf.repo.target(path).await? else {
+            bail!("{} is not signed in TUF metadata", file.filename);
+        };
+        let Some(signed) = target.hashes.get("sha256") else {
+            bail!("TUF metadata has no SHA-256 hash for {}", file.filename);
+        };
+        if let Some(listed) = file.hashes.get("sha256")
+            && !listed.eq_ignore_ascii_case(signed)
+        {
+            bail!(
+                "SHA-256 of {} listed by the index ({listed}) differs from the one in TUF metadata ({signed})",
+                file.filename,
+            );
+        }
+        tracing::debug!(filename = file.filename, "verified against TUF metadata");
+        file.hashes = target.hashes;
+        Ok(file)
+    }
+
+    /// Check a file’s hash against its pin, pinning it if it’s new
+    pub fn check_hash_pin(&self, filename: &str, sha256: &str) {
+        let Some(hash_pins) = &self.hash_pins else {
+            return;
+        };
+        let pinned = hash_pins
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .check(filename, sha256);
+        if let Some(pinned) = pinned {
+            warnings::warn(Warning::PinnedHashChanged {
+                filename: filename.to_owned(),
+                pinned,
+                actual: sha256.to_owned(),
+            });
+        }
+    }
+
+    /// Save newly pinned hashes, if pinning is enabled
+    pub fn save_hash_pins(&self) -> Result<()> {
+        match &self.hash_pins {
+            Some(hash_pins) => hash_pins
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .save(),
+            None => Ok(()),
+        }
+    }
+
+    /// Add middleware to the HTTP client, e.g. to observe or intercept requests.
+    /// It runs after previously added middleware, i.e. closer to the network.
+    #[must_use]
+    pub fn with_middleware(mut self, middleware: impl Middleware) -> Self {
+        self.http = reqwest_middleware::ClientBuilder::from_client(self.http)
+            .with(middleware)
+            .build();
+        self
+    }
+
+    /// Sign every request right before it’s sent, e.g. for gateways requiring signed headers.
+    /// Add it after other middleware, so it signs requests as they’re finally sent
+    #[must_use]
+    pub fn with_signer(self, signer: impl RequestSigner) -> Self {
+        self.with_middleware(Signing(signer))
+    }
+
+    /// Answer all requests with `transport` instead of the network.
+    /// Middleware added before it still runs, middleware added after it never does.
+    #[must_use]
+    pub fn with_transport(self, transport: impl HttpTransport) -> Self {
+        self.with_middleware(Transported(transport))
+    }
+
+    pub fn http(&self) -> &ClientWithMiddleware {
+        &self.http
+    }
+
+    pub fn indexes(&self) -> &[Url] {
+        &self.indexes
+    }
+
+    /// Fetch a project from the indexes offering it.
+    /// If there are multiple, their files are merged or the chooser is asked.
+    /// Projects that aren’t active are reported, and quarantined ones rejected if so configured.
+    #[tracing::instrument(skip(self))]
+    pub async fn find_project(&self, name: &PackageName) -> Result<Project> {
+        let project = self.choose_project(name).await?;
+        let ProjectStatus { status, reason } = &project.project_status;
+        if *status == Status::Quarantined && self.quarantined == Quarantined::Fail {
+            match reason {
+                Some(reason) => bail!("Project {name} is quarantined: {reason}"),
+                None => bail!("Project {name} is quarantined"),
+            }
+        }
+        if *status != Status::Active {
+            warnings::warn(Warning::ProjectStatus {
+                project: name.to_string(),
+                status: *status,
+                reason: reason.clone(),
+            });
+        }
+        Ok(project)
+    }
+
+    async fn choose_project(&self, name: &PackageName) -> Result<Project> {
+        let pinned = self.pinned.get(name).map(|index| Choice {
+            index: index.clone(),
+            name: name.clone(),
+        });
+        let chosen = pinned.or_else(|| {
+            self.chosen
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(name)
+                .and_then(|cell| cell.get().cloned())
+        });
+        if let Some(Choice { index, name }) = chosen {
+            if let Some(project) = self.fetch_project(&index, &name).await? {
+                return Ok(project);
+            }
+            let hint = self.suggestions(std::slice::from_ref(&index), &name).await;
+            bail!(Failure::not_found(format!(
+                "Project {name} not found on {index}{hint}"
+            )));
+        }
+        let found = try_join_all(self.indexes.iter().map(|index| async move {
+            let project = self.fetch_project(index, name).await?;
+            Ok::<_, eyre::Error>(project.map(|project| IndexMatch {
+                index: index.clone(),
+                project,
+            }))
+        }))
+        .await?;
+        let mut matches = found.into_iter().flatten().collect::<Vec<_>>();
+        match matches.len() {
+            0 => {
+                let hint = self.suggestions(&self.indexes, name).await;
+                bail!(Failure::not_found(format!(
+                    "Project {name} not found on any index{hint}"
+                )))
+            }
+            1 => {}
+            _ if self.merge_indexes => return Ok(merge_projects(matches)),
+            _ => {}
+        }
+        if matches.len() == 1 && (self.chooser.is_none() || self.merge_indexes) {
+            return Ok(matches.swap_remove(0).project);
+        }
+        // Root listings and prompts take a lot of state, so keep them out of the common path’s future
+        Box::pin(self.pick(name, matches)).await
+    }
+
+    /// Pick one of the projects offered for `name`, asking the chooser once per name.
+    /// Projects on the other indexes that are easily confused with `name` are offered too,
+    /// as the user can tell them apart when asked anyway.
+    async fn pick(&self, name: &PackageName, mut matches: Vec<IndexMatch>) -> Result<Project> {
+        let mut names = vec![name.clone(); matches.len()];
+        if self.chooser.is_some() && !self.merge_indexes {
+            let missing = self
+                .indexes
+                .iter()
+                .filter(|index| matches.iter().all(|m| m.index != **index))
+                .cloned()
+                .collect::<Vec<_>>();
+            for (near_miss, project) in self.near_misses(&missing, name).await? {
+                matches.push(project);
+                names.push(near_miss);
+            }
+        }
+        if matches.len() == 1 {
+            return Ok(matches.swap_remove(0).project);
+        }
+        let cell = self
+            .chosen
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(name.clone())
+            .or_default()
+            .clone();
+        let mut pending = Some(matches);
+        let choice = cell
+            .get_or_init(|| async {
+                let matches = pending.take().expect("matches are only taken once");
+                let (idx, matches) = self.ask_chooser(name, matches).await;
+                let choice = Choice {
+                    index: matches[idx].index.clone(),
+                    name: names[idx].clone(),
+                };
+                tracing::info!(%name, index = %choice.index, chosen = %choice.name, "project offered by multiple indexes");
+                pending = Some(matches);
+                choice
+            })
+            .await;
+        let mut matches = pending.expect("matches are given back");
+        let idx = matches
+            .iter()
+            .zip(&names)
+            .position(|(m, name)| m.index == choice.index && *name == choice.name)
+            .unwrap_or_default();
+        Ok(matches.swap_remove(idx).project)
+    }
+
+    /// The index the chooser picks of `matches`, or the first one.
+    /// The chooser might prompt, so it runs where blocking doesn’t hold up other requests.
+    async fn ask_chooser(
+        &self,
+        name: &PackageName,
+        matches: Vec<IndexMatch>,
+    ) -> (usize, Vec<IndexMatch>) {
+        let Some(choose) = self.chooser.clone() else {
+            return (0, matches);
+        };
+        let name = name.clone();
+        let len = matches.len();
+        let asked = tokio::task::spawn_blocking(move || (choose(&name, &matches), matches)).await;
+        match asked {
+            Ok((idx, matches)) => (idx.filter(|idx| *idx < len).unwrap_or_default(), matches),
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+
+    /// Projects on `indexes` whose names are easily confused with `name`, if name suggestions are enabled
+    async fn near_misses(
+        &self,
+        indexes: &[Url],
+        name: &PackageName,
+    ) -> Result<Vec<(PackageName, IndexMatch)>> {
+        let Some(suggester) = &self.suggester else {
+            return Ok(Vec::new());
+        };
+        let mut near_misses = Vec::new();
+        for index in indexes {
+            for near_miss in suggester.near_misses(&self.http, index, name).await {
+                let Ok(near_miss) = near_miss.parse::<PackageName>() else {
+                    continue;
+                };
+                if let Some(project) = self.fetch_project(index, &near_miss).await? {
+                    let index = index.clone();
+                    near_misses.push((near_miss, IndexMatch { index, project }));
+                }
+            }
+        }
+        Ok(near_misses)
+    }
+}
+
+impl LazyZipClient {
+    /// `, did you mean …?` with names similar to `name` on `indexes`, if enabled and there are any
+    async fn suggestions(&self, indexes: &[Url], name: &PackageName) -> String {
+        let Some(suggester) = &self.suggester else {
+            return String::new();
+        };
+        let names = suggester.suggest(&self.http, indexes, name).await;
+        if names.is_empty() {
+            return String::new();
+        }
+        format!(", did you mean {}?", names.join(" or "))
+    }
+}
+
+/// Drop files uploaded after `cutoff` or at an unknown time
+fn exclude_newer(project: &mut Project, cutoff: jiff::Timestamp) {
+    let total = project.files.len();
+    let mut unknown = 0;
+    project.files.retain(|file| {
+        if let Some(time) = file.upload_time {
+            return time <= cutoff;
+        }
+        unknown += 1;
+        false
+    });
+    if unknown > 0 {
+        tracing::warn!(
+            project = project.name,
+            "Ignoring {unknown} of {total} files without upload time, the index doesn’t support PEP 700"
+        );
+    }
+}
+
+fn with_trailing_slash(mut url: Url) -> Url {
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    url
+}
+
+/// Merge the files of a project offered by multiple indexes, in order of preference.
+/// Of files with the same name, the first one is kept, warning if others have different hashes.
+fn merge_projects(matches: Vec<IndexMatch>) -> Project {
+    let mut matches = matches.into_iter();
+    let mut merged = matches.next().expect("at least one match").project;
+    for IndexMatch { index, project } in matches {
+        for file in project.files {
+            let Some(existing) = merged.files.iter().find(|f| f.filename == file.filename) else {
+                merged.files.push(file);
+                continue;
+            };
+            let differs = file.hashes.iter().any(|(name, hash)| {
+                existing
+                    .hashes
+                    .get(name)
+                    .is_some_and(|h| !h.eq_ignore_ascii_case(hash))
+            });
+            if differs && let Some(preferred) = &existing.index {
+                warnings::warn(Warning::IndexConflict {
+                    filename: file.filename,
+                    preferred: preferred.clone(),
+                    ignored: index.clone(),
+                });
+            }
+        }
+    }
+    merged
+}
+
+impl Default for LazyZipClient {
+    fn default() -> Self {
+        Self::new(
+            reqwest::Client::new(),
+            [Url::parse(simple_repo_api::PYPI).expect("valid URL")],
+        )
+    }
+}
+
+impl fmt::Debug for LazyZipClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyZipClient")
+            .field("indexes", &self.indexes)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr as _;
+
+    use super::*;
+    use crate::bench::RequestStats;
+    use crate::test_util::{MockIndex, MockWheel};
+
+    #[tokio::test]
+    async fn test_project_status() {
+        let index = MockIndex::start([
+            MockWheel::new("foo", "1.0").project_status("quarantined"),
+            MockWheel::new("bar", "1.0").project_status("archived"),
+            MockWheel::new("baz", "1.0").project_status("someday-maybe"),
+        ])
+        .await
+        .unwrap();
+        let name = |n: &str| PackageName::from_str(n).unwrap();
+        let client = index.client();
+        let (result, warnings) = warnings::collect(client.find_project(&name("foo"))).await;
+        assert!(result.is_ok());
+        assert_eq!(
+            warnings,
+            [Warning::ProjectStatus {
+                project: "foo".to_owned(),
+                status: Status::Quarantined,
+                reason: Some("test".to_owned()),
+            }]
+        );
+        let (_, warnings) = warnings::collect(client.find_project(&name("baz"))).await;
+        assert_eq!(warnings, []);
+
+        let client = client.with_quarantined(Quarantined::Fail);
+        let err = client.find_project(&name("foo")).await.unwrap_err();
+        assert_eq!(err.to_string(), "Project foo is quarantined: test");
+        assert!(client.find_project(&name("bar")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_pinned_index() {
+        let public = MockIndex::start([MockWheel::new("foo", "2.0")])
+            .await
+            .unwrap();
+        let internal = MockIndex::start([MockWheel::new("foo", "1.0")])
+            .await
+            .unwrap();
+        let foo = PackageName::from_str("foo").unwrap();
+        let index = internal.index_url();
+        let client = public.client().with_pinned_index(
+            foo.clone(),
+            index.as_str().trim_end_matches('/').parse().unwrap(),
+        );
+        let project = client.find_project(&foo).await.unwrap();
+        assert_eq!(project.files[0].filename, "foo-1.0-py3-none-any.whl");
+
+        let client = client.with_pinned_index(foo.clone(), index.join("../other/").unwrap());
+        let err = client.find_project(&foo).await.unwrap_err();
+        assert!(err.to_string().contains("not found on"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_exclude_newer() {
+        let index = MockIndex::start([
+            MockWheel::new("foo", "1.0").uploaded("2023-01-01T00:00:00Z"),
+            MockWheel::new("foo", "2.0").uploaded("2023-06-01T00:00:01Z"),
+            MockWheel::new("foo", "3.0"),
+        ])
+        .await
+        .unwrap();
+        let cutoff = "2023-06-01T00:00:00Z".parse().unwrap();
+        let client = index.client().with_exclude_newer(cutoff);
+        let project = client.find_project(&"foo".parse().unwrap()).await.unwrap();
+        let filenames = project.files.iter().map(|f| f.filename.as_str());
+        assert!(filenames.eq(["foo-1.0-py3-none-any.whl"]));
+    }
+
+    #[tokio::test]
+    async fn test_name_suggestions() {
+        let index = MockIndex::start([MockWheel::new("foo-bar", "1.0")])
+            .await
+            .unwrap();
+        let foobar = PackageName::from_str("foobar").unwrap();
+        let err = index.client().find_project(&foobar).await.unwrap_err();
+        assert_eq!(err.to_string(), "Project foobar not found on any index");
+
+        let client = index
+            .client()
+            .with_name_suggestions(NameSuggester::new(None));
+        let err = client.find_project(&foobar).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Project foobar not found on any index, did you mean foo-bar?"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_project_cache() {
+        let index = MockIndex::start([MockWheel::new("foo", "1.0")])
+            .await
+            .unwrap();
+        let stats = RequestStats::default();
+        let client = index.client().with_middleware(stats.clone());
+        let (foo, foo_upper) = ("foo".parse().unwrap(), "Foo".parse().unwrap());
+        let (a, b) = tokio::join!(client.find_project(&foo), client.find_project(&foo_upper));
+        assert_eq!(a.unwrap().files.len(), 1);
+        assert_eq!(b.unwrap().files.len(), 1);
+        assert_eq!(stats.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_index_chooser() {
+        let first = MockIndex::start([MockWheel::new("foo", "1.0")])
+            .await
+            .unwrap();
+        let second = MockIndex::start([MockWheel::new("Foo", "2.0")])
+            .await
+            .unwrap();
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let client = LazyZipClient::new(
+            reqwest::Client::new(),
+            [first.index_url(), second.index_url()],
+        )
+        .with_index_chooser({
+            let asked = asked.clone();
+            move |name, matches| {
+                asked
+                    .lock()
+                    .unwrap()
+                    .push((name.to_string(), matches.len()));
+                Some(1)
+            }
+        });
+        // Concurrent lookups, spelled differently, ask once and use the same index
+        let (foo, foo_upper) = ("foo".parse().unwrap(), "FOO".parse().unwrap());
+        let (a, b) = tokio::join!(client.find_project(&foo), client.find_project(&foo_upper));
+        assert_eq!(a.unwrap().files[0].filename, "foo-2.0-py3-none-any.whl");
+        assert_eq!(b.unwrap().files[0].filename, "foo-2.0-py3-none-any.whl");
+        assert_eq!(*asked.lock().unwrap(), [("foo".to_owned(), 2)]);
+    }
+
+    #[tokio::test]
+    async fn test_index_chooser_near_miss() {
+        let first = MockIndex::start([MockWheel::new("foo-bar", "1.0")])
+            .await
+            .unwrap();
+        let second = MockIndex::start([MockWheel::new("foobar", "2.0")])
+            .await
+            .unwrap();
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let client = LazyZipClient::new(
+            reqwest::Client::new(),
+            [first.index_url(), second.index_url()],
+        )
+        .with_name_suggestions(NameSuggester::new(None))
+        .with_index_chooser({
+            let asked = asked.clone();
+            move |_, matches| {
+                let names = matches.iter().map(|m| m.project.name.clone());
+                asked.lock().unwrap().push(names.collect::<Vec<_>>());
+                Some(1)
+            }
+        });
+        let foo_bar = "foo-bar".parse().unwrap();
+        for _ in 0..2 {
+            let project = client.find_project(&foo_bar).await.unwrap();
+            assert_eq!(project.files[0].filename, "foobar-2.0-py3-none-any.whl");
+        }
+        assert_eq!(*asked.lock().unwrap(), [["foo-bar", "foobar"]]);
+    }
+
+    fn index_match(index: &str, files: &[(&str, &str)]) -> IndexMatch {
+        let index = Url::parse(index).unwrap();
+        let files = files
+            .iter()
+            .map(|(filename, sha256)| {
+                serde_json::json!({
+                    "filename": filename,
+                    "url": index.join(filename).unwrap(),
+                    "hashes": {"sha256": sha256},
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut project: Project = serde_json::from_value(serde_json::json!({
+            "meta": {"api-version": "1.0"},
+            "name": "foo",
+            "files": files,
+        }))
+        .unwrap();
+        for file in &mut project.files {
+            file.index = Some(index.clone());
+        }
+        IndexMatch { index, project }
+    }
+
+    #[tokio::test]
+    async fn test_merge_projects() {
+        let internal = index_match(
+            "https://internal.example/simple/",
+            &[("foo-1.0.tar.gz", "aa")],
+        );
+        let public = index_match(
+            "https://pypi.example/simple/",
+            &[("foo-1.0.tar.gz", "bb"), ("foo-2.0.tar.gz", "cc")],
+        );
+        let (merged, warnings) =
+            warnings::collect(async { merge_projects(vec![internal, public]) }).await;
+        let provenance = merged
+            .files
+            .iter()
+            .map(|f| {
+                (
+                    f.filename.as_str(),
+                    f.index.as_ref().unwrap().host_str().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            provenance,
+            [
+                ("foo-1.0.tar.gz", "internal.example"),
+                ("foo-2.0.tar.gz", "pypi.example")
+            ]
+        );
+        assert_eq!(
+            warnings,
+            [Warning::IndexConflict {
+                filename: "foo-1.0.tar.gz".to_owned(),
+                preferred: Url::parse("https://internal.example/simple/").unwrap(),
+                ignored: Url::parse("https://pypi.example/simple/").unwrap(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! Configuring a [`LazyZipClient`] in one place, for library users.
+//!
+//! ```no_run
+//! # fn example() -> eyre::Result<()> {
+//! use std::time::Duration;
+//!
+//! use pypi_lazyzip::LazyZipClient;
+//! use pypi_lazyzip::auth::Credentials;
+//! use pypi_lazyzip::retry::RetryPolicy;
+//!
+//! let client = LazyZipClient::builder()
+//!     .index_urls(["https://pkgs.example.com/simple/".parse()?])
+//!     .credentials("https://pkgs.example.com/".parse()?, Credentials::Bearer("…".to_owned()))
+//!     .timeout(Duration::from_secs(30))
+//!     .retries(RetryPolicy::with_max_retries(5))
+//!     .max_connections_per_host(8)
+//!     .user_cache()
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use eyre::Result;
+use reqwest::Url;
+
+use crate::LazyZipClient;
+use crate::auth::{Authenticate, Credentials, take_url_credentials};
+use crate::blob_store::BlobStore;
+use crate::prefetch_hints::PrefetchHints;
+use crate::python_pkg::Target;
+use crate::registries::GithubAssets;
+use crate::retry::{Retry, RetryPolicy};
+use crate::simple_repo_api;
+
+/// Options for building a [`LazyZipClient`], created by [`LazyZipClient::builder`].
+/// Everything else can be configured on the built client with its `with_*` methods.
+#[derive(Debug)]
+pub struct ClientOptions {
+    index_urls: Vec<Url>,
+    http: reqwest::ClientBuilder,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    retry: Option<RetryPolicy>,
+    credentials: Vec<(Url, Credentials)>,
+    max_connections_per_host: Option<usize>,
+    blob_store: Option<BlobStore>,
+    prefetch_hints: Option<PrefetchHints>,
+    target: Option<Target>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            index_urls: vec![Url::parse(simple_repo_api::PYPI).expect("valid URL")],
+            http: reqwest::Client::builder(),
+            timeout: None,
+            connect_timeout: None,
+            retry: None,
+            credentials: Vec::new(),
+            max_connections_per_host: None,
+            blob_store: None,
+            prefetch_hints: None,
+            target: None,
+        }
+    }
+}
+
+impl LazyZipClient {
+    /// Configure a client querying `PyPI` unless other indexes are given
+    pub fn builder() -> ClientOptions {
+        ClientOptions::default()
+    }
+}
+
+impl ClientOptions {
+    /// Query these indexes in order of preference instead of `PyPI`.
+    /// Credentials in their URLs are used for every URL on the same host, but not shown in output.
+    #[must_use]
+    pub fn index_urls(mut self, index_urls: impl IntoIterator<Item = Url>) -> Self {
+        self.index_urls = index_urls.into_iter().collect();
+        self
+    }
+
+    /// Start from this HTTP client configuration, e.g. with proxies or custom TLS roots
+    #[must_use]
+    pub fn http(mut self, http: reqwest::ClientBuilder) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Give up on requests that take longer than this in total
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Give up on connecting after this long
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Retry failed connections, timeouts and `429` and `5xx` responses
+    #[must_use]
+    pub fn retries(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Authenticate requests to URLs starting with `prefix`, e.g. an index’s base URL.
+    /// These take precedence over credentials in index URLs.
+    #[must_use]
+    pub fn credentials(mut self, prefix: Url, credentials: Credentials) -> Self {
+        self.credentials.push((prefix, credentials));
+        self
+    }
+
+    /// Limit concurrent connections per host, e.g. to go easy on public indexes
+    #[must_use]
+    pub fn max_connections_per_host(mut self, max: usize) -> Self {
+        self.max_connections_per_host = Some(max);
+        self
+    }
+
+    /// Reuse fully read entries like `METADATA` from `blob_store`, and store them there
+    #[must_use]
+    pub fn blob_store(mut self, blob_store: BlobStore) -> Self {
+        self.blob_store = Some(blob_store);
+        self
+    }
+
+    /// Size the first request for wheels by what was read from earlier versions
+    #[must_use]
+    pub fn prefetch_hints(mut self, prefetch_hints: PrefetchHints) -> Self {
+        self.prefetch_hints = Some(prefetch_hints);
+        self
+    }
+
+    /// Use the blob store and prefetch hints in the user’s cache directory, if there is one
+    #[must_use]
+    pub fn user_cache(mut self) -> Self {
+        self.blob_store = BlobStore::in_user_cache().or(self.blob_store);
+        self.prefetch_hints = PrefetchHints::in_user_cache().or(self.prefetch_hints);
+        self
+    }
+
+    /// Only choose wheels installable on `target`
+    #[must_use]
+    pub fn target(mut self, target: Target) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    pub fn build(self) -> Result<LazyZipClient> {
+        let mut http = self.http;
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            http = http.connect_timeout(timeout);
+        }
+        if let Some(max) = self.max_connections_per_host {
+            http = http.pool_max_idle_per_host(max);
+        }
+        let mut index_urls = self.index_urls;
+        let mut credentials = index_urls
+            .iter_mut()
+            .filter_map(take_url_credentials)
+            .collect::<Vec<_>>();
+        // Of equally long prefixes, the last wins
+        credentials.extend(self.credentials);
+        let mut http = reqwest_middleware::ClientBuilder::new(http.build()?);
+        // Retries go first, so every attempt is authenticated and signed anew
+        if let Some(policy) = self.retry {
+            http = http.with(Retry(policy));
+        }
+        if !credentials.is_empty() {
+            http = http.with(Authenticate(credentials));
+        }
+        http = http.with(GithubAssets);
+        let mut client = LazyZipClient::new(http.build(), index_urls);
+        if let Some(max) = self.max_connections_per_host {
+            client = client.with_max_connections_per_host(max);
+        }
+        if let Some(blob_store) = self.blob_store {
+            client = client.with_blob_store(blob_store);
+        }
+        if let Some(prefetch_hints) = self.prefetch_hints {
+            client = client.with_prefetch_hints(prefetch_hints);
+        }
+        if let Some(target) = self.target {
+            client = client.with_target(target);
+        }
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockIndex, MockWheel};
+
+    #[tokio::test]
+    async fn test_build() {
+        let index = MockIndex::start([
+            MockWheel::new("foo", "1.0"),
+            MockWheel::new("foo", "1.1").tag("cp312-cp312-win_amd64"),
+        ])
+        .await
+        .unwrap();
+        let client = LazyZipClient::builder()
+            .index_urls([index.index_url()])
+            .timeout(Duration::from_secs(10))
+            .retries(RetryPolicy::with_max_retries(1))
+            .target(Target {
+                python: Some((3, 12)),
+                platform: Some("manylinux_2_28_x86_64".to_owned()),
+            })
+            .build()
+            .unwrap();
+        assert_eq!(client.indexes(), [index.index_url()]);
+        let whl = crate::wheel::find_wheel(&client, &"foo".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(whl.filename, "foo-1.0-py3-none-any.whl");
+    }
+}
@@ -0,0 +1,201 @@
+//! Finding requirements that can’t be satisfied together, for `check-compat`.
+//!
+//! Instead of resolving the whole dependency tree, this collects the direct requirements and
+//! those of the newest matching release of each, one level deep, and reports pairs of
+//! constraints on the same package whose version ranges don’t overlap.
+//! That’s cheap and catches most real conflicts, like one package needing `urllib3<2`
+//! and another `urllib3>=2`.
+
+use std::collections::BTreeMap;
+
+use eyre::Result;
+use pep440_rs::VersionSpecifiers;
+use pep508_rs::{MarkerTree, Requirement, VersionOrUrl};
+use serde::Serialize;
+use version_ranges::Ranges;
+
+use crate::python_pkg::{Dependency, PackageName};
+use crate::wheel::pkg_metadata_headers;
+use crate::{LazyZipClient, PkgLoc};
+
+const DIRECT: &str = "direct";
+const CONSTRAINTS: &str = "constraints";
+
+/// A requirement and where it comes from
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Constraint {
+    /// `direct`, `constraints` or the requiring release, like `botocore 1.29.0`
+    pub required_by: String,
+    pub requirement: String,
+    #[serde(skip)]
+    specifiers: VersionSpecifiers,
+    #[serde(skip)]
+    marker: MarkerTree,
+}
+
+impl Constraint {
+    /// A constraint from a requirement, if it restricts versions
+    fn new(required_by: &str, req: &Requirement, extras: &[pep508_rs::ExtraName]) -> Option<Self> {
+        let Some(VersionOrUrl::VersionSpecifier(specifiers)) = &req.version_or_url else {
+            return None;
+        };
+        Some(Self {
+            required_by: required_by.to_owned(),
+            requirement: req.to_string(),
+            specifiers: specifiers.clone(),
+            marker: req.marker.clone().simplify_extras(extras),
+        })
+    }
+
+    fn describe(&self) -> String {
+        if self.required_by == DIRECT {
+            format!("{} is required directly", self.requirement)
+        } else if self.required_by == CONSTRAINTS {
+            format!("{} is constrained", self.requirement)
+        } else {
+            format!("{} needs {}", self.required_by, self.requirement)
+        }
+    }
+
+    /// Whether both constraints can apply at the same time but allow no common version
+    fn conflicts_with(&self, other: &Self) -> bool {
+        let intersection = Ranges::from(self.specifiers.clone())
+            .intersection(&Ranges::from(other.specifiers.clone()));
+        intersection.is_empty() && !self.marker.is_disjoint(&other.marker)
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub package: String,
+    pub explanation: String,
+    pub constraints: [Constraint; 2],
+}
+
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub conflicts: Vec<Conflict>,
+    /// Direct requirements whose metadata couldn’t be read, with the error
+    pub unresolved: BTreeMap<String, String>,
+}
+
+/// Collect the constraints of `requirements`, their requirements and the client’s constraints,
+/// and find conflicts
+pub async fn check(client: &LazyZipClient, requirements: &[Requirement]) -> Report {
+    let mut report = Report::default();
+    let mut constraints = BTreeMap::<PackageName, Vec<Constraint>>::new();
+    for (name, version_spec) in client.constraints().iter() {
+        let req = format!("{name}{version_spec}");
+        constraints
+            .entry(name.clone())
+            .or_default()
+            .push(Constraint {
+                required_by: CONSTRAINTS.to_owned(),
+                requirement: req,
+                specifiers: version_spec.clone(),
+                marker: MarkerTree::TRUE,
+            });
+    }
+    let mut add = |name: &pep508_rs::PackageName, constraint| {
+        if let (Ok(name), Some(constraint)) = (name.as_ref().parse(), constraint) {
+            constraints.entry(name).or_default().push(constraint);
+        }
+    };
+    for req in requirements {
+        add(&req.name, Constraint::new(DIRECT, req, &req.extras));
+    }
+    let fetched = futures::future::join_all(
+        requirements
+            .iter()
+            .map(|req| async move { (req, requirements_of(client, req).await) }),
+    )
+    .await;
+    for (req, result) in fetched {
+        match result {
+            Ok((release, requires_dist)) => {
+                for dep in requires_dist
+                    .iter()
+                    .filter(|dep| dep.marker.evaluate_extras(&req.extras))
+                {
+                    add(&dep.name, Constraint::new(&release, dep, &req.extras));
+                }
+            }
+            Err(e) => {
+                report
+                    .unresolved
+                    .insert(req.name.to_string(), format!("{e:#}"));
+            }
+        }
+    }
+    for (package, constraints) in constraints {
+        for (i, a) in constraints.iter().enumerate() {
+            for b in constraints[i + 1..].iter().filter(|b| a.conflicts_with(b)) {
+                report.conflicts.push(Conflict {
+                    package: package.to_string(),
+                    explanation: format!("{}, but {}", a.describe(), b.describe()),
+                    constraints: [a.clone(), b.clone()],
+                });
+            }
+        }
+    }
+    report
+}
+
+/// The release of the newest wheel matching `req`, like `foo 1.0`, and its requirements
+async fn requirements_of(
+    client: &LazyZipClient,
+    req: &Requirement,
+) -> Result<(String, Vec<Requirement>)> {
+    let dep = Dependency::from_requirement(req)?;
+    let (name, mut metadata) = pkg_metadata_headers(client, PkgLoc::Dependency(dep)).await?;
+    client.apply_overrides(&mut metadata);
+    let release = match metadata.get("Version") {
+        Some(version) => format!("{name} {version}"),
+        None => name.to_string(),
+    };
+    Ok((release, metadata.requires_dist()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr as _;
+
+    use super::*;
+    use crate::test_util::{MockIndex, MockWheel};
+
+    #[tokio::test]
+    async fn test_check() {
+        let index = MockIndex::start([
+            MockWheel::new("a", "1.0").requires("urllib3<2"),
+            MockWheel::new("b", "1.0")
+                .requires("urllib3>=2")
+                .requires("six<1; sys_platform == 'win32'")
+                .requires("idna<1; extra == 'old'"),
+            MockWheel::new("urllib3", "2.0"),
+            MockWheel::new("six", "1.0"),
+            MockWheel::new("idna", "3.0"),
+        ])
+        .await
+        .unwrap();
+        let client = index.client();
+        let requirements = [
+            "a",
+            "b",
+            "six>=1; sys_platform != 'win32'",
+            "idna>=3",
+            "missing",
+        ]
+        .map(|r| Requirement::from_str(r).unwrap());
+        let report = check(&client, &requirements).await;
+        let explanations = report
+            .conflicts
+            .iter()
+            .map(|c| c.explanation.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            explanations,
+            ["a 1.0 needs urllib3<2, but b 1.0 needs urllib3>=2"]
+        );
+        assert_eq!(report.unresolved.keys().collect::<Vec<_>>(), ["missing"]);
+    }
+}
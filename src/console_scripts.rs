@@ -0,0 +1,163 @@
+//! Previewing the commands a set of packages would install, for `console-scripts`.
+//!
+//! Installers create a launcher on `PATH` for every entry in the `console_scripts` and `gui_scripts`
+//! groups of `entry_points.txt`, and the last package installed wins if two declare the same name.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::python_pkg::PackageName;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptKind {
+    Console,
+    Gui,
+}
+
+/// A command declared in `entry_points.txt`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Script {
+    pub name: String,
+    pub kind: ScriptKind,
+    /// The object the launcher calls, like `module:function`
+    pub target: String,
+}
+
+/// The scripts declared in an `entry_points.txt`, ignoring other entry point groups
+pub fn scripts(entry_points: &str) -> Vec<Script> {
+    let mut kind = None;
+    let mut scripts = Vec::new();
+    for line in entry_points.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(group) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            kind = match group.trim() {
+                "console_scripts" => Some(ScriptKind::Console),
+                "gui_scripts" => Some(ScriptKind::Gui),
+                _ => None,
+            };
+            continue;
+        }
+        if let Some(kind) = kind
+            && let Some((name, target)) = line.split_once('=')
+        {
+            // Drop the deprecated extras suffix like `[admin]`
+            let target = target.split_once('[').map_or(target, |(target, _)| target);
+            scripts.push(Script {
+                name: name.trim().to_owned(),
+                kind,
+                target: target.trim().to_owned(),
+            });
+        }
+    }
+    scripts
+}
+
+/// A package installing a command
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Provider {
+    pub package: String,
+    pub kind: ScriptKind,
+    pub target: String,
+}
+
+/// The commands a set of packages would install, and which of them more than one package provides
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct ConsoleScriptsReport {
+    /// Packages providing each command, by command name
+    pub commands: BTreeMap<String, Vec<Provider>>,
+    /// Commands provided by more than one package, which would overwrite each other
+    pub collisions: Vec<String>,
+}
+
+impl ConsoleScriptsReport {
+    pub fn new(packages: impl IntoIterator<Item = (PackageName, Vec<Script>)>) -> Self {
+        let mut commands = BTreeMap::<_, Vec<_>>::new();
+        for (package, scripts) in packages {
+            for script in scripts {
+                commands.entry(script.name).or_default().push(Provider {
+                    package: package.original().to_owned(),
+                    kind: script.kind,
+                    target: script.target,
+                });
+            }
+        }
+        for providers in commands.values_mut() {
+            providers.sort_by(|a, b| a.package.cmp(&b.package));
+        }
+        let collisions = commands
+            .iter()
+            .filter(|(_, providers)| {
+                providers
+                    .windows(2)
+                    .any(|pair| pair[0].package != pair[1].package)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        Self {
+            commands,
+            collisions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripts() {
+        let entry_points = "[console_scripts]\n\
+                            foo = foo.cli:main\n\
+                            foo-admin=foo.admin:run [admin]\n\
+                            \n\
+                            [gui_scripts]\n\
+                            ; a comment\n\
+                            foo-gui = foo.gui:main\n\
+                            \n\
+                            [pytest11]\n\
+                            foo = foo.plugin\n";
+        assert_eq!(
+            scripts(entry_points),
+            [
+                Script {
+                    name: "foo".to_owned(),
+                    kind: ScriptKind::Console,
+                    target: "foo.cli:main".to_owned(),
+                },
+                Script {
+                    name: "foo-admin".to_owned(),
+                    kind: ScriptKind::Console,
+                    target: "foo.admin:run".to_owned(),
+                },
+                Script {
+                    name: "foo-gui".to_owned(),
+                    kind: ScriptKind::Gui,
+                    target: "foo.gui:main".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let pkg = |name: &str, scripts_txt: &str| (name.parse().unwrap(), scripts(scripts_txt));
+        let report = ConsoleScriptsReport::new([
+            pkg(
+                "foo",
+                "[console_scripts]\nfoo = foo:main\nshared = foo:shared\n",
+            ),
+            pkg("bar", "[console_scripts]\nshared = bar:shared\n"),
+        ]);
+        assert_eq!(report.collisions, ["shared"]);
+        let packages = report.commands["shared"]
+            .iter()
+            .map(|p| p.package.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(packages, ["bar", "foo"]);
+        assert_eq!(report.commands["foo"].len(), 1);
+    }
+}
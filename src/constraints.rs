@@ -0,0 +1,85 @@
+//! pip-style constraints files, given with `-c constraints.txt`.
+//!
+//! Constraints cap the versions chosen for packages without adding them to the set of packages,
+//! like `urllib3<2` applying only where something requires `urllib3`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use eyre::Result;
+use pep440_rs::VersionSpecifiers;
+
+use crate::lockfile;
+use crate::python_pkg::{Dependency, PackageName};
+
+#[derive(Debug, Clone, Default)]
+pub struct Constraints {
+    by_name: BTreeMap<PackageName, VersionSpecifiers>,
+}
+
+impl Constraints {
+    /// Read a constraints file, in the format of a requirements file.
+    /// Constraints of the same package are combined. URL constraints aren’t supported and skipped.
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        for req in lockfile::read_requirements(path)? {
+            let dep = Dependency::from_requirement(&req)?;
+            let Some(version_spec) = dep.version_spec() else {
+                tracing::warn!("Skipping constraint without versions: {req}");
+                continue;
+            };
+            self.add(dep.name().clone(), version_spec);
+        }
+        Ok(())
+    }
+
+    /// Allow only `version_spec` for `name`, in addition to earlier constraints
+    pub fn add(&mut self, name: PackageName, version_spec: &VersionSpecifiers) {
+        let combined = self
+            .by_name
+            .remove(&name)
+            .into_iter()
+            .flat_map(IntoIterator::into_iter)
+            .chain(version_spec.iter().cloned())
+            .collect();
+        self.by_name.insert(name, combined);
+    }
+
+    /// The constrained packages and their allowed versions
+    pub fn iter(&self) -> impl Iterator<Item = (&PackageName, &VersionSpecifiers)> {
+        self.by_name.iter()
+    }
+
+    /// Restrict a dependency to the versions allowed by its constraint, if any
+    pub fn apply(&self, dep: &Dependency) -> Dependency {
+        match self.by_name.get(dep.name()) {
+            Some(version_spec) => dep.clone().constrain(version_spec),
+            None => dep.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr as _;
+
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let mut constraints = Constraints::default();
+        let urllib3 = PackageName::from_str("urllib3").unwrap();
+        constraints.add(urllib3.clone(), &VersionSpecifiers::from_str("<2").unwrap());
+        constraints.add(urllib3, &VersionSpecifiers::from_str("!=1.26.0").unwrap());
+
+        let dep = Dependency::from_str("urllib3>=1.25").unwrap();
+        let constrained = constraints.apply(&dep);
+        let version_spec = constrained.version_spec().unwrap();
+        assert!(version_spec.contains(&"1.26.1".parse().unwrap()));
+        assert!(!version_spec.contains(&"1.26.0".parse().unwrap()));
+        assert!(!version_spec.contains(&"2.0".parse().unwrap()));
+        assert!(!version_spec.contains(&"1.24".parse().unwrap()));
+
+        let dep = Dependency::from_str("requests").unwrap();
+        assert_eq!(constraints.apply(&dep), dep);
+    }
+}
@@ -0,0 +1,147 @@
+//! Digests of a wheel’s contents from its central directory, for `fingerprint` and `fingerprint-diff`.
+//!
+//! The digest covers entry names, uncompressed sizes and CRCs, sorted by name,
+//! so rebuilds with different timestamps, compression or entry order still match.
+//! `RECORD` and its signatures are left out, as they’re derived from the other entries
+//! and their line order differs between tools.
+
+use std::collections::BTreeMap;
+
+use eyre::Result;
+use serde::Serialize;
+use sha2::{Digest as _, Sha256};
+
+use crate::hashes::hex;
+use crate::python_pkg::PackageName;
+use crate::wheel::open_zip;
+use crate::{LazyZipClient, PkgLoc};
+
+/// What’s compared of an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryStamp {
+    pub size: u64,
+    pub crc32: u32,
+}
+
+/// A wheel’s entries and their digest
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ContentFingerprint {
+    /// `sha256:` and the hex digest
+    pub digest: String,
+    pub entries: usize,
+    #[serde(skip)]
+    pub stamps: BTreeMap<String, EntryStamp>,
+}
+
+impl ContentFingerprint {
+    /// Fingerprint entries given as name, uncompressed size and CRC-32
+    pub fn new<'a>(entries: impl IntoIterator<Item = (&'a str, u64, u32)>) -> Self {
+        let stamps = entries
+            .into_iter()
+            .filter(|(name, _, _)| !name.ends_with('/') && !is_record(name))
+            .map(|(name, size, crc32)| (name.to_owned(), EntryStamp { size, crc32 }))
+            .collect::<BTreeMap<_, _>>();
+        let mut hasher = Sha256::new();
+        for (name, stamp) in &stamps {
+            hasher.update(name.as_bytes());
+            hasher.update([0]);
+            hasher.update(stamp.size.to_le_bytes());
+            hasher.update(stamp.crc32.to_le_bytes());
+        }
+        Self {
+            digest: format!("sha256:{}", hex(&hasher.finalize())),
+            entries: stamps.len(),
+            stamps,
+        }
+    }
+}
+
+/// `*.dist-info/RECORD` and its signatures
+fn is_record(name: &str) -> bool {
+    let Some((dir, file)) = name.rsplit_once('/') else {
+        return false;
+    };
+    dir.ends_with(".dist-info") && matches!(file, "RECORD" | "RECORD.jws" | "RECORD.p7s")
+}
+
+/// Which entries differ between two wheels
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct ContentDiff {
+    pub equal: bool,
+    pub old: String,
+    pub new: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Entries with a different size or CRC
+    pub changed: Vec<String>,
+}
+
+impl ContentDiff {
+    pub fn new(old: &ContentFingerprint, new: &ContentFingerprint) -> Self {
+        let mut diff = Self {
+            equal: old.digest == new.digest,
+            old: old.digest.clone(),
+            new: new.digest.clone(),
+            ..Self::default()
+        };
+        for (name, stamp) in &old.stamps {
+            match new.stamps.get(name) {
+                None => diff.removed.push(name.clone()),
+                Some(new_stamp) if new_stamp != stamp => diff.changed.push(name.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.added.extend(
+            (new.stamps.keys())
+                .filter(|name| !old.stamps.contains_key(*name))
+                .cloned(),
+        );
+        diff
+    }
+}
+
+/// Fingerprint a wheel from its central directory, without reading any entries
+#[tracing::instrument(skip(client), fields(pkg_loc = %pkg_loc))]
+pub async fn fingerprint(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+) -> Result<(PackageName, ContentFingerprint)> {
+    let (name, reader) = open_zip(client, pkg_loc).await?;
+    let entries = reader
+        .file()
+        .entries()
+        .iter()
+        .map(|e| Ok((e.filename().as_str()?, e.uncompressed_size(), e.crc32())))
+        .collect::<Result<Vec<_>>>()?;
+    Ok((name, ContentFingerprint::new(entries)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        let old = ContentFingerprint::new([
+            ("foo/", 0, 0),
+            ("foo/__init__.py", 10, 1),
+            ("foo/a.py", 20, 2),
+            ("foo-1.0.dist-info/RECORD", 100, 3),
+        ]);
+        assert_eq!(old.entries, 2);
+        let reordered = ContentFingerprint::new([
+            ("foo-1.0.dist-info/RECORD", 101, 4),
+            ("foo/a.py", 20, 2),
+            ("foo/__init__.py", 10, 1),
+        ]);
+        assert_eq!(old.digest, reordered.digest);
+        assert!(ContentDiff::new(&old, &reordered).equal);
+
+        let new = ContentFingerprint::new([("foo/__init__.py", 10, 5), ("foo/b.py", 20, 2)]);
+        let diff = ContentDiff::new(&old, &new);
+        assert!(!diff.equal);
+        assert_eq!(diff.added, ["foo/b.py"]);
+        assert_eq!(diff.removed, ["foo/a.py"]);
+        assert_eq!(diff.changed, ["foo/__init__.py"]);
+    }
+}
@@ -0,0 +1,206 @@
+//! Inventory of the non-Python files a wheel installs, for `data-files`.
+//!
+//! Only the central directory and `WHEEL` are read, so this is cheap even for huge wheels,
+//! which are the ones most likely to carry surprises like bundled model weights.
+
+use std::collections::BTreeMap;
+use std::str::FromStr as _;
+
+use eyre::Result;
+use serde::Serialize;
+
+use crate::python_pkg::{Metadata, PackageName};
+use crate::wheel::{find_entry, is_wheel_file, open_zip, read_entry_stored};
+use crate::{LazyZipClient, PkgLoc};
+
+/// Files at least this large are flagged
+const LARGE_FILE: u64 = 50 * 1024 * 1024;
+
+/// Extensions of Python source and bytecode files, which aren’t inventoried
+const PYTHON_EXTENSIONS: &[&str] = &["py", "pyi", "pyc", "pyx", "pxd"];
+
+/// Extensions of compiled code, which a pure wheel shouldn’t contain
+const NATIVE_EXTENSIONS: &[&str] = &["so", "pyd", "dll", "dylib", "a", "lib", "exe"];
+
+/// Extensions of serialized ML models and tensors
+const MODEL_EXTENSIONS: &[&str] = &[
+    "bin",
+    "ckpt",
+    "gguf",
+    "h5",
+    "onnx",
+    "pb",
+    "pt",
+    "pth",
+    "safetensors",
+    "tflite",
+];
+
+/// The number and total uncompressed size of some files
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Tally {
+    pub count: u64,
+    pub size: u64,
+}
+
+impl Tally {
+    fn add(&mut self, size: u64) {
+        self.count += 1;
+        self.size += size;
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Reason {
+    /// Compiled code in a wheel whose `WHEEL` says `Root-Is-Purelib: true`
+    NativeInPureWheel,
+    /// A file with a model or tensor format’s extension
+    ModelWeights,
+    /// A file of 50 MiB or more
+    Large,
+}
+
+/// A file that’s unexpected in a wheel
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Flagged {
+    pub path: String,
+    pub size: u64,
+    pub reasons: Vec<Reason>,
+}
+
+/// The non-Python files of a wheel, outside of `*.dist-info`
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct DataFiles {
+    pub total: Tally,
+    /// By lowercase extension, or `""` for files without one
+    pub by_extension: BTreeMap<String, Tally>,
+    /// By directory the files are directly in, or `""` for the wheel’s root
+    pub by_directory: BTreeMap<String, Tally>,
+    pub flagged: Vec<Flagged>,
+}
+
+impl DataFiles {
+    /// Inventory zip entries given as paths and uncompressed sizes
+    pub fn new<'a>(pure: bool, entries: impl IntoIterator<Item = (&'a str, u64)>) -> Self {
+        let mut files = Self::default();
+        for (path, size) in entries {
+            let (dir, file) = path.rsplit_once('/').unwrap_or(("", path));
+            if file.is_empty() || dir.split('/').any(|d| d.ends_with(".dist-info")) {
+                continue;
+            }
+            let extension = file
+                .rsplit_once('.')
+                .map(|(_, ext)| ext.to_ascii_lowercase())
+                .unwrap_or_default();
+            if PYTHON_EXTENSIONS.contains(&extension.as_str()) || file == "py.typed" {
+                continue;
+            }
+            files.total.add(size);
+            files
+                .by_directory
+                .entry(dir.to_owned())
+                .or_default()
+                .add(size);
+            let mut reasons = Vec::new();
+            // Shared objects often carry an ABI suffix, like `_foo.cpython-312-x86_64-linux-gnu.so.1`
+            if pure
+                && file
+                    .split('.')
+                    .skip(1)
+                    .any(|ext| NATIVE_EXTENSIONS.contains(&ext))
+            {
+                reasons.push(Reason::NativeInPureWheel);
+            }
+            if MODEL_EXTENSIONS.contains(&extension.as_str()) {
+                reasons.push(Reason::ModelWeights);
+            }
+            if size >= LARGE_FILE {
+                reasons.push(Reason::Large);
+            }
+            if !reasons.is_empty() {
+                files.flagged.push(Flagged {
+                    path: path.to_owned(),
+                    size,
+                    reasons,
+                });
+            }
+            files.by_extension.entry(extension).or_default().add(size);
+        }
+        files
+    }
+}
+
+/// Inventory a wheel’s non-Python files
+#[tracing::instrument(skip(client), fields(pkg_loc = %pkg_loc))]
+pub async fn inventory(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+) -> Result<(PackageName, DataFiles)> {
+    let (name, mut reader) = open_zip(client, pkg_loc).await?;
+    let pure = if let Some(idx) = find_entry(&mut reader, is_wheel_file) {
+        let mut buf = String::new();
+        read_entry_stored(client, &mut reader, idx, &mut buf).await?;
+        Metadata::from_str(&buf)?
+            .get("Root-Is-Purelib")
+            .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+    } else {
+        false
+    };
+    let entries = reader
+        .file()
+        .entries()
+        .iter()
+        .map(|e| Ok((e.filename().as_str()?, e.uncompressed_size())))
+        .collect::<Result<Vec<_>>>()?;
+    Ok((name, DataFiles::new(pure, entries)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let entries = [
+            ("foo/__init__.py", 10),
+            ("foo/py.typed", 0),
+            ("foo/data/", 0),
+            ("foo/data/table.CSV", 100),
+            ("foo/data/model.safetensors", LARGE_FILE),
+            ("foo/_speedups.cpython-312-x86_64-linux-gnu.so", 1000),
+            ("foo-1.0.dist-info/LICENSE", 5),
+            ("foo-1.0.data/scripts/foo", 20),
+        ];
+        let files = DataFiles::new(true, entries);
+        assert_eq!(
+            files.total,
+            Tally {
+                count: 4,
+                size: 1120 + LARGE_FILE
+            }
+        );
+        let keys = |map: &BTreeMap<String, Tally>| map.keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys(&files.by_extension), ["", "csv", "safetensors", "so"]);
+        assert_eq!(
+            keys(&files.by_directory),
+            ["foo", "foo-1.0.data/scripts", "foo/data"]
+        );
+        assert_eq!(
+            files.flagged,
+            [
+                Flagged {
+                    path: "foo/data/model.safetensors".to_owned(),
+                    size: LARGE_FILE,
+                    reasons: vec![Reason::ModelWeights, Reason::Large],
+                },
+                Flagged {
+                    path: "foo/_speedups.cpython-312-x86_64-linux-gnu.so".to_owned(),
+                    size: 1000,
+                    reasons: vec![Reason::NativeInPureWheel],
+                },
+            ]
+        );
+        assert_eq!(DataFiles::new(false, entries).flagged.len(), 1);
+    }
+}
@@ -0,0 +1,652 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::str::FromStr as _;
+
+use eyre::{Context as _, ContextCompat as _, Result};
+use futures::StreamExt as _;
+use futures::stream::FuturesUnordered;
+use pep440_rs::Version;
+use pep508_rs::{ExtraName, Requirement};
+use serde::Serialize;
+use serde_with::{DisplayFromStr, serde_as};
+
+use crate::error::Failure;
+use crate::glob::glob_matches;
+use crate::hashes::Hashes;
+use crate::python_pkg::{Dependency, Metadata, PackageName, WheelFilename};
+use crate::{LazyZipClient, PkgLoc};
+use crate::{simple_repo_api, wheel};
+
+/// Output format for dependency graphs.
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GraphFormat {
+    #[default]
+    Json,
+    Dot,
+    Mermaid,
+    Graphml,
+}
+
+/// Index of a node in a [`DepGraph`].
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(transparent)]
+pub struct NodeId(usize);
+
+/// Identifies a release: a normalized name and the resolved version.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeKey {
+    pub name: PackageName,
+    /// `None` if the package couldn’t be resolved
+    pub version: Option<Version>,
+}
+
+/// A release in a dependency graph.
+#[serde_as]
+#[derive(Serialize, Debug)]
+pub struct Node {
+    pub name: PackageName,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub version: Option<Version>,
+    /// Core metadata, fetched once per node
+    #[serde(skip)]
+    pub metadata: Option<Metadata>,
+    /// The wheel the release was resolved to, if it’s from an index
+    #[serde(skip)]
+    pub file: Option<simple_repo_api::File>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Matched by [`Traversal::exclude`], so not resolved or followed
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub excluded: bool,
+    /// Extras whose requirements have been followed, `None` if not expanded yet
+    #[serde(skip)]
+    extras: Option<BTreeSet<ExtraName>>,
+}
+
+/// A requirement of one release on another.
+#[serde_as]
+#[derive(Serialize, Debug)]
+pub struct Edge {
+    pub from: NodeId,
+    pub to: NodeId,
+    #[serde_as(as = "DisplayFromStr")]
+    pub requirement: Requirement,
+}
+
+/// A dependency graph with one node per release.
+///
+/// Releases reached via multiple paths are only resolved and fetched once.
+#[derive(Serialize, Debug, Default)]
+pub struct DepGraph {
+    roots: Vec<NodeId>,
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    #[serde(skip)]
+    index: HashMap<NodeKey, NodeId>,
+}
+
+/// How far to follow requirements when building a [`DepGraph`]
+#[derive(Debug, Clone)]
+pub struct Traversal {
+    /// Only follow requirements up to this many levels deep
+    pub max_depth: Option<usize>,
+    /// Follow the requirements of extras, like `bar` in `foo[bar]`
+    pub extras: bool,
+    /// Don’t resolve or follow required packages whose names match these patterns, like `boto*`
+    pub exclude: Vec<String>,
+}
+
+impl Default for Traversal {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            extras: true,
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl Traversal {
+    fn excludes(&self, name: &PackageName) -> bool {
+        let name = name.to_string();
+        self.exclude.iter().any(|pattern| {
+            let pattern = pattern.to_lowercase().replace(['_', '.'], "-");
+            glob_matches(&pattern, &name)
+        })
+    }
+}
+
+/// Something to add to the graph: a package to resolve, and the requirement that led to it.
+struct Pending {
+    parent: Option<(NodeId, Requirement)>,
+    pkg_loc: PkgLoc,
+    extras: Vec<ExtraName>,
+    /// Added to the graph without resolving it
+    excluded: bool,
+}
+
+/// Where a resolved release’s metadata comes from.
+enum Resolution {
+    Remote {
+        key: NodeKey,
+        file: Box<simple_repo_api::File>,
+    },
+    Local {
+        key: NodeKey,
+        metadata: Metadata,
+    },
+    /// Excluded from the traversal
+    Excluded {
+        key: NodeKey,
+    },
+}
+
+impl Node {
+    fn label(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{} {version}", self.name),
+            None if self.excluded => format!("{} (excluded)", self.name),
+            None => format!("{} (unresolved)", self.name),
+        }
+    }
+}
+
+impl DepGraph {
+    /// Recursively collect the requirements of packages, as far as `traversal` allows.
+    ///
+    /// Each requirement is resolved to the newest matching wheel on its own,
+    /// so this isn’t a resolver: the graph can contain multiple versions of a package.
+    pub async fn build(
+        client: &LazyZipClient,
+        pkg_locs: Vec<PkgLoc>,
+        traversal: &Traversal,
+    ) -> Result<Self> {
+        let mut graph = DepGraph::default();
+        let mut resolved = HashMap::<Dependency, NodeId>::new();
+        let mut level = pkg_locs
+            .into_iter()
+            .map(|pkg_loc| Pending {
+                parent: None,
+                pkg_loc,
+                extras: Vec::new(),
+                excluded: false,
+            })
+            .collect::<Vec<_>>();
+        let mut depth = 0;
+        while !level.is_empty() {
+            // Resolve what hasn’t been resolved yet
+            let mut resolutions = level
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| {
+                    !matches!(&p.pkg_loc, PkgLoc::Dependency(dep) if resolved.contains_key(dep))
+                })
+                .map(|(i, p)| async move { (i, resolve(client, p).await) })
+                .collect::<FuturesUnordered<_>>()
+                .collect::<Vec<_>>()
+                .await;
+            resolutions.sort_by_key(|(i, _)| *i);
+            let mut resolutions = resolutions.into_iter().peekable();
+
+            // Add nodes and edges, remembering which nodes to fetch and expand
+            let mut to_fetch = Vec::new();
+            let mut to_expand = Vec::new();
+            for (i, pending) in level.into_iter().enumerate() {
+                let id = match (&pending.pkg_loc, resolutions.next_if(|(j, _)| *j == i)) {
+                    (PkgLoc::Dependency(dep), None) => resolved[dep],
+                    (pkg_loc, Some((_, resolution))) => {
+                        let id = match resolution {
+                            Ok(Resolution::Remote { key, file }) => {
+                                let (id, is_new) = graph.insert(key);
+                                if is_new {
+                                    graph.nodes[id.0].file = Some((*file).clone());
+                                    to_fetch.push((id, *file));
+                                }
+                                id
+                            }
+                            Ok(Resolution::Local { key, metadata }) => {
+                                let (id, _) = graph.insert(key);
+                                graph.nodes[id.0].metadata.get_or_insert(metadata);
+                                id
+                            }
+                            Ok(Resolution::Excluded { key }) => {
+                                let (id, _) = graph.insert(key);
+                                graph.nodes[id.0].excluded = true;
+                                id
+                            }
+                            Err(e) => {
+                                let PkgLoc::Dependency(dep) = pkg_loc else {
+                                    return Err(e);
+                                };
+                                let key = NodeKey {
+                                    name: dep.name().clone(),
+                                    version: None,
+                                };
+                                let (id, _) = graph.insert(key);
+                                graph.nodes[id.0].error = Some(format!("{e:#}"));
+                                id
+                            }
+                        };
+                        if let PkgLoc::Dependency(dep) = pkg_loc {
+                            resolved.insert(dep.clone(), id);
+                        }
+                        id
+                    }
+                    (_, None) => unreachable!("files are always resolved"),
+                };
+                match pending.parent {
+                    Some((from, requirement)) => graph.edges.push(Edge {
+                        from,
+                        to: id,
+                        requirement,
+                    }),
+                    None if !graph.roots.contains(&id) => graph.roots.push(id),
+                    None => {}
+                }
+                to_expand.push((id, pending.extras));
+            }
+
+            graph.fetch_metadata(client, to_fetch).await;
+
+            // Follow requirements that became active
+            level = Vec::new();
+            if traversal
+                .max_depth
+                .is_some_and(|max_depth| depth >= max_depth)
+            {
+                break;
+            }
+            for (id, extras) in to_expand {
+                level.extend(graph.expand(id, extras, traversal)?);
+            }
+            depth += 1;
+        }
+        Ok(graph)
+    }
+
+    /// Fetch metadata of nodes concurrently
+    async fn fetch_metadata(
+        &mut self,
+        client: &LazyZipClient,
+        to_fetch: Vec<(NodeId, simple_repo_api::File)>,
+    ) {
+        let fetched = to_fetch
+            .into_iter()
+            .map(|(id, file)| {
+                if let Some(sha256) = file.hashes.get("sha256") {
+                    client.check_hash_pin(&file.filename, sha256);
+                }
+                let client = client.clone();
+                async move { (id, fetch_metadata(&client, file).await) }
+            })
+            .collect::<FuturesUnordered<_>>()
+            .collect::<Vec<_>>()
+            .await;
+        for (id, result) in fetched {
+            match result {
+                Ok(metadata) => self.nodes[id.0].metadata = Some(metadata),
+                Err(e) => self.nodes[id.0].error = Some(format!("{e:#}")),
+            }
+        }
+    }
+
+    /// Add a node if there’s none with that key yet. Returns its ID and if it was added.
+    fn insert(&mut self, key: NodeKey) -> (NodeId, bool) {
+        if let Some(id) = self.index.get(&key) {
+            return (*id, false);
+        }
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            name: key.name.clone(),
+            version: key.version.clone(),
+            metadata: None,
+            file: None,
+            error: None,
+            excluded: false,
+            extras: None,
+        });
+        self.index.insert(key, id);
+        (id, true)
+    }
+
+    /// Mark `extras` as followed for a node, returning requirements that weren’t active before
+    fn expand(
+        &mut self,
+        id: NodeId,
+        extras: Vec<ExtraName>,
+        traversal: &Traversal,
+    ) -> Result<Vec<Pending>> {
+        let node = &mut self.nodes[id.0];
+        let Some(metadata) = &node.metadata else {
+            return Ok(Vec::new());
+        };
+        let before = node.extras.clone();
+        let mut after = before.clone().unwrap_or_default();
+        after.extend(extras);
+        if before.as_ref() == Some(&after) {
+            return Ok(Vec::new());
+        }
+        let requires_dist = match metadata.requires_dist() {
+            Ok(requires_dist) => requires_dist,
+            Err(e) => {
+                node.error = Some(format!("{e:#}"));
+                return Ok(Vec::new());
+            }
+        };
+        let pending = newly_active(requires_dist, before.as_ref(), &after)
+            .map(|requirement| {
+                let dep = Dependency::from_requirement(&requirement)?;
+                Ok(Pending {
+                    excluded: traversal.excludes(dep.name()),
+                    pkg_loc: PkgLoc::Dependency(dep),
+                    extras: if traversal.extras {
+                        requirement.extras.clone()
+                    } else {
+                        Vec::new()
+                    },
+                    parent: Some((id, requirement)),
+                })
+            })
+            .collect::<Result<_>>()?;
+        node.extras = Some(after);
+        Ok(pending)
+    }
+
+    /// The nodes the graph was built from
+    pub fn roots(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.roots.iter().copied()
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes.iter().enumerate().map(|(i, n)| (NodeId(i), n))
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// Get a node by ID.
+    ///
+    /// # Panics
+    /// If the ID is from a different graph.
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    pub fn get(&self, key: &NodeKey) -> Option<NodeId> {
+        self.index.get(key).copied()
+    }
+
+    /// Requirements of a node, i.e. outgoing edges
+    pub fn dependencies(&self, id: NodeId) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |e| e.from == id)
+    }
+
+    /// Requirements on a node, i.e. incoming edges
+    pub fn dependents(&self, id: NodeId) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |e| e.to == id)
+    }
+
+    /// Render the graph in the given format.
+    pub fn render(&self, format: GraphFormat) -> Result<String, serde_json::Error> {
+        Ok(match format {
+            GraphFormat::Json => serde_json::to_string(self)?,
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Mermaid => self.to_mermaid(),
+            GraphFormat::Graphml => self.to_graphml(),
+        })
+    }
+
+    fn to_dot(&self) -> String {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut out = String::from("digraph dependencies {\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(out, "    n{i} [label={}];", quote(&node.label())).unwrap();
+        }
+        for edge in &self.edges {
+            let label = quote(&edge.requirement.to_string());
+            writeln!(
+                out,
+                "    n{} -> n{} [label={label}];",
+                edge.from.0, edge.to.0
+            )
+            .unwrap();
+        }
+        out.push('}');
+        out
+    }
+
+    fn to_mermaid(&self) -> String {
+        let quote = |s: &str| format!("\"{}\"", s.replace('"', "#quot;"));
+        let mut out = String::from("graph TD\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(out, "    n{i}[{}]", quote(&node.label())).unwrap();
+        }
+        for edge in &self.edges {
+            let label = quote(&edge.requirement.to_string());
+            writeln!(out, "    n{} -->|{label}| n{}", edge.from.0, edge.to.0).unwrap();
+        }
+        out.truncate(out.trim_end().len());
+        out
+    }
+
+    fn to_graphml(&self) -> String {
+        let mut out = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            "\n",
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#,
+            "\n",
+            r#"  <key id="name" for="node" attr.name="name" attr.type="string"/>"#,
+            "\n",
+            r#"  <key id="version" for="node" attr.name="version" attr.type="string"/>"#,
+            "\n",
+            r#"  <key id="requirement" for="edge" attr.name="requirement" attr.type="string"/>"#,
+            "\n",
+            r#"  <graph id="dependencies" edgedefault="directed">"#,
+            "\n",
+        ));
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(out, r#"    <node id="n{i}">"#).unwrap();
+            let name = xml_escape(&node.name.to_string());
+            writeln!(out, r#"      <data key="name">{name}</data>"#).unwrap();
+            if let Some(version) = &node.version {
+                let version = xml_escape(&version.to_string());
+                writeln!(out, r#"      <data key="version">{version}</data>"#).unwrap();
+            }
+            out.push_str("    </node>\n");
+        }
+        for edge in &self.edges {
+            let (from, to) = (edge.from.0, edge.to.0);
+            writeln!(out, r#"    <edge source="n{from}" target="n{to}">"#).unwrap();
+            let requirement = xml_escape(&edge.requirement.to_string());
+            writeln!(out, r#"      <data key="requirement">{requirement}</data>"#).unwrap();
+            out.push_str("    </edge>\n");
+        }
+        out.push_str("  </graph>\n</graphml>");
+        out
+    }
+}
+
+/// Find the release a package location refers to
+async fn resolve(client: &LazyZipClient, pending: &Pending) -> Result<Resolution> {
+    match &pending.pkg_loc {
+        PkgLoc::Dependency(dep) if pending.excluded => Ok(Resolution::Excluded {
+            key: NodeKey {
+                name: dep.name().clone(),
+                version: None,
+            },
+        }),
+        PkgLoc::Dependency(dep) => {
+            let whl = wheel::find_wheel(client, dep).await?;
+            let filename = WheelFilename::from_str(&whl.filename)?;
+            let key = NodeKey {
+                name: filename.name,
+                version: Some(filename.version),
+            };
+            Ok(Resolution::Remote {
+                key,
+                file: Box::new(whl),
+            })
+        }
+        pkg_loc @ (PkgLoc::Url(_) | PkgLoc::Path(_)) => {
+            let (_, mut metadata) = wheel::pkg_metadata_headers(client, pkg_loc.clone()).await?;
+            client.apply_overrides(&mut metadata);
+            let name = metadata.get("Name").context("METADATA has no Name")?;
+            let version = metadata.get("Version").context("METADATA has no Version")?;
+            let key = NodeKey {
+                name: PackageName::from_str(name)?,
+                version: Some(Version::from_str(version).context("invalid Version")?),
+            };
+            Ok(Resolution::Local { key, metadata })
+        }
+    }
+}
+
+async fn fetch_metadata(
+    client: &LazyZipClient,
+    mut file: simple_repo_api::File,
+) -> Result<Metadata> {
+    let hashes = Hashes::take_from(&mut file.hashes);
+    let reader = wheel::url_reader(client, file.url, &hashes, file.size).await?;
+    let mut zip_reader = wheel::zip_reader(reader).await?;
+    let idx = wheel::find_entry(&mut zip_reader, wheel::is_metadata)
+        .ok_or_else(|| Failure::entry_missing("wheel has no METADATA"))?;
+    let mut buf = String::new();
+    wheel::read_entry_headers(&mut zip_reader, idx, &mut buf).await?;
+    let mut metadata = Metadata::from_str(&buf)?;
+    client.apply_overrides(&mut metadata);
+    Ok(metadata)
+}
+
+/// Requirements active with extras `after`, but not with extras `before` (if expanded before)
+fn newly_active<'a>(
+    requires_dist: Vec<Requirement>,
+    before: Option<&'a BTreeSet<ExtraName>>,
+    after: &'a BTreeSet<ExtraName>,
+) -> impl Iterator<Item = Requirement> + 'a {
+    let before = before.map(|before| before.iter().cloned().collect::<Vec<_>>());
+    let after = after.iter().cloned().collect::<Vec<_>>();
+    requires_dist.into_iter().filter(move |req| {
+        req.marker.evaluate_extras(&after)
+            && !before
+                .as_ref()
+                .is_some_and(|before| req.marker.evaluate_extras(before))
+    })
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockIndex, MockWheel};
+
+    fn graph() -> DepGraph {
+        let mut graph = DepGraph::default();
+        let (foo, _) = graph.insert(NodeKey {
+            name: PackageName::from_str("foo").unwrap(),
+            version: Some(Version::from_str("1.0").unwrap()),
+        });
+        let (bar, _) = graph.insert(NodeKey {
+            name: PackageName::from_str("bar").unwrap(),
+            version: None,
+        });
+        graph.roots.push(foo);
+        graph.edges.push(Edge {
+            from: foo,
+            to: bar,
+            requirement: Requirement::from_str(r#"bar>=1 ; python_version < "4""#).unwrap(),
+        });
+        graph
+    }
+
+    #[test]
+    fn test_insert_dedup() {
+        let mut graph = graph();
+        let key = NodeKey {
+            name: PackageName::from_str("Foo").unwrap(),
+            version: Some(Version::from_str("1.0").unwrap()),
+        };
+        assert_eq!(graph.insert(key.clone()), (NodeId(0), false));
+        assert_eq!(graph.get(&key), Some(NodeId(0)));
+        assert_eq!(graph.dependencies(NodeId(0)).count(), 1);
+        assert_eq!(graph.dependents(NodeId(1)).count(), 1);
+    }
+
+    #[test]
+    fn test_newly_active() {
+        let reqs = ["a", "b; extra == 'x'", "c; extra == 'y'"]
+            .map(|r| Requirement::from_str(r).unwrap())
+            .to_vec();
+        let names = |before: Option<&BTreeSet<ExtraName>>, after: &BTreeSet<ExtraName>| {
+            newly_active(reqs.clone(), before, after)
+                .map(|r| r.name.to_string())
+                .collect::<Vec<_>>()
+        };
+        let none = BTreeSet::new();
+        let x = BTreeSet::from([ExtraName::from_str("x").unwrap()]);
+        assert_eq!(names(None, &none), ["a"]);
+        assert_eq!(names(None, &x), ["a", "b"]);
+        assert_eq!(names(Some(&none), &x), ["b"]);
+    }
+
+    #[tokio::test]
+    async fn test_traversal() {
+        let index = MockIndex::start([
+            MockWheel::new("foo", "1.0")
+                .requires("bar[x]")
+                .requires("boto3"),
+            MockWheel::new("bar", "1.0").requires("baz; extra == 'x'"),
+            MockWheel::new("baz", "1.0"),
+        ])
+        .await
+        .unwrap();
+        let client = index.client();
+        let traversal = Traversal {
+            extras: false,
+            exclude: vec!["Boto*".to_owned()],
+            ..Traversal::default()
+        };
+        let graph = DepGraph::build(&client, vec!["foo".parse().unwrap()], &traversal)
+            .await
+            .unwrap();
+        let labels = graph.nodes().map(|(_, n)| n.label()).collect::<Vec<_>>();
+        assert_eq!(labels, ["foo 1.0", "bar 1.0", "boto3 (excluded)"]);
+
+        let graph = DepGraph::build(&client, vec!["foo".parse().unwrap()], &Traversal::default())
+            .await
+            .unwrap();
+        assert!(graph.nodes().any(|(_, n)| n.name.to_string() == "baz"));
+    }
+
+    #[test]
+    fn test_dot() {
+        assert_eq!(
+            graph().to_dot(),
+            "digraph dependencies {\n    n0 [label=\"foo 1.0\"];\n    n1 [label=\"bar (unresolved)\"];\n    n0 -> n1 [label=\"bar>=1 ; python_full_version < '4'\"];\n}"
+        );
+    }
+
+    #[test]
+    fn test_mermaid() {
+        assert_eq!(
+            graph().to_mermaid(),
+            "graph TD\n    n0[\"foo 1.0\"]\n    n1[\"bar (unresolved)\"]\n    n0 -->|\"bar>=1 ; python_full_version < '4'\"| n1"
+        );
+    }
+
+    #[test]
+    fn test_graphml() {
+        let graphml = graph().to_graphml();
+        assert!(graphml.contains(
+            r#"<data key="requirement">bar&gt;=1 ; python_full_version &lt; '4'</data>"#
+        ));
+        assert!(!graphml.contains(r#"<data key="version">None"#));
+    }
+}
@@ -0,0 +1,229 @@
+//! Heuristics for deprecated or renamed packages, for `deprecation-check`.
+//!
+//! There’s no standard way to deprecate a package, so this looks for the usual signs:
+//! the “Inactive” development status, an empty wheel that only pulls in a successor,
+//! or a summary or description announcing it.
+
+use std::str::FromStr as _;
+
+use eyre::{Result, bail};
+use serde::Serialize;
+
+use crate::error::Failure;
+use crate::python_pkg::{Metadata, PackageName};
+use crate::wheel::{find_entry, is_metadata, open_zip, read_entry_stored};
+use crate::{LazyZipClient, PkgLoc};
+
+const INACTIVE: &str = "Development Status :: 7 - Inactive";
+
+/// Phrases that announce a deprecation without naming a successor
+const DEPRECATED: &[&str] = &[
+    "deprecated",
+    "no longer maintained",
+    "not maintained",
+    "unmaintained",
+    "obsolete",
+    "end of life",
+    "archived",
+];
+
+/// Phrases followed by the successor’s name
+const SUCCESSOR: &[&str] = &[
+    "renamed to ",
+    "moved to ",
+    "replaced by ",
+    "superseded by ",
+    "in favor of ",
+    "in favour of ",
+    "migrate to ",
+];
+
+/// Only this many lines of the description are checked,
+/// as changelogs further down mention deprecations of features
+const DESCRIPTION_LINES: usize = 10;
+
+/// A sign of deprecation
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "signal", rename_all = "kebab-case")]
+pub enum Signal {
+    /// The `Development Status :: 7 - Inactive` classifier
+    InactiveClassifier,
+    /// A wheel without modules, only requiring other packages
+    EmptyWheel { requires: Vec<String> },
+    /// A line of the summary or description announcing a deprecation
+    Announcement { text: String },
+}
+
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct Deprecation {
+    pub deprecated: bool,
+    pub signals: Vec<Signal>,
+    /// The package to use instead, if one was named
+    pub successor: Option<String>,
+}
+
+/// Check the latest wheel of a package, reading its file list and complete `METADATA`
+#[tracing::instrument(skip(client), fields(pkg_loc = %pkg_loc))]
+pub async fn check_pkg(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+) -> Result<(PackageName, Deprecation)> {
+    let (name, mut zip_reader) = open_zip(client, pkg_loc).await?;
+    let entries = zip_reader
+        .file()
+        .entries()
+        .iter()
+        .map(|e| e.filename().as_str().map(ToOwned::to_owned))
+        .collect::<Result<Vec<_>, _>>()?;
+    let Some(idx) = find_entry(&mut zip_reader, is_metadata) else {
+        bail!(Failure::entry_missing("wheel has no METADATA"));
+    };
+    let mut buf = String::new();
+    read_entry_stored(client, &mut zip_reader, idx, &mut buf).await?;
+    let metadata = Metadata::from_str(&buf)?;
+    let deprecation = check(&name, &metadata, &entries);
+    Ok((name, deprecation))
+}
+
+/// Look for signs of deprecation in a wheel’s metadata and file list
+pub fn check(name: &PackageName, metadata: &Metadata, entries: &[String]) -> Deprecation {
+    let mut signals = Vec::new();
+    let mut successor = None;
+    if metadata
+        .get_all("Classifier")
+        .any(|classifier| classifier.trim().eq_ignore_ascii_case(INACTIVE))
+    {
+        signals.push(Signal::InactiveClassifier);
+    }
+    let is_empty = entries.iter().all(|entry| {
+        entry.ends_with('/')
+            || entry
+                .split_once('/')
+                .is_some_and(|(dir, _)| dir.ends_with(".dist-info"))
+    });
+    if is_empty {
+        // Requirements for extras don’t point to a successor
+        let requires = metadata
+            .requires_dist()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|req| req.marker.top_level_extra().is_none())
+            .map(|req| req.name.to_string())
+            .collect::<Vec<_>>();
+        if let [only] = requires.as_slice() {
+            successor = Some(only.clone());
+        }
+        signals.push(Signal::EmptyWheel { requires });
+    }
+    let summary = metadata.get("Summary").unwrap_or_default().lines();
+    let description = metadata
+        .body()
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(DESCRIPTION_LINES);
+    for line in summary.chain(description) {
+        let (announces, named) = announcement(line);
+        if !announces {
+            continue;
+        }
+        // The package itself is often named in e.g. “foo is deprecated in favor of foo2”
+        if successor.is_none() {
+            successor = named.filter(|named| named != name).map(|n| n.to_string());
+        }
+        signals.push(Signal::Announcement {
+            text: line.trim().to_owned(),
+        });
+    }
+    Deprecation {
+        deprecated: !signals.is_empty(),
+        signals,
+        successor,
+    }
+}
+
+/// Whether a line announces a deprecation, and the successor it names
+fn announcement(line: &str) -> (bool, Option<PackageName>) {
+    let lower = line.to_ascii_lowercase();
+    let mut named = SUCCESSOR
+        .iter()
+        .flat_map(|phrase| lower.match_indices(phrase))
+        .find_map(|(i, phrase)| package_at(&line[i + phrase.len()..]));
+    // “Use foo instead”
+    if named.is_none() {
+        named = lower
+            .match_indices("use ")
+            .filter(|(i, _)| lower[*i..].contains(" instead"))
+            .find_map(|(i, phrase)| package_at(&line[i + phrase.len()..]));
+    }
+    let announces = named.is_some() || DEPRECATED.iter().any(|phrase| lower.contains(phrase));
+    (announces, named)
+}
+
+/// A package name at the start of `text`, possibly wrapped in markup like `` `foo` `` or `[foo](…)`
+fn package_at(text: &str) -> Option<PackageName> {
+    let text = text.trim_start_matches(|c: char| c.is_whitespace() || "`*_\"'[".contains(c));
+    let end = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || "-_.".contains(c)))
+        .unwrap_or(text.len());
+    let word = text[..end].trim_end_matches(['.', '-', '_']);
+    if ["a", "an", "the", "this", "its", "our", "new", "another"]
+        .iter()
+        .any(|stop| word.eq_ignore_ascii_case(stop))
+    {
+        return None;
+    }
+    word.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(content: &str) -> Metadata {
+        Metadata::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn test_inactive() {
+        let metadata = metadata("Name: foo\nClassifier: Development Status :: 7 - Inactive\n");
+        let entries = ["foo/__init__.py".to_owned()];
+        let deprecation = check(&"foo".parse().unwrap(), &metadata, &entries);
+        assert_eq!(deprecation.signals, [Signal::InactiveClassifier]);
+        assert_eq!(deprecation.successor, None);
+    }
+
+    #[test]
+    fn test_empty_wheel() {
+        let metadata = metadata(
+            "Name: foo\nRequires-Dist: foo-ng>=2\nRequires-Dist: pytest; extra == \"test\"\n",
+        );
+        let entries = ["foo-1.0.dist-info/METADATA", "foo-1.0.dist-info/RECORD"].map(String::from);
+        let deprecation = check(&"foo".parse().unwrap(), &metadata, &entries);
+        assert_eq!(
+            deprecation.signals,
+            [Signal::EmptyWheel {
+                requires: vec!["foo-ng".to_owned()]
+            }]
+        );
+        assert_eq!(deprecation.successor.as_deref(), Some("foo-ng"));
+    }
+
+    #[test]
+    fn test_announcement() {
+        let metadata = metadata(
+            "Name: foo\nSummary: A foo library\n\n# foo\n\n\
+             **This project has been renamed to [`foo2`](https://example.com/foo2).**\n",
+        );
+        let entries = ["foo/__init__.py".to_owned()];
+        let deprecation = check(&"foo".parse().unwrap(), &metadata, &entries);
+        assert!(deprecation.deprecated);
+        assert_eq!(deprecation.successor.as_deref(), Some("foo2"));
+
+        let (announces, named) = announcement("Deprecated: use `bar` instead.");
+        assert!(announces);
+        assert_eq!(named, Some("bar".parse().unwrap()));
+        assert_eq!(announcement("Moved to the new home"), (false, None));
+        assert_eq!(announcement("Fast and simple"), (false, None));
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// Differences between the requirements of two releases.
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct DepsDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<Change>,
+}
+
+/// A requirement whose constraints differ between two releases.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub from: String,
+    pub to: String,
+}
+
+impl DepsDiff {
+    /// Compare requirements, matching them by name and marker.
+    pub fn new(old: &[pep508_rs::Requirement], new: &[pep508_rs::Requirement]) -> Self {
+        let old = by_key(old);
+        let mut new = by_key(new);
+        let mut diff = DepsDiff::default();
+        for (key, from) in old {
+            match new.remove(&key) {
+                None => diff.removed.push(from),
+                Some(to) if to != from => diff.changed.push(Change { from, to }),
+                Some(_) => {}
+            }
+        }
+        diff.added.extend(new.into_values());
+        diff
+    }
+}
+
+/// Requirements keyed by normalized name and marker, to match them up across releases
+fn by_key(reqs: &[pep508_rs::Requirement]) -> BTreeMap<(String, Option<String>), String> {
+    reqs.iter()
+        .map(|req| {
+            let key = (req.name.to_string(), req.marker.try_to_string());
+            (key, req.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr as _;
+
+    use super::*;
+
+    fn reqs(reqs: &[&str]) -> Vec<pep508_rs::Requirement> {
+        reqs.iter()
+            .map(|r| pep508_rs::Requirement::from_str(r).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_diff() {
+        let diff = DepsDiff::new(
+            &reqs(&["a>=1", "b", "c; extra == 'x'"]),
+            &reqs(&["A>=2", "c; extra == 'x'", "c>=1", "d"]),
+        );
+        assert_eq!(diff.added, ["c>=1", "d"]);
+        assert_eq!(diff.removed, ["b"]);
+        assert_eq!(
+            diff.changed,
+            [Change {
+                from: "a>=1".to_owned(),
+                to: "a>=2".to_owned()
+            }]
+        );
+    }
+}
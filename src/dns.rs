@@ -0,0 +1,104 @@
+//! Name resolution with control over the IP address families connected to.
+
+use std::fmt;
+use std::net::SocketAddr;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+/// Which IP address families to connect to, and in which order
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IpFamily {
+    /// All addresses in the system resolver’s order, racing IPv6 and IPv4 (“happy eyeballs”)
+    #[default]
+    Any,
+    /// Only IPv4 addresses
+    Ipv4,
+    /// Only IPv6 addresses
+    Ipv6,
+    /// IPv4 addresses first, falling back to IPv6
+    PreferIpv4,
+    /// IPv6 addresses first, falling back to IPv4
+    PreferIpv6,
+}
+
+impl IpFamily {
+    fn allows(self, addr: &SocketAddr) -> bool {
+        match self {
+            IpFamily::Ipv4 => addr.is_ipv4(),
+            IpFamily::Ipv6 => addr.is_ipv6(),
+            IpFamily::Any | IpFamily::PreferIpv4 | IpFamily::PreferIpv6 => true,
+        }
+    }
+
+    /// Filter and order resolved addresses
+    fn apply(self, addrs: impl Iterator<Item = SocketAddr>) -> Vec<SocketAddr> {
+        let mut addrs = addrs.filter(|addr| self.allows(addr)).collect::<Vec<_>>();
+        // Stable sorts, so the resolver’s order is kept within a family
+        match self {
+            IpFamily::PreferIpv4 => addrs.sort_by_key(SocketAddr::is_ipv6),
+            IpFamily::PreferIpv6 => addrs.sort_by_key(SocketAddr::is_ipv4),
+            IpFamily::Any | IpFamily::Ipv4 | IpFamily::Ipv6 => {}
+        }
+        addrs
+    }
+}
+
+impl fmt::Display for IpFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IpFamily::Any => "IP",
+            IpFamily::Ipv4 | IpFamily::PreferIpv4 => "IPv4",
+            IpFamily::Ipv6 | IpFamily::PreferIpv6 => "IPv6",
+        })
+    }
+}
+
+/// Resolves names using the system resolver, keeping only addresses of the configured families.
+///
+/// Since the HTTP client races the first address family against the other one,
+/// ordering addresses decides which one is preferred.
+#[derive(Debug)]
+pub struct FamilyResolver(pub IpFamily);
+
+impl Resolve for FamilyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let family = self.0;
+        Box::pin(async move {
+            let addrs = family.apply(tokio::net::lookup_host((name.as_str(), 0)).await?);
+            if addrs.is_empty() {
+                return Err(format!("no {family} address found for {}", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let addrs = ["[::1]:0", "127.0.0.1:0", "[::2]:0", "127.0.0.2:0"]
+            .map(|addr| addr.parse::<SocketAddr>().unwrap());
+        let apply = |family: IpFamily| {
+            family
+                .apply(addrs.into_iter())
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(apply(IpFamily::Ipv4), ["127.0.0.1:0", "127.0.0.2:0"]);
+        assert_eq!(apply(IpFamily::Ipv6), ["[::1]:0", "[::2]:0"]);
+        assert_eq!(
+            apply(IpFamily::PreferIpv4),
+            ["127.0.0.1:0", "127.0.0.2:0", "[::1]:0", "[::2]:0"]
+        );
+        assert_eq!(
+            apply(IpFamily::PreferIpv6),
+            ["[::1]:0", "[::2]:0", "127.0.0.1:0", "127.0.0.2:0"]
+        );
+        assert_eq!(apply(IpFamily::Any).len(), addrs.len());
+    }
+}
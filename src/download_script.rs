@@ -0,0 +1,147 @@
+//! Scripts downloading the wheels a dependency graph resolved to, for `deps --emit-download-script`.
+//!
+//! This splits planning from downloading for air-gapped setups:
+//! a machine with index access resolves lazily, another one with plain internet access fetches the files.
+
+use std::fmt::Write as _;
+
+use eyre::Result;
+use reqwest::Url;
+
+use crate::LazyZipClient;
+use crate::dep_graph::DepGraph;
+
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScriptFormat {
+    /// A POSIX shell script using `curl` and `sha256sum`
+    #[default]
+    Sh,
+    /// An input file for `aria2c -i`
+    Aria2c,
+}
+
+/// A file to download
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    pub filename: String,
+    pub url: Url,
+    pub sha256: Option<String>,
+}
+
+/// The files of a graph’s resolved nodes, with URLs rewritten like the client would,
+/// and the names of packages that didn’t resolve to a file on an index
+pub fn artifacts(client: &LazyZipClient, graph: &DepGraph) -> Result<(Vec<Artifact>, Vec<String>)> {
+    let mut artifacts = Vec::new();
+    let mut missing = Vec::new();
+    for (_, node) in graph.nodes() {
+        let Some(file) = &node.file else {
+            if !node.excluded {
+                missing.push(node.name.to_string());
+            }
+            continue;
+        };
+        artifacts.push(Artifact {
+            filename: file.filename.clone(),
+            url: client.rewrite_url(file.url.clone())?,
+            sha256: file.hashes.get("sha256").cloned(),
+        });
+    }
+    Ok((artifacts, missing))
+}
+
+/// Render a script downloading `artifacts` to the current directory and verifying their hashes.
+/// Packages in `missing` are listed in a comment, as they have to be downloaded some other way.
+pub fn render(artifacts: &[Artifact], missing: &[String], format: ScriptFormat) -> String {
+    let mut out = String::new();
+    if format == ScriptFormat::Sh {
+        out.push_str(concat!(
+            "#!/bin/sh\n",
+            "set -eu\n",
+            "download() {\n",
+            "    curl --fail --location --retry 3 --output \"$2\" \"$1\"\n",
+            "    if [ -n \"$3\" ]; then echo \"$3  $2\" | sha256sum --check --quiet -; fi\n",
+            "}\n",
+        ));
+    }
+    if !missing.is_empty() {
+        writeln!(
+            out,
+            "# Not resolved to files on an index: {}",
+            missing.join(", ")
+        )
+        .unwrap();
+    }
+    for artifact in artifacts {
+        match format {
+            ScriptFormat::Sh => {
+                let sha256 = artifact.sha256.as_deref().unwrap_or_default();
+                writeln!(
+                    out,
+                    "download {} {} {}",
+                    sh_quote(artifact.url.as_str()),
+                    sh_quote(&artifact.filename),
+                    sh_quote(sha256),
+                )
+                .unwrap();
+            }
+            ScriptFormat::Aria2c => {
+                writeln!(out, "{}\n  out={}", artifact.url, artifact.filename).unwrap();
+                if let Some(sha256) = &artifact.sha256 {
+                    writeln!(out, "  checksum=sha-256={sha256}").unwrap();
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Quote a string for POSIX shells
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifacts() -> Vec<Artifact> {
+        vec![
+            Artifact {
+                filename: "foo-1.0-py3-none-any.whl".to_owned(),
+                url: Url::parse("https://files.example.com/foo-1.0-py3-none-any.whl").unwrap(),
+                sha256: Some("abc".to_owned()),
+            },
+            Artifact {
+                filename: "it's-1.0-py3-none-any.whl".to_owned(),
+                url: Url::parse("https://files.example.com/its-1.0-py3-none-any.whl").unwrap(),
+                sha256: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_render_sh() {
+        let script = render(&artifacts(), &["bar".to_owned()], ScriptFormat::Sh);
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("# Not resolved to files on an index: bar\n"));
+        assert!(script.ends_with(concat!(
+            "download 'https://files.example.com/foo-1.0-py3-none-any.whl' 'foo-1.0-py3-none-any.whl' 'abc'\n",
+            "download 'https://files.example.com/its-1.0-py3-none-any.whl' 'it'\\''s-1.0-py3-none-any.whl' ''\n",
+        )));
+    }
+
+    #[test]
+    fn test_render_aria2c() {
+        assert_eq!(
+            render(&artifacts(), &[], ScriptFormat::Aria2c),
+            concat!(
+                "https://files.example.com/foo-1.0-py3-none-any.whl\n",
+                "  out=foo-1.0-py3-none-any.whl\n",
+                "  checksum=sha-256=abc\n",
+                "https://files.example.com/its-1.0-py3-none-any.whl\n",
+                "  out=it's-1.0-py3-none-any.whl\n",
+            )
+        );
+    }
+}
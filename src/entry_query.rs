@@ -0,0 +1,281 @@
+//! Selecting zip entries by name, size and compression, for `list --query` and [`find_entry`](crate::wheel::find_entry).
+//!
+//! Queries combine with [`EntryQuery::and`], [`EntryQuery::or`] and [`EntryQuery::not`]:
+//! `EntryQuery::glob("*.pyi").or(EntryQuery::name_exact("py.typed")).and(EntryQuery::max_size(1_000_000))`.
+//! On the command line, that’s `'(glob:*.pyi or name:py.typed) and max-size:1MB'`.
+
+use std::str::FromStr;
+
+use async_zip::{Compression, StoredZipEntry};
+use eyre::{Context as _, Error, Result, bail};
+
+use crate::glob::glob_matches;
+use crate::python_pkg::PackageName;
+use crate::wheel::open_zip;
+use crate::{LazyZipClient, PkgLoc};
+
+/// A selection of zip entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryQuery {
+    /// Paths matching a pattern where `*` and `?` don’t match `/`.
+    /// Patterns without a `/` match file names in any directory.
+    Glob(String),
+    /// The exact path, or the file name in any directory if it has no `/`
+    NameExact(String),
+    /// At most this many bytes uncompressed
+    MaxSize(u64),
+    /// At least this many bytes uncompressed
+    MinSize(u64),
+    /// Stored without compression
+    Stored,
+    /// Directory entries
+    Dir,
+    And(Box<Self>, Box<Self>),
+    Or(Box<Self>, Box<Self>),
+    Not(Box<Self>),
+}
+
+impl EntryQuery {
+    pub fn glob(pattern: impl Into<String>) -> Self {
+        Self::Glob(pattern.into())
+    }
+
+    pub fn name_exact(name: impl Into<String>) -> Self {
+        Self::NameExact(name.into())
+    }
+
+    pub fn max_size(size: u64) -> Self {
+        Self::MaxSize(size)
+    }
+
+    pub fn min_size(size: u64) -> Self {
+        Self::MinSize(size)
+    }
+
+    #[must_use]
+    pub fn and(self, other: Self) -> Self {
+        Self::And(Box::new(self), Box::new(other))
+    }
+
+    #[must_use]
+    pub fn or(self, other: Self) -> Self {
+        Self::Or(Box::new(self), Box::new(other))
+    }
+
+    #[must_use]
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Self::Not(Box::new(self))
+    }
+
+    /// Whether an entry is selected. Entries with names that aren’t UTF-8 never are.
+    pub fn matches(&self, entry: &StoredZipEntry) -> bool {
+        let Ok(path) = entry.filename().as_str() else {
+            return false;
+        };
+        self.matches_parts(
+            path,
+            entry.uncompressed_size(),
+            entry.compression() == Compression::Stored,
+        )
+    }
+
+    fn matches_parts(&self, path: &str, size: u64, stored: bool) -> bool {
+        let file_name = || {
+            path.trim_end_matches('/')
+                .rsplit_once('/')
+                .map_or(path, |(_, name)| name)
+        };
+        match self {
+            Self::Glob(pattern) if pattern.contains('/') => glob_matches(pattern, path),
+            Self::Glob(pattern) => glob_matches(pattern, file_name()),
+            Self::NameExact(name) if name.contains('/') => path == name,
+            Self::NameExact(name) => file_name() == name,
+            Self::MaxSize(max) => size <= *max,
+            Self::MinSize(min) => size >= *min,
+            Self::Stored => stored,
+            Self::Dir => path.ends_with('/'),
+            Self::And(l, r) => {
+                l.matches_parts(path, size, stored) && r.matches_parts(path, size, stored)
+            }
+            Self::Or(l, r) => {
+                l.matches_parts(path, size, stored) || r.matches_parts(path, size, stored)
+            }
+            Self::Not(query) => !query.matches_parts(path, size, stored),
+        }
+    }
+}
+
+/// The paths of a package’s entries matching `query`
+#[tracing::instrument(skip(client), fields(pkg_loc = %pkg_loc))]
+pub async fn list_matching(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+    query: &EntryQuery,
+) -> Result<(PackageName, Vec<String>)> {
+    let (name, reader) = open_zip(client, pkg_loc).await?;
+    let paths = reader
+        .file()
+        .entries()
+        .iter()
+        .filter(|e| query.matches(e))
+        .map(|e| Ok(e.filename().as_str()?.to_owned()))
+        .collect::<Result<Vec<_>>>()?;
+    Ok((name, paths))
+}
+
+/// Parse queries like `glob:*.dist-info/*.txt and not (dir or min-size:1MB)`.
+/// `and` binds tighter than `or`.
+impl FromStr for EntryQuery {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let tokens = tokenize(s);
+        let mut pos = 0;
+        let query = parse_or(&tokens, &mut pos)?;
+        if let Some(token) = tokens.get(pos) {
+            bail!("unexpected {token:?} in query {s:?}");
+        }
+        Ok(query)
+    }
+}
+
+fn parse_or(tokens: &[&str], pos: &mut usize) -> Result<EntryQuery> {
+    let mut query = parse_and(tokens, pos)?;
+    while tokens.get(*pos) == Some(&"or") {
+        *pos += 1;
+        query = query.or(parse_and(tokens, pos)?);
+    }
+    Ok(query)
+}
+
+fn parse_and(tokens: &[&str], pos: &mut usize) -> Result<EntryQuery> {
+    let mut query = parse_not(tokens, pos)?;
+    while tokens.get(*pos) == Some(&"and") {
+        *pos += 1;
+        query = query.and(parse_not(tokens, pos)?);
+    }
+    Ok(query)
+}
+
+fn parse_not(tokens: &[&str], pos: &mut usize) -> Result<EntryQuery> {
+    let Some(&token) = tokens.get(*pos) else {
+        bail!("unexpected end of query");
+    };
+    *pos += 1;
+    match token {
+        "not" => Ok(parse_not(tokens, pos)?.not()),
+        "(" => {
+            let query = parse_or(tokens, pos)?;
+            if tokens.get(*pos) != Some(&")") {
+                bail!("missing `)` in query");
+            }
+            *pos += 1;
+            Ok(query)
+        }
+        "stored" => Ok(EntryQuery::Stored),
+        "dir" => Ok(EntryQuery::Dir),
+        _ => match token.split_once(':') {
+            Some(("glob", pattern)) => Ok(EntryQuery::glob(pattern)),
+            Some(("name", name)) => Ok(EntryQuery::name_exact(name)),
+            Some(("max-size", size)) => Ok(EntryQuery::max_size(parse_size(size)?)),
+            Some(("min-size", size)) => Ok(EntryQuery::min_size(parse_size(size)?)),
+            _ => bail!(
+                "unknown query term {token:?}, expected `glob:`, `name:`, `max-size:`, `min-size:`, `stored` or `dir`"
+            ),
+        },
+    }
+}
+
+fn tokenize(query: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    for word in query.split_whitespace() {
+        let mut rest = word;
+        while let Some(inner) = rest.strip_prefix('(') {
+            tokens.push("(");
+            rest = inner;
+        }
+        let closing = rest.len() - rest.trim_end_matches(')').len();
+        if closing < rest.len() {
+            tokens.push(&rest[..rest.len() - closing]);
+        }
+        tokens.extend(std::iter::repeat_n(")", closing));
+    }
+    tokens
+}
+
+/// Parse a size in bytes with an optional decimal (`kB`, `MB`, `GB`) or binary (`KiB`, `MiB`, `GiB`) unit
+pub fn parse_size(size: &str) -> Result<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let factor: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "m" | "mb" => 1000_u64.pow(2),
+        "g" | "gb" => 1000_u64.pow(3),
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        _ => bail!("unknown unit {:?}", unit.trim()),
+    };
+    let number: f64 = number.parse().context("expected a number like 900MB")?;
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    Ok((number * factor as f64).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let query = EntryQuery::glob("*.pyi")
+            .or(EntryQuery::name_exact("py.typed"))
+            .and(EntryQuery::max_size(1_000_000));
+        assert!(query.matches_parts("foo/bar/__init__.pyi", 100, false));
+        assert!(query.matches_parts("foo/py.typed", 0, true));
+        assert!(!query.matches_parts("foo/huge.pyi", 2_000_000, false));
+        assert!(!query.matches_parts("foo/bar.py", 100, false));
+
+        let query = EntryQuery::glob("*.dist-info/*").and(EntryQuery::Dir.not());
+        assert!(query.matches_parts("foo-1.0.dist-info/METADATA", 100, false));
+        assert!(!query.matches_parts("foo-1.0.dist-info/licenses/LICENSE", 100, false));
+        assert!(!query.matches_parts("foo/METADATA", 100, false));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "(glob:*.pyi or name:py.typed) and max-size:1MB"
+                .parse::<EntryQuery>()
+                .unwrap(),
+            EntryQuery::glob("*.pyi")
+                .or(EntryQuery::name_exact("py.typed"))
+                .and(EntryQuery::max_size(1_000_000))
+        );
+        assert_eq!(
+            "glob:*.so or stored and not dir"
+                .parse::<EntryQuery>()
+                .unwrap(),
+            EntryQuery::glob("*.so").or(EntryQuery::Stored.and(EntryQuery::Dir.not()))
+        );
+        assert!("(glob:*.so".parse::<EntryQuery>().is_err());
+        assert!("size:1".parse::<EntryQuery>().is_err());
+        assert!("glob:*.so name:x".parse::<EntryQuery>().is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("900").unwrap(), 900);
+        assert_eq!(parse_size("1.5 MB").unwrap(), 1_500_000);
+        assert_eq!(parse_size("2KiB").unwrap(), 2048);
+        assert!(parse_size("5 parsecs").is_err());
+    }
+}
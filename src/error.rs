@@ -0,0 +1,183 @@
+//! Classifying library errors, for callers that handle some kinds of failure differently.
+//!
+//! Library functions return [`eyre::Report`](eyre::Report)s with context describing what was attempted.
+//! Converting one into an [`Error`] tells what kind of failure it was, keeping the report:
+//!
+//! ```no_run
+//! # async fn example(client: &pypi_lazyzip::LazyZipClient) {
+//! use pypi_lazyzip::error::Error;
+//!
+//! match pypi_lazyzip::wheel::pkg_metadata(client, "foo".parse().unwrap()).await.map_err(Error::from) {
+//!     Ok((_, metadata)) => println!("{:?}", metadata.get("Summary")),
+//!     Err(Error::NotFound(_) | Error::NoCompatibleFile(_)) => println!("no wheel"),
+//!     Err(e) => eprintln!("{e}"),
+//! }
+//! # }
+//! ```
+
+use eyre::Report;
+
+/// A library error, by what failed. Every variant has the whole report, with its context.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An index returned an unusable page, e.g. invalid JSON or an unsupported API version
+    #[error("{0:#}")]
+    Index(Report),
+    /// No index has the project
+    #[error("{0:#}")]
+    NotFound(Report),
+    /// The project has no file matching the requirement, e.g. no wheel or none for the target
+    #[error("{0:#}")]
+    NoCompatibleFile(Report),
+    /// A file isn’t a readable zip archive
+    #[error("{0:#}")]
+    Zip(Report),
+    /// An archive lacks an entry that’s needed, e.g. `METADATA`
+    #[error("{0:#}")]
+    EntryMissing(Report),
+    /// A request failed or got an error response
+    #[error("{0:#}")]
+    Http(Report),
+    #[error("{0:#}")]
+    Other(Report),
+}
+
+impl Error {
+    pub fn report(&self) -> &Report {
+        match self {
+            Self::Index(report)
+            | Self::NotFound(report)
+            | Self::NoCompatibleFile(report)
+            | Self::Zip(report)
+            | Self::EntryMissing(report)
+            | Self::Http(report)
+            | Self::Other(report) => report,
+        }
+    }
+
+    pub fn into_report(self) -> Report {
+        match self {
+            Self::Index(report)
+            | Self::NotFound(report)
+            | Self::NoCompatibleFile(report)
+            | Self::Zip(report)
+            | Self::EntryMissing(report)
+            | Self::Http(report)
+            | Self::Other(report) => report,
+        }
+    }
+}
+
+/// Classify by the outermost [`Failure`] in the report, else by the errors it was caused by
+impl From<Report> for Error {
+    fn from(report: Report) -> Self {
+        if let Some(failure) = report.downcast_ref::<Failure>() {
+            return match failure.kind {
+                Kind::Index => Self::Index(report),
+                Kind::NotFound => Self::NotFound(report),
+                Kind::NoCompatibleFile => Self::NoCompatibleFile(report),
+                Kind::EntryMissing => Self::EntryMissing(report),
+            };
+        }
+        if report
+            .chain()
+            .any(<dyn std::error::Error>::is::<async_zip::error::ZipError>)
+        {
+            Self::Zip(report)
+        } else if report
+            .chain()
+            .any(|e| e.is::<reqwest::Error>() || e.is::<reqwest_middleware::Error>())
+        {
+            Self::Http(report)
+        } else {
+            Self::Other(report)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Index,
+    NotFound,
+    NoCompatibleFile,
+    EntryMissing,
+}
+
+/// A failure of a known kind, raised or added as context where it happens, so [`Error::from`] can tell
+#[derive(thiserror::Error, Debug)]
+#[error("{message}")]
+pub(crate) struct Failure {
+    kind: Kind,
+    message: String,
+}
+
+impl Failure {
+    pub(crate) fn index(message: impl Into<String>) -> Self {
+        Self::new(Kind::Index, message)
+    }
+
+    pub(crate) fn not_found(message: impl Into<String>) -> Self {
+        Self::new(Kind::NotFound, message)
+    }
+
+    pub(crate) fn no_compatible_file(message: impl Into<String>) -> Self {
+        Self::new(Kind::NoCompatibleFile, message)
+    }
+
+    pub(crate) fn entry_missing(message: impl Into<String>) -> Self {
+        Self::new(Kind::EntryMissing, message)
+    }
+
+    fn new(kind: Kind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eyre::{WrapErr as _, eyre};
+
+    use super::*;
+    use crate::test_util::{MockIndex, MockWheel};
+
+    #[test]
+    fn test_classify() {
+        let report = Err::<(), _>(Failure::entry_missing("wheel has no METADATA"))
+            .wrap_err("reading foo")
+            .unwrap_err();
+        let error = Error::from(report);
+        assert!(matches!(error, Error::EntryMissing(_)));
+        assert_eq!(error.to_string(), "reading foo: wheel has no METADATA");
+
+        let report = Err::<(), _>(eyre!("bad page"))
+            .wrap_err(Failure::index("Unusable page for foo"))
+            .wrap_err("looking up foo")
+            .unwrap_err();
+        assert!(matches!(Error::from(report), Error::Index(_)));
+        assert!(matches!(Error::from(eyre!("other")), Error::Other(_)));
+    }
+
+    #[tokio::test]
+    async fn test_from_library() {
+        let index = MockIndex::start([MockWheel::new("foo", "1.0").corrupt()])
+            .await
+            .unwrap();
+        let client = index.client();
+        let err = crate::wheel::pkg_metadata(&client, "bar".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert!(matches!(Error::from(err), Error::NotFound(_)));
+        let err = crate::wheel::pkg_metadata(&client, "foo>1".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert!(matches!(Error::from(err), Error::NoCompatibleFile(_)));
+        let err = crate::wheel::pkg_metadata(&client, "foo".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert!(matches!(Error::from(err), Error::Zip(_)));
+    }
+}
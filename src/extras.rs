@@ -0,0 +1,110 @@
+//! Which requirements each extra of a package activates, for `extras`.
+//!
+//! Derived from `Provides-Extra` and the `extra` markers in `Requires-Dist`,
+//! which also reveals extras that are used in markers but not declared.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr as _;
+
+use eyre::Result;
+use pep508_rs::{ExtraName, MarkerExpression, MarkerValueExtra, Requirement};
+use serde::Serialize;
+
+use crate::python_pkg::Metadata;
+
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct Extras {
+    /// Requirements activated by each declared extra, without the `extra` part of their markers
+    pub extras: BTreeMap<String, Vec<String>>,
+    /// Extras used in markers without being declared, which installers won’t let users ask for
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub undeclared: BTreeMap<String, Vec<String>>,
+}
+
+impl Extras {
+    pub fn from_metadata(metadata: &Metadata) -> Result<Self> {
+        let declared = metadata
+            .get_all("Provides-Extra")
+            .map(ExtraName::from_str)
+            .collect::<Result<BTreeSet<_>, _>>()?;
+        let requires_dist = metadata.requires_dist()?;
+        let used = requires_dist
+            .iter()
+            .flat_map(|req| req.marker.to_dnf())
+            .flatten()
+            .filter_map(|expression| match expression {
+                MarkerExpression::Extra {
+                    name: MarkerValueExtra::Extra(name),
+                    ..
+                } => Some(name),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>();
+        let report = |extras: &mut dyn Iterator<Item = &ExtraName>| {
+            extras
+                .map(|extra| (extra.to_string(), activated(&requires_dist, extra)))
+                .collect()
+        };
+        Ok(Self {
+            extras: report(&mut declared.iter()),
+            undeclared: report(&mut used.difference(&declared)),
+        })
+    }
+}
+
+/// Requirements only active with `extra`, with it removed from their markers
+fn activated(requires_dist: &[Requirement], extra: &ExtraName) -> Vec<String> {
+    let extras = std::slice::from_ref(extra);
+    requires_dist
+        .iter()
+        .filter(|req| req.marker.evaluate_extras(extras) && !req.marker.evaluate_extras(&[]))
+        .map(|req| {
+            let mut req = req.clone();
+            req.marker = req.marker.simplify_extras(extras);
+            req.to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_metadata() {
+        let metadata = Metadata::from_str(
+            "Metadata-Version: 2.1\n\
+             Name: foo\n\
+             Version: 1.0\n\
+             Provides-Extra: socks\n\
+             Provides-Extra: Docs\n\
+             Provides-Extra: empty\n\
+             Requires-Dist: bar>=1\n\
+             Requires-Dist: pysocks>=1.5; extra == 'socks'\n\
+             Requires-Dist: sphinx; extra == 'docs' and python_version >= '3.9'\n\
+             Requires-Dist: pytest; extra == 'test'\n",
+        )
+        .unwrap();
+        let extras = Extras::from_metadata(&metadata).unwrap();
+        let expected = |entries: &[(&str, &[&str])]| {
+            entries
+                .iter()
+                .map(|(extra, reqs)| {
+                    let reqs = reqs.iter().map(|&req| req.to_owned()).collect();
+                    ((*extra).to_owned(), reqs)
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+        assert_eq!(
+            extras,
+            Extras {
+                extras: expected(&[
+                    ("docs", &["sphinx ; python_full_version >= '3.9'"]),
+                    ("empty", &[]),
+                    ("socks", &["pysocks>=1.5"]),
+                ]),
+                undeclared: expected(&[("test", &["pytest"])]),
+            }
+        );
+    }
+}
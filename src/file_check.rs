@@ -0,0 +1,168 @@
+//! Probing all files of a release with `HEAD` requests, for `check-files`.
+//!
+//! Checks that a mirror serves every file, with the size the index lists (PEP 700),
+//! and that it supports the range requests lazy reading relies on, without downloading anything.
+
+use eyre::{OptionExt as _, Result};
+use futures::future::join_all;
+use reqwest::StatusCode;
+use reqwest::header::{ACCEPT_RANGES, CONTENT_LENGTH};
+use serde::Serialize;
+
+use crate::LazyZipClient;
+use crate::lockfile::release_version;
+use crate::python_pkg::Dependency;
+use crate::simple_repo_api::{self, Yanking};
+
+/// The result of probing one file
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct FileCheck {
+    pub filename: String,
+    pub url: String,
+    /// Unset if the request failed
+    pub status: Option<u16>,
+    pub problems: Vec<Problem>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Problem {
+    /// The request failed without a response
+    RequestFailed { error: String },
+    /// The response wasn’t successful
+    Unavailable,
+    /// The response has no `Content-Length` to compare with the index’s `size`
+    LengthMissing,
+    /// The file’s length differs from the size the index lists
+    SizeMismatch { size: u64, content_length: u64 },
+    /// The host doesn’t announce `Accept-Ranges: bytes`, so files are downloaded fully
+    RangesUnsupported,
+}
+
+/// The newest release matching `dep` and the constraints with unyanked files, and the probes of its files
+pub async fn check_release(
+    client: &LazyZipClient,
+    dep: &Dependency,
+) -> Result<(pep440_rs::Version, Vec<FileCheck>)> {
+    let dep = client.constrain(dep);
+    let project = client.find_project(dep.name()).await?;
+    let files = project
+        .files
+        .into_iter()
+        .filter(|file| matches!(file.yanked, Yanking::NotYanked))
+        .filter_map(|file| Some((release_version(&file.filename)?, file)))
+        .filter(|(version, _)| {
+            dep.version_spec()
+                .is_none_or(|version_spec| version_spec.contains(version))
+        })
+        .collect::<Vec<_>>();
+    let version = files
+        .iter()
+        .map(|(version, _)| version)
+        .max()
+        .cloned()
+        .ok_or_eyre(format!("No release matching {dep} found"))?;
+    let checks = join_all(
+        files
+            .into_iter()
+            .filter(|(v, _)| *v == version)
+            .map(|(_, file)| check_file(client, file)),
+    )
+    .await;
+    Ok((version, checks))
+}
+
+async fn check_file(client: &LazyZipClient, file: simple_repo_api::File) -> FileCheck {
+    let mut check = FileCheck {
+        filename: file.filename,
+        url: file.url.to_string(),
+        status: None,
+        problems: Vec::new(),
+    };
+    let url = match client.rewrite_url(file.url) {
+        Ok(url) => url,
+        Err(e) => {
+            check.problems.push(Problem::RequestFailed {
+                error: format!("{e:#}"),
+            });
+            return check;
+        }
+    };
+    check.url = url.to_string();
+    let response = match client.http().head(url).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            check.problems.push(Problem::RequestFailed {
+                error: e.to_string(),
+            });
+            return check;
+        }
+    };
+    check.status = Some(response.status().as_u16());
+    if response.status() != StatusCode::OK {
+        check.problems.push(Problem::Unavailable);
+        return check;
+    }
+    let headers = response.headers();
+    // `Response::content_length` is the body’s, which is empty for `HEAD`
+    let content_length = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|len| len.to_str().ok()?.parse::<u64>().ok());
+    match (content_length, file.size) {
+        (None, _) => check.problems.push(Problem::LengthMissing),
+        (Some(content_length), Some(size)) if content_length != size => {
+            check.problems.push(Problem::SizeMismatch {
+                size,
+                content_length,
+            });
+        }
+        _ => {}
+    }
+    let ranges = headers
+        .get_all(ACCEPT_RANGES)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|unit| unit.trim().eq_ignore_ascii_case("bytes"));
+    if !ranges {
+        check.problems.push(Problem::RangesUnsupported);
+    }
+    check
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr as _;
+
+    use super::*;
+    use crate::test_util::{MockIndex, MockWheel};
+
+    #[tokio::test]
+    async fn test_check_release() {
+        let index = MockIndex::start([
+            MockWheel::new("foo", "1.0"),
+            MockWheel::new("foo", "2.0"),
+            MockWheel::new("foo", "3.0").yanked(None),
+        ])
+        .await
+        .unwrap();
+        let client = index.client();
+        let dep = Dependency::from_str("foo").unwrap();
+        let (version, checks) = check_release(&client, &dep).await.unwrap();
+        assert_eq!(version.to_string(), "2.0");
+        assert_eq!(
+            checks,
+            [FileCheck {
+                filename: "foo-2.0-py3-none-any.whl".to_owned(),
+                url: index.file_url("foo-2.0-py3-none-any.whl").to_string(),
+                status: Some(200),
+                problems: vec![],
+            }]
+        );
+
+        let client = client.with_url_rewrite(&index.file_url(""), &index.file_url("missing/"));
+        let (_, checks) = check_release(&client, &dep).await.unwrap();
+        assert_eq!(checks[0].status, Some(404));
+        assert_eq!(checks[0].problems, [Problem::Unavailable]);
+    }
+}
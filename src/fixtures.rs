@@ -0,0 +1,158 @@
+//! Recording HTTP interactions to a directory and replaying them without network access,
+//! for hermetic tests and reproducible bug reports.
+//!
+//! Every interaction is stored as `<key>.json` with the response’s status and headers,
+//! and `<key>.body` with its body, where the key is a hash of the request’s method, URL, and `Range` header.
+
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use http::Extensions;
+use reqwest::header::RANGE;
+use reqwest::{Request, Response, ResponseBuilderExt as _, Url};
+use reqwest_middleware::{Error, Middleware, Next};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Interaction {
+    method: String,
+    url: Url,
+    range: Option<String>,
+    status: u16,
+    headers: Vec<(String, String)>,
+}
+
+/// What identifies a request when replaying
+struct RequestKey {
+    method: String,
+    url: Url,
+    range: Option<String>,
+}
+
+impl RequestKey {
+    fn new(req: &Request) -> Self {
+        Self {
+            method: req.method().to_string(),
+            url: req.url().clone(),
+            range: req
+                .headers()
+                .get(RANGE)
+                .and_then(|range| range.to_str().ok())
+                .map(ToOwned::to_owned),
+        }
+    }
+
+    fn path(&self, dir: &Path, extension: &str) -> PathBuf {
+        let hash = Sha256::digest(format!(
+            "{} {} {}",
+            self.method,
+            self.url,
+            self.range.as_deref().unwrap_or_default()
+        ));
+        let name = hash[..16].iter().fold(String::new(), |mut name, b| {
+            let _ = write!(name, "{b:02x}");
+            name
+        });
+        dir.join(name).with_extension(extension)
+    }
+}
+
+/// Middleware saving every response to a directory
+#[derive(Debug)]
+pub struct Recorder {
+    dir: PathBuf,
+}
+
+impl Recorder {
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for Recorder {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let key = RequestKey::new(&req);
+        let response = next.run(req, extensions).await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?;
+
+        let interaction = Interaction {
+            method: key.method.clone(),
+            url: key.url.clone(),
+            range: key.range.clone(),
+            status: status.as_u16(),
+            headers: headers
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_owned()))
+                })
+                .collect(),
+        };
+        let json = serde_json::to_vec_pretty(&interaction).map_err(Error::middleware)?;
+        std::fs::write(key.path(&self.dir, "json"), json).map_err(Error::middleware)?;
+        std::fs::write(key.path(&self.dir, "body"), &body).map_err(Error::middleware)?;
+        tracing::debug!(method = key.method, url = %key.url, range = key.range, "recorded");
+
+        let mut builder = http::Response::builder().status(status).url(key.url);
+        if let Some(builder_headers) = builder.headers_mut() {
+            *builder_headers = headers;
+        }
+        Ok(builder.body(body).map_err(Error::middleware)?.into())
+    }
+}
+
+/// Middleware answering requests with responses saved by [`Recorder`], never accessing the network
+#[derive(Debug)]
+pub struct Replayer {
+    dir: PathBuf,
+}
+
+impl Replayer {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for Replayer {
+    async fn handle(
+        &self,
+        req: Request,
+        _extensions: &mut Extensions,
+        _next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let key = RequestKey::new(&req);
+        let json = std::fs::read(key.path(&self.dir, "json")).map_err(|e| {
+            let range = key
+                .range
+                .as_deref()
+                .map(|r| format!(" ({r})"))
+                .unwrap_or_default();
+            Error::middleware(io::Error::new(
+                e.kind(),
+                format!("no recorded response for {} {}{range}", key.method, key.url),
+            ))
+        })?;
+        let interaction: Interaction = serde_json::from_slice(&json).map_err(Error::middleware)?;
+        let body = std::fs::read(key.path(&self.dir, "body")).map_err(Error::middleware)?;
+
+        let mut builder = http::Response::builder()
+            .status(interaction.status)
+            .url(interaction.url);
+        for (name, value) in interaction.headers {
+            builder = builder.header(name, value);
+        }
+        Ok(builder.body(body).map_err(Error::middleware)?.into())
+    }
+}
@@ -0,0 +1,20 @@
+//! Shell-style wildcard patterns, for TUF delegations and `deps --exclude`.
+
+/// Match a path against a pattern where `*` and `?` don’t match `/`
+pub(crate) fn glob_matches(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[u8], path: &[u8]) -> bool {
+        match (pattern.split_first(), path.split_first()) {
+            (None, None) => true,
+            (Some((b'*', rest)), _) => {
+                matches(rest, path)
+                    || path
+                        .split_first()
+                        .is_some_and(|(c, path)| *c != b'/' && matches(pattern, path))
+            }
+            (Some((b'?', rest)), Some((c, path))) => *c != b'/' && matches(rest, path),
+            (Some((p, rest)), Some((c, path))) => p == c && matches(rest, path),
+            _ => false,
+        }
+    }
+    matches(pattern.as_bytes(), path.as_bytes())
+}
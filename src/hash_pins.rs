@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use eyre::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+
+/// Trust-on-first-use store of file hashes.
+///
+/// The first hash seen for a file name is pinned, later ones are compared against it.
+/// Files published on the Python Package Index are immutable, so a changed hash hints at tampering.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct HashPins {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(skip)]
+    dirty: bool,
+    /// SHA-256 hashes by file name
+    sha256: BTreeMap<String, String>,
+}
+
+impl HashPins {
+    /// Load pins from `path`, starting empty if it doesn’t exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut pins = if path.exists() {
+            let content = std::fs::read(path)
+                .with_context(|| format!("Failed to read hash pins {}", path.display()))?;
+            serde_json::from_slice::<Self>(&content)
+                .with_context(|| format!("Failed to parse hash pins {}", path.display()))?
+        } else {
+            Self::default()
+        };
+        path.clone_into(&mut pins.path);
+        Ok(pins)
+    }
+
+    /// Pin `sha256` for `filename` if it’s new. Returns the pinned hash if it differs.
+    pub fn check(&mut self, filename: &str, sha256: &str) -> Option<String> {
+        match self.sha256.get(filename) {
+            Some(pinned) if pinned.eq_ignore_ascii_case(sha256) => None,
+            Some(pinned) => Some(pinned.clone()),
+            None => {
+                self.sha256.insert(filename.to_owned(), sha256.to_owned());
+                self.dirty = true;
+                None
+            }
+        }
+    }
+
+    /// Write the pins back if new ones were added, replacing the file only once it’s fully written
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write hash pins {}", self.path.display()))?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to replace hash pins {}", self.path.display()))?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let mut pins = HashPins::default();
+        assert_eq!(pins.check("foo-1.0-py3-none-any.whl", "abc"), None);
+        assert_eq!(pins.check("foo-1.0-py3-none-any.whl", "ABC"), None);
+        assert_eq!(
+            pins.check("foo-1.0-py3-none-any.whl", "def"),
+            Some("abc".to_owned())
+        );
+        assert_eq!(pins.check("bar-1.0-py3-none-any.whl", "def"), None);
+    }
+}
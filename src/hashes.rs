@@ -0,0 +1,151 @@
+//! Verifying downloaded files against the hashes indexes or URL fragments list for them.
+//!
+//! Every digest is computed on its own blocking thread while chunks arrive,
+//! so hashing overlaps with the download instead of starting once it’s done.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use blake2::Blake2b512;
+use bytes::Bytes;
+use eyre::{Result, bail};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Chunks buffered per digest before the download waits for hashing to catch up
+const CHUNK_BACKLOG: usize = 16;
+
+/// The expected hashes of a file, named like in `hashlib`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hashes {
+    pub sha256: Option<String>,
+    /// `BLAKE2b` with a 512 bit digest
+    pub blake2b: Option<String>,
+}
+
+impl Hashes {
+    /// Take the supported hashes out of an index’s or URL fragment’s hashes
+    pub fn take_from(hashes: &mut HashMap<String, String>) -> Self {
+        Self {
+            sha256: hashes.remove("sha256"),
+            blake2b: hashes.remove("blake2b"),
+        }
+    }
+}
+
+/// Computes digests of a file as it’s streamed, to compare them to the expected [`Hashes`]
+pub struct StreamHasher {
+    workers: Vec<Worker>,
+}
+
+struct Worker {
+    name: &'static str,
+    expected: String,
+    chunks: mpsc::Sender<Bytes>,
+    digest: JoinHandle<String>,
+}
+
+impl StreamHasher {
+    /// Start hashing for every expected hash
+    pub fn new(expected: &Hashes) -> Self {
+        let mut workers = Vec::new();
+        if let Some(sha256) = &expected.sha256 {
+            workers.push(Worker::spawn::<Sha256>("SHA-256", sha256));
+        }
+        if let Some(blake2b) = &expected.blake2b {
+            workers.push(Worker::spawn::<Blake2b512>("BLAKE2b", blake2b));
+        }
+        Self { workers }
+    }
+
+    /// Hash the next chunk of the file
+    pub async fn update(&self, chunk: &Bytes) {
+        for worker in &self.workers {
+            // If the worker is gone, `verify` reports why
+            let _ = worker.chunks.send(chunk.clone()).await;
+        }
+    }
+
+    /// Wait for all digests and compare them to the expected hashes
+    pub async fn verify(self, url: &Url) -> Result<()> {
+        for worker in self.workers {
+            drop(worker.chunks);
+            let actual = worker.digest.await?;
+            if !actual.eq_ignore_ascii_case(&worker.expected) {
+                bail!(
+                    "{} mismatch for {url}: expected {}, got {actual}",
+                    worker.name,
+                    worker.expected
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Worker {
+    fn spawn<D: Digest + Send + 'static>(name: &'static str, expected: &str) -> Self {
+        let (chunks, mut rx) = mpsc::channel::<Bytes>(CHUNK_BACKLOG);
+        let digest = tokio::task::spawn_blocking(move || {
+            let mut digest = D::new();
+            while let Some(chunk) = rx.blocking_recv() {
+                digest.update(&chunk);
+            }
+            hex(&digest.finalize())
+        });
+        Self {
+            name,
+            expected: expected.to_owned(),
+            chunks,
+            digest,
+        }
+    }
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, b| {
+        let _ = write!(hex, "{b:02x}");
+        hex
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn verify(expected: &Hashes, chunks: &[&'static [u8]]) -> Result<()> {
+        let hasher = StreamHasher::new(expected);
+        for chunk in chunks {
+            hasher.update(&Bytes::from_static(chunk)).await;
+        }
+        hasher
+            .verify(&Url::parse("https://example.com/foo.whl").unwrap())
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let expected = Hashes {
+            sha256: Some(hex(&Sha256::digest(b"foobar"))),
+            blake2b: Some(hex(&Blake2b512::digest(b"foobar"))),
+        };
+        verify(&expected, &[b"foo", b"bar"]).await.unwrap();
+        verify(&Hashes::default(), &[b"baz"]).await.unwrap();
+        let err = verify(&expected, &[b"foo", b"baz"]).await.unwrap_err();
+        assert!(err.to_string().starts_with("SHA-256 mismatch"), "{err}");
+    }
+
+    #[test]
+    fn test_take_from() {
+        let mut hashes = HashMap::from([
+            ("sha256".to_owned(), "abc".to_owned()),
+            ("md5".to_owned(), "def".to_owned()),
+        ]);
+        let taken = Hashes::take_from(&mut hashes);
+        assert_eq!(taken.sha256.as_deref(), Some("abc"));
+        assert_eq!(taken.blake2b, None);
+        assert_eq!(hashes.len(), 1);
+    }
+}
@@ -0,0 +1,254 @@
+//! Static simple-API trees for curated sets of files, for `index-gen`.
+//!
+//! The tree has `index.html` ([PEP 503](https://peps.python.org/pep-0503/))
+//! and `index.json` ([PEP 691](https://peps.python.org/pep-0691/)) files at its root and in a directory per project,
+//! so any static file server can host it. As they can’t negotiate content types,
+//! they serve the HTML variant to installers unless configured otherwise.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use eyre::Result;
+use serde::Serialize;
+
+use crate::blob_store::write_atomic;
+use crate::python_pkg::PackageName;
+use crate::simple_repo_api;
+
+/// The API version the generated files declare
+const API_VERSION: &str = "1.0";
+
+/// A file listed in the index
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct IndexFile {
+    pub filename: String,
+    /// Where installers download the file from
+    pub url: String,
+    pub hashes: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requires_python: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+impl IndexFile {
+    /// List `file` with its hashes, linking to `url`
+    pub fn new(file: &simple_repo_api::File, url: String) -> Self {
+        Self {
+            filename: file.filename.clone(),
+            url,
+            hashes: file.hashes.clone().into_iter().collect(),
+            requires_python: file.requires_python.clone(),
+            size: file.size,
+        }
+    }
+}
+
+/// What was written
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct Generated {
+    pub projects: usize,
+    pub files: usize,
+}
+
+/// A simple-API index with files added one by one
+#[derive(Debug, Default)]
+pub struct StaticIndex {
+    projects: BTreeMap<PackageName, BTreeMap<String, IndexFile>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Meta {
+    api_version: &'static str,
+}
+
+#[derive(Serialize)]
+struct RootJson<'a> {
+    meta: Meta,
+    projects: Vec<ProjectEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct ProjectEntry<'a> {
+    name: &'a str,
+}
+
+#[derive(Serialize)]
+struct ProjectJson<'a> {
+    meta: Meta,
+    name: &'a str,
+    files: Vec<&'a IndexFile>,
+}
+
+impl StaticIndex {
+    /// Add a file to a project. Files with the same name are only listed once.
+    pub fn add(&mut self, name: PackageName, file: IndexFile) {
+        self.projects
+            .entry(name)
+            .or_default()
+            .insert(file.filename.clone(), file);
+    }
+
+    /// Write the tree into `dest`, replacing files that exist
+    pub fn write(&self, dest: &Path) -> Result<Generated> {
+        write_atomic(&dest.join("index.html"), self.root_html().as_bytes())?;
+        write_atomic(&dest.join("index.json"), &self.root_json()?)?;
+        let mut generated = Generated::default();
+        for (name, files) in &self.projects {
+            let dir = dest.join(name.as_normalized());
+            write_atomic(
+                &dir.join("index.html"),
+                project_html(name, files).as_bytes(),
+            )?;
+            write_atomic(&dir.join("index.json"), &project_json(name, files)?)?;
+            generated.projects += 1;
+            generated.files += files.len();
+        }
+        Ok(generated)
+    }
+
+    fn root_html(&self) -> String {
+        let mut html = html_head("Simple index");
+        for name in self.projects.keys() {
+            let name = name.as_normalized();
+            writeln!(html, "<a href=\"{name}/\">{name}</a><br/>").unwrap();
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn root_json(&self) -> Result<Vec<u8>> {
+        let projects = self
+            .projects
+            .keys()
+            .map(|name| ProjectEntry {
+                name: name.as_normalized(),
+            })
+            .collect();
+        let root = RootJson {
+            meta: Meta {
+                api_version: API_VERSION,
+            },
+            projects,
+        };
+        Ok(serde_json::to_vec_pretty(&root)?)
+    }
+}
+
+fn project_html(name: &PackageName, files: &BTreeMap<String, IndexFile>) -> String {
+    let mut html = html_head(&format!("Links for {}", name.as_normalized()));
+    for file in files.values() {
+        let mut href = file.url.clone();
+        if let Some(sha256) = file.hashes.get("sha256") {
+            write!(href, "#sha256={sha256}").unwrap();
+        }
+        write!(html, "<a href=\"{}\"", html_escape(&href)).unwrap();
+        if let Some(requires_python) = &file.requires_python {
+            write!(
+                html,
+                " data-requires-python=\"{}\"",
+                html_escape(requires_python)
+            )
+            .unwrap();
+        }
+        writeln!(html, ">{}</a><br/>", html_escape(&file.filename)).unwrap();
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn project_json(name: &PackageName, files: &BTreeMap<String, IndexFile>) -> Result<Vec<u8>> {
+    let project = ProjectJson {
+        meta: Meta {
+            api_version: API_VERSION,
+        },
+        name: name.as_normalized(),
+        files: files.values().collect(),
+    };
+    Ok(serde_json::to_vec_pretty(&project)?)
+}
+
+fn html_head(title: &str) -> String {
+    format!(
+        concat!(
+            "<!DOCTYPE html>\n",
+            "<html>\n",
+            "<head>\n",
+            "<meta name=\"pypi:repository-version\" content=\"{version}\">\n",
+            "<title>{title}</title>\n",
+            "</head>\n",
+            "<body>\n",
+            "<h1>{title}</h1>\n",
+        ),
+        version = API_VERSION,
+        title = html_escape(title),
+    )
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(filename: &str, sha256: &str) -> IndexFile {
+        IndexFile {
+            filename: filename.to_owned(),
+            url: format!("../../files/{filename}"),
+            hashes: BTreeMap::from([("sha256".to_owned(), sha256.to_owned())]),
+            requires_python: Some(">=3.9".to_owned()),
+            size: Some(3),
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let mut index = StaticIndex::default();
+        index.add(
+            "Foo_Bar".parse().unwrap(),
+            file("foo_bar-1.0-py3-none-any.whl", "abc"),
+        );
+        index.add(
+            "foo-bar".parse().unwrap(),
+            file("foo_bar-1.0-py3-none-any.whl", "abc"),
+        );
+        index.add(
+            "baz".parse().unwrap(),
+            file("baz-2.0-py3-none-any.whl", "def"),
+        );
+        let dest =
+            std::env::temp_dir().join(format!("pypi-lazyzip-index-gen-{}", std::process::id()));
+        let generated = index.write(&dest).unwrap();
+        assert_eq!(
+            generated,
+            Generated {
+                projects: 2,
+                files: 2
+            }
+        );
+
+        let root = std::fs::read_to_string(dest.join("index.html")).unwrap();
+        assert!(root.contains("<a href=\"baz/\">baz</a><br/>\n<a href=\"foo-bar/\">foo-bar</a>"));
+        let html = std::fs::read_to_string(dest.join("foo-bar/index.html")).unwrap();
+        assert!(html.contains(concat!(
+            "<a href=\"../../files/foo_bar-1.0-py3-none-any.whl#sha256=abc\" data-requires-python=\"&gt;=3.9\">",
+            "foo_bar-1.0-py3-none-any.whl</a><br/>",
+        )));
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dest.join("foo-bar/index.json")).unwrap())
+                .unwrap();
+        assert_eq!(json["meta"]["api-version"], "1.0");
+        assert_eq!(json["name"], "foo-bar");
+        assert_eq!(json["files"][0]["requires-python"], ">=3.9");
+        assert_eq!(json["files"][0]["hashes"]["sha256"], "abc");
+        std::fs::remove_dir_all(dest).unwrap();
+    }
+}
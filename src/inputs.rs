@@ -0,0 +1,54 @@
+use color_eyre::eyre::Result;
+use pypi_lazyzip::lockfile::{self, LockFormat, LockedPackage};
+use pypi_lazyzip::python_pkg::{Dependency, PackageName};
+use pypi_lazyzip::{LazyZipClient, PkgLoc, top_packages};
+
+use crate::cli::InputArgs;
+
+/// Packages to process in addition to the ones given on the command line
+#[derive(Debug, Default)]
+pub struct Inputs {
+    /// From `--lockfile`
+    locked: Vec<LockedPackage>,
+    /// From `--lockfile` if it pins no versions
+    unlocked: Vec<Dependency>,
+    /// From `--from-top-packages`
+    top_packages: Vec<PackageName>,
+}
+
+impl Inputs {
+    pub async fn load(client: &LazyZipClient, input: &InputArgs) -> Result<Self> {
+        let top_packages = match &input.from_top_packages {
+            Some(source) => top_packages::load(client, source, input.top).await?,
+            None => Vec::new(),
+        };
+        let (locked, unlocked) = match &input.lockfile {
+            Some(path) if !LockFormat::detect(path).is_locked() => {
+                (Vec::new(), lockfile::read_unlocked(path, &input.group)?)
+            }
+            Some(path) => (lockfile::read(path, &input.group)?, Vec::new()),
+            None => (Vec::new(), Vec::new()),
+        };
+        Ok(Self {
+            locked,
+            unlocked,
+            top_packages,
+        })
+    }
+
+    /// Add the inputs to `pkg_locs`, locating locked packages with `locate`
+    pub fn add_to(
+        &self,
+        mut pkg_locs: Vec<PkgLoc>,
+        locate: fn(&LockedPackage) -> PkgLoc,
+    ) -> Vec<PkgLoc> {
+        pkg_locs.extend(self.locked.iter().map(locate));
+        pkg_locs.extend(self.unlocked.iter().cloned().map(PkgLoc::Dependency));
+        pkg_locs.extend(
+            self.top_packages
+                .iter()
+                .map(|name| PkgLoc::Dependency(Dependency::new(name.clone(), None))),
+        );
+        pkg_locs
+    }
+}
@@ -0,0 +1,145 @@
+//! Metadata of legacy `.egg` and `.zip` distributions, for old projects that never published wheels.
+//!
+//! Both formats have `PKG-INFO` instead of `METADATA`.
+//! Setuptools puts requirements into a separate `requires.txt`, which is converted to `Requires-Dist`.
+
+use std::str::FromStr as _;
+
+use async_zip::StoredZipEntry;
+use async_zip::base::read::seek::ZipFileReader;
+use eyre::{Context as _, Result};
+use futures::{AsyncBufRead, AsyncSeek};
+
+use crate::python_pkg::Metadata;
+use crate::wheel::{find_entry, read_entry};
+
+/// `PKG-INFO` of an egg
+fn is_egg_pkg_info(e: &StoredZipEntry) -> bool {
+    e.filename()
+        .as_str()
+        .is_ok_and(|n| n == "EGG-INFO/PKG-INFO")
+}
+
+/// `PKG-INFO` in the top-level directory of a zip sdist
+fn is_sdist_pkg_info(e: &StoredZipEntry) -> bool {
+    e.filename().as_str().is_ok_and(|n| {
+        n.split_once('/')
+            .is_some_and(|(dir, file)| !dir.is_empty() && file == "PKG-INFO")
+    })
+}
+
+/// `requires.txt` of an egg, or of the `*.egg-info` directory in a zip sdist
+fn is_requires_txt(e: &StoredZipEntry) -> bool {
+    e.filename().as_str().is_ok_and(|n| {
+        n == "EGG-INFO/requires.txt"
+            || n.rsplit_once('/')
+                .is_some_and(|(dir, file)| dir.ends_with(".egg-info") && file == "requires.txt")
+    })
+}
+
+/// Read the metadata of an egg or zip sdist, if it has any.
+/// Requirements from `requires.txt` are added as `Requires-Dist` unless `PKG-INFO` already has them.
+pub async fn metadata<R>(reader: &mut ZipFileReader<R>) -> Result<Option<Metadata>>
+where
+    R: AsyncBufRead + AsyncSeek + Unpin,
+{
+    let Some(idx) =
+        find_entry(reader, is_egg_pkg_info).or_else(|| find_entry(reader, is_sdist_pkg_info))
+    else {
+        return Ok(None);
+    };
+    let mut buf = String::new();
+    read_entry(reader, idx, &mut buf).await?;
+    let mut metadata = Metadata::from_str(&buf).context("invalid PKG-INFO")?;
+    if metadata.get("Requires-Dist").is_none()
+        && let Some(idx) = find_entry(reader, is_requires_txt)
+    {
+        let mut buf = String::new();
+        read_entry(reader, idx, &mut buf).await?;
+        for requirement in requires_dist(&buf) {
+            metadata.push("Requires-Dist", requirement);
+        }
+    }
+    Ok(Some(metadata))
+}
+
+/// Convert setuptools’ `requires.txt` to `Requires-Dist` values.
+///
+/// Requirements are grouped into sections like `[extra]`, `[extra:marker]`, or `[:marker]`.
+fn requires_dist(requires_txt: &str) -> Vec<String> {
+    let mut section_marker = None;
+    let mut requirements = Vec::new();
+    for line in requires_txt.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let (extra, marker) = section.split_once(':').unwrap_or((section, ""));
+            section_marker = match (extra.trim(), marker.trim()) {
+                ("", "") => None,
+                ("", marker) => Some(marker.to_owned()),
+                (extra, "") => Some(format!("extra == \"{extra}\"")),
+                (extra, marker) => Some(format!("({marker}) and extra == \"{extra}\"")),
+            };
+            continue;
+        }
+        requirements.push(match &section_marker {
+            Some(marker) => format!("{line}; {marker}"),
+            None => line.to_owned(),
+        });
+    }
+    requirements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_egg_metadata() {
+        let egg = crate::test_util::build_zip(&[
+            (
+                "EGG-INFO/PKG-INFO".to_owned(),
+                b"Metadata-Version: 1.0\nName: foo\nVersion: 0.1\n".to_vec(),
+            ),
+            (
+                "EGG-INFO/requires.txt".to_owned(),
+                b"bar\n[test]\nbaz\n".to_vec(),
+            ),
+        ]);
+        let mut reader = ZipFileReader::new(futures::io::Cursor::new(egg))
+            .await
+            .unwrap();
+        let metadata = metadata(&mut reader).await.unwrap().unwrap();
+        assert_eq!(metadata.get("Name"), Some("foo"));
+        assert_eq!(
+            metadata.get_all("Requires-Dist").collect::<Vec<_>>(),
+            ["bar", "baz; extra == \"test\""]
+        );
+    }
+
+    #[test]
+    fn test_requires_dist() {
+        let requires_txt = "\
+foo>=1.0
+
+[docs]
+sphinx
+
+[test:python_version < \"3\"]
+mock
+
+[:sys_platform == \"win32\"]
+pywin32
+";
+        assert_eq!(
+            requires_dist(requires_txt),
+            [
+                "foo>=1.0",
+                "sphinx; extra == \"docs\"",
+                "mock; (python_version < \"3\") and extra == \"test\"",
+                "pywin32; sys_platform == \"win32\"",
+            ]
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Lazily read files from Python wheels, locally or on package indexes.
+#![deny(clippy::pedantic)]
+#![allow(clippy::missing_errors_doc, clippy::must_use_candidate)]
+
+pub mod auth;
+pub mod authors;
+pub mod availability;
+pub mod bench;
+pub mod blob_store;
+pub mod build_backend;
+pub mod cache_integrity;
+pub mod changelog;
+pub mod client;
+pub mod client_options;
+pub mod compat;
+pub mod console_scripts;
+pub mod constraints;
+pub mod content_fingerprint;
+pub mod data_files;
+pub mod dep_graph;
+pub mod deprecation;
+pub mod deps_diff;
+pub mod dns;
+pub mod doctor;
+pub mod download_script;
+pub mod entry_query;
+pub mod error;
+pub mod extras;
+pub mod file_check;
+pub mod fixtures;
+mod glob;
+pub mod hash_pins;
+pub mod hashes;
+pub mod index_gen;
+pub mod legacy;
+pub mod licenses;
+pub mod lockfile;
+pub mod metadata_mirror;
+pub mod metrics;
+pub mod overrides;
+pub mod package_report;
+mod parallel_ranges;
+mod pkg_loc;
+pub mod prefetch_hints;
+pub mod project_urls;
+pub mod python_pkg;
+pub mod range_trace;
+mod registries;
+pub mod requires_python;
+pub mod result_cache;
+pub mod retry;
+pub mod sdist;
+mod signed_urls;
+pub mod signing;
+pub mod simple_repo_api;
+pub mod snapshot;
+pub mod suggestions;
+pub mod template;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+pub mod top_packages;
+pub mod transport;
+#[cfg(feature = "tuf")]
+pub mod tuf;
+pub mod vendored;
+pub mod warnings;
+pub mod watch;
+pub mod wheel;
+pub mod wheel_lint;
+pub mod wheel_matrix;
+
+pub use client::LazyZipClient;
+pub use pkg_loc::PkgLoc;
@@ -0,0 +1,444 @@
+//! Licenses of a dependency graph’s releases and a policy gate over them, for `licenses`.
+//!
+//! Licenses come from `License-Expression` ([PEP 639](https://peps.python.org/pep-0639/)) if present,
+//! or are guessed from the legacy `License` field and `License ::` classifiers.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::dep_graph::DepGraph;
+use crate::python_pkg::{Metadata, PackageName};
+
+/// `License ::` classifiers and their SPDX identifiers
+const CLASSIFIERS: &[(&str, &str)] = &[
+    ("OSI Approved :: Apache Software License", "Apache-2.0"),
+    ("OSI Approved :: BSD License", "BSD-3-Clause"),
+    ("OSI Approved :: MIT License", "MIT"),
+    (
+        "OSI Approved :: MIT No Attribution License (MIT-0)",
+        "MIT-0",
+    ),
+    ("OSI Approved :: ISC License (ISCL)", "ISC"),
+    (
+        "OSI Approved :: Python Software Foundation License",
+        "PSF-2.0",
+    ),
+    (
+        "OSI Approved :: Mozilla Public License 2.0 (MPL 2.0)",
+        "MPL-2.0",
+    ),
+    (
+        "OSI Approved :: GNU General Public License v2 (GPLv2)",
+        "GPL-2.0-only",
+    ),
+    (
+        "OSI Approved :: GNU General Public License v2 or later (GPLv2+)",
+        "GPL-2.0-or-later",
+    ),
+    (
+        "OSI Approved :: GNU General Public License v3 (GPLv3)",
+        "GPL-3.0-only",
+    ),
+    (
+        "OSI Approved :: GNU General Public License v3 or later (GPLv3+)",
+        "GPL-3.0-or-later",
+    ),
+    (
+        "OSI Approved :: GNU Lesser General Public License v2 (LGPLv2)",
+        "LGPL-2.0-only",
+    ),
+    (
+        "OSI Approved :: GNU Lesser General Public License v2 or later (LGPLv2+)",
+        "LGPL-2.0-or-later",
+    ),
+    (
+        "OSI Approved :: GNU Lesser General Public License v3 (LGPLv3)",
+        "LGPL-3.0-only",
+    ),
+    (
+        "OSI Approved :: GNU Lesser General Public License v3 or later (LGPLv3+)",
+        "LGPL-3.0-or-later",
+    ),
+    (
+        "OSI Approved :: GNU Affero General Public License v3",
+        "AGPL-3.0-only",
+    ),
+    (
+        "OSI Approved :: GNU Affero General Public License v3 or later (AGPLv3+)",
+        "AGPL-3.0-or-later",
+    ),
+    (
+        "OSI Approved :: Eclipse Public License 2.0 (EPL-2.0)",
+        "EPL-2.0",
+    ),
+    ("OSI Approved :: The Unlicense (Unlicense)", "Unlicense"),
+    ("OSI Approved :: zlib/libpng License", "Zlib"),
+    (
+        "OSI Approved :: Boost Software License 1.0 (BSL-1.0)",
+        "BSL-1.0",
+    ),
+    (
+        "OSI Approved :: Historical Permission Notice and Disclaimer (HPND)",
+        "HPND",
+    ),
+    (
+        "CC0 1.0 Universal (CC0 1.0) Public Domain Dedication",
+        "CC0-1.0",
+    ),
+];
+
+/// Common spellings in the `License` field and their SPDX identifiers, compared case-insensitively
+const ALIASES: &[(&str, &str)] = &[
+    ("mit", "MIT"),
+    ("mit license", "MIT"),
+    ("bsd", "BSD-3-Clause"),
+    ("bsd license", "BSD-3-Clause"),
+    ("new bsd", "BSD-3-Clause"),
+    ("new bsd license", "BSD-3-Clause"),
+    ("3-clause bsd", "BSD-3-Clause"),
+    ("bsd 3-clause", "BSD-3-Clause"),
+    ("simplified bsd", "BSD-2-Clause"),
+    ("bsd 2-clause", "BSD-2-Clause"),
+    ("apache", "Apache-2.0"),
+    ("apache 2", "Apache-2.0"),
+    ("apache 2.0", "Apache-2.0"),
+    ("apache-2", "Apache-2.0"),
+    ("apache license 2.0", "Apache-2.0"),
+    ("apache license, version 2.0", "Apache-2.0"),
+    ("apache software license", "Apache-2.0"),
+    ("isc", "ISC"),
+    ("isc license", "ISC"),
+    ("mpl 2.0", "MPL-2.0"),
+    ("mpl-2", "MPL-2.0"),
+    ("psf", "PSF-2.0"),
+    ("psf license", "PSF-2.0"),
+    ("gplv2", "GPL-2.0-only"),
+    ("gplv3", "GPL-3.0-only"),
+    ("lgplv3", "LGPL-3.0-only"),
+    ("public domain", "LicenseRef-Public-Domain"),
+];
+
+/// Where a license was found
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Source {
+    LicenseExpression,
+    License,
+    Classifier,
+}
+
+/// A release’s license
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PackageLicense {
+    pub name: PackageName,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// An SPDX expression, `None` if unknown
+    pub license: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<Source>,
+}
+
+impl PackageLicense {
+    fn label(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{} {version}", self.name),
+            None => self.name.to_string(),
+        }
+    }
+}
+
+/// The license of a release, if its metadata names one
+pub fn detect(metadata: &Metadata) -> Option<(String, Source)> {
+    if let Some(expression) = metadata.get("License-Expression") {
+        return Some((expression.trim().to_owned(), Source::LicenseExpression));
+    }
+    if let Some(license) = metadata.get("License").and_then(from_license_field) {
+        return Some((license, Source::License));
+    }
+    let classifiers = metadata
+        .get_all("Classifier")
+        .filter_map(|c| c.strip_prefix("License ::"))
+        .filter_map(|c| {
+            let c = c.trim();
+            CLASSIFIERS
+                .iter()
+                .find(|(classifier, _)| *classifier == c)
+                .map(|(_, id)| *id)
+        })
+        .collect::<Vec<_>>();
+    match classifiers.as_slice() {
+        [] => None,
+        [id] => Some(((*id).to_owned(), Source::Classifier)),
+        // Multiple license classifiers mean users can pick one, like the classifiers of dual-licensed projects
+        ids => Some((ids.join(" OR "), Source::Classifier)),
+    }
+}
+
+/// An SPDX identifier from a `License` field that holds a short name rather than a license text
+fn from_license_field(license: &str) -> Option<String> {
+    let license = license.trim();
+    if license.is_empty() || license.lines().count() > 1 || license.len() > 50 {
+        return None;
+    }
+    let lower = license.to_lowercase();
+    let lower = lower.trim_end_matches('.');
+    if let Some((_, id)) = ALIASES.iter().find(|(alias, _)| *alias == lower) {
+        return Some((*id).to_owned());
+    }
+    // Already an SPDX expression, like `MIT OR Apache-2.0`
+    let is_expression = license.split_whitespace().all(|token| {
+        matches!(token, "AND" | "OR" | "WITH")
+            || token
+                .trim_matches(['(', ')'])
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '+' | ':'))
+    }) && license.chars().any(|c| c.is_ascii_digit() || c == '-');
+    is_expression.then(|| license.to_owned())
+}
+
+/// The licenses of all resolved releases in a graph
+pub fn collect(graph: &DepGraph) -> Vec<PackageLicense> {
+    let mut licenses = graph
+        .nodes()
+        .filter_map(|(_, node)| {
+            let metadata = node.metadata.as_ref()?;
+            let detected = detect(metadata);
+            Some(PackageLicense {
+                name: node.name.clone(),
+                version: node.version.as_ref().map(ToString::to_string),
+                source: detected.as_ref().map(|(_, source)| *source),
+                license: detected.map(|(license, _)| license),
+            })
+        })
+        .collect::<Vec<_>>();
+    licenses.sort_by(|l, r| (&l.name, &l.version).cmp(&(&r.name, &r.version)));
+    licenses
+}
+
+/// Which licenses are acceptable, by SPDX identifier
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    /// If not empty, only these are acceptable
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl Policy {
+    fn accepts_id(&self, id: &str) -> bool {
+        let listed = |ids: &[String]| ids.iter().any(|i| i.eq_ignore_ascii_case(id));
+        !listed(&self.deny) && (self.allow.is_empty() || listed(&self.allow))
+    }
+
+    /// Whether a license is acceptable. Of alternatives (`OR`), one has to be acceptable,
+    /// of combinations (`AND`), all of them. Exceptions (`WITH`) aren’t checked.
+    /// Unknown licenses are only acceptable without an allow list.
+    pub fn accepts(&self, license: Option<&str>) -> bool {
+        match license {
+            None => self.allow.is_empty(),
+            Some(license) => {
+                let tokens = tokenize(license);
+                let mut pos = 0;
+                self.eval_or(&tokens, &mut pos)
+            }
+        }
+    }
+
+    fn eval_or(&self, tokens: &[&str], pos: &mut usize) -> bool {
+        let mut accepted = self.eval_and(tokens, pos);
+        while tokens
+            .get(*pos)
+            .is_some_and(|t| t.eq_ignore_ascii_case("OR"))
+        {
+            *pos += 1;
+            accepted |= self.eval_and(tokens, pos);
+        }
+        accepted
+    }
+
+    fn eval_and(&self, tokens: &[&str], pos: &mut usize) -> bool {
+        let mut accepted = self.eval_term(tokens, pos);
+        while tokens
+            .get(*pos)
+            .is_some_and(|t| t.eq_ignore_ascii_case("AND"))
+        {
+            *pos += 1;
+            accepted &= self.eval_term(tokens, pos);
+        }
+        accepted
+    }
+
+    fn eval_term(&self, tokens: &[&str], pos: &mut usize) -> bool {
+        let Some(&token) = tokens.get(*pos) else {
+            return false;
+        };
+        *pos += 1;
+        let accepted = if token == "(" {
+            let accepted = self.eval_or(tokens, pos);
+            if tokens.get(*pos) == Some(&")") {
+                *pos += 1;
+            }
+            accepted
+        } else {
+            self.accepts_id(token)
+        };
+        if tokens
+            .get(*pos)
+            .is_some_and(|t| t.eq_ignore_ascii_case("WITH"))
+        {
+            *pos += 2;
+        }
+        accepted
+    }
+}
+
+fn tokenize(expression: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    for word in expression.split_whitespace() {
+        let mut rest = word;
+        while let Some(inner) = rest.strip_prefix('(') {
+            tokens.push("(");
+            rest = inner;
+        }
+        let closing = rest.len() - rest.trim_end_matches(')').len();
+        if !rest[..rest.len() - closing].is_empty() {
+            tokens.push(&rest[..rest.len() - closing]);
+        }
+        tokens.extend(std::iter::repeat_n(")", closing));
+    }
+    tokens
+}
+
+/// A release whose license isn’t acceptable
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub package: String,
+    pub license: Option<String>,
+}
+
+/// Counts of licenses over a set of releases
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct LicenseSummary {
+    /// Releases per license expression
+    pub licenses: BTreeMap<String, usize>,
+    /// Releases without a known license
+    pub unknown: Vec<String>,
+    pub violations: Vec<Violation>,
+}
+
+impl LicenseSummary {
+    pub fn new(licenses: &[PackageLicense], policy: &Policy) -> Self {
+        let mut summary = Self {
+            violations: violations(licenses, policy),
+            ..Self::default()
+        };
+        for package in licenses {
+            match &package.license {
+                Some(license) => *summary.licenses.entry(license.clone()).or_default() += 1,
+                None => summary.unknown.push(package.label()),
+            }
+        }
+        summary
+    }
+}
+
+/// The releases whose licenses `policy` doesn’t accept
+pub fn violations(licenses: &[PackageLicense], policy: &Policy) -> Vec<Violation> {
+    licenses
+        .iter()
+        .filter(|package| !policy.accepts(package.license.as_deref()))
+        .map(|package| Violation {
+            package: package.label(),
+            license: package.license.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr as _;
+
+    use super::*;
+
+    fn metadata(headers: &str) -> Metadata {
+        Metadata::from_str(&format!("Metadata-Version: 2.4\nName: foo\n{headers}")).unwrap()
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            detect(&metadata(
+                "License-Expression: MIT OR Apache-2.0\nLicense: BSD\n"
+            )),
+            Some(("MIT OR Apache-2.0".to_owned(), Source::LicenseExpression))
+        );
+        assert_eq!(
+            detect(&metadata("License: Apache License 2.0\n")),
+            Some(("Apache-2.0".to_owned(), Source::License))
+        );
+        assert_eq!(
+            detect(&metadata(
+                "License: Copyright (c) 2024, somebody, all rights reserved and more text\nClassifier: License :: OSI Approved :: MIT License\n"
+            )),
+            Some(("MIT".to_owned(), Source::Classifier))
+        );
+        assert_eq!(
+            detect(&metadata("License: UNKNOWN\n")),
+            None,
+            "Placeholders aren’t licenses"
+        );
+    }
+
+    #[test]
+    fn test_policy() {
+        let policy = Policy {
+            allow: vec!["MIT".to_owned(), "Apache-2.0".to_owned()],
+            deny: Vec::new(),
+        };
+        assert!(policy.accepts(Some("mit")));
+        assert!(policy.accepts(Some("GPL-3.0-only OR MIT")));
+        assert!(policy.accepts(Some("(MIT AND Apache-2.0) OR GPL-3.0-only")));
+        assert!(!policy.accepts(Some("MIT AND GPL-3.0-only")));
+        assert!(policy.accepts(Some("Apache-2.0 WITH LLVM-exception")));
+        assert!(!policy.accepts(None));
+
+        let policy = Policy {
+            allow: Vec::new(),
+            deny: vec!["GPL-3.0-only".to_owned()],
+        };
+        assert!(policy.accepts(None));
+        assert!(!policy.accepts(Some("GPL-3.0-only")));
+        assert!(policy.accepts(Some("(GPL-3.0-only OR BSD-3-Clause)")));
+    }
+
+    #[test]
+    fn test_summary() {
+        let package = |name: &str, license: Option<&str>| PackageLicense {
+            name: name.parse().unwrap(),
+            version: Some("1.0".to_owned()),
+            license: license.map(ToOwned::to_owned),
+            source: None,
+        };
+        let licenses = [
+            package("a", Some("MIT")),
+            package("b", Some("MIT")),
+            package("c", None),
+            package("d", Some("GPL-3.0-only")),
+        ];
+        let policy = Policy {
+            allow: Vec::new(),
+            deny: vec!["GPL-3.0-only".to_owned()],
+        };
+        let summary = LicenseSummary::new(&licenses, &policy);
+        assert_eq!(summary.licenses["MIT"], 2);
+        assert_eq!(summary.unknown, ["c 1.0"]);
+        assert_eq!(
+            summary.violations,
+            [Violation {
+                package: "d 1.0".to_owned(),
+                license: Some("GPL-3.0-only".to_owned()),
+            }]
+        );
+    }
+}
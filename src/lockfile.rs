@@ -0,0 +1,710 @@
+//! Reading the artifacts pinned by lock files, to process them with `--lockfile`,
+//! or check them against the index with `verify-lock`.
+//!
+//! Supported are `uv.lock`, `poetry.lock`, `Pipfile.lock`, and `requirements.txt` files,
+//! e.g. from `pip-compile --generate-hashes`.
+//! Files are immutable once published, so a lock file drifting from the index means a file was
+//! removed, republished under the same name, or yanked.
+
+use std::path::Path;
+use std::str::FromStr as _;
+
+use eyre::{Context as _, ContextCompat as _, Result, bail};
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::python_pkg::{Dependency, LegacyFilename, PackageName, SdistFilename, WheelFilename};
+use crate::sdist::has_suffix;
+use crate::simple_repo_api::{self, Yanking};
+use crate::{LazyZipClient, PkgLoc};
+
+pub use self::pipenv::Group;
+
+mod conda;
+mod pipenv;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockFormat {
+    Requirements,
+    Uv,
+    Poetry,
+    PipfileLock,
+    /// Not a lock file, see [`read_unlocked`]
+    Pipfile,
+    /// Conda’s `environment.yml`, not a lock file either
+    CondaEnvironment,
+}
+
+impl LockFormat {
+    /// Guess the format from the file name, defaulting to a requirements file
+    pub fn detect(path: &Path) -> Self {
+        match path.file_name().and_then(|name| name.to_str()) {
+            Some("uv.lock") => Self::Uv,
+            Some("poetry.lock") => Self::Poetry,
+            Some("Pipfile.lock") => Self::PipfileLock,
+            Some("Pipfile") => Self::Pipfile,
+            Some(name)
+                if name.starts_with("environment")
+                    && (has_suffix(name, ".yml") || has_suffix(name, ".yaml")) =>
+            {
+                Self::CondaEnvironment
+            }
+            _ => Self::Requirements,
+        }
+    }
+
+    /// Whether the format pins exact versions
+    pub fn is_locked(self) -> bool {
+        !matches!(self, Self::Pipfile | Self::CondaEnvironment)
+    }
+}
+
+/// A release pinned by a lock file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    pub name: PackageName,
+    pub version: pep440_rs::Version,
+    pub artifacts: Vec<Artifact>,
+}
+
+/// A file of a locked release. Requirements files only know the hashes of files, not their names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    pub filename: Option<String>,
+    /// Only known for `uv.lock` files
+    pub url: Option<Url>,
+    pub sha256: Option<String>,
+}
+
+impl LockedPackage {
+    /// The locked release as a dependency, e.g. for finding its sdist
+    pub fn dependency(&self) -> PkgLoc {
+        let spec = pep440_rs::VersionSpecifier::equals_version(self.version.clone());
+        PkgLoc::Dependency(Dependency::new(self.name.clone(), Some(spec.into())))
+    }
+
+    /// A locked wheel, preferring pure Python ones, with its hash to verify downloads.
+    /// If no wheel URLs are known, the newest wheel of the locked release.
+    pub fn wheel_loc(&self) -> PkgLoc {
+        let wheels = self.artifacts.iter().filter(|a| {
+            a.url.is_some()
+                && a.filename
+                    .as_ref()
+                    .is_some_and(|filename| has_suffix(filename, ".whl"))
+        });
+        let wheel = wheels.clone().find(|a| {
+            a.filename
+                .as_ref()
+                .is_some_and(|filename| has_suffix(filename, "-none-any.whl"))
+        });
+        let Some(Artifact {
+            url: Some(url),
+            sha256,
+            ..
+        }) = wheel.or_else(|| wheels.clone().next())
+        else {
+            return self.dependency();
+        };
+        let mut url = url.clone();
+        if let Some(sha256) = sha256 {
+            url.set_fragment(Some(&format!("sha256={sha256}")));
+        }
+        PkgLoc::Url(url)
+    }
+}
+
+/// Read the packages pinned by the lock file at `path`.
+/// Packages not from an index, like editable or Git ones, are skipped.
+/// `groups` selects Pipenv’s package categories, other formats are read whole.
+pub fn read(path: &Path, groups: &[Group]) -> Result<Vec<LockedPackage>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read lock file {}", path.display()))?;
+    parse(LockFormat::detect(path), &content, groups)
+        .with_context(|| format!("Failed to parse lock file {}", path.display()))
+}
+
+pub fn parse(format: LockFormat, content: &str, groups: &[Group]) -> Result<Vec<LockedPackage>> {
+    match format {
+        LockFormat::Requirements => parse_requirements(content),
+        LockFormat::Uv => parse_uv(content),
+        LockFormat::Poetry => parse_poetry(content),
+        LockFormat::PipfileLock => pipenv::parse_lock(content, groups),
+        LockFormat::Pipfile => bail!("a Pipfile pins no versions, lock it to a Pipfile.lock"),
+        LockFormat::CondaEnvironment => bail!("an environment.yml pins no versions"),
+    }
+}
+
+/// Read the requirements of a file that doesn’t pin versions,
+/// a `Pipfile` or the `pip:` section of a conda `environment.yml`.
+/// Packages not from an index, like editable or Git ones, are skipped.
+pub fn read_unlocked(path: &Path, groups: &[Group]) -> Result<Vec<Dependency>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let deps = match LockFormat::detect(path) {
+        LockFormat::Pipfile => pipenv::parse_pipfile(&content, groups),
+        LockFormat::CondaEnvironment => conda::parse_environment(&content),
+        _ => bail!("{} pins versions, read it as a lock file", path.display()),
+    };
+    deps.with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Read the requirements of a requirements file with any version specifiers, like `foo>=1,<2`.
+/// Hashes and options like `-r other.txt` are ignored.
+pub fn read_requirements(path: &Path) -> Result<Vec<pep508_rs::Requirement>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    requirement_lines(&content)
+        .map(|(lineno, line)| {
+            let requirement = line
+                .split_whitespace()
+                .filter(|word| !word.starts_with("--hash="))
+                .collect::<Vec<_>>()
+                .join(" ");
+            pep508_rs::Requirement::from_str(&requirement)
+                .with_context(|| format!("{}:{lineno}: invalid requirement", path.display()))
+        })
+        .collect()
+}
+
+fn parse_requirements(content: &str) -> Result<Vec<LockedPackage>> {
+    let mut packages = Vec::new();
+    for (lineno, line) in requirement_lines(content) {
+        let mut requirement = String::new();
+        let mut artifacts = Vec::new();
+        for word in line.split_whitespace() {
+            if let Some(hash) = word.strip_prefix("--hash=") {
+                artifacts.extend(sha256(hash).map(|sha256| Artifact {
+                    filename: None,
+                    url: None,
+                    sha256: Some(sha256),
+                }));
+            } else {
+                requirement.push_str(word);
+                requirement.push(' ');
+            }
+        }
+        let package = parse_pin(requirement.trim(), artifacts)
+            .with_context(|| format!("line {lineno}: invalid requirement"))?;
+        packages.push(package);
+    }
+    Ok(packages)
+}
+
+/// The requirement lines of a requirements file with their line numbers,
+/// joining continuation lines and skipping comments and options
+fn requirement_lines(content: &str) -> impl Iterator<Item = (usize, String)> {
+    // Join continuation lines, keeping the number of the first line for errors
+    let mut logical = Vec::<(usize, String)>::new();
+    let mut in_continuation = false;
+    for (i, line) in content.lines().enumerate() {
+        let (line, continues) = match line.trim_end().strip_suffix('\\') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        match logical.last_mut() {
+            Some((_, joined)) if in_continuation => {
+                joined.push(' ');
+                joined.push_str(line);
+            }
+            _ => logical.push((i + 1, line.to_owned())),
+        }
+        in_continuation = continues;
+    }
+    logical.into_iter().filter_map(|(lineno, line)| {
+        let line = strip_comment(&line).trim();
+        // Options like `--index-url` and `-r other.txt`
+        (!line.is_empty() && !line.starts_with('-')).then(|| (lineno, line.to_owned()))
+    })
+}
+
+/// Remove a `#` comment, which needs to start the line or follow whitespace
+fn strip_comment(line: &str) -> &str {
+    let end = line
+        .match_indices('#')
+        .find(|&(i, _)| i == 0 || line[..i].ends_with(char::is_whitespace))
+        .map_or(line.len(), |(i, _)| i);
+    &line[..end]
+}
+
+/// Parse a requirement pinned with `==`, e.g. `foo[bar]==1.0 ; python_version >= "3.9"`
+fn parse_pin(requirement: &str, artifacts: Vec<Artifact>) -> Result<LockedPackage> {
+    let req = pep508_rs::Requirement::<pep508_rs::VerbatimUrl>::from_str(requirement)?;
+    let version = match &req.version_or_url {
+        Some(pep508_rs::VersionOrUrl::VersionSpecifier(specs)) => match &**specs {
+            [spec] if *spec.operator() == pep440_rs::Operator::Equal => spec.version().clone(),
+            _ => bail!("{} is not pinned with `==`", req.name),
+        },
+        _ => bail!("{} is not pinned with `==`", req.name),
+    };
+    Ok(LockedPackage {
+        name: req.name.as_ref().parse()?,
+        version,
+        artifacts,
+    })
+}
+
+#[derive(Deserialize, Debug)]
+struct UvLock {
+    #[serde(default)]
+    package: Vec<UvPackage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct UvPackage {
+    name: String,
+    /// Missing for dynamic versions of local packages
+    version: Option<String>,
+    source: UvSource,
+    sdist: Option<UvFile>,
+    #[serde(default)]
+    wheels: Vec<UvFile>,
+}
+
+#[derive(Deserialize, Debug)]
+struct UvSource {
+    /// Only set for packages from an index, others are e.g. `editable` or `git`
+    registry: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct UvFile {
+    url: Option<String>,
+    hash: Option<String>,
+}
+
+fn parse_uv(content: &str) -> Result<Vec<LockedPackage>> {
+    let lock: UvLock = toml::from_str(content)?;
+    lock.package
+        .into_iter()
+        .filter(|package| package.source.registry.is_some())
+        .map(|package| {
+            let version = package
+                .version
+                .with_context(|| format!("{} has no version", package.name))?;
+            let artifacts = package
+                .sdist
+                .into_iter()
+                .chain(package.wheels)
+                .map(|file| {
+                    let url = file.url.as_deref().and_then(|url| Url::parse(url).ok());
+                    Artifact {
+                        filename: url.as_ref().and_then(url_filename),
+                        url,
+                        sha256: file.hash.as_deref().and_then(sha256),
+                    }
+                })
+                .collect();
+            Ok(LockedPackage {
+                name: package.name.parse()?,
+                version: version.parse()?,
+                artifacts,
+            })
+        })
+        .collect()
+}
+
+/// The decoded last path segment of a file URL
+fn url_filename(url: &Url) -> Option<String> {
+    let name = url
+        .path_segments()?
+        .next_back()
+        .filter(|name| !name.is_empty())?;
+    Some(percent_decode_str(name).decode_utf8_lossy().into_owned())
+}
+
+#[derive(Deserialize, Debug)]
+struct PoetryLock {
+    #[serde(default)]
+    package: Vec<PoetryPackage>,
+    /// Before Poetry 1.5, files were listed here by package name
+    metadata: Option<PoetryMetadata>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PoetryPackage {
+    name: String,
+    version: String,
+    source: Option<PoetrySource>,
+    #[serde(default)]
+    files: Vec<PoetryFile>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PoetrySource {
+    /// `legacy` for secondary indexes, others are e.g. `git` or `directory`
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct PoetryMetadata {
+    #[serde(default)]
+    files: std::collections::HashMap<String, Vec<PoetryFile>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PoetryFile {
+    file: String,
+    hash: String,
+}
+
+fn parse_poetry(content: &str) -> Result<Vec<LockedPackage>> {
+    let mut lock: PoetryLock = toml::from_str(content)?;
+    let mut legacy_files = lock.metadata.take().map(|m| m.files).unwrap_or_default();
+    lock.package
+        .into_iter()
+        .filter(|package| package.source.as_ref().is_none_or(|s| s.kind == "legacy"))
+        .map(|package| {
+            let files = if package.files.is_empty() {
+                legacy_files.remove(&package.name).unwrap_or_default()
+            } else {
+                package.files
+            };
+            let artifacts = files
+                .into_iter()
+                .map(|file| Artifact {
+                    sha256: sha256(&file.hash),
+                    url: None,
+                    filename: Some(file.file),
+                })
+                .collect();
+            Ok(LockedPackage {
+                name: package.name.parse()?,
+                version: package.version.parse()?,
+                artifacts,
+            })
+        })
+        .collect()
+}
+
+/// The digest of a `sha256:…` hash. Other algorithms aren’t checked.
+fn sha256(hash: &str) -> Option<String> {
+    hash.strip_prefix("sha256:").map(str::to_ascii_lowercase)
+}
+
+/// How a locked artifact differs from what the index offers
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Drift {
+    pub name: PackageName,
+    pub version: String,
+    #[serde(flatten)]
+    pub kind: DriftKind,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "drift", rename_all = "kebab-case")]
+pub enum DriftKind {
+    /// The index doesn’t offer the project
+    ProjectMissing { error: String },
+    /// The index has no files for the locked version
+    ReleaseMissing,
+    /// A locked file was removed
+    FileMissing { filename: String },
+    /// A locked file was republished with different content
+    HashMismatch {
+        filename: String,
+        locked: String,
+        index: Option<String>,
+    },
+    /// No file of the release has a locked hash
+    HashMissing { sha256: String },
+    /// A locked file, or with no file names known, all of the release, was yanked
+    Yanked {
+        filename: Option<String>,
+        reason: Option<String>,
+    },
+}
+
+/// Check that every locked artifact is still on the index, unyanked,
+/// and if `check_hashes`, with the locked hash.
+/// Artifacts only known by their hash are only checked with `check_hashes`,
+/// otherwise just whether their release was yanked.
+pub async fn verify(
+    client: &LazyZipClient,
+    packages: &[LockedPackage],
+    check_hashes: bool,
+) -> Vec<Drift> {
+    futures::future::join_all(
+        packages
+            .iter()
+            .map(|package| verify_package(client, package, check_hashes)),
+    )
+    .await
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+#[tracing::instrument(skip_all, fields(name = %package.name, version = %package.version))]
+async fn verify_package(
+    client: &LazyZipClient,
+    package: &LockedPackage,
+    check_hashes: bool,
+) -> Vec<Drift> {
+    let drift = |kind| Drift {
+        name: package.name.clone(),
+        version: package.version.to_string(),
+        kind,
+    };
+    let project = match client.find_project(&package.name).await {
+        Ok(project) => project,
+        Err(e) => {
+            let error = format!("{e:#}");
+            return vec![drift(DriftKind::ProjectMissing { error })];
+        }
+    };
+    let release = project
+        .files
+        .into_iter()
+        .filter(|file| release_version(&file.filename).as_ref() == Some(&package.version))
+        .collect::<Vec<_>>();
+    if release.is_empty() {
+        return vec![drift(DriftKind::ReleaseMissing)];
+    }
+    let mut kinds = Vec::new();
+    let mut checked = false;
+    for artifact in &package.artifacts {
+        let (file, locked) = match (&artifact.filename, &artifact.sha256) {
+            (Some(filename), locked) => {
+                checked = true;
+                let Some(file) = release.iter().find(|f| f.filename == *filename) else {
+                    kinds.push(DriftKind::FileMissing {
+                        filename: filename.clone(),
+                    });
+                    continue;
+                };
+                (file, locked.as_ref().filter(|_| check_hashes))
+            }
+            (None, Some(sha256)) if check_hashes => {
+                checked = true;
+                let Some(file) = release.iter().find(|f| index_sha256(f) == Some(sha256)) else {
+                    kinds.push(DriftKind::HashMissing {
+                        sha256: sha256.clone(),
+                    });
+                    continue;
+                };
+                (file, None)
+            }
+            (None, _) => continue,
+        };
+        if let Some(locked) = locked {
+            let index = index_sha256(file);
+            if index.is_some_and(|index| !index.eq_ignore_ascii_case(locked)) {
+                kinds.push(DriftKind::HashMismatch {
+                    filename: file.filename.clone(),
+                    locked: locked.clone(),
+                    index: index.cloned(),
+                });
+            }
+        }
+        if let Yanking::Yanked(reason) = &file.yanked {
+            kinds.push(DriftKind::Yanked {
+                filename: Some(file.filename.clone()),
+                reason: reason.clone(),
+            });
+        }
+    }
+    // If no files were checked, the release as a whole can still be yanked
+    if !checked
+        && release
+            .iter()
+            .all(|f| matches!(f.yanked, Yanking::Yanked(_)))
+    {
+        kinds.push(DriftKind::Yanked {
+            filename: None,
+            reason: release.iter().find_map(|f| match &f.yanked {
+                Yanking::Yanked(reason) => reason.clone(),
+                Yanking::NotYanked => None,
+            }),
+        });
+    }
+    kinds.into_iter().map(drift).collect()
+}
+
+fn index_sha256(file: &simple_repo_api::File) -> Option<&String> {
+    file.hashes.get("sha256")
+}
+
+/// The version of a wheel, sdist or legacy file
+pub(crate) fn release_version(filename: &str) -> Option<pep440_rs::Version> {
+    WheelFilename::from_str(filename)
+        .map(|f| f.version)
+        .ok()
+        .or_else(|| SdistFilename::from_str(filename).map(|f| f.version).ok())
+        .or_else(|| LegacyFilename::from_str(filename).map(|f| f.version).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockIndex, MockWheel};
+
+    const SHA: &str = "8a1e2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f8";
+
+    #[test]
+    fn test_parse_requirements() {
+        let content = format!(
+            "# via pip-compile\n--index-url https://example.com/simple\n\
+             foo[bar]==1.0 ; python_version >= \"3.9\" \\\n    --hash=sha256:{SHA} \\\n    --hash=md5:abc\n\
+             Baz_Qux==2.0.post1  # pinned\n"
+        );
+        let packages = parse(LockFormat::Requirements, &content, &[]).unwrap();
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name.original(), "foo");
+        assert_eq!(packages[0].version.to_string(), "1.0");
+        assert_eq!(
+            packages[0].artifacts,
+            [Artifact {
+                filename: None,
+                url: None,
+                sha256: Some(SHA.to_owned())
+            }]
+        );
+        assert_eq!(packages[1].name.as_normalized(), "baz-qux");
+        assert!(packages[1].artifacts.is_empty());
+
+        let err = parse(LockFormat::Requirements, "foo>=1.0\n", &[]).unwrap_err();
+        assert!(format!("{err:#}").contains("not pinned"), "{err:#}");
+    }
+
+    #[test]
+    fn test_parse_uv() {
+        let content = format!(
+            r#"
+            version = 1
+
+            [[package]]
+            name = "app"
+            version = "0.1.0"
+            source = {{ editable = "." }}
+
+            [[package]]
+            name = "foo"
+            version = "1.0"
+            source = {{ registry = "https://pypi.org/simple" }}
+            sdist = {{ url = "https://files.example.com/foo-1.0.tar.gz", hash = "sha256:{SHA}", size = 10 }}
+            wheels = [
+                {{ url = "https://files.example.com/foo-1.0-py3-none-any.whl", hash = "sha256:{SHA}" }},
+            ]
+            "#
+        );
+        let packages = parse(LockFormat::Uv, &content, &[]).unwrap();
+        assert_eq!(packages.len(), 1);
+        let filenames = packages[0]
+            .artifacts
+            .iter()
+            .map(|a| a.filename.as_deref().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(filenames, ["foo-1.0.tar.gz", "foo-1.0-py3-none-any.whl"]);
+        assert_eq!(
+            packages[0].wheel_loc().to_string(),
+            format!("https://files.example.com/foo-1.0-py3-none-any.whl#sha256={SHA}")
+        );
+        assert_eq!(packages[0].dependency().to_string(), "foo==1.0");
+    }
+
+    #[test]
+    fn test_parse_poetry() {
+        let content = format!(
+            r#"
+            [[package]]
+            name = "foo"
+            version = "1.0"
+            files = [{{ file = "foo-1.0-py3-none-any.whl", hash = "sha256:{SHA}" }}]
+
+            [[package]]
+            name = "bar"
+            version = "2.0"
+
+            [package.source]
+            type = "git"
+            url = "https://github.com/example/bar.git"
+
+            [[package]]
+            name = "baz"
+            version = "3.0"
+
+            [metadata.files]
+            baz = [{{ file = "baz-3.0.tar.gz", hash = "sha256:{SHA}" }}]
+            "#
+        );
+        let packages = parse(LockFormat::Poetry, &content, &[]).unwrap();
+        let names = packages
+            .iter()
+            .map(|p| p.name.original())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["foo", "baz"]);
+        assert_eq!(
+            packages[1].artifacts[0].filename.as_deref(),
+            Some("baz-3.0.tar.gz")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let foo = MockWheel::new("foo", "1.0");
+        let bar = MockWheel::new("bar", "1.0").yanked(Some("broken"));
+        let index = MockIndex::start([foo.clone(), bar.clone()]).await.unwrap();
+        let client = index.client();
+        let project = client.find_project(&"foo".parse().unwrap()).await.unwrap();
+        let foo_sha = project.files[0].hashes["sha256"].clone();
+        let locked = |name: &str, version: &str, artifacts| LockedPackage {
+            name: name.parse().unwrap(),
+            version: version.parse().unwrap(),
+            artifacts,
+        };
+        let packages = [
+            locked(
+                "foo",
+                "1.0",
+                vec![
+                    Artifact {
+                        filename: Some(foo.filename()),
+                        url: None,
+                        sha256: Some(SHA.to_owned()),
+                    },
+                    Artifact {
+                        filename: None,
+                        url: None,
+                        sha256: Some(foo_sha),
+                    },
+                    Artifact {
+                        filename: Some("foo-1.0.tar.gz".to_owned()),
+                        url: None,
+                        sha256: None,
+                    },
+                ],
+            ),
+            locked("bar", "1.0", Vec::new()),
+            locked("foo", "2.0", Vec::new()),
+            locked("missing", "1.0", Vec::new()),
+        ];
+
+        let kinds = |drifts: Vec<Drift>| {
+            drifts
+                .into_iter()
+                .map(|d| serde_json::to_value(d).unwrap()["drift"].clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            kinds(verify(&client, &packages, false).await),
+            [
+                "file-missing",
+                "yanked",
+                "release-missing",
+                "project-missing"
+            ]
+        );
+        assert_eq!(
+            kinds(verify(&client, &packages, true).await),
+            [
+                "hash-mismatch",
+                "file-missing",
+                "yanked",
+                "release-missing",
+                "project-missing"
+            ]
+        );
+    }
+}
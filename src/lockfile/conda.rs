@@ -0,0 +1,109 @@
+//! The `pip:` dependencies of conda’s `environment.yml`.
+//!
+//! Only the block style conda writes is understood, not all of YAML:
+//! a top-level `dependencies:` list containing a `- pip:` item with a nested list.
+
+use std::str::FromStr as _;
+
+use eyre::{Context as _, Result};
+
+use super::strip_comment;
+use crate::python_pkg::Dependency;
+
+pub(super) fn parse_environment(content: &str) -> Result<Vec<Dependency>> {
+    let mut deps = Vec::new();
+    let mut in_dependencies = false;
+    // Indentation of the `- pip:` item while reading its entries
+    let mut pip_indent = None;
+    for (i, line) in content.lines().enumerate() {
+        let line = strip_comment(line).trim_end();
+        let item = line.trim_start();
+        if item.is_empty() {
+            continue;
+        }
+        let indent = line.len() - item.len();
+        if indent == 0 && !item.starts_with('-') {
+            in_dependencies = item == "dependencies:";
+            pip_indent = None;
+            continue;
+        }
+        let Some(item) = item.strip_prefix('-').map(str::trim_start) else {
+            continue;
+        };
+        if !in_dependencies {
+            continue;
+        }
+        if pip_indent.is_some_and(|pip_indent| indent > pip_indent) {
+            let entry = unquote(item);
+            if let Some(dep) =
+                parse_entry(entry).with_context(|| format!("line {}: invalid pip entry", i + 1))?
+            {
+                deps.push(dep);
+            }
+        } else {
+            pip_indent = (item == "pip:").then_some(indent);
+        }
+    }
+    Ok(deps)
+}
+
+/// Parse a requirement, skipping options like `-r requirements.txt` and URLs
+fn parse_entry(entry: &str) -> Result<Option<Dependency>> {
+    if entry.starts_with('-') {
+        tracing::warn!(entry, "skipping pip option");
+        return Ok(None);
+    }
+    let req = match pep508_rs::Requirement::from_str(entry) {
+        Ok(req) => req,
+        // Like `git+https://…`
+        Err(_) if entry.contains("://") => {
+            tracing::warn!(entry, "skipping URL requirement");
+            return Ok(None);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if let Some(pep508_rs::VersionOrUrl::Url(_)) = req.version_or_url {
+        tracing::warn!(entry, "skipping URL requirement");
+        return Ok(None);
+    }
+    Dependency::from_requirement(&req).map(Some)
+}
+
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .into_iter()
+        .find_map(|q| value.strip_prefix(q)?.strip_suffix(q))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_environment() {
+        let content = r#"
+name: analysis
+channels:
+  - conda-forge
+dependencies:
+  - python=3.12
+  - numpy>=2  # from conda
+  - pip
+  - pip:
+      - requests==2.32.3
+      - "pandas[excel]>=2 ; python_version >= '3.10'"
+      - -r requirements.txt
+      - git+https://github.com/example/foo.git
+  - scipy
+variables:
+  - pip:
+      - ignored
+"#;
+        let deps = parse_environment(content).unwrap();
+        let deps = deps.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(deps, ["requests==2.32.3", "pandas>=2"]);
+
+        assert!(parse_environment("dependencies:\n  - pip:\n    - foo===\n").is_err());
+    }
+}
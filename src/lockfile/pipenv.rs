@@ -0,0 +1,164 @@
+//! Pipenv’s `Pipfile.lock`, and its unlocked `Pipfile`
+
+use std::collections::BTreeMap;
+
+use eyre::{Context as _, ContextCompat as _, Result};
+use serde::Deserialize;
+
+use super::{Artifact, LockedPackage, sha256};
+use crate::python_pkg::Dependency;
+
+/// A Pipenv package category
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Group {
+    /// `[packages]` in `Pipfile`, `default` in `Pipfile.lock`
+    Default,
+    /// `[dev-packages]` in `Pipfile`, `develop` in `Pipfile.lock`
+    Develop,
+}
+
+#[derive(Deserialize, Debug)]
+struct PipfileLock {
+    #[serde(default)]
+    default: BTreeMap<String, LockEntry>,
+    #[serde(default)]
+    develop: BTreeMap<String, LockEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LockEntry {
+    /// E.g. `==1.0`, missing for Git, path, or editable packages
+    version: Option<String>,
+    #[serde(default)]
+    hashes: Vec<String>,
+}
+
+pub(super) fn parse_lock(content: &str, groups: &[Group]) -> Result<Vec<LockedPackage>> {
+    let mut lock: PipfileLock = serde_json::from_str(content)?;
+    let mut packages = Vec::new();
+    for group in groups {
+        let entries = match group {
+            Group::Default => std::mem::take(&mut lock.default),
+            Group::Develop => std::mem::take(&mut lock.develop),
+        };
+        for (name, entry) in entries {
+            let Some(version) = entry.version else {
+                continue;
+            };
+            let version = version
+                .strip_prefix("==")
+                .with_context(|| format!("{name} is not pinned with `==`"))?;
+            let artifacts = entry
+                .hashes
+                .iter()
+                .filter_map(|hash| sha256(hash))
+                .map(|sha256| Artifact {
+                    filename: None,
+                    url: None,
+                    sha256: Some(sha256),
+                })
+                .collect();
+            packages.push(LockedPackage {
+                name: name.parse()?,
+                version: version.parse()?,
+                artifacts,
+            });
+        }
+    }
+    Ok(packages)
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+struct Pipfile {
+    #[serde(default)]
+    packages: BTreeMap<String, PipfileEntry>,
+    #[serde(default)]
+    dev_packages: BTreeMap<String, PipfileEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum PipfileEntry {
+    /// A version specifier like `>=1.0`, or `*`
+    Version(String),
+    /// With extras, markers, etc., or not from an index, like `{git = "…"}`
+    Table { version: Option<String> },
+}
+
+pub(super) fn parse_pipfile(content: &str, groups: &[Group]) -> Result<Vec<Dependency>> {
+    let mut pipfile: Pipfile = toml::from_str(content)?;
+    let mut deps = Vec::new();
+    for group in groups {
+        let entries = match group {
+            Group::Default => std::mem::take(&mut pipfile.packages),
+            Group::Develop => std::mem::take(&mut pipfile.dev_packages),
+        };
+        for (name, entry) in entries {
+            let (PipfileEntry::Version(version)
+            | PipfileEntry::Table {
+                version: Some(version),
+            }) = entry
+            else {
+                continue;
+            };
+            let version_spec = match version.trim() {
+                "*" | "" => None,
+                spec => Some(
+                    spec.parse()
+                        .with_context(|| format!("invalid version of {name}"))?,
+                ),
+            };
+            deps.push(Dependency::new(name.parse()?, version_spec));
+        }
+    }
+    Ok(deps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lock() {
+        let content = r#"{
+            "_meta": {"sources": [{"name": "pypi", "url": "https://pypi.org/simple"}]},
+            "default": {
+                "foo": {"hashes": ["sha256:ABC", "sha256:def"], "index": "pypi", "version": "==1.0"},
+                "app": {"editable": true, "path": "."}
+            },
+            "develop": {"bar": {"hashes": [], "version": "==2.0"}}
+        }"#;
+        let packages = parse_lock(content, &[Group::Default]).unwrap();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].version.to_string(), "1.0");
+        let hashes = packages[0]
+            .artifacts
+            .iter()
+            .map(|a| a.sha256.as_deref().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(hashes, ["abc", "def"]);
+
+        let packages = parse_lock(content, &[Group::Default, Group::Develop]).unwrap();
+        assert_eq!(packages.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_pipfile() {
+        let content = r#"
+            [packages]
+            foo = "*"
+            bar = {version = ">=2", extras = ["baz"]}
+            app = {path = ".", editable = true}
+
+            [dev-packages]
+            pytest = "==8.0"
+        "#;
+        let deps = parse_pipfile(content, &[Group::Default]).unwrap();
+        let deps = deps.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(deps, ["bar>=2", "foo"]);
+        let deps = parse_pipfile(content, &[Group::Develop]).unwrap();
+        assert_eq!(deps[0].to_string(), "pytest==8.0");
+    }
+}
@@ -1,170 +1,1336 @@
 #![deny(clippy::pedantic)]
 
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
 use std::str::FromStr as _;
+use std::time::{Duration, Instant};
 
-use async_http_range_reader::{AsyncHttpRangeReader, CheckSupportMethod};
-use async_zip::StoredZipEntry;
-use async_zip::base::read::seek::ZipFileReader;
-use clap::Parser;
-use color_eyre::eyre::{Context as _, ContextCompat, Result};
-use futures::io::BufReader;
+use clap::{CommandFactory as _, Parser};
+use color_eyre::eyre::{Context as _, ContextCompat, Error, Result, bail};
 use futures::stream::FuturesUnordered;
-use futures::{AsyncBufRead, AsyncRead, AsyncSeek, TryStreamExt as _};
-use reqwest::header::HeaderMap;
-use tokio_util::compat::TokioAsyncReadCompatExt as _;
+use futures::{FutureExt as _, StreamExt as _, TryStreamExt as _};
+use pypi_lazyzip::authors::AuthorsReport;
+use pypi_lazyzip::console_scripts::{self, ConsoleScriptsReport};
+use pypi_lazyzip::content_fingerprint::{self, ContentDiff};
+use pypi_lazyzip::dep_graph::{DepGraph, GraphFormat, Traversal};
+use pypi_lazyzip::deprecation;
+use pypi_lazyzip::download_script::{self, ScriptFormat};
+use pypi_lazyzip::entry_query::{self, EntryQuery};
+use pypi_lazyzip::extras::Extras;
+use pypi_lazyzip::index_gen::{IndexFile, StaticIndex};
+use pypi_lazyzip::licenses::{self, LicenseSummary, Policy};
+use pypi_lazyzip::lockfile::{Group, LockedPackage};
+use pypi_lazyzip::metrics::Metrics;
+use pypi_lazyzip::package_report::{FileInfo, PackageReport};
+use pypi_lazyzip::project_urls::ProjectUrls;
+use pypi_lazyzip::python_pkg::{self, Dependency, Metadata, PackageName, Target, WheelFilename};
+use pypi_lazyzip::range_trace::RangeTracer;
+use pypi_lazyzip::result_cache::{CachedResult, ResultCache};
+use pypi_lazyzip::snapshot::{self, Pacer, Query};
+use pypi_lazyzip::warnings::{self, Warning};
+use pypi_lazyzip::watch;
+use pypi_lazyzip::wheel::{
+    entry_reader, extract, find_entry, find_wheel, is_entry_points, is_top_level, list_entries,
+    open_zip, pkg_metadata, pkg_metadata_headers, read_entry_stored, top_level_names,
+};
+use pypi_lazyzip::wheel_lint;
+use pypi_lazyzip::wheel_matrix::{MatrixFormat, WheelMatrix};
+use pypi_lazyzip::{
+    LazyZipClient, PkgLoc, availability, bench, build_backend, cache_integrity, changelog, compat,
+    data_files, deps_diff, doctor, file_check, lockfile, metadata_mirror, requires_python,
+    top_packages, vendored,
+};
+use serde::Serialize;
 use tracing::instrument::Instrument as _;
 use tracing_subscriber::EnvFilter;
 use tracing_subscriber::fmt::format::FmtSpan;
 
-use crate::cli::{Cli, PkgLoc};
-use crate::python_pkg::{Dependency, PackageName, WheelFilename};
-use crate::simple_repo_api::fetch_project;
+use crate::cli::{CacheCommand, Cli, Command, InputArgs, PkgArgs, StateArgs};
+use crate::inputs::Inputs;
+use crate::merge::MergeTarget;
+use crate::output::{Output, WarningsMode, report_access_maps, report_warnings};
+use crate::state::{Completed, RunState};
 
 mod cli;
-mod python_pkg;
-mod simple_repo_api;
+mod inputs;
+mod merge;
+mod output;
+mod state;
+#[cfg(feature = "tui")]
+mod tui;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
     color_eyre::install()?;
+    let args = Cli::parse();
+    // Generating docs needs no client, and must not print logs into them
+    match &args.command {
+        Some(Command::Completions { shell }) => return completions(*shell),
+        Some(Command::Man { out_dir }) => return man(out_dir.as_deref()),
+        _ => {}
+    }
+    let mut filter = EnvFilter::from_default_env();
+    if args.output.trace_ranges {
+        filter = filter.add_directive("pypi_lazyzip::ranges=info".parse()?);
+    }
+    if args.output.explain {
+        filter = filter.add_directive("pypi_lazyzip::explain=info".parse()?);
+    }
     tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
+        .with_env_filter(filter)
         .with_span_events(FmtSpan::CLOSE | FmtSpan::NEW)
         .with_writer(std::io::stderr)
         .init();
+    if let Some(Command::Cache {
+        command: CacheCommand::Info { verify },
+    }) = &args.command
+    {
+        return args
+            .output
+            .output()
+            .write_json(&cache_integrity::info(*verify)?);
+    }
 
-    let args = Cli::try_parse()?;
+    // Prompts would garble the TUI
+    #[cfg(feature = "tui")]
+    let can_prompt = !matches!(args.command, Some(Command::Tui(_)));
+    #[cfg(not(feature = "tui"))]
+    let can_prompt = true;
+    let writable_dirs = args.writable_dirs();
+    let mut client = args.selection.apply(args.client.into_client(can_prompt)?)?;
+    let range_tracer = args.output.trace_ranges.then(RangeTracer::default);
+    if let Some(range_tracer) = &range_tracer {
+        client = client.with_middleware(range_tracer.clone());
+    }
+    let metrics = args.output.metrics.is_some().then(Metrics::default);
+    if let Some(metrics) = &metrics {
+        client = client.with_metrics(metrics.clone());
+    }
+    #[cfg(feature = "tuf")]
+    let client = args.tuf.apply(client).await?;
+    let client = &client;
+    let out = &args.output.output();
 
-    run(args).await
+    // Warnings about individual packages are collected in `batch`, these are about the whole run
+    let command = run(
+        args.command,
+        args.pkg_locs,
+        &args.input,
+        &args.state,
+        client,
+        out,
+        &writable_dirs,
+    );
+    let (result, warnings) = warnings::collect(Box::pin(command)).await;
+    report_warnings(None, &warnings)?;
+    if let Some(range_tracer) = range_tracer {
+        report_access_maps(&range_tracer.access_maps())?;
+    }
+    client.save_hash_pins()?;
+    client.save_prefetch_hints()?;
+    if let (Some(metrics), Some(path)) = (metrics, &args.output.metrics) {
+        std::fs::write(path, metrics.render())
+            .with_context(|| format!("Failed to write metrics {}", path.display()))?;
+    }
+    result
 }
 
-async fn run(args: Cli) -> Result<()> {
-    let is_top_level = |e: &StoredZipEntry| {
-        e.filename()
-            .as_str()
-            .is_ok_and(|n| n.ends_with("/top_level.txt"))
+async fn run(
+    command: Option<Command>,
+    pkg_locs: Vec<PkgLoc>,
+    input: &InputArgs,
+    state: &StateArgs,
+    client: &LazyZipClient,
+    out: &Output,
+    writable_dirs: &[PathBuf],
+) -> Result<()> {
+    // Snapshots crawl the top packages list themselves
+    let inputs = if matches!(command, Some(Command::Snapshot { .. })) {
+        Inputs::default()
+    } else {
+        Inputs::load(client, input).await?
     };
-    let contents = args
-        .pkg_locs
-        .into_iter()
-        .map(|e| extract(e, is_top_level))
-        .collect::<FuturesUnordered<_>>()
-        .try_collect::<Vec<_>>()
-        .await?;
-
-    serde_json::to_writer(
-        std::io::stdout(),
-        &contents
-            .into_iter()
-            .map(|(name, lines)| (name.into(), lines.into()))
-            .collect::<serde_json::Map<_, _>>(),
-    )?;
-
-    Ok(())
+    let with_inputs = |pkg_locs, locate| inputs.add_to(pkg_locs, locate);
+    // Commands reading wheels can read the locked ones, others need the release
+    let with_wheel_inputs = |pkg_locs| with_inputs(pkg_locs, LockedPackage::wheel_loc);
+    let wheel_pkgs = |pkgs: PkgArgs| with_wheel_inputs(pkgs.into_pkg_locs());
+    let release_pkgs = |pkgs: PkgArgs| with_inputs(pkgs.into_pkg_locs(), LockedPackage::dependency);
+    match command {
+        None => top_level(client, state, out, with_wheel_inputs(pkg_locs)).await,
+        Some(Command::TopLevel(pkgs)) => top_level(client, state, out, wheel_pkgs(pkgs)).await,
+        Some(Command::Metadata(pkgs)) => metadata(client, state, out, wheel_pkgs(pkgs)).await,
+        Some(Command::Requires(pkgs)) => requires(client, state, out, wheel_pkgs(pkgs)).await,
+        Some(Command::List { pkgs, query }) => {
+            list(client, state, out, wheel_pkgs(pkgs), query.as_ref()).await
+        }
+        Some(Command::Urls(pkgs)) => urls(client, state, out, wheel_pkgs(pkgs)).await,
+        Some(Command::RequiresPython {
+            pkgs,
+            python_version,
+        }) => {
+            let pkg_locs = wheel_pkgs(pkgs);
+            requires_python(client, out, pkg_locs, python_version.as_ref()).await
+        }
+        Some(Command::DeprecationCheck(pkgs)) => {
+            let pkg_locs = wheel_pkgs(pkgs);
+            deprecation_check(client, state, out, pkg_locs).await
+        }
+        Some(Command::Authors(pkgs)) => authors(client, out, wheel_pkgs(pkgs)).await,
+        Some(Command::DataFiles(pkgs)) => data_files(client, state, out, wheel_pkgs(pkgs)).await,
+        Some(Command::MirrorMetadata {
+            pkgs,
+            requirement_files,
+            dest,
+        }) => {
+            let pkg_locs = with_requirement_files(pkgs.into_pkg_locs(), &requirement_files)?;
+            mirror_metadata(client, state, out, with_wheel_inputs(pkg_locs), &dest).await
+        }
+        Some(Command::Fingerprint(pkgs)) => {
+            fingerprint_contents(client, state, out, wheel_pkgs(pkgs)).await
+        }
+        Some(Command::VendoredScan(pkgs)) => {
+            vendored_scan(client, state, out, wheel_pkgs(pkgs)).await
+        }
+        Some(Command::ConsoleScripts(pkgs)) => console_scripts(client, out, wheel_pkgs(pkgs)).await,
+        Some(Command::Sdist(pkgs)) => sdist(client, state, out, release_pkgs(pkgs)).await,
+        Some(Command::BuildBackend(pkgs)) => {
+            build_backend(client, state, out, release_pkgs(pkgs)).await
+        }
+        Some(Command::Snapshot { query, rate }) => {
+            snapshot(client, input, state, out, &query, rate).await
+        }
+        Some(Command::Extras(pkgs)) => extras(client, state, out, wheel_pkgs(pkgs)).await,
+        Some(
+            command @ (Command::Deps { .. } | Command::IndexGen { .. } | Command::Licenses { .. }),
+        ) => run_graph(command, client, out, with_wheel_inputs).await,
+        Some(command) => run_standalone(command, input, client, out, writable_dirs).await,
+    }
 }
 
-#[tracing::instrument(skip(predicate), fields(pkg_loc = %pkg_loc))]
-async fn extract(
-    pkg_loc: PkgLoc,
-    predicate: fn(&StoredZipEntry) -> bool,
-) -> Result<(PackageName, Vec<String>)> {
-    let (name, reader) = pkg_reader(pkg_loc).await?;
-    let buf_reader = BufReader::new(reader);
-    let mut zip_reader = ZipFileReader::new(buf_reader)
-        .instrument(tracing::info_span!("create_zip_reader"))
-        .await?;
-    let Some(idx_entry) = find_entry(&mut zip_reader, predicate) else {
-        return Ok((name, Vec::new()));
-    };
-    let mut buf = String::new();
-    read_entry(&mut zip_reader, idx_entry, &mut buf).await?;
-    let lines = buf
-        .split_terminator('\n')
-        .map(ToOwned::to_owned)
-        .collect::<Vec<_>>();
-    Ok((name, lines))
-}
-
-trait AsyncRS: AsyncRead + AsyncSeek + Unpin {}
-
-impl<R> AsyncRS for R where R: AsyncRead + AsyncSeek + Unpin {}
-
-#[tracing::instrument(skip_all)]
-async fn pkg_reader(pkg_loc: PkgLoc) -> Result<(PackageName, Box<dyn AsyncRS>)> {
-    match pkg_loc {
-        PkgLoc::Dependency(dep) => {
-            let client = reqwest::Client::new(); //.builder().http2_prior_knowledge().build()?
-            let whl = find_wheel(&client, &dep)
-                .instrument(tracing::info_span!("find_wheel"))
-                .await?;
-            let (reader, _headers) = AsyncHttpRangeReader::new(
+/// Run commands building a dependency graph from packages, adding `--input` packages with `with_wheel_inputs`
+async fn run_graph(
+    command: Command,
+    client: &LazyZipClient,
+    out: &Output,
+    with_wheel_inputs: impl Fn(Vec<PkgLoc>) -> Vec<PkgLoc>,
+) -> Result<()> {
+    match command {
+        Command::Deps {
+            pkgs,
+            format,
+            emit_download_script,
+            traversal,
+        } => {
+            let pkg_locs = with_wheel_inputs(pkgs.into_pkg_locs());
+            let traversal = traversal.into();
+            if let Some(format) = emit_download_script {
+                download_script(client, out, pkg_locs, format, &traversal).await
+            } else {
+                deps(client, out, pkg_locs, format, &traversal).await
+            }
+        }
+        Command::IndexGen {
+            pkgs,
+            requirement_files,
+            dest,
+            files_url,
+            traversal,
+        } => {
+            let pkg_locs = with_requirement_files(pkgs.into_pkg_locs(), &requirement_files)?;
+            index_gen(
                 client,
-                whl.url,
-                CheckSupportMethod::Head,
-                HeaderMap::new(),
+                out,
+                with_wheel_inputs(pkg_locs),
+                &dest,
+                files_url.as_deref(),
+                &traversal.into(),
             )
-            .instrument(tracing::info_span!("create_range_reader"))
-            .await?;
-            Ok((dep.into_name(), Box::new(reader.compat())))
+            .await
+        }
+        Command::Licenses {
+            pkgs,
+            requirement_files,
+            summary,
+            allow,
+            deny,
+            traversal,
+        } => {
+            let pkg_locs = with_requirement_files(pkgs.into_pkg_locs(), &requirement_files)?;
+            let policy = Policy { allow, deny };
+            licenses(
+                client,
+                out,
+                with_wheel_inputs(pkg_locs),
+                summary,
+                &policy,
+                &traversal.into(),
+            )
+            .await
         }
-        PkgLoc::Path(path) => {
-            let name = PackageName::from_str(
-                path.file_name()
-                    .context("file without name")?
-                    .to_str()
-                    .context("file name not UTF-8")?,
-            )?;
-            let reader = tokio::fs::File::open(path).await?;
-            Ok((name, Box::new(reader.compat())))
+        _ => unreachable!("only commands building a dependency graph are run by `run_graph`"),
+    }
+}
+
+/// Run commands that don’t read packages from `--input`
+async fn run_standalone(
+    command: Command,
+    input: &InputArgs,
+    client: &LazyZipClient,
+    out: &Output,
+    writable_dirs: &[PathBuf],
+) -> Result<()> {
+    match command {
+        Command::Changelog { pkg, full } => changelog(client, out, pkg.into_pkg_loc(), full).await,
+        #[cfg(feature = "tui")]
+        Command::Tui(pkg) => tui::run(client, pkg.into_pkg_loc()).await,
+        Command::DepsDiff { old, new } => deps_diff(client, out, old, new).await,
+        Command::FingerprintDiff { old, new } => fingerprint_diff(client, out, old, new).await,
+        Command::Normalize { names } => normalize(out, &names),
+        Command::Completions { .. } | Command::Man { .. } => {
+            unreachable!("docs are generated before creating a client")
+        }
+        Command::Cache { .. } => unreachable!("the cache is inspected before creating a client"),
+        Command::Bench(pkg) => out.write_json(&bench::bench(client, pkg.into_pkg_loc()).await?),
+        Command::Doctor { package } => doctor(client, out, &package, writable_dirs).await,
+        Command::Cat { pkg, path } => cat(client, out, pkg.into_pkg_loc(), &path).await,
+        Command::Assert {
+            requirement,
+            python_version,
+            platform,
+            has_wheel,
+        } => {
+            let criteria = availability::Criteria {
+                target: target(python_version.as_ref(), platform),
+                has_wheel,
+            };
+            assert_installable(client, out, &requirement, &criteria).await
         }
+        Command::AssertSize {
+            requirement,
+            max_compressed,
+            python_version,
+            platform,
+            has_wheel,
+        } => {
+            let criteria = availability::Criteria {
+                target: target(python_version.as_ref(), platform),
+                has_wheel,
+            };
+            assert_size(client, out, &requirement, &criteria, max_compressed).await
+        }
+        Command::WheelMatrix {
+            requirement,
+            format,
+        } => wheel_matrix(client, out, &requirement, format).await,
+        Command::VerifyLock { lock_file, hashes } => {
+            verify_lock(client, out, &lock_file, &input.group, hashes).await
+        }
+        Command::Watch { package, interval } => watch(client, out, &package, interval).await,
+        Command::CheckFiles { requirement } => check_files(client, out, &requirement).await,
+        Command::LintWheel(pkg) => lint_wheel(client, out, pkg.into_pkg_loc()).await,
+        Command::CheckCompat {
+            requirements,
+            requirement_files,
+        } => check_compat(client, out, requirements, &requirement_files).await,
+        _ => unreachable!("commands reading inputs are run by `run`"),
     }
 }
 
-async fn find_wheel(client: &reqwest::Client, dep: &Dependency) -> Result<simple_repo_api::File> {
-    fetch_project(client, dep.name())
+async fn top_level(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+) -> Result<()> {
+    batch(
+        client,
+        state,
+        out,
+        "top-level",
+        Some("top-level"),
+        pkg_locs,
+        |pkg_loc| async move {
+            let (name, content) = extract(client, pkg_loc, is_top_level).await?;
+            let lines = top_level_names(content.as_deref().unwrap_or_default());
+            Ok((name, lines))
+        },
+    )
+    .await
+}
+
+async fn metadata(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+) -> Result<()> {
+    // The long description is the only field in the body, so skip it if it won’t be output
+    let headers_only = out
+        .fields
+        .as_ref()
+        .is_some_and(|fields| !fields.iter().any(|f| f == "description"));
+    batch(
+        client,
+        state,
+        out,
+        "metadata",
+        Some(if headers_only {
+            "metadata-headers"
+        } else {
+            "metadata"
+        }),
+        pkg_locs,
+        |pkg_loc| async move {
+            if headers_only {
+                pkg_metadata_headers(client, pkg_loc).await
+            } else {
+                pkg_metadata(client, pkg_loc).await
+            }
+        },
+    )
+    .await
+}
+
+async fn urls(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+) -> Result<()> {
+    batch(
+        client,
+        state,
+        out,
+        "urls",
+        Some("urls"),
+        pkg_locs,
+        |pkg_loc| async move {
+            let (name, metadata) = pkg_metadata_headers(client, pkg_loc).await?;
+            Ok((name, ProjectUrls::from_metadata(&metadata)))
+        },
+    )
+    .await
+}
+
+async fn deprecation_check(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+) -> Result<()> {
+    batch(
+        client,
+        state,
+        out,
+        "deprecation-check",
+        Some("deprecation-check"),
+        pkg_locs,
+        |pkg_loc| deprecation::check_pkg(client, pkg_loc),
+    )
+    .await
+}
+
+async fn requires(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+) -> Result<()> {
+    batch(
+        client,
+        state,
+        out,
+        "requires",
+        Some("requires"),
+        pkg_locs,
+        |pkg_loc| async move {
+            let (name, mut metadata) = pkg_metadata_headers(client, pkg_loc).await?;
+            client.apply_overrides(&mut metadata);
+            let requires_dist = metadata
+                .get_all("Requires-Dist")
+                .map(ToOwned::to_owned)
+                .collect::<Vec<_>>();
+            Ok((name, requires_dist))
+        },
+    )
+    .await
+}
+
+async fn extras(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+) -> Result<()> {
+    batch(
+        client,
+        state,
+        out,
+        "extras",
+        Some("extras"),
+        pkg_locs,
+        |pkg_loc| async move {
+            let (name, mut metadata) = pkg_metadata_headers(client, pkg_loc).await?;
+            client.apply_overrides(&mut metadata);
+            Ok((name, Extras::from_metadata(&metadata)?))
+        },
+    )
+    .await
+}
+
+async fn data_files(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+) -> Result<()> {
+    batch(
+        client,
+        state,
+        out,
+        "data-files",
+        Some("data-files"),
+        pkg_locs,
+        |pkg_loc| data_files::inventory(client, pkg_loc),
+    )
+    .await
+}
+
+async fn list(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+    query: Option<&EntryQuery>,
+) -> Result<()> {
+    let Some(query) = query else {
+        return batch(
+            client,
+            state,
+            out,
+            "list",
+            Some("list"),
+            pkg_locs,
+            |pkg_loc| list_entries(client, pkg_loc),
+        )
+        .await;
+    };
+    batch(client, state, out, "list", None, pkg_locs, |pkg_loc| {
+        entry_query::list_matching(client, pkg_loc, query)
+    })
+    .await
+}
+
+/// Add the packages in requirements files to `pkg_locs`
+fn with_requirement_files(mut pkg_locs: Vec<PkgLoc>, paths: &[PathBuf]) -> Result<Vec<PkgLoc>> {
+    for path in paths {
+        for requirement in lockfile::read_requirements(path)? {
+            pkg_locs.push(PkgLoc::Dependency(Dependency::from_requirement(
+                &requirement,
+            )?));
+        }
+    }
+    Ok(pkg_locs)
+}
+
+async fn mirror_metadata(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+    dest: &Path,
+) -> Result<()> {
+    batch(
+        client,
+        state,
+        out,
+        "mirror-metadata",
+        None,
+        pkg_locs,
+        |pkg_loc| metadata_mirror::mirror(client, pkg_loc, dest),
+    )
+    .await
+}
+
+async fn fingerprint_contents(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+) -> Result<()> {
+    batch(
+        client,
+        state,
+        out,
+        "fingerprint",
+        Some("fingerprint"),
+        pkg_locs,
+        |pkg_loc| content_fingerprint::fingerprint(client, pkg_loc),
+    )
+    .await
+}
+
+async fn vendored_scan(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+) -> Result<()> {
+    batch(
+        client,
+        state,
+        out,
+        "vendored-scan",
+        Some("vendored-scan"),
+        pkg_locs,
+        |pkg_loc| async move {
+            let (name, paths) = list_entries(client, pkg_loc).await?;
+            Ok((name, vendored::scan(paths.iter().map(String::as_str))))
+        },
+    )
+    .await
+}
+
+async fn doctor(
+    client: &LazyZipClient,
+    out: &Output,
+    package: &PackageName,
+    writable_dirs: &[PathBuf],
+) -> Result<()> {
+    let checks = doctor::diagnose(client, package, writable_dirs).await;
+    let report = checks.iter().map(ToString::to_string).collect::<Vec<_>>();
+    out.write_text(&report.join("\n"))?;
+    let failed = checks
+        .iter()
+        .filter(|c| c.status == doctor::Status::Fail)
+        .count();
+    if failed > 0 {
+        bail!("{failed} of {} checks failed", checks.len());
+    }
+    Ok(())
+}
+
+fn target(python_version: Option<&pep440_rs::Version>, platform: Option<String>) -> Target {
+    let python = python_version.map(|version| {
+        let release = version.release();
+        (release[0], release.get(1).copied().unwrap_or_default())
+    });
+    Target { python, platform }
+}
+
+async fn assert_installable(
+    client: &LazyZipClient,
+    out: &Output,
+    requirement: &Dependency,
+    criteria: &availability::Criteria,
+) -> Result<()> {
+    let Some(file) = availability::find_installable(client, requirement, criteria).await? else {
+        let kind = if criteria.has_wheel { "wheel" } else { "file" };
+        let python = criteria
+            .target
+            .python
+            .map(|(major, minor)| format!(" for Python {major}.{minor}"));
+        let platform =
+            (criteria.target.platform.as_ref()).map(|platform| format!(" on {platform}"));
+        let target = python.into_iter().chain(platform).collect::<String>();
+        bail!("No release of {requirement} has a compatible {kind}{target}");
+    };
+    out.write_text(&file.filename)
+}
+
+async fn assert_size(
+    client: &LazyZipClient,
+    out: &Output,
+    requirement: &Dependency,
+    criteria: &availability::Criteria,
+    max_size: u64,
+) -> Result<()> {
+    let Some((file, size)) =
+        availability::largest_installable(client, requirement, criteria).await?
+    else {
+        bail!("No release of {requirement} has a compatible file");
+    };
+    out.write_text(&format!("{} {size}", file.filename))?;
+    if size > max_size {
+        bail!(
+            "{} is {size} bytes, over the budget of {max_size} bytes",
+            file.filename
+        );
+    }
+    Ok(())
+}
+
+async fn wheel_matrix(
+    client: &LazyZipClient,
+    out: &Output,
+    requirement: &Dependency,
+    format: MatrixFormat,
+) -> Result<()> {
+    let matrix = WheelMatrix::build(client, requirement).await?;
+    match format {
+        MatrixFormat::Table => out.write_text(matrix.render_table().trim_end()),
+        MatrixFormat::Json => out.write_json(&matrix),
+    }
+}
+
+async fn verify_lock(
+    client: &LazyZipClient,
+    out: &Output,
+    lock_file: &Path,
+    groups: &[Group],
+    hashes: bool,
+) -> Result<()> {
+    let packages = lockfile::read(lock_file, groups)?;
+    let drifts = lockfile::verify(client, &packages, hashes).await;
+    out.write_json(&drifts)?;
+    let drifted = drifts
+        .iter()
+        .map(|d| (&d.name, &d.version))
+        .collect::<std::collections::BTreeSet<_>>();
+    if !drifted.is_empty() {
+        bail!(
+            "{} of {} locked packages drifted from the index",
+            drifted.len(),
+            packages.len()
+        );
+    }
+    Ok(())
+}
+
+async fn watch(
+    client: &LazyZipClient,
+    out: &Output,
+    package: &PackageName,
+    interval: u64,
+) -> Result<()> {
+    let interval = Duration::from_secs(interval);
+    watch::watch(client, package, interval, |patch| {
+        out.write_json_line(&patch)
+    })
+    .await
+}
+
+async fn check_files(client: &LazyZipClient, out: &Output, requirement: &Dependency) -> Result<()> {
+    let (version, checks) = file_check::check_release(client, requirement).await?;
+    out.write_json(&checks)?;
+    let failed = checks.iter().filter(|c| !c.problems.is_empty()).count();
+    if failed > 0 {
+        bail!(
+            "{failed} of {} files of {} {version} have problems",
+            checks.len(),
+            requirement.name()
+        );
+    }
+    Ok(())
+}
+
+async fn lint_wheel(client: &LazyZipClient, out: &Output, pkg_loc: PkgLoc) -> Result<()> {
+    let lint = wheel_lint::lint(client, pkg_loc).await?;
+    out.write_json(&lint)?;
+    if !lint.problems.is_empty() {
+        bail!("{} has {} problems", lint.filename, lint.problems.len());
+    }
+    Ok(())
+}
+
+async fn check_compat(
+    client: &LazyZipClient,
+    out: &Output,
+    mut requirements: Vec<pep508_rs::Requirement>,
+    requirement_files: &[PathBuf],
+) -> Result<()> {
+    for path in requirement_files {
+        requirements.extend(lockfile::read_requirements(path)?);
+    }
+    let report = compat::check(client, &requirements).await;
+    out.write_json(&report)?;
+    if !report.conflicts.is_empty() {
+        let packages = report
+            .conflicts
+            .iter()
+            .map(|conflict| conflict.package.as_str())
+            .collect::<std::collections::BTreeSet<_>>();
+        bail!(
+            "Conflicting requirements for {}",
+            packages.into_iter().collect::<Vec<_>>().join(", ")
+        );
+    }
+    Ok(())
+}
+
+async fn sdist(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+) -> Result<()> {
+    batch(
+        client,
+        state,
+        out,
+        "sdist",
+        Some("sdist"),
+        pkg_locs,
+        |pkg_loc| pypi_lazyzip::sdist::inspect(client, pkg_loc),
+    )
+    .await
+}
+
+async fn build_backend(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+) -> Result<()> {
+    batch(
+        client,
+        state,
+        out,
+        "build-backend",
+        Some("build-backend"),
+        pkg_locs,
+        |pkg_loc| build_backend::detect(client, pkg_loc),
+    )
+    .await
+}
+
+async fn snapshot(
+    client: &LazyZipClient,
+    input: &InputArgs,
+    state: &StateArgs,
+    out: &Output,
+    queries: &[Query],
+    rate: f64,
+) -> Result<()> {
+    let source = input
+        .from_top_packages
+        .as_deref()
+        .unwrap_or(top_packages::TOP_PACKAGES_URL);
+    let pkg_locs = top_packages::load(client, source, Some(input.top.unwrap_or(1000)))
         .await?
-        .files
         .into_iter()
-        .filter_map(|p| {
-            let n = WheelFilename::from_str(&p.filename).ok()?;
-            let is_valid = !&p.yanked
-                && dep
-                    .version_spec()
-                    .is_none_or(|version_spec| version_spec.contains(&n.version));
-            is_valid.then_some((n, p))
-        })
-        .max_by(|(name_l, _), (name_r, _)| name_l.version.cmp(&name_r.version))
-        .map(|(_, whl)| whl)
-        .with_context(|| format!("No wheel found for {dep}"))
+        .map(|name| PkgLoc::Dependency(Dependency::new(name, None)))
+        .collect();
+    let pacer = &Pacer::new(rate);
+    batch(
+        client,
+        state,
+        out,
+        "snapshot",
+        None,
+        pkg_locs,
+        |pkg_loc| async move {
+            let PkgLoc::Dependency(dep) = pkg_loc else {
+                bail!("snapshots only crawl projects on indexes");
+            };
+            pacer.wait().await;
+            Ok(snapshot::query(client, dep.into_name(), queries).await)
+        },
+    )
+    .await
 }
 
-fn find_entry<R>(
-    reader: &mut ZipFileReader<R>,
-    predicate: fn(&StoredZipEntry) -> bool,
-) -> Option<usize>
-where
-    R: AsyncBufRead + AsyncSeek + Unpin,
-{
-    reader
+#[tracing::instrument(skip(client, out), fields(pkg_loc = %pkg_loc))]
+async fn cat(client: &LazyZipClient, out: &Output, pkg_loc: PkgLoc, path: &str) -> Result<()> {
+    let (name, mut zip_reader) = open_zip(client, pkg_loc).await?;
+    let idx = zip_reader
         .file()
         .entries()
         .iter()
-        .enumerate()
-        .find(|(_, e)| predicate(e))
-        .map(|(i, _)| i)
+        .position(|e| e.filename().as_str().is_ok_and(|n| n == path))
+        .with_context(|| format!("{name} has no file {path}"))?;
+    zip_reader.prefetch_entry(idx).await?;
+    let reader = entry_reader(&mut zip_reader, idx).await?;
+    out.write_stream(reader).await
 }
 
-#[tracing::instrument(skip(reader, buf))]
-async fn read_entry<R>(reader: &mut ZipFileReader<R>, idx: usize, buf: &mut String) -> Result<usize>
+/// Run `f` for all packages concurrently and write the results as a JSON object.
+///
+/// With `--state`, every result is recorded as soon as it’s done,
+/// and with `--resume`, packages completed in an earlier run are skipped
+/// and their earlier results are included in the output.
+/// With `--merge-into`, earlier results are reused for packages whose file didn’t change.
+async fn batch<V, Fut>(
+    client: &LazyZipClient,
+    state: &StateArgs,
+    out: &Output,
+    command: &str,
+    cache_as: Option<&str>,
+    pkg_locs: Vec<PkgLoc>,
+    f: impl Fn(PkgLoc) -> Fut,
+) -> Result<()>
 where
-    R: AsyncBufRead + AsyncSeek + Unpin,
+    V: Serialize,
+    Fut: Future<Output = Result<(PackageName, V)>>,
 {
-    reader
-        .reader_with_entry(idx)
-        .instrument(tracing::info_span!("create_entry_reader"))
-        .await?
-        .read_to_string_checked(buf)
-        .instrument(tracing::info_span!("read_to_string"))
-        .await
-        .context("Failed to read entry")
+    // Overrides change results without changing the file
+    let cache = cache_as
+        .filter(|_| !state.no_result_cache && client.overrides().is_none())
+        .and_then(|kind| Some((ResultCache::in_user_cache()?, kind)));
+    // Collect warnings per package, and embed them in its report or report them right away
+    let run = |pkg_loc: PkgLoc, fingerprint| {
+        let embed = (out.warnings == WarningsMode::Embed).then(|| pkg_loc.clone());
+        let future = cached(client, cache.as_ref(), command, pkg_loc, fingerprint, &f);
+        async move {
+            let start = Instant::now();
+            let (result, warnings) = future.await;
+            let (name, value) = result?;
+            let report = PackageReport::new(name, value)
+                .with_warnings(warnings)
+                .with_elapsed(start.elapsed());
+            let Some(pkg_loc) = embed else {
+                report_warnings(Some(&report.name.to_string()), &report.warnings)?;
+                return Ok::<_, Error>((report.name, report.result));
+            };
+            let report = report.with_specifier(match &pkg_loc {
+                PkgLoc::Dependency(dep) => dep.version_spec(),
+                PkgLoc::Url(_) | PkgLoc::Path(_) => None,
+            });
+            let report = match file_info(client, command, pkg_loc).await {
+                Some(file) => report.with_file(file),
+                None => report,
+            };
+            Ok((report.name.clone(), serde_json::to_value(report)?))
+        }
+    };
+    if let Some(path) = &state.merge_into {
+        let mut target = MergeTarget::open(path, command)?;
+        let outcomes = pkg_locs
+            .into_iter()
+            .map(|pkg_loc| {
+                let key = pkg_loc.to_string();
+                let target = &target;
+                async move {
+                    let fingerprint = fingerprint(client, command, pkg_loc.clone()).await;
+                    if let Some(unchanged) = fingerprint
+                        .as_deref()
+                        .and_then(|fingerprint| target.unchanged(&key, fingerprint))
+                    {
+                        tracing::info!(pkg_loc = key, "unchanged");
+                        return Ok((key, fingerprint, unchanged));
+                    }
+                    let (name, value) = run(pkg_loc, fingerprint.clone()).await?;
+                    Ok::<_, Error>((key, fingerprint, (name.into(), value)))
+                }
+            })
+            .collect::<FuturesUnordered<_>>()
+            .try_collect::<Vec<_>>()
+            .await?;
+        for (pkg_loc, fingerprint, (name, value)) in outcomes {
+            target.update(pkg_loc, name, fingerprint, value);
+        }
+        return target.save(out);
+    }
+    let Some(path) = &state.state else {
+        let contents = pkg_locs
+            .into_iter()
+            .map(|pkg_loc| run(pkg_loc, None))
+            .collect::<FuturesUnordered<_>>()
+            .try_collect::<Vec<_>>()
+            .await?;
+        return out.write_map(command, contents);
+    };
+    let mut run_state = RunState::open(path, command, state.resume)?;
+    let mut pending = pkg_locs
+        .into_iter()
+        .filter(|pkg_loc| !run_state.is_completed(pkg_loc))
+        .map(|pkg_loc| {
+            let key = pkg_loc.to_string();
+            run(pkg_loc, None).map(|result| (key, result))
+        })
+        .collect::<FuturesUnordered<_>>();
+    while let Some((pkg_loc, result)) = pending.next().await {
+        let (name, value) = result?;
+        let completed = Completed {
+            name: name.into(),
+            result: value,
+        };
+        run_state.complete(pkg_loc, completed)?;
+    }
+    out.write_map(
+        command,
+        run_state.into_results().map(|c| (c.name, c.result)),
+    )
+}
+
+/// Run `f` collecting its warnings, or take its result from the result cache
+/// if the file is known by its hash. Only results without warnings are cached,
+/// as those are about the index and not the file.
+async fn cached<V, Fut>(
+    client: &LazyZipClient,
+    cache: Option<&(ResultCache, &str)>,
+    command: &str,
+    pkg_loc: PkgLoc,
+    fingerprint: Option<String>,
+    f: impl FnOnce(PkgLoc) -> Fut,
+) -> (Result<(PackageName, serde_json::Value)>, Vec<Warning>)
+where
+    V: Serialize,
+    Fut: Future<Output = Result<(PackageName, V)>>,
+{
+    let run = |pkg_loc| async {
+        let (result, warnings) = warnings::collect(f(pkg_loc)).await;
+        let result = result.and_then(|(name, value)| Ok((name, serde_json::to_value(value)?)));
+        (result, warnings)
+    };
+    let Some((cache, kind)) = cache else {
+        return run(pkg_loc).await;
+    };
+    let (fingerprint, located_warnings) = warnings::collect(async {
+        match fingerprint {
+            Some(fingerprint) => Some(fingerprint),
+            None => self::fingerprint(client, command, pkg_loc.clone()).await,
+        }
+    })
+    .await;
+    let sha256 = fingerprint
+        .as_deref()
+        .and_then(|fingerprint| fingerprint.strip_prefix("sha256:"));
+    let hit = sha256.and_then(|sha256| cache.get(kind, sha256));
+    if sha256.is_some() {
+        client.record_cache_lookup("result_cache", hit.is_some());
+    }
+    if let Some(hit) = hit
+        && let Ok(name) = PackageName::from_str(&hit.name)
+    {
+        tracing::info!(pkg_loc = %pkg_loc, "cached");
+        return (Ok((name, hit.value)), located_warnings);
+    }
+    let (result, warnings) = run(pkg_loc).await;
+    if let (Ok((name, value)), Some(sha256), true) = (&result, sha256, warnings.is_empty()) {
+        let entry = CachedResult {
+            name: name.to_string(),
+            value: value.clone(),
+        };
+        if let Err(e) = cache.put(kind, sha256, &entry) {
+            tracing::warn!("Failed to cache result: {e:#}");
+        }
+    }
+    (result, warnings)
+}
+
+/// The file `command` reads for a package, if it can be resolved
+async fn file_info(client: &LazyZipClient, command: &str, pkg_loc: PkgLoc) -> Option<FileInfo> {
+    let file_info = if command == "sdist" {
+        pypi_lazyzip::sdist::file_info(client, pkg_loc).await
+    } else {
+        pypi_lazyzip::wheel::file_info(client, pkg_loc).await
+    };
+    file_info.ok()
+}
+
+/// What identifies the file `command` reads for a package, if it can be resolved.
+/// If it can’t, running the command reports why.
+async fn fingerprint(client: &LazyZipClient, command: &str, pkg_loc: PkgLoc) -> Option<String> {
+    let fingerprint = if command == "sdist" {
+        pypi_lazyzip::sdist::fingerprint(client, pkg_loc).await
+    } else {
+        pypi_lazyzip::wheel::fingerprint(client, pkg_loc).await
+    };
+    fingerprint.ok().flatten()
+}
+
+async fn deps(
+    client: &LazyZipClient,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+    format: GraphFormat,
+    traversal: &Traversal,
+) -> Result<()> {
+    let graph = DepGraph::build(client, pkg_locs, traversal).await?;
+    match format {
+        GraphFormat::Json => out.write_json(&graph),
+        _ => out.write_text(&graph.render(format)?),
+    }
+}
+
+async fn download_script(
+    client: &LazyZipClient,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+    format: ScriptFormat,
+    traversal: &Traversal,
+) -> Result<()> {
+    let graph = DepGraph::build(client, pkg_locs, traversal).await?;
+    let (artifacts, missing) = download_script::artifacts(client, &graph)?;
+    if !missing.is_empty() {
+        tracing::warn!("Not resolved to files on an index: {}", missing.join(", "));
+    }
+    let script = download_script::render(&artifacts, &missing, format);
+    out.write_text(script.trim_end())
+}
+
+async fn index_gen(
+    client: &LazyZipClient,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+    dest: &Path,
+    files_url: Option<&str>,
+    traversal: &Traversal,
+) -> Result<()> {
+    let graph = DepGraph::build(client, pkg_locs, traversal).await?;
+    let mut index = StaticIndex::default();
+    let mut missing = Vec::new();
+    for (_, node) in graph.nodes() {
+        let Some(file) = &node.file else {
+            if !node.excluded {
+                missing.push(node.name.to_string());
+            }
+            continue;
+        };
+        let url = match files_url {
+            Some(base) => format!("{}/{}", base.trim_end_matches('/'), file.filename),
+            None => client.rewrite_url(file.url.clone())?.to_string(),
+        };
+        index.add(node.name.clone(), IndexFile::new(file, url));
+    }
+    if !missing.is_empty() {
+        tracing::warn!("Not resolved to files on an index: {}", missing.join(", "));
+    }
+    out.write_json(&index.write(dest)?)
+}
+
+async fn licenses(
+    client: &LazyZipClient,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+    summary: bool,
+    policy: &Policy,
+    traversal: &Traversal,
+) -> Result<()> {
+    let graph = DepGraph::build(client, pkg_locs, traversal).await?;
+    let packages = licenses::collect(&graph);
+    let violations = if summary {
+        let summary = LicenseSummary::new(&packages, policy);
+        out.write_json(&summary)?;
+        summary.violations
+    } else {
+        out.write_json(&packages)?;
+        licenses::violations(&packages, policy)
+    };
+    if !violations.is_empty() {
+        let packages = violations
+            .iter()
+            .map(|v| {
+                format!(
+                    "{} ({})",
+                    v.package,
+                    v.license.as_deref().unwrap_or("unknown")
+                )
+            })
+            .collect::<Vec<_>>();
+        bail!(
+            "{} packages have licenses the policy rejects: {}",
+            packages.len(),
+            packages.join(", ")
+        );
+    }
+    Ok(())
+}
+
+#[tracing::instrument(skip(client), fields(pkg_loc = %pkg_loc))]
+async fn changelog(
+    client: &LazyZipClient,
+    out: &Output,
+    pkg_loc: PkgLoc,
+    full: bool,
+) -> Result<()> {
+    let (name, metadata, found) = match open_zip(client, pkg_loc.clone()).await {
+        Ok((name, mut zip_reader)) => {
+            let metadata =
+                if let Some(idx) = find_entry(&mut zip_reader, pypi_lazyzip::wheel::is_metadata) {
+                    let mut buf = String::new();
+                    read_entry_stored(client, &mut zip_reader, idx, &mut buf).await?;
+                    Metadata::from_str(&buf)?
+                } else {
+                    warnings::warn(Warning::MetadataMissing);
+                    Metadata::default()
+                };
+            let best = zip_reader
+                .file()
+                .entries()
+                .iter()
+                .enumerate()
+                .filter_map(|(i, e)| Some((changelog::rank(e.filename().as_str().ok()?)?, i)))
+                .min();
+            let found = if let Some((_, idx)) = best {
+                let mut buf = String::new();
+                read_entry_stored(client, &mut zip_reader, idx, &mut buf).await?;
+                Some(buf)
+            } else {
+                sdist_changelog(client, &pkg_loc, &metadata).await
+            };
+            (name, metadata, found)
+        }
+        // Packages only published as sdists
+        Err(e) => {
+            let Ok((name, sdist)) = pypi_lazyzip::sdist::changelog(client, pkg_loc).await else {
+                return Err(e);
+            };
+            let metadata = sdist.metadata.unwrap_or_else(|| {
+                warnings::warn(Warning::MetadataMissing);
+                Metadata::default()
+            });
+            (name, metadata, sdist.changelog)
+        }
+    };
+    let Some(buf) = found else {
+        let url = changelog::url(&metadata)
+            .with_context(|| format!("No changelog file or URL found for {name}"))?;
+        return out.write_text(url);
+    };
+    let section = metadata
+        .get("Version")
+        .filter(|_| !full)
+        .and_then(|version| changelog::section(&buf, version));
+    out.write_text(section.unwrap_or(&buf).trim_end())
+}
+
+/// The changelog in the top-level directory of the sdist of the same release as a wheel without one.
+/// Only dependencies can be matched to an sdist.
+async fn sdist_changelog(
+    client: &LazyZipClient,
+    pkg_loc: &PkgLoc,
+    metadata: &Metadata,
+) -> Option<String> {
+    let PkgLoc::Dependency(dep) = pkg_loc else {
+        return None;
+    };
+    let version_spec =
+        pep440_rs::VersionSpecifiers::from_str(&format!("=={}", metadata.get("Version")?)).ok()?;
+    let pkg_loc = PkgLoc::Dependency(dep.clone().constrain(&version_spec));
+    match pypi_lazyzip::sdist::changelog(client, pkg_loc).await {
+        Ok((_, sdist)) => sdist.changelog,
+        Err(e) => {
+            tracing::debug!("No sdist changelog for {}: {e:#}", dep.name());
+            None
+        }
+    }
+}
+
+async fn deps_diff(client: &LazyZipClient, out: &Output, old: PkgLoc, new: PkgLoc) -> Result<()> {
+    let ((_, old), (_, new)) = futures::try_join!(
+        pkg_metadata_headers(client, old),
+        pkg_metadata_headers(client, new)
+    )?;
+    let diff = deps_diff::DepsDiff::new(&old.requires_dist()?, &new.requires_dist()?);
+    out.write_json(&diff)
+}
+
+async fn fingerprint_diff(
+    client: &LazyZipClient,
+    out: &Output,
+    old: PkgLoc,
+    new: PkgLoc,
+) -> Result<()> {
+    let ((_, old), (_, new)) = futures::try_join!(
+        content_fingerprint::fingerprint(client, old),
+        content_fingerprint::fingerprint(client, new)
+    )?;
+    out.write_json(&ContentDiff::new(&old, &new))
+}
+
+fn completions(shell: clap_complete::Shell) -> Result<()> {
+    let mut cmd = Cli::command();
+    let name = cmd.get_name().to_owned();
+    // `generate` panics on write errors, e.g. when piped into `head`
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut cmd, name, &mut script);
+    std::io::stdout().write_all(&script)?;
+    Ok(())
+}
+
+fn man(out_dir: Option<&Path>) -> Result<()> {
+    let Some(out_dir) = out_dir else {
+        clap_mangen::Man::new(Cli::command()).render(&mut std::io::stdout())?;
+        return Ok(());
+    };
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create {}", out_dir.display()))?;
+    clap_mangen::generate_to(Cli::command(), out_dir)
+        .with_context(|| format!("Failed to write man pages to {}", out_dir.display()))
+}
+
+fn normalize(out: &Output, names: &[String]) -> Result<()> {
+    let normalized = names
+        .iter()
+        .map(|name| Ok((name.clone(), python_pkg::normalize(name)?)))
+        .collect::<Result<Vec<_>>>()?;
+    out.write_map("normalize", normalized)
+}
+
+async fn requires_python(
+    client: &LazyZipClient,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+    python_version: Option<&pep440_rs::Version>,
+) -> Result<()> {
+    let packages = pkg_locs
+        .into_iter()
+        .map(|pkg_loc| pkg_requires_python(client, pkg_loc))
+        .collect::<FuturesUnordered<_>>()
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    out.write_json(&requires_python::Report::new(packages, python_version))
+}
+
+async fn authors(client: &LazyZipClient, out: &Output, pkg_locs: Vec<PkgLoc>) -> Result<()> {
+    let packages = pkg_locs
+        .into_iter()
+        .map(|pkg_loc| pkg_metadata_headers(client, pkg_loc))
+        .collect::<FuturesUnordered<_>>()
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    out.write_json(&AuthorsReport::new(packages))
+}
+
+async fn console_scripts(
+    client: &LazyZipClient,
+    out: &Output,
+    pkg_locs: Vec<PkgLoc>,
+) -> Result<()> {
+    let packages = pkg_locs
+        .into_iter()
+        .map(|pkg_loc| async move {
+            let (name, content) = extract(client, pkg_loc, is_entry_points).await?;
+            let scripts = console_scripts::scripts(content.as_deref().unwrap_or_default());
+            Ok::<_, Error>((name, scripts))
+        })
+        .collect::<FuturesUnordered<_>>()
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    out.write_json(&ConsoleScriptsReport::new(packages))
+}
+
+/// Get `Requires-Python` from the index if possible, or from the wheel’s `METADATA`
+#[tracing::instrument(skip(client), fields(pkg_loc = %pkg_loc))]
+async fn pkg_requires_python(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+) -> Result<(PackageName, Option<pep440_rs::VersionSpecifiers>)> {
+    if let PkgLoc::Dependency(dep) = &pkg_loc {
+        let whl = find_wheel(client, dep)
+            .instrument(tracing::info_span!("find_wheel"))
+            .await?;
+        let overridden = client.overrides().and_then(|overrides| {
+            let version = WheelFilename::from_str(&whl.filename).ok()?.version;
+            overrides.requires_python(dep.name(), &version).cloned()
+        });
+        if overridden.is_some() {
+            return Ok((dep.name().clone(), overridden));
+        }
+        let requires_python = whl
+            .requires_python
+            .as_deref()
+            .map(pep440_rs::VersionSpecifiers::from_str)
+            .transpose()
+            .with_context(|| format!("invalid requires-python for {}", whl.filename))?;
+        return Ok((dep.name().clone(), requires_python));
+    }
+    let (name, mut metadata) = pkg_metadata_headers(client, pkg_loc).await?;
+    client.apply_overrides(&mut metadata);
+    Ok((name, metadata.requires_python()?))
 }
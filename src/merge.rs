@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{Context as _, ContextCompat as _, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use pypi_lazyzip::blob_store::write_atomic;
+
+use crate::output::{Dest, Output};
+
+/// The output of an earlier run to update, and which files its results were read from
+#[derive(Debug)]
+pub struct MergeTarget {
+    path: PathBuf,
+    results: serde_json::Map<String, serde_json::Value>,
+    sources: Sources,
+}
+
+/// Saved next to the output, as it needs to stay a plain map of results
+#[derive(Serialize, Deserialize, Debug)]
+struct Sources {
+    /// The subcommand producing the results
+    command: String,
+    /// Files by package location as given on the command line
+    files: BTreeMap<String, Source>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Source {
+    /// The package name the result is stored under
+    name: String,
+    /// What identifies the file, see [`pypi_lazyzip::wheel::fingerprint`]
+    fingerprint: String,
+}
+
+impl MergeTarget {
+    /// Read the results and sources of an earlier run, if any
+    pub fn open(path: &Path, command: &str) -> Result<Self> {
+        let sources_path = sources_path(path)?;
+        if !path.exists() {
+            return Ok(Self {
+                path: path.to_owned(),
+                results: serde_json::Map::new(),
+                sources: Sources {
+                    command: command.to_owned(),
+                    files: BTreeMap::new(),
+                },
+            });
+        }
+        let results = read_json(path)?;
+        let sources: Sources = if sources_path.exists() {
+            read_json(&sources_path)?
+        } else {
+            // Results from runs without `--merge-into` are all re-fetched
+            Sources {
+                command: command.to_owned(),
+                files: BTreeMap::new(),
+            }
+        };
+        if sources.command != command {
+            bail!(
+                "{} has results of `{}`, not `{command}`",
+                path.display(),
+                sources.command
+            );
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            results,
+            sources,
+        })
+    }
+
+    /// The earlier result for `pkg_loc`, if it was read from the same file
+    pub fn unchanged(
+        &self,
+        pkg_loc: &str,
+        fingerprint: &str,
+    ) -> Option<(String, serde_json::Value)> {
+        let source = self.sources.files.get(pkg_loc)?;
+        if source.fingerprint != fingerprint {
+            return None;
+        }
+        let result = self.results.get(&source.name)?;
+        Some((source.name.clone(), result.clone()))
+    }
+
+    /// Replace the result for `pkg_loc`.
+    /// Without a fingerprint (e.g. for local files), it will be re-fetched next time.
+    pub fn update(
+        &mut self,
+        pkg_loc: String,
+        name: String,
+        fingerprint: Option<String>,
+        result: serde_json::Value,
+    ) {
+        match fingerprint {
+            Some(fingerprint) => {
+                let source = Source {
+                    name: name.clone(),
+                    fingerprint,
+                };
+                self.sources.files.insert(pkg_loc, source);
+            }
+            None => {
+                self.sources.files.remove(&pkg_loc);
+            }
+        }
+        self.results.insert(name, result);
+    }
+
+    /// Write the merged results, then their sources
+    pub fn save(self, out: &Output) -> Result<()> {
+        let out = Output {
+            dest: Dest::File(self.path.clone()),
+            ..out.clone()
+        };
+        out.write_map(&self.sources.command, self.results)?;
+        write_atomic(
+            &sources_path(&self.path)?,
+            &serde_json::to_vec(&self.sources)?,
+        )
+        .context("Failed to save the sources of merged results")
+    }
+}
+
+/// A hidden file next to the output, e.g. `.metadata.json.sources` for `metadata.json`
+fn sources_path(path: &Path) -> Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .context("--merge-into path has no file name")?;
+    let mut name = std::ffi::OsString::from(".");
+    name.push(file_name);
+    name.push(".sources");
+    Ok(path.with_file_name(name))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
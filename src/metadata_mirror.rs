@@ -0,0 +1,111 @@
+//! Copying wheels’ `*.dist-info` directories into a local tree, for `mirror-metadata`.
+//!
+//! The tree has a directory per project with the `*.dist-info` directories of its wheels,
+//! like `mirror/requests/requests-2.32.3.dist-info/METADATA`,
+//! which is enough for tools that resolve or audit offline without the wheels themselves.
+
+use std::path::{Component, Path};
+
+use eyre::{Result, bail};
+use serde::Serialize;
+
+use crate::blob_store::write_atomic;
+use crate::error::Failure;
+use crate::python_pkg::PackageName;
+use crate::wheel::{open_zip, read_entry_bytes};
+use crate::{LazyZipClient, PkgLoc};
+
+/// The files copied from a wheel
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct Mirrored {
+    /// Paths relative to the tree’s root
+    pub files: Vec<String>,
+    pub bytes: u64,
+}
+
+/// Copy the `*.dist-info` directory of a wheel to `dest/{name}/`
+#[tracing::instrument(skip(client), fields(pkg_loc = %pkg_loc))]
+pub async fn mirror(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+    dest: &Path,
+) -> Result<(PackageName, Mirrored)> {
+    let (name, mut reader) = open_zip(client, pkg_loc).await?;
+    let entries = reader
+        .file()
+        .entries()
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, e)| {
+            let path = e.filename().as_str().ok()?;
+            let (dir, file) = path.split_once('/')?;
+            (dir.ends_with(".dist-info") && !file.is_empty() && !path.ends_with('/'))
+                .then(|| (idx, path.to_owned()))
+        })
+        .collect::<Vec<_>>();
+    let mut mirrored = Mirrored::default();
+    for (idx, path) in entries {
+        let relative = Path::new(name.as_normalized()).join(checked_relative(&path)?);
+        let mut content = Vec::new();
+        read_entry_bytes(&mut reader, idx, &mut content).await?;
+        write_atomic(&dest.join(&relative), &content)?;
+        mirrored.bytes += content.len() as u64;
+        mirrored
+            .files
+            .push(relative.to_string_lossy().replace('\\', "/"));
+    }
+    if mirrored.files.is_empty() {
+        bail!(Failure::entry_missing(format!(
+            "{name} has no .dist-info directory"
+        )));
+    }
+    Ok((name, mirrored))
+}
+
+/// An entry’s path, if it can’t escape the directory it’s written to
+fn checked_relative(path: &str) -> Result<&Path> {
+    let relative = Path::new(path);
+    if !relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        bail!("refusing to write entry {path:?} outside the destination");
+    }
+    Ok(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockIndex, MockWheel};
+
+    #[test]
+    fn test_checked_relative() {
+        assert!(checked_relative("foo-1.0.dist-info/licenses/LICENSE").is_ok());
+        assert!(checked_relative("foo-1.0.dist-info/../../etc/passwd").is_err());
+        assert!(checked_relative("/etc/passwd").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mirror() {
+        let index = MockIndex::start([
+            MockWheel::new("foo", "1.0").file("foo-1.0.dist-info/licenses/LICENSE", "MIT")
+        ])
+        .await
+        .unwrap();
+        let client = index.client();
+        let dest = std::env::temp_dir().join(format!("pypi-lazyzip-mirror-{}", std::process::id()));
+        let (_, mirrored) = mirror(&client, "foo".parse().unwrap(), &dest)
+            .await
+            .unwrap();
+        assert!(
+            mirrored
+                .files
+                .contains(&"foo/foo-1.0.dist-info/METADATA".to_owned())
+        );
+        assert!(!mirrored.files.iter().any(|f| f.starts_with("foo/foo/")));
+        let license = dest.join("foo/foo-1.0.dist-info/licenses/LICENSE");
+        assert_eq!(std::fs::read_to_string(license).unwrap(), "MIT");
+        std::fs::remove_dir_all(dest).unwrap();
+    }
+}
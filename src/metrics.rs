@@ -0,0 +1,241 @@
+//! Request and cache metrics in the Prometheus text format.
+//!
+//! [`Metrics`] counts requests, response bytes and latencies separately for indexes
+//! and file hosts, and lookups in the local caches, e.g. for a node exporter’s textfile collector.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+use http::Extensions;
+use reqwest::header::{CONTENT_LENGTH, RANGE};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+
+/// Upper bounds of the latency histogram buckets, in seconds
+const BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1., 2.5, 5., 10.];
+
+/// Extensions of files, as opposed to index pages
+const FILE_EXTENSIONS: &[&str] = &[".whl", ".zip", ".tar.gz", ".egg", ".metadata"];
+
+/// Middleware collecting request metrics, also counting cache lookups
+#[derive(Debug, Clone, Default)]
+pub struct Metrics(Arc<Mutex<Counters>>);
+
+#[derive(Debug, Default)]
+struct Counters {
+    /// Requests by host kind and status, or `error` if there was no response
+    requests: BTreeMap<(HostKind, String), u64>,
+    response_bytes: BTreeMap<HostKind, u64>,
+    latencies: BTreeMap<HostKind, Histogram>,
+    /// Lookups by cache and whether they hit
+    cache_lookups: BTreeMap<(&'static str, bool), u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum HostKind {
+    Index,
+    File,
+}
+
+impl HostKind {
+    /// Files are requested in ranges or have a distribution’s file name
+    fn of(req: &Request) -> Self {
+        let file_name = req
+            .url()
+            .path_segments()
+            .and_then(Iterator::last)
+            .unwrap_or_default();
+        if req.headers().contains_key(RANGE)
+            || FILE_EXTENSIONS.iter().any(|ext| file_name.ends_with(ext))
+        {
+            Self::File
+        } else {
+            Self::Index
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Index => "index",
+            Self::File => "file",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Observations per bucket, not cumulative, with one more for `+Inf`
+    buckets: Vec<u64>,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        self.buckets.resize(BUCKETS.len() + 1, 0);
+        let idx = BUCKETS.partition_point(|&bound| bound < value);
+        self.buckets[idx] += 1;
+        self.sum += value;
+    }
+}
+
+impl Metrics {
+    fn counters(&self) -> std::sync::MutexGuard<'_, Counters> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Count a lookup in a local cache, like `blob_store`
+    pub fn cache_lookup(&self, cache: &'static str, hit: bool) {
+        *self
+            .counters()
+            .cache_lookups
+            .entry((cache, hit))
+            .or_default() += 1;
+    }
+
+    /// The metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let counters = self.counters();
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "# HELP pypi_lazyzip_http_requests_total HTTP requests by kind of host and status\n\
+             # TYPE pypi_lazyzip_http_requests_total counter"
+        );
+        for ((host, status), count) in &counters.requests {
+            let _ = writeln!(
+                out,
+                "pypi_lazyzip_http_requests_total{{host=\"{}\",status=\"{status}\"}} {count}",
+                host.label()
+            );
+        }
+        let _ = writeln!(
+            out,
+            "# HELP pypi_lazyzip_http_response_bytes_total Bytes in response bodies by kind of host\n\
+             # TYPE pypi_lazyzip_http_response_bytes_total counter"
+        );
+        for (host, bytes) in &counters.response_bytes {
+            let _ = writeln!(
+                out,
+                "pypi_lazyzip_http_response_bytes_total{{host=\"{}\"}} {bytes}",
+                host.label()
+            );
+        }
+        let _ = writeln!(
+            out,
+            "# HELP pypi_lazyzip_http_request_duration_seconds Time until response headers by kind of host\n\
+             # TYPE pypi_lazyzip_http_request_duration_seconds histogram"
+        );
+        for (host, histogram) in &counters.latencies {
+            let host = host.label();
+            let mut cumulative = 0;
+            let bounds = BUCKETS
+                .iter()
+                .map(ToString::to_string)
+                .chain(["+Inf".into()]);
+            for (bound, count) in bounds.zip(&histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "pypi_lazyzip_http_request_duration_seconds_bucket{{host=\"{host}\",le=\"{bound}\"}} {cumulative}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "pypi_lazyzip_http_request_duration_seconds_sum{{host=\"{host}\"}} {}\n\
+                 pypi_lazyzip_http_request_duration_seconds_count{{host=\"{host}\"}} {cumulative}",
+                histogram.sum
+            );
+        }
+        let _ = writeln!(
+            out,
+            "# HELP pypi_lazyzip_cache_lookups_total Lookups in local caches by result\n\
+             # TYPE pypi_lazyzip_cache_lookups_total counter"
+        );
+        for ((cache, hit), count) in &counters.cache_lookups {
+            let result = if *hit { "hit" } else { "miss" };
+            let _ = writeln!(
+                out,
+                "pypi_lazyzip_cache_lookups_total{{cache=\"{cache}\",result=\"{result}\"}} {count}"
+            );
+        }
+        out
+    }
+
+    fn record(&self, host: HostKind, status: String, bytes: u64, seconds: f64) {
+        let mut counters = self.counters();
+        *counters.requests.entry((host, status)).or_default() += 1;
+        *counters.response_bytes.entry(host).or_default() += bytes;
+        counters.latencies.entry(host).or_default().observe(seconds);
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for Metrics {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let host = HostKind::of(&req);
+        let start = Instant::now();
+        let result = next.run(req, extensions).await;
+        let seconds = start.elapsed().as_secs_f64();
+        let (status, bytes) = match &result {
+            Ok(response) => (
+                response.status().as_u16().to_string(),
+                response
+                    .headers()
+                    .get(CONTENT_LENGTH)
+                    .and_then(|len| len.to_str().ok()?.parse().ok())
+                    .unwrap_or_default(),
+            ),
+            Err(_) => ("error".to_owned(), 0),
+        };
+        self.record(host, status, bytes, seconds);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{Method, Url};
+
+    use super::*;
+
+    #[test]
+    fn test_host_kind() {
+        let kind = |url| HostKind::of(&Request::new(Method::GET, Url::parse(url).unwrap()));
+        assert_eq!(
+            kind("https://files.example.com/foo-1.0-py3-none-any.whl"),
+            HostKind::File
+        );
+        assert_eq!(
+            kind("https://files.example.com/foo-1.0.tar.gz"),
+            HostKind::File
+        );
+        assert_eq!(kind("https://pypi.org/simple/foo/"), HostKind::Index);
+    }
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        metrics.record(HostKind::Index, "200".to_owned(), 100, 0.02);
+        metrics.record(HostKind::Index, "200".to_owned(), 50, 3.);
+        metrics.cache_lookup("blob_store", true);
+        let rendered = metrics.render();
+        for line in [
+            "pypi_lazyzip_http_requests_total{host=\"index\",status=\"200\"} 2",
+            "pypi_lazyzip_http_response_bytes_total{host=\"index\"} 150",
+            "pypi_lazyzip_http_request_duration_seconds_bucket{host=\"index\",le=\"0.01\"} 0",
+            "pypi_lazyzip_http_request_duration_seconds_bucket{host=\"index\",le=\"0.025\"} 1",
+            "pypi_lazyzip_http_request_duration_seconds_bucket{host=\"index\",le=\"+Inf\"} 2",
+            "pypi_lazyzip_http_request_duration_seconds_count{host=\"index\"} 2",
+            "pypi_lazyzip_cache_lookups_total{cache=\"blob_store\",result=\"hit\"} 1",
+        ] {
+            assert!(rendered.lines().any(|l| l == line), "{line} in {rendered}");
+        }
+    }
+}
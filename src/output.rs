@@ -0,0 +1,324 @@
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{Context as _, Result, bail};
+use futures::AsyncRead;
+use futures::io::AllowStdIo;
+use pypi_lazyzip::blob_store::{tmp_path, write_atomic};
+use pypi_lazyzip::range_trace::AccessMap;
+use pypi_lazyzip::template::Template;
+use pypi_lazyzip::warnings::Warning;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Where to write results, and how to report warnings about them
+#[derive(Debug, Clone)]
+pub struct Output {
+    pub dest: Dest,
+    pub warnings: WarningsMode,
+    /// Fields to keep in JSON results, `None` for all
+    pub fields: Option<Vec<String>>,
+    /// Render results as text with this instead of writing JSON
+    pub template: Option<Template>,
+}
+
+/// How to report [warnings](pypi_lazyzip::warnings) about results
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WarningsMode {
+    /// As JSON lines on stderr
+    #[default]
+    Stderr,
+    /// Per package, as `{"result": ..., "warnings": [...], "version": ..., "specifier": ..., "file": {...}, ...}` where possible
+    Embed,
+}
+
+#[derive(Debug, Clone)]
+pub enum Dest {
+    Stdout,
+    /// Replace the file once the results are complete
+    File(PathBuf),
+    /// Append results to the file as newline-delimited JSON
+    Append(PathBuf),
+}
+
+impl Output {
+    /// Write a JSON document, as a single line if appending
+    pub fn write_json(&self, value: &impl Serialize) -> Result<()> {
+        let value = self.select(None, serde_json::to_value(value)?);
+        if let Some(template) = &self.template {
+            return self.write_text(&template.render(&value));
+        }
+        self.write_json_unselected(&value)
+    }
+
+    /// Write a JSON object mapping package names to `command`’s results.
+    /// When appending, every entry becomes a line with a single-entry object.
+    /// With `--template`, every entry is rendered as a line instead.
+    pub fn write_map<K: Into<String>, V: Serialize>(
+        &self,
+        command: &str,
+        contents: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<()> {
+        let entries = contents.into_iter().map(|(name, value)| {
+            let name = name.into();
+            let value = self.select(Some((command, &name)), serde_json::to_value(value)?);
+            Ok((name, value))
+        });
+        let map = entries.collect::<Result<serde_json::Map<_, _>>>()?;
+        if let Some(template) = &self.template {
+            let lines = map
+                .into_iter()
+                .map(|(name, value)| template.render(&self.template_context(command, name, value)))
+                .collect::<Vec<_>>();
+            return self.write_text(&lines.join("\n"));
+        }
+        match &self.dest {
+            Dest::Append(path) => {
+                let lines = map
+                    .into_iter()
+                    .map(|entry| serde_json::Map::from_iter([entry]));
+                append_lines(path, lines)
+            }
+            _ => self.write_json_unselected(&map),
+        }
+    }
+
+    fn write_json_unselected(&self, value: &impl Serialize) -> Result<()> {
+        match &self.dest {
+            Dest::Stdout => serde_json::to_writer(std::io::stdout(), value)?,
+            Dest::File(path) => write_atomic(path, &serde_json::to_vec(value)?)?,
+            Dest::Append(path) => append_lines(path, [value])?,
+        }
+        Ok(())
+    }
+
+    /// Apply `--fields` to a result, and to the report it’s embedded in with its warnings.
+    /// A package’s result, given with its command and name, also has the field `name`,
+    /// and is named after the command, e.g. `top_level`, if it has no fields to select from.
+    fn select(&self, package: Option<(&str, &str)>, value: Value) -> Value {
+        let Some(fields) = &self.fields else {
+            return value;
+        };
+        let (result, report) = self.split_embedded(value);
+        let result = match (result, package) {
+            (Value::Object(mut result), Some((_, name))) => {
+                result
+                    .entry("name")
+                    .or_insert_with(|| Value::String(name.to_owned()));
+                Value::Object(result)
+            }
+            (result, Some((command, name))) if !has_fields(&result) => {
+                Value::Object(serde_json::Map::from_iter([
+                    (command.replace('-', "_"), result),
+                    ("name".to_owned(), Value::String(name.to_owned())),
+                ]))
+            }
+            (result, _) => result,
+        };
+        let result = select_fields(result, fields);
+        match report {
+            Some(report) => {
+                let mut report = select_fields(Value::Object(report), fields);
+                if let Value::Object(report) = &mut report {
+                    report.insert("result".to_owned(), result);
+                }
+                report
+            }
+            None => result,
+        }
+    }
+
+    /// Split a result from the report it’s embedded in, with its warnings, file and so on
+    fn split_embedded(&self, value: Value) -> (Value, Option<serde_json::Map<String, Value>>) {
+        match value {
+            Value::Object(mut report)
+                if self.warnings == WarningsMode::Embed
+                    && report.contains_key("result")
+                    && report.contains_key("warnings") =>
+            {
+                (report.remove("result").unwrap_or_default(), Some(report))
+            }
+            value => (value, None),
+        }
+    }
+
+    /// The fields a template can use for a package: those of its result if that’s an object,
+    /// or else the result named after the command, e.g. `top_level`.
+    /// `name` defaults to the package name, and the fields of embedding reports,
+    /// like `warnings` and `file`, are available unless the result has fields of the same names.
+    fn template_context(&self, command: &str, name: String, value: Value) -> Value {
+        let (result, report) = self.split_embedded(value);
+        let mut context = match result {
+            Value::Object(fields) => fields,
+            result => serde_json::Map::from_iter([(command.replace('-', "_"), result)]),
+        };
+        if let Some(mut report) = report {
+            if let Some(warnings) = report.remove("warnings") {
+                context.insert("warnings".to_owned(), warnings);
+            }
+            for (key, value) in report {
+                context.entry(key).or_insert(value);
+            }
+        }
+        context.entry("name").or_insert(Value::String(name));
+        Value::Object(context)
+    }
+
+    /// Copy bytes as they’re read, e.g. a file in a package
+    pub async fn write_stream(&self, reader: impl AsyncRead + Unpin) -> Result<()> {
+        match &self.dest {
+            Dest::Stdout => {
+                futures::io::copy(reader, &mut AllowStdIo::new(std::io::stdout())).await?;
+            }
+            Dest::File(path) => {
+                let tmp = tmp_path(path)?;
+                let file = std::fs::File::create(&tmp)
+                    .with_context(|| format!("Failed to write {}", tmp.display()))?;
+                futures::io::copy(reader, &mut AllowStdIo::new(file))
+                    .await
+                    .with_context(|| format!("Failed to write {}", tmp.display()))?;
+                std::fs::rename(&tmp, path)
+                    .with_context(|| format!("Failed to replace {}", path.display()))?;
+            }
+            Dest::Append(_) => bail!("--append only works for commands with JSON output"),
+        }
+        Ok(())
+    }
+
+    /// Write a JSON document on a line of its own, e.g. one of a stream of events
+    pub fn write_json_line(&self, value: &impl Serialize) -> Result<()> {
+        match &self.dest {
+            Dest::Append(path) => append_lines(path, [value]),
+            _ => self.write_text(&serde_json::to_string(value)?),
+        }
+    }
+
+    /// Write text that isn’t JSON, followed by a newline
+    pub fn write_text(&self, text: &str) -> Result<()> {
+        match &self.dest {
+            Dest::Stdout => println!("{text}"),
+            Dest::File(path) => write_atomic(path, format!("{text}\n").as_bytes())?,
+            Dest::Append(_) => bail!("--append only works for commands with JSON output"),
+        }
+        Ok(())
+    }
+}
+
+/// Keep only the given fields of an object, or of all objects in an array
+fn select_fields(value: Value, fields: &[String]) -> Value {
+    match value {
+        Value::Object(map) => map
+            .into_iter()
+            .filter(|(key, _)| fields.contains(key))
+            .collect(),
+        Value::Array(values) => values
+            .into_iter()
+            .map(|value| select_fields(value, fields))
+            .collect(),
+        value => value,
+    }
+}
+
+/// Whether [`select_fields`] can select from a value, i.e. it’s an object or contains some
+fn has_fields(value: &Value) -> bool {
+    match value {
+        Value::Object(_) => true,
+        Value::Array(values) => values.iter().any(Value::is_object),
+        _ => false,
+    }
+}
+
+/// Write warnings as JSON lines on stderr, tagged with the package they’re about if any
+pub fn report_warnings(package: Option<&str>, warnings: &[Warning]) -> Result<()> {
+    let mut stderr = std::io::stderr().lock();
+    for warning in warnings {
+        let mut record = serde_json::to_value(warning)?;
+        if let (Some(package), Some(record)) = (package, record.as_object_mut()) {
+            record.insert("package".to_owned(), package.into());
+        }
+        writeln!(stderr, "{record}")?;
+    }
+    Ok(())
+}
+
+/// Write access maps to stderr as JSON lines
+pub fn report_access_maps(maps: &[AccessMap]) -> Result<()> {
+    let mut stderr = std::io::stderr().lock();
+    for map in maps {
+        let mut record = serde_json::to_value(map)?;
+        if let Some(record) = record.as_object_mut() {
+            record.insert("kind".to_owned(), "access-map".into());
+        }
+        writeln!(stderr, "{record}")?;
+    }
+    Ok(())
+}
+
+/// Append values as JSON lines, each written in one go
+fn append_lines<T: Serialize>(path: &Path, values: impl IntoIterator<Item = T>) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    for value in values {
+        let mut line = serde_json::to_vec(&value)?;
+        line.push(b'\n');
+        file.write_all(&line)
+            .with_context(|| format!("Failed to append to {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_fields() {
+        let value = serde_json::json!({"name": "foo", "version": "1.0", "summary": "Foo"});
+        let fields = ["name".to_owned(), "version".to_owned()];
+        assert_eq!(
+            select_fields(value.clone(), &fields),
+            serde_json::json!({"name": "foo", "version": "1.0"})
+        );
+        assert_eq!(
+            select_fields(serde_json::json!([value, "bar"]), &fields),
+            serde_json::json!([{"name": "foo", "version": "1.0"}, "bar"])
+        );
+    }
+
+    #[test]
+    fn test_select_top_level() {
+        let fields = "name,version,top_level"
+            .split(',')
+            .map(str::to_owned)
+            .collect();
+        let mut out = Output {
+            dest: Dest::Stdout,
+            warnings: WarningsMode::Stderr,
+            fields: Some(fields),
+            template: None,
+        };
+        let result = serde_json::json!(["foo", "_foo"]);
+        assert_eq!(
+            out.select(Some(("top-level", "foo")), result.clone()),
+            serde_json::json!({"name": "foo", "top_level": ["foo", "_foo"]})
+        );
+        out.warnings = WarningsMode::Embed;
+        let report = serde_json::json!({
+            "result": result,
+            "warnings": [],
+            "version": "1.0",
+            "specifier": ">=1",
+            "file": {"filename": "foo-1.0-py3-none-any.whl"},
+        });
+        assert_eq!(
+            out.select(Some(("top-level", "foo")), report),
+            serde_json::json!({
+                "result": {"name": "foo", "top_level": ["foo", "_foo"]},
+                "version": "1.0",
+            })
+        );
+    }
+}
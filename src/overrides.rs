@@ -0,0 +1,213 @@
+//! Local patches of packages’ metadata, to model known-bad upstream metadata with `--override`.
+//!
+//! An overrides file is TOML with a `[[package]]` table per patch:
+//!
+//! ```toml
+//! [[package]]
+//! name = "foo"
+//! version = "<2"  # optional, all releases otherwise
+//! requires-dist = ["bar>=1"]  # replaces all requirements
+//! add-requires-dist = ["baz"]
+//! remove-requires-dist = ["qux"]  # by name
+//! requires-python = ">=3.9"
+//! ```
+
+use std::path::Path;
+use std::str::FromStr as _;
+
+use eyre::{Context as _, Result};
+use pep440_rs::{Version, VersionSpecifiers};
+use serde::Deserialize;
+use serde_with::{DisplayFromStr, serde_as};
+
+use crate::python_pkg::{Metadata, PackageName};
+
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    packages: Vec<Override>,
+}
+
+/// A patch of the metadata of a package’s releases
+#[serde_as]
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Override {
+    #[serde_as(as = "DisplayFromStr")]
+    name: PackageName,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    version: Option<VersionSpecifiers>,
+    #[serde_as(as = "Option<Vec<DisplayFromStr>>")]
+    #[serde(default)]
+    requires_dist: Option<Vec<pep508_rs::Requirement>>,
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    #[serde(default)]
+    add_requires_dist: Vec<pep508_rs::Requirement>,
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    #[serde(default)]
+    remove_requires_dist: Vec<PackageName>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    requires_python: Option<VersionSpecifiers>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OverridesFile {
+    #[serde(default)]
+    package: Vec<Override>,
+}
+
+impl Overrides {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read overrides {}", path.display()))?;
+        Self::from_toml(&content)
+            .with_context(|| format!("Failed to parse overrides {}", path.display()))
+    }
+
+    fn from_toml(content: &str) -> Result<Self> {
+        let file: OverridesFile = toml::from_str(content)?;
+        Ok(Self {
+            packages: file.package,
+        })
+    }
+
+    /// The overrides for a release, in the order given
+    fn matching<'a, 'b>(
+        &'a self,
+        name: &'b PackageName,
+        version: Option<&'b Version>,
+    ) -> impl Iterator<Item = &'a Override> + use<'a, 'b> {
+        self.packages.iter().filter(move |o| {
+            o.name == *name
+                && o.version.as_ref().is_none_or(|specifiers| {
+                    version.is_some_and(|version| specifiers.contains(version))
+                })
+        })
+    }
+
+    /// The overridden `Requires-Python` of a release, if any
+    pub fn requires_python(
+        &self,
+        name: &PackageName,
+        version: &Version,
+    ) -> Option<&VersionSpecifiers> {
+        self.matching(name, Some(version))
+            .filter_map(|o| o.requires_python.as_ref())
+            .last()
+    }
+
+    /// Patch metadata, identifying the release by its `Name` and `Version`
+    pub fn apply(&self, metadata: &mut Metadata) {
+        let Some(name) = metadata
+            .get("Name")
+            .and_then(|n| PackageName::from_str(n).ok())
+        else {
+            return;
+        };
+        let version = metadata
+            .get("Version")
+            .and_then(|v| Version::from_str(v).ok());
+        for o in self.matching(&name, version.as_ref()) {
+            tracing::debug!("Overriding metadata of {name}");
+            if let Some(requires_dist) = &o.requires_dist {
+                metadata.remove("Requires-Dist");
+                for req in requires_dist {
+                    metadata.push("Requires-Dist", req.to_string());
+                }
+            }
+            if !o.remove_requires_dist.is_empty() {
+                let kept = metadata
+                    .get_all("Requires-Dist")
+                    .filter(|req| {
+                        pep508_rs::Requirement::<pep508_rs::VerbatimUrl>::from_str(req)
+                            .ok()
+                            .and_then(|req| PackageName::from_str(req.name.as_ref()).ok())
+                            .is_none_or(|name| !o.remove_requires_dist.contains(&name))
+                    })
+                    .map(ToOwned::to_owned)
+                    .collect::<Vec<_>>();
+                metadata.remove("Requires-Dist");
+                for req in kept {
+                    metadata.push("Requires-Dist", req);
+                }
+            }
+            for req in &o.add_requires_dist {
+                metadata.push("Requires-Dist", req.to_string());
+            }
+            if let Some(requires_python) = &o.requires_python {
+                metadata.remove("Requires-Python");
+                metadata.push("Requires-Python", requires_python.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OVERRIDES: &str = r#"
+        [[package]]
+        name = "Foo"
+        version = "<2"
+        remove-requires-dist = ["bar"]
+        add-requires-dist = ["bar>=2"]
+        requires-python = ">=3.9"
+
+        [[package]]
+        name = "baz"
+        requires-dist = []
+    "#;
+
+    fn metadata(content: &str) -> Metadata {
+        Metadata::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn test_apply() {
+        let overrides = Overrides::from_toml(OVERRIDES).unwrap();
+
+        let mut foo = metadata(
+            "Name: foo\nVersion: 1.0\nRequires-Python: >=3.6\n\
+             Requires-Dist: bar<2\nRequires-Dist: qux\n",
+        );
+        overrides.apply(&mut foo);
+        assert_eq!(
+            foo.get_all("Requires-Dist").collect::<Vec<_>>(),
+            ["qux", "bar>=2"]
+        );
+        assert_eq!(foo.get("Requires-Python"), Some(">=3.9"));
+
+        let mut foo2 = metadata("Name: foo\nVersion: 2.0\nRequires-Dist: bar<2\n");
+        overrides.apply(&mut foo2);
+        assert_eq!(foo2.get("Requires-Dist"), Some("bar<2"));
+
+        let mut baz = metadata("Name: baz\nVersion: 1.0\nRequires-Dist: bar\n");
+        overrides.apply(&mut baz);
+        assert_eq!(baz.get("Requires-Dist"), None);
+    }
+
+    #[test]
+    fn test_requires_python() {
+        let overrides = Overrides::from_toml(OVERRIDES).unwrap();
+        let foo = PackageName::from_str("foo").unwrap();
+        let requires_python = overrides.requires_python(&foo, &Version::new([1]));
+        assert_eq!(
+            requires_python.map(ToString::to_string).as_deref(),
+            Some(">=3.9")
+        );
+        assert!(
+            overrides
+                .requires_python(&foo, &Version::new([2]))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let err = Overrides::from_toml("[[package]]\nname = \"foo\"\nrequires-dist = [\"bar >\"]");
+        assert!(err.is_err());
+        assert!(Overrides::from_toml("[[package]]\nname = \"foo\"\ntypo = 1").is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! Per-package results of batch commands, with the file they were read from and what happened on the way.
+
+use std::path::PathBuf;
+use std::str::FromStr as _;
+use std::time::Duration;
+
+use either::Either;
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+
+use crate::python_pkg::{PackageName, SdistFilename, WheelFilename};
+use crate::warnings::Warning;
+use crate::wheel::RemoteFile;
+
+/// The file a package resolved to
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileInfo {
+    pub filename: String,
+    /// A URL, or a path for local files
+    pub location: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+impl FileInfo {
+    pub(crate) fn from_located(file: Either<RemoteFile, PathBuf>) -> Self {
+        match file {
+            Either::Left(remote) => Self {
+                filename: remote
+                    .url
+                    .path_segments()
+                    .and_then(Iterator::last)
+                    .map(|name| percent_decode_str(name).decode_utf8_lossy().into_owned())
+                    .unwrap_or_default(),
+                location: remote.url.to_string(),
+                sha256: remote.hashes.sha256,
+                size: remote.size,
+            },
+            Either::Right(path) => Self {
+                filename: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                size: std::fs::metadata(&path).ok().map(|m| m.len()),
+                location: path.display().to_string(),
+                sha256: None,
+            },
+        }
+    }
+
+    /// The version in the file name, if it’s a wheel or sdist
+    pub fn version(&self) -> Option<String> {
+        let version = WheelFilename::from_str(&self.filename)
+            .map(|whl| whl.version)
+            .or_else(|_| SdistFilename::from_str(&self.filename).map(|sdist| sdist.version));
+        version.ok().map(|v| v.to_string())
+    }
+}
+
+/// A package’s result, with where and how it was computed
+#[derive(Serialize, Debug, Clone)]
+pub struct PackageReport<V> {
+    pub name: PackageName,
+    /// The resolved version, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The version specifier the package was requested with, like `>=1.0, <2`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub specifier: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<FileInfo>,
+    pub result: V,
+    pub warnings: Vec<Warning>,
+    pub elapsed_ms: u64,
+}
+
+impl<V> PackageReport<V> {
+    pub fn new(name: PackageName, result: V) -> Self {
+        Self {
+            name,
+            version: None,
+            specifier: None,
+            file: None,
+            result,
+            warnings: Vec::new(),
+            elapsed_ms: 0,
+        }
+    }
+
+    /// Add the file the result was computed from, and the version it’s for
+    #[must_use]
+    pub fn with_file(mut self, file: FileInfo) -> Self {
+        self.version = file.version();
+        self.file = Some(file);
+        self
+    }
+
+    /// Add the version specifier the package was requested with, normalized like `>=1.0, <2`
+    #[must_use]
+    pub fn with_specifier(mut self, specifier: Option<&pep440_rs::VersionSpecifiers>) -> Self {
+        self.specifier = specifier.map(ToString::to_string);
+        self
+    }
+
+    #[must_use]
+    pub fn with_warnings(mut self, warnings: Vec<Warning>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    #[must_use]
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+        self
+    }
+
+    /// Convert the result, keeping everything else
+    pub fn map<U>(self, f: impl FnOnce(V) -> U) -> PackageReport<U> {
+        PackageReport {
+            name: self.name,
+            version: self.version,
+            specifier: self.specifier,
+            file: self.file,
+            result: f(self.result),
+            warnings: self.warnings,
+            elapsed_ms: self.elapsed_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let file = FileInfo {
+            filename: "foo-1.0-py3-none-any.whl".to_owned(),
+            location: "https://files.example.com/foo-1.0-py3-none-any.whl".to_owned(),
+            sha256: None,
+            size: Some(3),
+        };
+        let specifier = pep440_rs::VersionSpecifiers::from_str(">= 1.0,<2").unwrap();
+        let report = PackageReport::new("foo".parse().unwrap(), vec!["foo"])
+            .with_specifier(Some(&specifier))
+            .with_file(file)
+            .with_elapsed(Duration::from_millis(1500))
+            .map(|top_level| top_level.len());
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "name": "foo",
+                "version": "1.0",
+                "specifier": ">=1.0, <2",
+                "file": {
+                    "filename": "foo-1.0-py3-none-any.whl",
+                    "location": "https://files.example.com/foo-1.0-py3-none-any.whl",
+                    "size": 3,
+                },
+                "result": 1,
+                "warnings": [],
+                "elapsed_ms": 1500,
+            })
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! Fetching large zip entries with parallel range requests,
+//! e.g. from fast CDNs where a single connection’s throughput is the bottleneck.
+//!
+//! Lazy readers fetch what’s read as it’s read, one request at a time.
+//! Before a large entry is read fully, [`Regions::fetch`] splits its bytes into parts fetched concurrently,
+//! and [`RegionReader`] serves reads from them in order.
+
+use std::io::SeekFrom;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, ready};
+
+use bytes::Bytes;
+use eyre::{Result, ensure};
+use futures::{AsyncRead, AsyncSeek, StreamExt as _, TryStreamExt as _};
+use reqwest::header::RANGE;
+use reqwest::{StatusCode, Url};
+use reqwest_middleware::ClientWithMiddleware;
+
+use crate::LazyZipClient;
+
+/// Parts are at least this large, so smaller entries are fetched with fewer or one request
+const MIN_PART_SIZE: u64 = 1024 * 1024;
+
+/// Regions of a remote file fetched in parallel, shared with the [`RegionReader`] serving them
+pub(crate) struct Regions {
+    /// For permits of the connections beyond the reader’s own
+    client: LazyZipClient,
+    http: ClientWithMiddleware,
+    url: Url,
+    len: u64,
+    parts: usize,
+    /// Fetched parts by start offset
+    fetched: Mutex<Vec<(u64, Bytes)>>,
+}
+
+impl Regions {
+    pub(crate) fn new(
+        client: LazyZipClient,
+        http: ClientWithMiddleware,
+        url: Url,
+        len: u64,
+        parts: usize,
+    ) -> Self {
+        Self {
+            client,
+            http,
+            url,
+            len,
+            parts,
+            fetched: Mutex::default(),
+        }
+    }
+
+    /// Fetch `range` with up to `parts` concurrent requests, unless it’s too small to split.
+    /// One request is the reader’s own connection, the others need free connections to the host.
+    pub(crate) async fn fetch(&self, range: Range<u64>) -> Result<()> {
+        let parts = split(range.start..range.end.min(self.len), self.parts);
+        if parts.len() < 2 {
+            return Ok(());
+        }
+        // Don’t wait for permits: the reader holds one already, so that could deadlock
+        let permits = self.client.try_host_permits(&self.url, parts.len() - 1);
+        let concurrent = permits
+            .as_ref()
+            .map_or(parts.len(), |permits| permits.len() + 1);
+        tracing::debug!(
+            ?range,
+            parts = parts.len(),
+            concurrent,
+            "fetching region in parallel"
+        );
+        let fetched = futures::stream::iter(parts)
+            .map(|part| self.fetch_part(part))
+            .buffered(concurrent)
+            .try_collect::<Vec<_>>()
+            .await?;
+        drop(permits);
+        self.fetched
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend(fetched);
+        Ok(())
+    }
+
+    async fn fetch_part(&self, part: Range<u64>) -> Result<(u64, Bytes)> {
+        let response = self
+            .http
+            .get(self.url.clone())
+            .header(RANGE, format!("bytes={}-{}", part.start, part.end - 1))
+            .send()
+            .await?
+            .error_for_status()?;
+        ensure!(
+            response.status() == StatusCode::PARTIAL_CONTENT,
+            "{} ignored a range request with {}",
+            self.url,
+            response.status()
+        );
+        let body = response.bytes().await?;
+        ensure!(
+            body.len() as u64 == part.end - part.start,
+            "{} sent {} bytes for range {part:?}",
+            self.url,
+            body.len()
+        );
+        Ok((part.start, body))
+    }
+
+    /// Up to `max` fetched bytes starting at `pos`
+    fn read_at(&self, pos: u64, max: usize) -> Option<Bytes> {
+        let fetched = self.fetched.lock().unwrap_or_else(PoisonError::into_inner);
+        let (start, bytes) = fetched
+            .iter()
+            .find(|(start, bytes)| (*start..*start + bytes.len() as u64).contains(&pos))?;
+        #[allow(clippy::cast_possible_truncation)]
+        let offset = (pos - start) as usize;
+        Some(bytes.slice(offset..bytes.len().min(offset + max)))
+    }
+}
+
+/// Split `range` into up to `parts` contiguous parts of at least [`MIN_PART_SIZE`]
+fn split(range: Range<u64>, parts: usize) -> Vec<Range<u64>> {
+    let len = range.end.saturating_sub(range.start);
+    let parts = (len / MIN_PART_SIZE).clamp(1, parts.max(1) as u64);
+    let size = len.div_ceil(parts);
+    (0..parts)
+        .map(|i| range.start + i * size..(range.start + (i + 1) * size).min(range.end))
+        .collect()
+}
+
+/// A lazy reader serving reads from fetched regions where it can, and from `inner` elsewhere
+pub(crate) struct RegionReader<R> {
+    pub(crate) inner: R,
+    pub(crate) regions: Arc<Regions>,
+    pub(crate) pos: u64,
+    /// If `inner` is at `pos`, i.e. no reads were served from regions since it was last sought
+    pub(crate) synced: bool,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncRead for RegionReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        if let Some(bytes) = self.regions.read_at(self.pos, buf.len()) {
+            buf[..bytes.len()].copy_from_slice(&bytes);
+            self.pos += bytes.len() as u64;
+            self.synced = false;
+            return Poll::Ready(Ok(bytes.len()));
+        }
+        if !self.synced {
+            let pos = self.pos;
+            ready!(Pin::new(&mut self.inner).poll_seek(cx, SeekFrom::Start(pos)))?;
+            self.synced = true;
+        }
+        let read = ready!(Pin::new(&mut self.inner).poll_read(cx, buf))?;
+        self.pos += read as u64;
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl<R: AsyncSeek + Unpin> AsyncSeek for RegionReader<R> {
+    fn poll_seek(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<std::io::Result<u64>> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+            SeekFrom::End(offset) => self.regions.len.checked_add_signed(offset),
+        };
+        let Some(pos) = pos else {
+            return Poll::Ready(Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "seek before the start of the file",
+            )));
+        };
+        // `inner` is sought lazily, before reading from it
+        self.pos = pos;
+        self.synced = false;
+        Poll::Ready(Ok(pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use http::Extensions;
+    use reqwest::{Request, Response};
+    use reqwest_middleware::{Middleware, Next};
+
+    use super::*;
+    use crate::PkgLoc;
+    use crate::bench::RequestStats;
+    use crate::test_util::{MockIndex, MockWheel};
+    use crate::wheel::{find_entry, open_zip, read_entry_bytes};
+
+    #[test]
+    fn test_split() {
+        const MIB: u64 = MIN_PART_SIZE;
+        assert_eq!(split(0..MIB / 2, 4), vec![0..MIB / 2]);
+        assert_eq!(
+            split(10..10 + 2 * MIB, 4),
+            [10..10 + MIB, 10 + MIB..10 + 2 * MIB]
+        );
+        let parts = split(5..100 * MIB + 3, 4);
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[0].start, 5);
+        assert_eq!(parts[3].end, 100 * MIB + 3);
+        assert!(parts.windows(2).all(|w| w[0].end == w[1].start));
+        assert_eq!(split(0..100 * MIB, 1), vec![0..100 * MIB]);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_entry() {
+        let big = (0..4 * MIN_PART_SIZE)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let whl = MockWheel::new("foo", "1.0")
+            .file("foo/big.bin", big.clone())
+            .file("foo/tail.bin", vec![0; 300 * 1024]);
+        let index = MockIndex::start([whl]).await.unwrap();
+        let stats = RequestStats::default();
+        let client = index
+            .client()
+            .with_parallel_ranges(4)
+            .with_middleware(stats.clone());
+        let (_, mut zip) = open_zip(&client, PkgLoc::Dependency("foo".parse().unwrap()))
+            .await
+            .unwrap();
+        let idx = find_entry(&mut zip, |e| {
+            e.filename()
+                .as_str()
+                .is_ok_and(|name| name == "foo/big.bin")
+        })
+        .unwrap();
+        // Wait for the speculative request, which is for part of this
+        let tail = find_entry(&mut zip, |e| {
+            e.filename()
+                .as_str()
+                .is_ok_and(|name| name == "foo/tail.bin")
+        })
+        .unwrap();
+        read_entry_bytes(&mut zip, tail, &mut Vec::new())
+            .await
+            .unwrap();
+        let before = stats.requests();
+        zip.prefetch_entry(idx).await.unwrap();
+        assert_eq!(stats.requests() - before, 4);
+        let mut content = Vec::new();
+        read_entry_bytes(&mut zip, idx, &mut content).await.unwrap();
+        assert!(content == big, "entry content differs");
+        assert_eq!(stats.requests() - before, 4);
+    }
+
+    /// Middleware recording the most requests in flight at once
+    #[derive(Clone, Default)]
+    struct InFlight(Arc<(AtomicUsize, AtomicUsize)>);
+
+    #[async_trait::async_trait]
+    impl Middleware for InFlight {
+        async fn handle(
+            &self,
+            req: Request,
+            extensions: &mut Extensions,
+            next: Next<'_>,
+        ) -> reqwest_middleware::Result<Response> {
+            let (now, most) = &*self.0;
+            most.fetch_max(now.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+            // Let concurrent requests overlap
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            let response = next.run(req, extensions).await;
+            now.fetch_sub(1, Ordering::SeqCst);
+            response
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_host_limit() {
+        let big = vec![7; 4 << 20];
+        let whl = MockWheel::new("foo", "1.0")
+            .file("foo/big.bin", big.clone())
+            .file("foo/tail.bin", vec![0; 300 * 1024]);
+        let index = MockIndex::start([whl]).await.unwrap();
+        for (limit, expected) in [(1, 1), (2, 2), (8, 4)] {
+            let in_flight = InFlight::default();
+            let client = index
+                .client()
+                .with_parallel_ranges(4)
+                .with_max_connections_per_host(limit)
+                .with_middleware(in_flight.clone());
+            let (_, mut zip) = open_zip(&client, PkgLoc::Dependency("foo".parse().unwrap()))
+                .await
+                .unwrap();
+            let idx = find_entry(&mut zip, |e| {
+                e.filename()
+                    .as_str()
+                    .is_ok_and(|name| name == "foo/big.bin")
+            })
+            .unwrap();
+            // Wait for the speculative request
+            let tail = find_entry(&mut zip, |e| {
+                e.filename()
+                    .as_str()
+                    .is_ok_and(|name| name == "foo/tail.bin")
+            })
+            .unwrap();
+            read_entry_bytes(&mut zip, tail, &mut Vec::new())
+                .await
+                .unwrap();
+            in_flight.0.1.store(0, Ordering::SeqCst);
+            // The reader holds the only permit with a limit of 1, so this must not wait for another
+            tokio::time::timeout(std::time::Duration::from_secs(10), zip.prefetch_entry(idx))
+                .await
+                .expect("prefetch waited for a permit")
+                .unwrap();
+            assert_eq!(
+                in_flight.0.1.load(Ordering::SeqCst),
+                expected,
+                "limit {limit}"
+            );
+            let mut content = Vec::new();
+            read_entry_bytes(&mut zip, idx, &mut content).await.unwrap();
+            assert!(content == big, "entry content differs");
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use eyre::Error;
+use reqwest::Url;
+
+use crate::python_pkg::Dependency;
+use crate::warnings::{self, Warning};
+
+/// Where to find a package: on the index, as a direct URL, or as a local file.
+#[derive(Debug, Clone)]
+pub enum PkgLoc {
+    Dependency(Dependency),
+    /// A file URL, optionally with a `#<hashname>=<hashvalue>` fragment
+    Url(Url),
+    Path(PathBuf),
+}
+
+impl PkgLoc {
+    /// Restrict the allowed versions of a dependency. Files are left as they are.
+    #[must_use]
+    pub fn constrain(self, version_spec: &pep440_rs::VersionSpecifiers) -> Self {
+        match self {
+            PkgLoc::Dependency(dep) => PkgLoc::Dependency(dep.constrain(version_spec)),
+            file => {
+                warnings::warn(Warning::ConstraintIgnored {
+                    location: file.to_string(),
+                });
+                file
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for PkgLoc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PkgLoc::Dependency(dep) => {
+                dep.name().fmt(f)?;
+                dep.version_spec().map(|vs| vs.fmt(f)).transpose()?;
+                Ok(())
+            }
+            PkgLoc::Url(url) => url.fmt(f),
+            PkgLoc::Path(path) => path.display().fmt(f),
+        }
+    }
+}
+
+impl FromStr for PkgLoc {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(url) = Url::parse(s)
+            && matches!(url.scheme(), "http" | "https")
+        {
+            Ok(PkgLoc::Url(url))
+        } else {
+            match Dependency::from_str(s) {
+                Ok(dep) => Ok(PkgLoc::Dependency(dep)),
+                // Like `foo>=1.0.*`, which is unlikely to be a file name
+                Err(e) if s.contains(['<', '>', '=', '!', '~', '^']) && !Path::new(s).exists() => {
+                    Err(e)
+                }
+                Err(_) => Ok(PkgLoc::Path(PathBuf::from(s))),
+            }
+        }
+    }
+}
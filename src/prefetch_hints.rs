@@ -0,0 +1,136 @@
+//! How much of the end of a package’s wheels to fetch in the first request, learned from earlier runs.
+//!
+//! Lazily reading a wheel starts with a request for its end, and takes more requests if the
+//! central directory and `*.dist-info` files before it aren’t covered.
+//! Wheels of a package built by the same tool are laid out alike across versions,
+//! so recording where `*.dist-info` starts lets the first request for later versions cover all of it at once.
+//! Hints are capped, as prefetching more than that only pays off for few wheels.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use eyre::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::cache_integrity::{read_checked, write_checked};
+use crate::python_pkg::PackageName;
+use crate::result_cache::user_cache_dir;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PrefetchHints {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(skip)]
+    dirty: bool,
+    /// Where `*.dist-info` starts, by normalized package name
+    tails: BTreeMap<String, Hint>,
+}
+
+/// Bytes from the end of a wheel covering its `*.dist-info` and central directory,
+/// and the tool that built it, from `WHEEL`’s `Generator`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Hint {
+    backend: Option<String>,
+    tail: u64,
+}
+
+/// Hints are at most this large
+pub const MAX_TAIL_HINT: u64 = 4 * 1024 * 1024;
+
+impl PrefetchHints {
+    /// Load hints from `path`, starting empty if it doesn’t exist yet or is unreadable
+    /// Corrupt hints are removed and counted in the directory containing them, like other cache entries.
+    pub fn load(path: &Path) -> Self {
+        let mut hints = read_checked(path.parent().unwrap_or(Path::new(".")), path)
+            .and_then(|content| {
+                serde_json::from_slice::<Self>(&content)
+                    .inspect_err(|e| tracing::debug!("Ignoring invalid prefetch hints: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default();
+        path.clone_into(&mut hints.path);
+        hints
+    }
+
+    /// `prefetch-hints.json` in the user’s cache directory, if there is one
+    pub fn in_user_cache() -> Option<Self> {
+        Some(Self::load(&user_cache_dir()?.join("prefetch-hints.json")))
+    }
+
+    /// Bytes to fetch from the end of a wheel of `name`, with some room for growth since
+    pub fn tail_size(&self, name: &PackageName) -> Option<u64> {
+        let tail = self.tails.get(&name.to_string())?.tail;
+        Some((tail + tail / 8).min(MAX_TAIL_HINT))
+    }
+
+    /// Record how many bytes from the end of a wheel of `name` built by `backend` its `*.dist-info` starts.
+    /// Wheels by the same backend keep the largest hint, so it covers all of them,
+    /// while another backend lays wheels out anew, so its hint replaces the earlier one.
+    /// Wheels whose `*.dist-info` is further from the end get no hint.
+    pub fn record(&mut self, name: &PackageName, backend: Option<&str>, tail: u64) {
+        if tail > MAX_TAIL_HINT {
+            return;
+        }
+        let previous = self.tails.get(&name.to_string());
+        let tail = match previous {
+            Some(hint) if hint.backend.as_deref() == backend => hint.tail.max(tail),
+            _ => tail,
+        };
+        let hint = Hint {
+            backend: backend.map(str::to_owned),
+            tail,
+        };
+        self.dirty |= previous != Some(&hint);
+        self.tails.insert(name.to_string(), hint);
+    }
+
+    /// Write the hints back if they changed, replacing the file only once it’s fully written
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        write_checked(&self.path, &serde_json::to_vec(self)?)
+            .context("Failed to save prefetch hints")?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr as _;
+
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = std::env::temp_dir().join(format!(
+            "pypi-lazyzip-prefetch-hints-{}",
+            std::process::id()
+        ));
+        let path = dir.join("prefetch-hints.json");
+        let mut hints = PrefetchHints::load(&path);
+        let name = PackageName::from_str("Foo_Bar").unwrap();
+        assert_eq!(hints.tail_size(&name), None);
+        hints.record(&name, Some("hatchling"), 80_000);
+        hints.record(&name, Some("hatchling"), 40_000);
+        hints.save().unwrap();
+
+        let mut hints = PrefetchHints::load(&path);
+        let name = PackageName::from_str("foo-bar").unwrap();
+        assert_eq!(hints.tail_size(&name), Some(90_000));
+        // Another backend’s layout replaces the hint, and far away `*.dist-info` isn’t hinted
+        hints.record(&name, Some("maturin"), 8_000);
+        assert_eq!(hints.tail_size(&name), Some(9_000));
+        hints.record(&name, Some("maturin"), 500 * 1024 * 1024);
+        assert_eq!(hints.tail_size(&name), Some(9_000));
+
+        // Corrupt hints are dropped
+        let mut content = std::fs::read(&path).unwrap();
+        *content.last_mut().unwrap() ^= 1;
+        std::fs::write(&path, content).unwrap();
+        assert_eq!(PrefetchHints::load(&path).tail_size(&name), None);
+        assert!(!path.exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+//! Classifying a project’s URLs from its metadata, for `urls`.
+//!
+//! `Project-URL` labels are matched against the
+//! [well-known labels](https://packaging.python.org/en/latest/specifications/well-known-project-urls/)
+//! and their common aliases. If no label names the repository, a homepage or other URL on a
+//! code hosting site is used instead.
+
+use std::collections::BTreeMap;
+
+use reqwest::Url;
+use serde::Serialize;
+
+use crate::python_pkg::Metadata;
+
+/// Hosts whose URLs point at a project’s repository
+const CODE_HOSTS: &[&str] = &[
+    "github.com",
+    "gitlab.com",
+    "bitbucket.org",
+    "codeberg.org",
+    "sr.ht",
+];
+
+/// A project’s URLs by purpose
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct ProjectUrls {
+    pub homepage: Option<String>,
+    pub repository: Option<String>,
+    pub documentation: Option<String>,
+    pub changelog: Option<String>,
+    pub issues: Option<String>,
+    pub funding: Option<String>,
+    pub download: Option<String>,
+    /// URLs with other labels, by label as given
+    pub other: BTreeMap<String, String>,
+}
+
+impl ProjectUrls {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let mut urls = Self::default();
+        for entry in metadata.get_all("Project-URL") {
+            let Some((label, url)) = entry.split_once(',') else {
+                continue;
+            };
+            let url = url.trim().to_owned();
+            let slot = match normalize_label(label).as_str() {
+                "homepage" | "home" => &mut urls.homepage,
+                "source" | "repository" | "sourcecode" | "code" | "github" | "gitlab" => {
+                    &mut urls.repository
+                }
+                "documentation" | "docs" => &mut urls.documentation,
+                "changelog" | "changes" | "releasenotes" | "history" | "whatsnew" => {
+                    &mut urls.changelog
+                }
+                "issues" | "bugs" | "issue" | "tracker" | "issuetracker" | "bugtracker" => {
+                    &mut urls.issues
+                }
+                "funding" | "sponsor" | "donate" | "donation" => &mut urls.funding,
+                "download" => &mut urls.download,
+                _ => {
+                    urls.other.insert(label.trim().to_owned(), url);
+                    continue;
+                }
+            };
+            // The first URL for a purpose wins, others are kept
+            if slot.is_none() {
+                *slot = Some(url);
+            } else {
+                urls.other.insert(label.trim().to_owned(), url);
+            }
+        }
+        // Fields deprecated in favor of `Project-URL`
+        if urls.homepage.is_none() {
+            urls.homepage = metadata.get("Home-page").map(ToOwned::to_owned);
+        }
+        if urls.download.is_none() {
+            urls.download = metadata.get("Download-URL").map(ToOwned::to_owned);
+        }
+        if urls.repository.is_none() {
+            urls.repository = urls
+                .homepage
+                .iter()
+                .chain(urls.other.values())
+                .find(|url| is_on_code_host(url))
+                .cloned();
+        }
+        urls
+    }
+}
+
+/// Normalize a `Project-URL` label as in
+/// [the spec](https://packaging.python.org/en/latest/specifications/well-known-project-urls/#label-normalization)
+pub(crate) fn normalize_label(label: &str) -> String {
+    label
+        .chars()
+        .filter(|c| !c.is_ascii_punctuation() && !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+fn is_on_code_host(url: &str) -> bool {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(ToOwned::to_owned))
+        .is_some_and(|host| {
+            CODE_HOSTS
+                .iter()
+                .any(|code_host| host == *code_host || host.ends_with(&format!(".{code_host}")))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_metadata() {
+        let mut metadata = Metadata::default();
+        metadata.push("Home-page", "https://foo.example.com");
+        metadata.push("Project-URL", "Documentation, https://foo.readthedocs.io");
+        metadata.push("Project-URL", "Source Code, https://github.com/example/foo");
+        metadata.push("Project-URL", "Release Notes, https://foo.example.com/news");
+        metadata.push(
+            "Project-URL",
+            "Bug-Tracker, https://github.com/example/foo/issues",
+        );
+        metadata.push("Project-URL", "Chat, https://discord.gg/foo");
+        let urls = ProjectUrls::from_metadata(&metadata);
+        assert_eq!(
+            urls,
+            ProjectUrls {
+                homepage: Some("https://foo.example.com".to_owned()),
+                repository: Some("https://github.com/example/foo".to_owned()),
+                documentation: Some("https://foo.readthedocs.io".to_owned()),
+                changelog: Some("https://foo.example.com/news".to_owned()),
+                issues: Some("https://github.com/example/foo/issues".to_owned()),
+                other: BTreeMap::from([("Chat".to_owned(), "https://discord.gg/foo".to_owned())]),
+                ..ProjectUrls::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_repository_fallback() {
+        let mut metadata = Metadata::default();
+        metadata.push("Home-page", "https://github.com/example/foo");
+        let urls = ProjectUrls::from_metadata(&metadata);
+        assert_eq!(urls.repository, urls.homepage);
+    }
+}
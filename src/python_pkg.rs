@@ -1,7 +1,17 @@
 mod dependency;
+mod legacy_filename;
+mod metadata;
 mod package_name;
+mod sdist_filename;
+mod specifiers;
 mod wheel_filename;
+mod wheel_tags;
 
 pub use self::dependency::Dependency;
-pub use self::package_name::PackageName;
+pub use self::legacy_filename::{LegacyFilename, LegacyFormat};
+pub use self::metadata::Metadata;
+pub use self::package_name::{PackageName, normalize};
+pub use self::sdist_filename::{SdistFilename, SdistFormat};
+pub use self::specifiers::{parse_requirement, parse_specifiers};
 pub use self::wheel_filename::WheelFilename;
+pub use self::wheel_tags::{Target, WheelTags};
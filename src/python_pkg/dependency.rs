@@ -1,20 +1,35 @@
 use std::{fmt::Display, str::FromStr};
 
-use color_eyre::eyre::{Context as _, Error, bail};
+use eyre::{Error, bail};
 
 use super::package_name::{ID_START_RE, PackageName};
+use super::specifiers::parse_specifiers_in;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Dependency {
     name: PackageName,
-    version_spec: Option<pep440_rs::VersionSpecifier>,
+    version_spec: Option<pep440_rs::VersionSpecifiers>,
 }
 
 impl Dependency {
-    pub fn new(name: PackageName, version_spec: Option<pep440_rs::VersionSpecifier>) -> Self {
+    pub fn new(name: PackageName, version_spec: Option<pep440_rs::VersionSpecifiers>) -> Self {
         Self { name, version_spec }
     }
 
+    /// Convert a requirement, ignoring extras and markers. URL requirements have no version spec.
+    pub fn from_requirement(req: &pep508_rs::Requirement) -> Result<Self, Error> {
+        let version_spec = match &req.version_or_url {
+            Some(pep508_rs::VersionOrUrl::VersionSpecifier(spec)) if !spec.is_empty() => {
+                Some(spec.clone())
+            }
+            _ => None,
+        };
+        Ok(Self::new(
+            PackageName::from_str(req.name.as_ref())?,
+            version_spec,
+        ))
+    }
+
     #[allow(dead_code)]
     pub fn has_version_spec(&self) -> bool {
         self.version_spec.is_some()
@@ -28,17 +43,30 @@ impl Dependency {
         self.name
     }
 
-    pub fn version_spec(&self) -> Option<&pep440_rs::VersionSpecifier> {
+    pub fn version_spec(&self) -> Option<&pep440_rs::VersionSpecifiers> {
         self.version_spec.as_ref()
     }
 
+    /// Further restrict the allowed versions.
+    #[must_use]
+    pub fn constrain(mut self, version_spec: &pep440_rs::VersionSpecifiers) -> Self {
+        let merged = self
+            .version_spec
+            .into_iter()
+            .flat_map(IntoIterator::into_iter)
+            .chain(version_spec.iter().cloned())
+            .collect();
+        self.version_spec = Some(merged);
+        self
+    }
+
     #[allow(dead_code)]
-    pub fn into_version_spec(self) -> Option<pep440_rs::VersionSpecifier> {
+    pub fn into_version_spec(self) -> Option<pep440_rs::VersionSpecifiers> {
         self.version_spec
     }
 
     #[allow(dead_code)]
-    pub fn into_inner(self) -> (PackageName, Option<pep440_rs::VersionSpecifier>) {
+    pub fn into_inner(self) -> (PackageName, Option<pep440_rs::VersionSpecifiers>) {
         (self.name, self.version_spec)
     }
 }
@@ -49,11 +77,9 @@ impl FromStr for Dependency {
         let Some(name) = ID_START_RE.find(s) else {
             bail!("invalid identifier");
         };
-        let rest = &s[name.len()..];
-        let version_spec = (!rest.is_empty())
-            .then(|| pep440_rs::VersionSpecifier::from_str(rest))
-            .transpose()
-            .with_context(|| format!("could not parse version from {rest}"))?;
+        let version_spec = (name.len() < s.len())
+            .then(|| parse_specifiers_in(s, name.len()))
+            .transpose()?;
         Ok(Self::new(
             PackageName::from_str(name.as_str())?,
             version_spec,
@@ -80,5 +106,15 @@ mod tests {
         assert!(Dependency::from_str("foo ==1.0.1").is_ok_and(|v| v.has_version_spec()));
         assert!(Dependency::from_str("foo!!1.0").is_err());
         assert!(Dependency::from_str("-_==1.0").is_err());
+        assert!(Dependency::from_str("foo>=1,<2").is_ok_and(|v| v.has_version_spec()));
+    }
+
+    #[test]
+    fn test_constrain() {
+        let spec = pep440_rs::VersionSpecifiers::from_str("<2").unwrap();
+        let dep = Dependency::from_str("foo").unwrap().constrain(&spec);
+        assert_eq!(dep.to_string(), "foo<2");
+        let dep = Dependency::from_str("foo>=1").unwrap().constrain(&spec);
+        assert_eq!(dep.to_string(), "foo>=1, <2");
     }
 }
@@ -0,0 +1,69 @@
+use std::str::FromStr;
+
+use eyre::{Error, Result, bail};
+
+pub use super::package_name::PackageName;
+
+/// Legacy distribution formats, which are zips like wheels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LegacyFormat {
+    /// A setuptools egg, with metadata in `EGG-INFO/`
+    Egg,
+    /// A zip source distribution, with metadata in `<name>-<version>/PKG-INFO`
+    Zip,
+}
+
+/// An `.egg` or `.zip` filename, partially parsed.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LegacyFilename {
+    pub name: PackageName,
+    pub version: pep440_rs::Version,
+    pub format: LegacyFormat,
+}
+
+impl FromStr for LegacyFilename {
+    type Err = Error;
+
+    fn from_str(filename: &str) -> Result<Self, Self::Err> {
+        let (name, version, format) = if let Some(stem) = filename.strip_suffix(".egg") {
+            // `<name>-<version>[-py<X.Y>[-<platform>]]`, with `-` in names and versions escaped as `_`
+            let mut parts = stem.splitn(3, '-');
+            let (Some(name), Some(version)) = (parts.next(), parts.next()) else {
+                bail!("invalid egg filename: {stem}");
+            };
+            (name, version, LegacyFormat::Egg)
+        } else if let Some(stem) = filename.strip_suffix(".zip") {
+            let Some((name, version)) = stem.rsplit_once('-') else {
+                bail!("invalid zip filename: {stem}");
+            };
+            (name, version, LegacyFormat::Zip)
+        } else {
+            bail!("not an .egg or .zip file");
+        };
+        Ok(LegacyFilename {
+            name: PackageName::from_str(name)?,
+            version: pep440_rs::Version::from_str(version)?,
+            format,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let egg = LegacyFilename::from_str("Foo_Bar-1.2-py2.7-linux-x86_64.egg").unwrap();
+        assert_eq!(egg.name.as_normalized(), "foo-bar");
+        assert_eq!(egg.version.to_string(), "1.2");
+        assert_eq!(egg.format, LegacyFormat::Egg);
+
+        let zip = LegacyFilename::from_str("foo-bar-0.1.zip").unwrap();
+        assert_eq!(zip.name.as_normalized(), "foo-bar");
+        assert_eq!(zip.version.to_string(), "0.1");
+        assert_eq!(zip.format, LegacyFormat::Zip);
+
+        assert!(LegacyFilename::from_str("foo-1.0-py3-none-any.whl").is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use std::str::FromStr;
+
+use eyre::{Context as _, Error, Result};
+use serde::Serialize;
+use serde::ser::SerializeMap as _;
+
+/// Fields that may appear multiple times.
+const MULTIPLE_USE: &[&str] = &[
+    "Dynamic",
+    "Platform",
+    "Supported-Platform",
+    "Classifier",
+    "Requires-Dist",
+    "Requires-External",
+    "Project-URL",
+    "Provides-Extra",
+    "Provides-Dist",
+    "Obsoletes-Dist",
+    "License-File",
+    "Import-Name",
+    "Import-Namespace",
+    "Requires",
+    "Provides",
+    "Obsoletes",
+];
+
+/// Core metadata of a distribution, as found in a wheel’s `METADATA` file.
+/// See [spec](https://packaging.python.org/en/latest/specifications/core-metadata/).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+}
+
+impl Metadata {
+    /// The first value of a header field, matched case-insensitively.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// All values of a (multiple-use) header field, matched case-insensitively.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Add a header field, after existing ones
+    pub fn push(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.headers.push((key.into(), value.into()));
+    }
+
+    /// Remove all values of a header field, matched case-insensitively
+    pub fn remove(&mut self, key: &str) {
+        self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+    }
+
+    /// The message body (usually the long description).
+    pub fn body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+
+    pub fn requires_dist(&self) -> Result<Vec<pep508_rs::Requirement>> {
+        self.get_all("Requires-Dist")
+            .map(|req| {
+                pep508_rs::Requirement::from_str(req)
+                    .with_context(|| format!("invalid Requires-Dist: {req}"))
+            })
+            .collect()
+    }
+
+    pub fn requires_python(&self) -> Result<Option<pep440_rs::VersionSpecifiers>> {
+        self.get("Requires-Python")
+            .map(pep440_rs::VersionSpecifiers::from_str)
+            .transpose()
+            .context("invalid Requires-Python")
+    }
+}
+
+/// Serialize as [JSON-compatible metadata](https://peps.python.org/pep-0566/#json-compatible-metadata)
+impl Serialize for Metadata {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        let mut seen = Vec::<&str>::new();
+        for (key, value) in &self.headers {
+            if seen.iter().any(|k| k.eq_ignore_ascii_case(key)) {
+                continue;
+            }
+            seen.push(key);
+            let json_key = key.to_ascii_lowercase().replace('-', "_");
+            if MULTIPLE_USE.iter().any(|k| k.eq_ignore_ascii_case(key)) {
+                map.serialize_entry(&json_key, &self.get_all(key).collect::<Vec<_>>())?;
+            } else if json_key == "keywords" {
+                let keywords = value
+                    .split([',', ' '])
+                    .filter(|k| !k.is_empty())
+                    .collect::<Vec<_>>();
+                map.serialize_entry(&json_key, &keywords)?;
+            } else {
+                map.serialize_entry(&json_key, value)?;
+            }
+        }
+        if let Some(body) = &self.body {
+            map.serialize_entry("description", body)?;
+        }
+        map.end()
+    }
+}
+
+impl FromStr for Metadata {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut headers = Vec::<(String, String)>::new();
+        let mut lines = s.split_inclusive('\n');
+        for line in lines.by_ref() {
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                break;
+            }
+            // Continuation lines start with whitespace and belong to the previous header
+            if line.starts_with([' ', '\t']) {
+                if let Some((_, value)) = headers.last_mut() {
+                    value.push('\n');
+                    value.push_str(line.trim_start());
+                }
+                continue;
+            }
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            headers.push((key.trim().to_owned(), value.trim().to_owned()));
+        }
+        let body = lines.collect::<String>();
+        Ok(Metadata {
+            headers,
+            body: (!body.is_empty()).then_some(body),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let md = Metadata::from_str(
+            "Metadata-Version: 2.1\nName: foo\nRequires-Dist: bar\nrequires-dist: baz>=1\n\
+             Summary: a\n  multiline\n\nLong description\n",
+        )
+        .unwrap();
+        assert_eq!(md.get("name"), Some("foo"));
+        assert_eq!(
+            md.get_all("Requires-Dist").collect::<Vec<_>>(),
+            ["bar", "baz>=1"]
+        );
+        assert_eq!(md.get("Summary"), Some("a\nmultiline"));
+        assert_eq!(md.body(), Some("Long description\n"));
+        assert!(md.requires_python().is_ok_and(|rp| rp.is_none()));
+    }
+
+    #[test]
+    fn test_serialize() {
+        let md = Metadata::from_str("Name: foo\nKeywords: a,b c\nClassifier: X\n\nDesc").unwrap();
+        assert_eq!(
+            serde_json::to_string(&md).unwrap(),
+            r#"{"name":"foo","keywords":["a","b","c"],"classifier":["X"],"description":"Desc"}"#
+        );
+    }
+}
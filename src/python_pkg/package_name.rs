@@ -1,19 +1,80 @@
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
 use std::{fmt::Display, str::FromStr, sync::LazyLock};
 
 use caseless::Caseless;
-use color_eyre::eyre::{Error, bail};
+use eyre::{Error, bail};
 use serde::Serialize;
 
 /// A Python package name, normalized for comparison.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct PackageName(String);
+///
+/// The spelling it was created from is kept for [`original`](Self::original),
+/// e.g. for registries with case-sensitive URLs,
+/// but comparison, hashing, and formatting use the normalized name.
+#[derive(Debug, Clone)]
+pub struct PackageName {
+    normalized: String,
+    original: String,
+}
+
+impl PackageName {
+    /// Create a package name without checking if it’s a valid identifier,
+    /// e.g. for names from registries that don’t enforce the rules.
+    pub fn new_unchecked(original: impl Into<String>) -> Self {
+        let original = original.into();
+        PackageName {
+            normalized: original
+                .chars()
+                .default_case_fold()
+                .map(|c| if c == '_' { '-' } else { c })
+                .collect(),
+            original,
+        }
+    }
+
+    /// The case-folded name with `_` replaced by `-`
+    pub fn as_normalized(&self) -> &str {
+        &self.normalized
+    }
+
+    /// The name as it was spelled when created
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+}
+
+impl PartialEq for PackageName {
+    fn eq(&self, other: &Self) -> bool {
+        self.normalized == other.normalized
+    }
+}
+
+impl Eq for PackageName {}
+
+impl PartialOrd for PackageName {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PackageName {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.normalized.cmp(&other.normalized)
+    }
+}
+
+impl Hash for PackageName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.normalized.hash(state);
+    }
+}
 
 impl Serialize for PackageName {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.0)
+        serializer.serialize_str(&self.normalized)
     }
 }
 
@@ -25,27 +86,76 @@ impl FromStr for PackageName {
     type Err = Error;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        // If the match is not as long as the whole thing, there is more after
-        if ID_START_RE.find(s).is_none_or(|m| m.len() != s.len()) {
-            bail!("invalid identifier");
+        validate(s)?;
+        Ok(PackageName::new_unchecked(s))
+    }
+}
+
+/// Validate a package name and normalize it strictly as in [PEP 503](https://peps.python.org/pep-0503/#normalized-names):
+/// case-folded, with runs of `-`, `_`, and `.` replaced by a single `-`.
+///
+/// [`PackageName`] is more lenient and keeps `.`,
+/// so names derived from file names stay distinguishable.
+pub fn normalize(name: &str) -> Result<String, Error> {
+    validate(name)?;
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.chars().default_case_fold() {
+        if matches!(c, '-' | '_' | '.') {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.push(c);
         }
-        Ok(PackageName(
-            s.chars()
-                .default_case_fold()
-                .map(|c| if c == '_' { '-' } else { c })
-                .collect(),
-        ))
     }
+    Ok(normalized)
+}
+
+/// Check that `name` is a valid Python package identifier
+fn validate(name: &str) -> Result<(), Error> {
+    // If the match is not as long as the whole thing, there is more after
+    if ID_START_RE.find(name).is_none_or(|m| m.len() != name.len()) {
+        bail!("invalid package name {name:?}");
+    }
+    Ok(())
 }
 
 impl From<PackageName> for String {
     fn from(value: PackageName) -> Self {
-        value.0
+        value.normalized
     }
 }
 
 impl Display for PackageName {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.0)
+        f.write_str(&self.normalized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_original() {
+        let name = PackageName::from_str("Foo_Bar").unwrap();
+        assert_eq!(name.as_normalized(), "foo-bar");
+        assert_eq!(name.original(), "Foo_Bar");
+        assert_eq!(name, PackageName::from_str("foo-bar").unwrap());
+        assert!(PackageName::from_str("foo bar").is_err());
+        assert_eq!(
+            PackageName::new_unchecked("Foo Bar").as_normalized(),
+            "foo bar"
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Friendly-Bard").unwrap(), "friendly-bard");
+        assert_eq!(normalize("FRIENDLY._-BARD").unwrap(), "friendly-bard");
+        assert_eq!(normalize("zope.interface").unwrap(), "zope-interface");
+        assert!(normalize("-foo").is_err());
+        assert!(normalize("foo!").is_err());
+        assert!(normalize("").is_err());
     }
 }
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::str::FromStr;
+
+use eyre::{Error, OptionExt as _, Result};
+
+pub use super::package_name::PackageName;
+use super::package_name::normalize;
+
+/// Archive formats of source distributions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SdistFormat {
+    /// `.tar.gz`, the only one allowed since [PEP 625](https://peps.python.org/pep-0625/)
+    TarGz,
+    /// `.zip`, common before PEP 625
+    Zip,
+    /// Older formats, like `.tar.bz2`
+    Other(&'static str),
+}
+
+impl SdistFormat {
+    const LEGACY_EXTENSIONS: &[&str] = &[".tar.bz2", ".tar.xz", ".tgz", ".tbz", ".tar"];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            SdistFormat::TarGz => ".tar.gz",
+            SdistFormat::Zip => ".zip",
+            SdistFormat::Other(extension) => extension,
+        }
+    }
+}
+
+/// A source distribution filename, partially parsed.
+///
+/// PEP 625 filenames are `{name}-{version}.tar.gz` with the name normalized and `-` replaced by `_`.
+/// Older ones may keep `-` in the name, like `python-dateutil-2.8.2.tar.gz`,
+/// so the version starts at the first `-` followed by a valid version.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SdistFilename {
+    pub name: PackageName,
+    pub version: pep440_rs::Version,
+    pub format: SdistFormat,
+}
+
+impl FromStr for SdistFilename {
+    type Err = Error;
+
+    fn from_str(filename: &str) -> Result<Self, Self::Err> {
+        let lower = filename.to_ascii_lowercase();
+        let format = [SdistFormat::TarGz, SdistFormat::Zip]
+            .into_iter()
+            .chain(
+                SdistFormat::LEGACY_EXTENSIONS
+                    .iter()
+                    .map(|ext| SdistFormat::Other(ext)),
+            )
+            .find(|format| lower.ends_with(format.extension()))
+            .ok_or_eyre("not a source distribution")?;
+        let stem = &filename[..filename.len() - format.extension().len()];
+        let (name, version) = stem
+            .match_indices('-')
+            .find_map(|(i, _)| {
+                let version = pep440_rs::Version::from_str(&stem[i + 1..]).ok()?;
+                Some((&stem[..i], version))
+            })
+            .ok_or_eyre("invalid source distribution filename")?;
+        Ok(SdistFilename {
+            name: PackageName::from_str(name)?,
+            version,
+            format,
+        })
+    }
+}
+
+/// The normalized filename, as PEP 625 requires it for `.tar.gz` files
+impl fmt::Display for SdistFilename {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = normalize(self.name.original()).map_err(|_| fmt::Error)?;
+        write!(
+            f,
+            "{}-{}{}",
+            name.replace('-', "_"),
+            self.version,
+            self.format.extension()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let sdist = SdistFilename::from_str("foo_bar-1.0.post1.tar.gz").unwrap();
+        assert_eq!(sdist.name.as_normalized(), "foo-bar");
+        assert_eq!(sdist.version.to_string(), "1.0.post1");
+        assert_eq!(sdist.format, SdistFormat::TarGz);
+
+        let legacy = SdistFilename::from_str("python-dateutil-2.8.2.ZIP").unwrap();
+        assert_eq!(legacy.name.as_normalized(), "python-dateutil");
+        assert_eq!(legacy.format, SdistFormat::Zip);
+        assert_eq!(legacy.to_string(), "python_dateutil-2.8.2.zip");
+
+        let bz2 = SdistFilename::from_str("Foo.Bar-01.0-2.tar.bz2").unwrap();
+        assert_eq!(bz2.format, SdistFormat::Other(".tar.bz2"));
+        assert_eq!(bz2.to_string(), "foo_bar-1.0.post2.tar.bz2");
+
+        assert!(SdistFilename::from_str("foo-1.0-py3-none-any.whl").is_err());
+        assert!(SdistFilename::from_str("foo.tar.gz").is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Parsing version specifiers with errors that tell what’s wrong and how to fix it.
+
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::str::FromStr as _;
+
+use eyre::{Error, eyre};
+use pep440_rs::{VersionSpecifier, VersionSpecifiers};
+
+const GRAMMAR: &str = "Version specifiers are comparisons separated by commas, like `>=1.0, <2`, `==1.4.*` or `~=2.2`, \
+    with the operators `==`, `!=`, `<`, `<=`, `>`, `>=`, `~=` and `===`";
+
+const OPERATOR_CHARS: [char; 6] = ['<', '>', '=', '!', '~', '^'];
+
+/// Parse version specifiers like `>=1.0, <2`.
+/// Errors mark the invalid comparison, suggest a fix if there’s a likely one, and explain the grammar.
+pub fn parse_specifiers(spec: &str) -> Result<VersionSpecifiers, Error> {
+    parse_specifiers_in(spec, 0)
+}
+
+/// Parse a requirement like `foo[bar]>=1.0; python_version < "3.12"`,
+/// explaining errors in its version specifiers like [`parse_specifiers`]
+pub fn parse_requirement(req: &str) -> Result<pep508_rs::Requirement, Error> {
+    pep508_rs::Requirement::from_str(req).or_else(|err| {
+        // Direct URLs contain operator characters, but no specifiers
+        if let Some(start) = req.find(OPERATOR_CHARS).filter(|_| !req.contains('@')) {
+            let end = req[start..]
+                .find([';', ')'])
+                .map_or(req.len(), |end| start + end);
+            parse_specifiers_in(&req[..end], start)?;
+        }
+        Err(err.into())
+    })
+}
+
+/// Parse the version specifiers from `start` to the end of `line`, e.g. after the name in `foo>=1.0`.
+/// Errors show all of `line`, so users recognize the argument.
+pub(crate) fn parse_specifiers_in(line: &str, start: usize) -> Result<VersionSpecifiers, Error> {
+    let spec = &line[start..];
+    if spec.is_empty() {
+        return Ok(VersionSpecifiers::empty());
+    }
+    let mut offset = start;
+    let mut specifiers = Vec::new();
+    for clause in spec.split(',') {
+        let trimmed = clause.trim();
+        let clause_start = offset + clause.len() - clause.trim_start().len();
+        match VersionSpecifier::from_str(clause) {
+            Ok(specifier) => specifiers.push(specifier),
+            Err(err) => {
+                let range = clause_start..clause_start + trimmed.len();
+                return Err(explain(line, range, &err));
+            }
+        }
+        offset += clause.len() + 1;
+    }
+    Ok(specifiers.into_iter().collect())
+}
+
+fn explain(line: &str, range: Range<usize>, err: &impl std::fmt::Display) -> Error {
+    let clause = &line[range.clone()];
+    let marker = format!(
+        "{}{}",
+        " ".repeat(line[..range.start].chars().count()),
+        "^".repeat(clause.chars().count().max(1)),
+    );
+    let mut message = format!("invalid version specifier `{clause}`: {err}\n{line}\n{marker}\n");
+    if let Some((fixed, reason)) = suggest(clause) {
+        let fixed = format!("{}{fixed}{}", &line[..range.start], &line[range.end..]);
+        writeln!(message, "help: {reason}, did you mean `{fixed}`?").unwrap();
+    }
+    message.push_str(GRAMMAR);
+    eyre!(message)
+}
+
+/// A valid replacement for an invalid comparison, and why
+fn suggest(clause: &str) -> Option<(String, &'static str)> {
+    let (fixed, reason) = if let Some(fixed) = split_comparisons(clause) {
+        (fixed, "comparisons are separated by commas")
+    } else {
+        let split = clause
+            .find(|c| !OPERATOR_CHARS.contains(&c))
+            .unwrap_or(clause.len());
+        let (operator, version) = (&clause[..split], clause[split..].trim());
+        match operator {
+            "=>" => (format!(">={version}"), "the operator is `>=`"),
+            "=<" => (format!("<={version}"), "the operator is `<=`"),
+            "=" => (format!("=={version}"), "equality is `==`"),
+            "<>" => (format!("!={version}"), "inequality is `!=`"),
+            "^" => (caret(version)?, "`^` isn’t supported"),
+            "~" => (tilde(version)?, "`~` isn’t supported"),
+            "" if version.starts_with(|c: char| c.is_ascii_digit()) => (
+                format!("=={version}"),
+                "versions need a comparison operator",
+            ),
+            "~=" if !version.contains('.') => (
+                format!("~={version}.0"),
+                "`~=` needs at least two release segments",
+            ),
+            operator if operator != "==" && operator != "!=" && version.ends_with(".*") => (
+                format!("{operator}{}", version.trim_end_matches(".*")),
+                "`.*` only works with `==` and `!=`",
+            ),
+            _ => return None,
+        }
+    };
+    VersionSpecifiers::from_str(&fixed)
+        .is_ok()
+        .then_some((fixed, reason))
+}
+
+/// `>=1 <2` as `>=1, <2`
+fn split_comparisons(clause: &str) -> Option<String> {
+    let is_operator = |part: &str| part.chars().all(|c| OPERATOR_CHARS.contains(&c));
+    let mut parts = Vec::<String>::new();
+    for token in clause.split_whitespace() {
+        match parts.last_mut() {
+            // `>= 1`
+            Some(last) if is_operator(last) => last.push_str(token),
+            _ => parts.push(token.to_owned()),
+        }
+    }
+    (parts.len() > 1 && parts.iter().all(|part| part.starts_with(OPERATOR_CHARS)))
+        .then(|| parts.join(", "))
+}
+
+fn release(version: &str) -> Option<Vec<u64>> {
+    version
+        .split('.')
+        .map(|segment| segment.parse().ok())
+        .collect()
+}
+
+/// Poetry’s `^1.2`: up to the next release bumping the first non-zero segment
+fn caret(version: &str) -> Option<String> {
+    let release = release(version)?;
+    let bump = release
+        .iter()
+        .position(|&segment| segment != 0)
+        .unwrap_or(release.len() - 1);
+    let upper = release[..=bump]
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| if i == bump { segment + 1 } else { *segment })
+        .map(|segment| segment.to_string())
+        .collect::<Vec<_>>();
+    Some(format!(">={version}, <{}", upper.join(".")))
+}
+
+/// Poetry’s `~1.2`: up to the next minor release, or major release for `~1`
+fn tilde(version: &str) -> Option<String> {
+    match release(version)?.len() {
+        1 | 2 => Some(format!("~={version}.0")),
+        _ => Some(format!("~={version}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_specifiers() {
+        assert_eq!(
+            parse_specifiers(">=1.0, <2").unwrap().to_string(),
+            ">=1.0, <2"
+        );
+        assert!(parse_specifiers("").unwrap().is_empty());
+
+        let err = parse_specifiers_in("foo>=1, <2.*", 3).unwrap_err();
+        let lines = err.to_string();
+        let lines = lines.lines().collect::<Vec<_>>();
+        assert!(lines[0].starts_with("invalid version specifier `<2.*`"));
+        assert_eq!(lines[1..3], ["foo>=1, <2.*", "        ^^^^"]);
+        assert_eq!(
+            lines[3],
+            "help: `.*` only works with `==` and `!=`, did you mean `foo>=1, <2`?"
+        );
+        assert!(lines[4].starts_with("Version specifiers are comparisons"));
+    }
+
+    #[test]
+    fn test_suggest() {
+        let fixed = |clause| suggest(clause).map(|(fixed, _)| fixed);
+        assert_eq!(fixed(">=1.0.*").as_deref(), Some(">=1.0"));
+        assert_eq!(fixed("=1.0").as_deref(), Some("==1.0"));
+        assert_eq!(fixed("=>1.0").as_deref(), Some(">=1.0"));
+        assert_eq!(fixed("1.0").as_deref(), Some("==1.0"));
+        assert_eq!(fixed("~=1").as_deref(), Some("~=1.0"));
+        assert_eq!(fixed("^1.2.3").as_deref(), Some(">=1.2.3, <2"));
+        assert_eq!(fixed("^0.2").as_deref(), Some(">=0.2, <0.3"));
+        assert_eq!(fixed("~1.2").as_deref(), Some("~=1.2.0"));
+        assert_eq!(fixed(">=1 <2").as_deref(), Some(">=1, <2"));
+        assert_eq!(fixed(">= 1 < 2").as_deref(), Some(">=1, <2"));
+        assert_eq!(fixed("??"), None);
+    }
+
+    #[test]
+    fn test_parse_requirement() {
+        assert!(parse_requirement("foo[bar]>=1; python_version < '3.12'").is_ok());
+        let err = parse_requirement("foo>=1.0.*; python_version < '3.12'").unwrap_err();
+        assert!(
+            err.to_string().contains("did you mean `foo>=1.0`?"),
+            "{err}"
+        );
+        assert!(parse_requirement("foo @ https://example.com/foo.whl#sha256=").is_ok());
+    }
+}
@@ -1,8 +1,9 @@
 use std::str::FromStr;
 
-use color_eyre::eyre::{Error, OptionExt as _, Result, bail};
+use eyre::{Error, OptionExt as _, Result, bail};
 
 pub use super::package_name::PackageName;
+use super::wheel_tags::WheelTags;
 
 /// A wheel filename, partially parsed.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -30,3 +31,10 @@ impl FromStr for WheelFilename {
         })
     }
 }
+
+impl WheelFilename {
+    /// The compatibility tags, without the build tag
+    pub fn tags(&self) -> Result<WheelTags> {
+        self.tags.parse()
+    }
+}
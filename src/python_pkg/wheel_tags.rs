@@ -0,0 +1,189 @@
+use std::str::FromStr;
+
+use eyre::{Error, Result, bail};
+
+/// The compatibility tags of a wheel, e.g. `cp313-cp313-manylinux_2_28_x86_64`.
+/// Each can be a `.`-separated set, like `py2.py3`.
+/// See [spec](https://packaging.python.org/en/latest/specifications/platform-compatibility-tags/).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WheelTags {
+    pub python: Vec<String>,
+    pub abi: Vec<String>,
+    pub platform: Vec<String>,
+}
+
+impl FromStr for WheelTags {
+    type Err = Error;
+
+    /// Parse the tags from the part of a wheel filename after the version, ignoring a build tag
+    fn from_str(tags: &str) -> Result<Self, Self::Err> {
+        let &[platform, abi, python, ..] = tags.rsplitn(4, '-').collect::<Vec<_>>().as_slice()
+        else {
+            bail!("invalid wheel tags: {tags}");
+        };
+        let split = |tag: &str| tag.split('.').map(str::to_ascii_lowercase).collect();
+        Ok(Self {
+            python: split(python),
+            abi: split(abi),
+            platform: split(platform),
+        })
+    }
+}
+
+/// An interpreter to install wheels for. Unset parts match any wheel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Target {
+    /// Major and minor version, e.g. `(3, 13)`
+    pub python: Option<(u64, u64)>,
+    /// A platform tag like `manylinux_2_28_x86_64`, `macosx_14_0_arm64` or `win_amd64`
+    pub platform: Option<String>,
+}
+
+impl Target {
+    /// Whether a wheel with `tags` can be installed on the target
+    pub fn supports(&self, tags: &WheelTags) -> bool {
+        let python = self.python.is_none_or(|version| {
+            tags.python.iter().any(|python| {
+                tags.abi
+                    .iter()
+                    .any(|abi| python_supports(version, python, abi))
+            })
+        });
+        let platform = tags.platform.iter().any(|platform| {
+            platform == "any"
+                || self
+                    .platform
+                    .as_deref()
+                    .is_none_or(|target| platform_supports(target, platform))
+        });
+        python && platform
+    }
+}
+
+/// Whether Python `major.minor` can run a wheel for `python` and `abi`
+fn python_supports((major, minor): (u64, u64), python: &str, abi: &str) -> bool {
+    let minor_of = |prefix: &str| {
+        let version = python.strip_prefix(prefix)?;
+        let rest = version.strip_prefix(&major.to_string())?;
+        // `py3` is for every minor version
+        if rest.is_empty() {
+            return Some(None);
+        }
+        rest.parse::<u64>().ok().map(Some)
+    };
+    let cpython = format!("cp{major}{minor}");
+    match abi {
+        // Pure Python, or only depending on the interpreter
+        "none" => python == cpython || minor_of("py").is_some_and(|m| m.is_none_or(|m| m <= minor)),
+        // The stable ABI of this and later versions
+        "abi3" => minor_of("cp").is_some_and(|m| m.is_some_and(|m| m <= minor)),
+        abi => python == cpython && abi == cpython,
+    }
+}
+
+/// Whether a `target` platform can install a wheel for `platform`
+fn platform_supports(target: &str, platform: &str) -> bool {
+    let (Some(target), Some(wheel)) = (Versioned::parse(target), Versioned::parse(platform)) else {
+        return target.eq_ignore_ascii_case(platform);
+    };
+    let arch = target.arch == wheel.arch
+        || (target.family == "macosx"
+            && wheel.arch == "universal2"
+            && matches!(target.arch, "x86_64" | "arm64"));
+    target.family == wheel.family && wheel.version <= target.version && arch
+}
+
+/// A platform tag with a minimum OS or libc version, e.g. `manylinux_2_28_x86_64`
+struct Versioned<'a> {
+    family: &'a str,
+    version: (u32, u32),
+    arch: &'a str,
+}
+
+impl<'a> Versioned<'a> {
+    fn parse(tag: &'a str) -> Option<Self> {
+        // Aliases from before PEP 600
+        for (alias, version) in [
+            ("manylinux1_", (2, 5)),
+            ("manylinux2010_", (2, 12)),
+            ("manylinux2014_", (2, 17)),
+        ] {
+            if let Some(arch) = tag.strip_prefix(alias) {
+                return Some(Self {
+                    family: "manylinux",
+                    version,
+                    arch,
+                });
+            }
+        }
+        let (family, rest) = tag.split_once('_')?;
+        if !matches!(family, "manylinux" | "musllinux" | "macosx") {
+            return None;
+        }
+        let mut parts = rest.splitn(3, '_');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        let arch = parts.next()?;
+        Some(Self {
+            family,
+            version: (major, minor),
+            arch,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supports(python: Option<(u64, u64)>, platform: Option<&str>, tags: &str) -> bool {
+        let target = Target {
+            python,
+            platform: platform.map(ToOwned::to_owned),
+        };
+        target.supports(&tags.parse().unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        let tags: WheelTags = "1-py2.py3-none-any".parse().unwrap();
+        assert_eq!(tags.python, ["py2", "py3"]);
+        assert_eq!(tags.abi, ["none"]);
+        assert_eq!(tags.platform, ["any"]);
+        assert!("py3-any".parse::<WheelTags>().is_err());
+    }
+
+    #[test]
+    fn test_python() {
+        let py313 = Some((3, 13));
+        assert!(supports(py313, None, "py3-none-any"));
+        assert!(supports(py313, None, "py312-none-any"));
+        assert!(!supports(py313, None, "py2-none-any"));
+        assert!(supports(py313, None, "cp313-cp313-win_amd64"));
+        assert!(!supports(py313, None, "cp312-cp312-win_amd64"));
+        assert!(!supports(py313, None, "cp313-cp313t-win_amd64"));
+        assert!(supports(py313, None, "cp39-abi3-win_amd64"));
+        assert!(!supports(Some((3, 8)), None, "cp39-abi3-win_amd64"));
+    }
+
+    #[test]
+    fn test_platform() {
+        let linux = Some("manylinux_2_28_x86_64");
+        assert!(supports(
+            None,
+            linux,
+            "cp313-cp313-manylinux_2_17_x86_64.manylinux2014_x86_64"
+        ));
+        assert!(supports(None, linux, "cp313-cp313-manylinux1_x86_64"));
+        assert!(!supports(None, linux, "cp313-cp313-manylinux_2_34_x86_64"));
+        assert!(!supports(None, linux, "cp313-cp313-manylinux_2_17_aarch64"));
+        assert!(!supports(None, linux, "cp313-cp313-musllinux_1_2_x86_64"));
+        assert!(supports(None, linux, "py3-none-any"));
+        let mac = Some("macosx_14_0_arm64");
+        assert!(supports(None, mac, "cp313-cp313-macosx_11_0_arm64"));
+        assert!(supports(None, mac, "cp313-cp313-macosx_10_13_universal2"));
+        assert!(!supports(None, mac, "cp313-cp313-macosx_15_0_arm64"));
+        assert!(supports(None, Some("win_amd64"), "cp313-cp313-win_amd64"));
+        assert!(!supports(None, Some("win_amd64"), "cp313-cp313-win32"));
+    }
+}
@@ -0,0 +1,175 @@
+//! Tracing of the byte ranges fetched from remote files.
+//!
+//! [`RangeTracer`] logs every range request as a `pypi_lazyzip::ranges` event
+//! and aggregates them into an [`AccessMap`] per file, showing which regions were touched.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+use http::Extensions;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, HeaderName, RANGE};
+use reqwest::{Method, Request, Response, Url};
+use reqwest_middleware::{Middleware, Next};
+use serde::Serialize;
+
+/// Middleware logging range requests and collecting access maps
+#[derive(Debug, Clone, Default)]
+pub struct RangeTracer(Arc<Mutex<BTreeMap<Url, AccessMap>>>);
+
+/// Regions of a file fetched by range requests or full downloads
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccessMap {
+    pub url: Url,
+    /// Size of the file, if a response announced it
+    pub size: Option<u64>,
+    pub requests: usize,
+    /// Bytes in all responses, counting overlapping regions multiple times
+    pub bytes_fetched: u64,
+    /// Disjoint, sorted regions of the file that were fetched
+    pub ranges: Vec<Range<u64>>,
+}
+
+impl AccessMap {
+    fn new(url: Url) -> Self {
+        Self {
+            url,
+            size: None,
+            requests: 0,
+            bytes_fetched: 0,
+            ranges: Vec::new(),
+        }
+    }
+
+    /// Add a fetched region, merging it with touching ones
+    fn insert(&mut self, range: Range<u64>) {
+        self.bytes_fetched += range.end - range.start;
+        let mut merged = range;
+        self.ranges.retain(|r| {
+            let touches = r.start <= merged.end && merged.start <= r.end;
+            if touches {
+                merged = merged.start.min(r.start)..merged.end.max(r.end);
+            }
+            !touches
+        });
+        let idx = self.ranges.partition_point(|r| r.start < merged.start);
+        self.ranges.insert(idx, merged);
+    }
+}
+
+impl RangeTracer {
+    /// Access maps of all files requested so far, by URL
+    pub fn access_maps(&self) -> Vec<AccessMap> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    fn record(&self, url: &Url, size: Option<u64>, range: Option<Range<u64>>) {
+        let mut maps = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let map = maps
+            .entry(url.clone())
+            .or_insert_with(|| AccessMap::new(url.clone()));
+        map.requests += 1;
+        map.size = map.size.or(size);
+        if let Some(range) = range {
+            map.insert(range);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for RangeTracer {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let url = req.url().clone();
+        let method = req.method().clone();
+        let is_head = method == Method::HEAD;
+        let requested = req.headers().get(RANGE).cloned();
+        let start = Instant::now();
+        let response = next.run(req, extensions).await?;
+        let duration = start.elapsed();
+        let header = |name: HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        // Index pages aren’t interesting here, only files
+        if requested.is_none() && header(CONTENT_TYPE).is_some_and(|t| t.contains("json")) {
+            return Ok(response);
+        }
+
+        let content_range = header(CONTENT_RANGE).and_then(parse_content_range);
+        let content_length = header(CONTENT_LENGTH).and_then(|len| len.parse::<u64>().ok());
+        let (range, size) = match content_range {
+            Some((range, size)) => (Some(range), size),
+            // A full response, or HEAD announcing the size
+            None if is_head => (None, content_length),
+            None => (content_length.map(|len| 0..len), content_length),
+        };
+        tracing::info!(
+            target: "pypi_lazyzip::ranges",
+            %url,
+            %method,
+            offset = range.as_ref().map(|r| r.start),
+            length = range.as_ref().map(|r| r.end - r.start),
+            status = response.status().as_u16(),
+            duration_ms = duration.as_secs_f64() * 1000.,
+            "fetched",
+        );
+        self.record(&url, size, range);
+        Ok(response)
+    }
+}
+
+/// Parse `bytes <start>-<end>/<size>` into the fetched range and the file size, if known.
+/// Invalid ranges, like ones ending before they start, are ignored.
+fn parse_content_range(content_range: &str) -> Option<(Range<u64>, Option<u64>)> {
+    let (range, size) = content_range.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.parse::<u64>().ok()?, end.parse::<u64>().ok()?);
+    if start > end {
+        return None;
+    }
+    Some((start..end.checked_add(1)?, size.parse().ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert() {
+        let mut map = AccessMap::new(Url::parse("https://example.com/foo.whl").unwrap());
+        map.insert(90..100);
+        map.insert(0..10);
+        map.insert(50..60);
+        map.insert(55..90);
+        assert_eq!(map.ranges, [0..10, 50..100]);
+        assert_eq!(map.bytes_fetched, 65);
+    }
+
+    #[test]
+    fn test_parse_content_range() {
+        assert_eq!(
+            parse_content_range("bytes 90-99/100"),
+            Some((90..100, Some(100)))
+        );
+        assert_eq!(parse_content_range("bytes 0-9/*"), Some((0..10, None)));
+        assert_eq!(parse_content_range("bytes */100"), None);
+        assert_eq!(parse_content_range("bytes 10-5/100"), None);
+        assert_eq!(
+            parse_content_range(&format!("bytes 0-{}/*", u64::MAX)),
+            None
+        );
+    }
+}
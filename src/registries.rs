@@ -0,0 +1,150 @@
+//! Quirks of package registries hosted by code forges.
+//!
+//! - GitLab’s `PyPI` registry serves HTML pages of the simple API, and links files on its own host,
+//!   which redirects to signed storage URLs. Credentials in the index URL, like
+//!   `https://__token__:<token>@gitlab.example.com/api/v4/projects/<id>/packages/pypi/simple/`,
+//!   are sent to its whole host, but not on to the storage, which would reject them.
+//! - GitHub has no `PyPI` registry, but wheels attached to releases can be read by URL.
+//!   Those of private repositories are read via the API, e.g.
+//!   `https://api.github.com/repos/<owner>/<repo>/releases/assets/<id>`, with a token
+//!   registered via [`ClientOptions::credentials`](crate::client_options::ClientOptions::credentials).
+//!   It answers with JSON unless asked for the file, which [`ClientOptions::build`](crate::client_options::ClientOptions::build)
+//!   sets up, and redirects to signed storage URLs, too.
+//!
+//! Reading a file lazily only follows its redirect once, further ranges are read from the storage URL.
+
+use http::Extensions;
+use reqwest::header::{ACCEPT, HeaderValue};
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Middleware, Next};
+
+/// Middleware asking GitHub’s API for release assets’ content instead of their description
+pub(crate) struct GithubAssets;
+
+/// If `url` is a release asset on GitHub’s or a GitHub Enterprise Server’s API
+fn is_github_asset(url: &Url) -> bool {
+    let on_api = url.host_str() == Some("api.github.com") || url.path().starts_with("/api/v3/");
+    on_api && url.path().contains("/releases/assets/")
+}
+
+#[async_trait::async_trait]
+impl Middleware for GithubAssets {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if is_github_asset(req.url()) && !req.headers().contains_key(ACCEPT) {
+            req.headers_mut()
+                .insert(ACCEPT, HeaderValue::from_static("application/octet-stream"));
+        }
+        next.run(req, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use bytes::Bytes;
+    use http_body_util::Full;
+    use hyper::StatusCode;
+    use hyper::header::{AUTHORIZATION, CONTENT_TYPE, LOCATION};
+
+    use super::*;
+    use crate::LazyZipClient;
+    use crate::test_util::{
+        assert_far_entry, far_entry_wheel, file_response, open_far_entry_wheel, serve, status,
+    };
+
+    #[test]
+    fn test_is_github_asset() {
+        let asset = |url: &str| is_github_asset(&url.parse().unwrap());
+        assert!(asset(
+            "https://api.github.com/repos/owner/repo/releases/assets/123"
+        ));
+        assert!(asset(
+            "https://github.example.com/api/v3/repos/owner/repo/releases/assets/123"
+        ));
+        assert!(!asset(
+            "https://github.com/owner/repo/releases/download/v1/foo-1.0-py3-none-any.whl"
+        ));
+    }
+
+    /// Counts of requests to a mock GitLab registry
+    #[derive(Default)]
+    struct Hits {
+        redirects: AtomicUsize,
+        storage: AtomicUsize,
+    }
+
+    /// Serve `wheel` like GitLab: an HTML page and a file link needing credentials,
+    /// redirecting to storage on another host (`localhost` instead of `127.0.0.1`) that rejects them
+    fn gitlab(
+        wheel: &Bytes,
+        port: u16,
+        hits: &Hits,
+        req: &hyper::Request<hyper::body::Incoming>,
+    ) -> hyper::Response<Full<Bytes>> {
+        let authorized = req.headers().get(AUTHORIZATION);
+        let filename = "foo-1.0-py3-none-any.whl";
+        match req.uri().path() {
+            _ if req.uri().path().starts_with("/storage/") => {
+                hits.storage.fetch_add(1, Ordering::SeqCst);
+                if authorized.is_some() {
+                    return status(StatusCode::BAD_REQUEST);
+                }
+                file_response(wheel, req)
+            }
+            _ if authorized.is_none_or(|auth| auth != "Basic X190b2tlbl9fOnNlY3JldA==") => {
+                status(StatusCode::UNAUTHORIZED)
+            }
+            "/api/v4/projects/1/packages/pypi/simple/foo/" => hyper::Response::builder()
+                .header(CONTENT_TYPE, "text/html")
+                .body(Full::new(Bytes::from(format!(
+                    r#"<a href="../../files/abc/{filename}">{filename}</a>"#
+                ))))
+                .unwrap(),
+            path if path.ends_with(filename) => {
+                hits.redirects.fetch_add(1, Ordering::SeqCst);
+                hyper::Response::builder()
+                    .status(StatusCode::FOUND)
+                    .header(
+                        LOCATION,
+                        format!("http://localhost:{port}/storage/{filename}?signature=abc"),
+                    )
+                    .body(Full::default())
+                    .unwrap()
+            }
+            _ => status(StatusCode::NOT_FOUND),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gitlab() {
+        let wheel = far_entry_wheel();
+        let hits = Arc::new(Hits::default());
+        let server = serve({
+            let hits = hits.clone();
+            move |addr, req| gitlab(&wheel, addr.port(), &hits, req)
+        })
+        .await
+        .unwrap();
+
+        let index_url = format!(
+            "http://__token__:secret@{}/api/v4/projects/1/packages/pypi/simple/",
+            server.addr()
+        );
+        let client = LazyZipClient::builder()
+            .index_urls([index_url.parse().unwrap()])
+            .build()
+            .unwrap();
+        assert_eq!(client.indexes()[0].password(), None);
+        let mut zip = open_far_entry_wheel(&client).await;
+        assert_far_entry(&mut zip).await;
+        assert_eq!(hits.redirects.load(Ordering::SeqCst), 1);
+        assert!(hits.storage.load(Ordering::SeqCst) >= 2);
+    }
+}
@@ -0,0 +1,94 @@
+use pep440_rs::{Version, VersionSpecifiers};
+use serde::Serialize;
+use serde_with::{DisplayFromStr, Map, serde_as};
+use version_ranges::Ranges;
+
+use crate::python_pkg::PackageName;
+
+/// The Python versions supported by a set of packages.
+#[serde_as]
+#[derive(Serialize, Debug)]
+pub struct Report {
+    /// All constraints combined, i.e. the versions every package supports
+    #[serde_as(as = "DisplayFromStr")]
+    pub intersection: VersionSpecifiers,
+    /// True if no Python version satisfies all constraints
+    pub conflict: bool,
+    /// Each package’s `Requires-Python`, if any
+    #[serde_as(as = "Map<_, Option<DisplayFromStr>>")]
+    pub packages: Vec<(PackageName, Option<VersionSpecifiers>)>,
+    /// Packages that don’t support the target interpreter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excluding: Option<Vec<PackageName>>,
+}
+
+impl Report {
+    pub fn new(
+        mut packages: Vec<(PackageName, Option<VersionSpecifiers>)>,
+        target: Option<&Version>,
+    ) -> Self {
+        packages.sort_by(|(l, _), (r, _)| l.cmp(r));
+        let mut intersection = Vec::new();
+        for spec in packages
+            .iter()
+            .filter_map(|(_, rp)| rp.as_ref())
+            .flat_map(|rp| rp.iter())
+        {
+            if !intersection.contains(spec) {
+                intersection.push(spec.clone());
+            }
+        }
+        let intersection = VersionSpecifiers::from_iter(intersection);
+        let conflict = Ranges::from(intersection.clone()).is_empty();
+        let excluding = target.map(|target| {
+            packages
+                .iter()
+                .filter(|(_, rp)| rp.as_ref().is_some_and(|rp| !rp.contains(target)))
+                .map(|(name, _)| name.clone())
+                .collect()
+        });
+        Report {
+            intersection,
+            conflict,
+            packages,
+            excluding,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr as _;
+
+    use super::*;
+
+    fn pkg(name: &str, rp: Option<&str>) -> (PackageName, Option<VersionSpecifiers>) {
+        (
+            PackageName::from_str(name).unwrap(),
+            rp.map(|rp| VersionSpecifiers::from_str(rp).unwrap()),
+        )
+    }
+
+    #[test]
+    fn test_report() {
+        let target = Version::from_str("3.9").unwrap();
+        let report = Report::new(
+            vec![
+                pkg("a", Some(">=3.8")),
+                pkg("b", Some(">=3.10,<4")),
+                pkg("c", None),
+            ],
+            Some(&target),
+        );
+        assert_eq!(report.intersection.to_string(), ">=3.8, >=3.10, <4");
+        assert!(!report.conflict);
+        assert_eq!(
+            report.excluding,
+            Some(vec![PackageName::from_str("b").unwrap()])
+        );
+
+        let report = Report::new(vec![pkg("a", Some("<3")), pkg("b", Some(">=3.6"))], None);
+        assert!(report.conflict);
+        assert_eq!(report.excluding, None);
+    }
+}
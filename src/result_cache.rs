@@ -0,0 +1,88 @@
+//! Results of commands by the SHA-256 of the file they were computed from.
+//!
+//! Files on package indexes are immutable, so results computed from a file with a known hash
+//! stay valid, and repeating a query needs no range requests at all.
+//! Entries are kept per version of this tool, as output formats may change between versions,
+//! and checksummed, so corrupt ones are computed again.
+
+use std::path::{Path, PathBuf};
+
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::cache_integrity::{read_checked, write_checked};
+
+/// A directory of cached results, by command and SHA-256
+#[derive(Debug, Clone)]
+pub struct ResultCache {
+    dir: PathBuf,
+}
+
+/// A cached result, with the name of the package it’s for
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CachedResult {
+    pub name: String,
+    pub value: serde_json::Value,
+}
+
+impl ResultCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `results` in the user’s cache directory, if there is one
+    pub fn in_user_cache() -> Option<Self> {
+        Some(Self::new(user_cache_dir()?.join("results")))
+    }
+
+    fn path(&self, command: &str, sha256: &str) -> PathBuf {
+        self.dir
+            .join(env!("CARGO_PKG_VERSION"))
+            .join(command)
+            .join(format!("{}.json", sha256.to_ascii_lowercase()))
+    }
+
+    /// The cached result of `command` for the file with `sha256`, if any.
+    /// Unreadable or corrupt entries count as missing.
+    pub fn get(&self, command: &str, sha256: &str) -> Option<CachedResult> {
+        let content = read_checked(&self.dir, &self.path(command, sha256))?;
+        serde_json::from_slice(&content)
+            .inspect_err(|e| tracing::debug!("Ignoring invalid cached result: {e}"))
+            .ok()
+    }
+
+    /// Cache a result, replacing the entry only once it’s fully written
+    pub fn put(&self, command: &str, sha256: &str, result: &CachedResult) -> Result<()> {
+        write_checked(&self.path(command, sha256), &serde_json::to_vec(result)?)
+    }
+}
+
+/// `pypi-lazyzip` in the user’s cache directory, if there is one
+pub(crate) fn user_cache_dir() -> Option<PathBuf> {
+    let cache_home = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(cache_home.join("pypi-lazyzip"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_get() {
+        let dir =
+            std::env::temp_dir().join(format!("pypi-lazyzip-result-cache-{}", std::process::id()));
+        let cache = ResultCache::new(&dir);
+        assert_eq!(cache.get("metadata", "ABC"), None);
+        let result = CachedResult {
+            name: "foo".to_owned(),
+            value: serde_json::json!({"version": "1.0"}),
+        };
+        cache.put("metadata", "ABC", &result).unwrap();
+        assert_eq!(cache.get("metadata", "abc"), Some(result));
+        assert_eq!(cache.get("list", "abc"), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+//! Retrying requests that failed for reasons likely to go away, like dropped connections or overloaded servers.
+
+use std::time::Duration;
+
+use http::Extensions;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Request, Response, StatusCode};
+use reqwest_middleware::{Middleware, Next};
+
+/// How often and how patiently to retry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for every further one
+    pub backoff: Duration,
+    /// Longest wait, also capping what servers ask for via `Retry-After`
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The default policy with `max_retries` retries
+    pub fn with_max_retries(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Self::default()
+        }
+    }
+
+    /// How long to wait before retry number `retry` (from 0)
+    fn backoff(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        let exponential = self
+            .backoff
+            .saturating_mul(2_u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        retry_after.map_or(exponential, |after| after.min(self.max_backoff))
+    }
+}
+
+/// Middleware retrying connection failures, timeouts and `429` and `5xx` responses
+pub(crate) struct Retry(pub RetryPolicy);
+
+#[async_trait::async_trait]
+impl Middleware for Retry {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let mut retry = 0;
+        loop {
+            // Requests with streamed bodies can’t be repeated
+            let Some(attempt) = req.try_clone().filter(|_| retry < self.0.max_retries) else {
+                return next.run(req, extensions).await;
+            };
+            let result = next.clone().run(attempt, extensions).await;
+            let retry_after = match &result {
+                Ok(response) if is_transient(response.status()) => response
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|after| after.to_str().ok()?.parse().ok())
+                    .map(Duration::from_secs),
+                Err(reqwest_middleware::Error::Reqwest(e)) if e.is_connect() || e.is_timeout() => {
+                    None
+                }
+                Ok(_) | Err(_) => return result,
+            };
+            let wait = self.0.backoff(retry, retry_after);
+            tracing::info!(url = %req.url(), retry = retry + 1, ?wait, "retrying request");
+            tokio::time::sleep(wait).await;
+            retry += 1;
+        }
+    }
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(0, None), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2, None), Duration::from_secs(2));
+        assert_eq!(policy.backoff(10, None), Duration::from_secs(30));
+        assert_eq!(
+            policy.backoff(0, Some(Duration::from_secs(5))),
+            Duration::from_secs(5)
+        );
+        assert_eq!(
+            policy.backoff(0, Some(Duration::from_hours(1))),
+            Duration::from_secs(30)
+        );
+    }
+}
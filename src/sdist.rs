@@ -0,0 +1,458 @@
+//! Inspecting source distributions: core metadata from `PKG-INFO`,
+//! the build backend and requirements from `pyproject.toml`, and top-level changelogs.
+//!
+//! `.zip` sdists are read lazily like wheels.
+//! `.tar.gz` ones can’t be read out of order, so they’re streamed until all wanted files were found.
+
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr as _;
+
+use async_compression::tokio::bufread::GzipDecoder;
+use either::Either;
+use eyre::{Context as _, Result, bail};
+use futures::{StreamExt as _, TryStreamExt as _};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt as _, BufReader};
+use tokio_util::compat::FuturesAsyncReadCompatExt as _;
+
+use crate::package_report::FileInfo;
+use crate::python_pkg::{Dependency, Metadata, PackageName, SdistFilename, SdistFormat};
+use crate::warnings::{self, Warning};
+use crate::wheel::{self, RemoteFile};
+use crate::{LazyZipClient, PkgLoc};
+use crate::{changelog, simple_repo_api};
+
+/// What a source distribution says about itself
+#[derive(Serialize, Debug, Default)]
+pub struct SdistInfo {
+    /// Core metadata from `PKG-INFO`
+    pub metadata: Option<Metadata>,
+    /// Fields `PKG-INFO` marks as `Dynamic`, i.e. computed at build time.
+    /// Their values in `metadata` may be missing or incomplete.
+    pub dynamic: Vec<String>,
+    /// `build-system.build-backend` from `pyproject.toml`.
+    /// If it’s missing, installers fall back to `setuptools.build_meta:__legacy__`.
+    pub build_backend: Option<String>,
+    /// `build-system.requires` from `pyproject.toml`
+    pub build_requires: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+struct PyProject {
+    build_system: Option<BuildSystem>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+struct BuildSystem {
+    #[serde(default)]
+    requires: Vec<String>,
+    build_backend: Option<String>,
+}
+
+/// The name of a file in the top-level directory of an sdist, e.g. `PKG-INFO` for `foo-1.0/PKG-INFO`
+fn top_level(path: &str) -> Option<&str> {
+    let (dir, file) = path.split_once('/')?;
+    (!dir.is_empty() && !file.is_empty() && !file.contains('/')).then_some(file)
+}
+
+/// Files to read from the top-level directory of an sdist
+trait Collect {
+    /// Whether to read the top-level file `name`
+    fn wants(&self, name: &str) -> bool;
+    fn store(&mut self, name: &str, content: String);
+    /// Whether all wanted files were found, so reading can stop
+    fn is_complete(&self) -> bool;
+}
+
+/// Files of interest in the top-level directory of an sdist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Member {
+    PkgInfo,
+    PyProject,
+}
+
+impl Member {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "PKG-INFO" => Some(Self::PkgInfo),
+            "pyproject.toml" => Some(Self::PyProject),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Members {
+    pkg_info: Option<String>,
+    pyproject: Option<String>,
+}
+
+impl Collect for Members {
+    fn wants(&self, name: &str) -> bool {
+        Member::from_name(name).is_some()
+    }
+
+    fn store(&mut self, name: &str, content: String) {
+        if let Some(member) = Member::from_name(name) {
+            *self.slot(member) = Some(content);
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.pkg_info.is_some() && self.pyproject.is_some()
+    }
+}
+
+impl Members {
+    fn slot(&mut self, member: Member) -> &mut Option<String> {
+        match member {
+            Member::PkgInfo => &mut self.pkg_info,
+            Member::PyProject => &mut self.pyproject,
+        }
+    }
+
+    fn into_info(self) -> Result<SdistInfo> {
+        let metadata = self
+            .pkg_info
+            .as_deref()
+            .map(Metadata::from_str)
+            .transpose()
+            .context("invalid PKG-INFO")?;
+        let dynamic = metadata
+            .iter()
+            .flat_map(|metadata| metadata.get_all("Dynamic"))
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>();
+        if !dynamic.is_empty() {
+            warnings::warn(Warning::DynamicMetadata {
+                fields: dynamic.clone(),
+            });
+        }
+        let build_system = self
+            .pyproject
+            .as_deref()
+            .map(toml::from_str::<PyProject>)
+            .transpose()
+            .context("invalid pyproject.toml")?
+            .and_then(|pyproject| pyproject.build_system);
+        let (build_backend, build_requires) = build_system
+            .map(|b| (b.build_backend, b.requires))
+            .unwrap_or_default();
+        Ok(SdistInfo {
+            metadata,
+            dynamic,
+            build_backend,
+            build_requires,
+        })
+    }
+}
+
+/// Read `PKG-INFO` and `pyproject.toml` from a source distribution
+#[tracing::instrument(skip(client), fields(pkg_loc = %pkg_loc))]
+pub async fn inspect(client: &LazyZipClient, pkg_loc: PkgLoc) -> Result<(PackageName, SdistInfo)> {
+    let (name, members) = read_members(client, pkg_loc, Members::default()).await?;
+    Ok((name, members.into_info()?))
+}
+
+/// A changelog found in the top-level directory of a source distribution
+#[derive(Debug, Default)]
+pub struct SdistChangelog {
+    /// Core metadata from `PKG-INFO`
+    pub metadata: Option<Metadata>,
+    /// The best ranked changelog file, see [`changelog::rank`]
+    pub changelog: Option<String>,
+}
+
+/// `PKG-INFO` and the best ranked changelog file in the top-level directory of an sdist
+#[derive(Debug, Default)]
+struct ChangelogMembers {
+    pkg_info: Option<String>,
+    changelog: Option<(usize, String)>,
+}
+
+impl Collect for ChangelogMembers {
+    fn wants(&self, name: &str) -> bool {
+        name == "PKG-INFO"
+            || changelog::rank(name)
+                .is_some_and(|rank| self.changelog.as_ref().is_none_or(|(best, _)| rank < *best))
+    }
+
+    fn store(&mut self, name: &str, content: String) {
+        if name == "PKG-INFO" {
+            self.pkg_info = Some(content);
+        } else if let Some(rank) = changelog::rank(name) {
+            self.changelog = Some((rank, content));
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.pkg_info.is_some() && matches!(self.changelog, Some((0, _)))
+    }
+}
+
+/// Read `PKG-INFO` and a changelog like `CHANGELOG.md` or `NEWS.rst` from the top-level directory of a source distribution
+#[tracing::instrument(skip(client), fields(pkg_loc = %pkg_loc))]
+pub async fn changelog(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+) -> Result<(PackageName, SdistChangelog)> {
+    let (name, members) = read_members(client, pkg_loc, ChangelogMembers::default()).await?;
+    let metadata = members
+        .pkg_info
+        .as_deref()
+        .map(Metadata::from_str)
+        .transpose()
+        .context("invalid PKG-INFO")?;
+    let changelog = members.changelog.map(|(_, changelog)| changelog);
+    Ok((
+        name,
+        SdistChangelog {
+            metadata,
+            changelog,
+        },
+    ))
+}
+
+/// Read the files `collect` wants from the source distribution `pkg_loc` resolves to
+async fn read_members<C: Collect>(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+    mut collect: C,
+) -> Result<(PackageName, C)> {
+    let (name, file) = locate(client, pkg_loc).await?;
+    let filename = match &file {
+        Either::Left(remote) => remote
+            .url
+            .path_segments()
+            .and_then(Iterator::last)
+            .unwrap_or_default()
+            .to_owned(),
+        Either::Right(path) => path.to_string_lossy().into_owned(),
+    };
+    if has_suffix(&filename, ".zip") {
+        let mut zip_reader = match file {
+            Either::Left(remote) => wheel::open_remote_zip(client, &remote).await?,
+            Either::Right(path) => wheel::open_local_zip(path).await?,
+        };
+        for idx in 0..zip_reader.file().entries().len() {
+            let Some(member) = zip_reader.file().entries()[idx]
+                .filename()
+                .as_str()
+                .ok()
+                .and_then(top_level)
+                .filter(|name| collect.wants(name))
+                .map(ToOwned::to_owned)
+            else {
+                continue;
+            };
+            let mut buf = String::new();
+            wheel::read_entry(&mut zip_reader, idx, &mut buf).await?;
+            collect.store(&member, buf);
+            if collect.is_complete() {
+                break;
+            }
+        }
+    } else if has_suffix(&filename, ".tar.gz") {
+        match file {
+            Either::Left(remote) => {
+                let url = client.rewrite_url(remote.url)?;
+                let _permit = client.host_permit(&url).await;
+                let response = client.http().get(url).send().await?.error_for_status()?;
+                let body = response
+                    .bytes_stream()
+                    .map_err(io::Error::other)
+                    .into_async_read()
+                    .compat();
+                read_tar_gz(body, &mut collect).await?;
+            }
+            Either::Right(path) => {
+                read_tar_gz(tokio::fs::File::open(path).await?, &mut collect).await?;
+            }
+        }
+    } else {
+        bail!("{filename} is neither a .tar.gz nor a .zip source distribution");
+    }
+    Ok((name, collect))
+}
+
+/// Like [`wheel::fingerprint`], for the source distribution a package resolves to
+pub async fn fingerprint(client: &LazyZipClient, pkg_loc: PkgLoc) -> Result<Option<String>> {
+    let (_, file) = locate(client, pkg_loc).await?;
+    Ok(file.left().map(|file| file.fingerprint()))
+}
+
+/// Like [`wheel::file_info`], for the source distribution a package resolves to
+pub async fn file_info(client: &LazyZipClient, pkg_loc: PkgLoc) -> Result<FileInfo> {
+    let (_, file) = locate(client, pkg_loc).await?;
+    Ok(FileInfo::from_located(file))
+}
+
+async fn locate(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+) -> Result<(PackageName, Either<RemoteFile, PathBuf>)> {
+    match pkg_loc {
+        PkgLoc::Dependency(dep) => {
+            let sdist = find_sdist(client, &dep).await?;
+            let file = RemoteFile::from_index(client, sdist);
+            Ok((dep.into_name(), Either::Left(file)))
+        }
+        pkg_loc => wheel::locate(client, pkg_loc).await,
+    }
+}
+
+/// Find the newest sdist matching `dep`, skipping yanked ones
+pub async fn find_sdist(client: &LazyZipClient, dep: &Dependency) -> Result<simple_repo_api::File> {
+    wheel::find_newest(client, dep, "source distribution", sdist_version).await
+}
+
+pub(crate) fn has_suffix(filename: &str, suffix: &str) -> bool {
+    filename
+        .len()
+        .checked_sub(suffix.len())
+        .and_then(|start| filename.get(start..))
+        .is_some_and(|end| end.eq_ignore_ascii_case(suffix))
+}
+
+/// The version in an sdist file name like `foo-1.0.tar.gz`, if it’s in a format we can read
+pub(crate) fn sdist_version(filename: &str) -> Option<pep440_rs::Version> {
+    SdistFilename::from_str(filename)
+        .ok()
+        .filter(|sdist| matches!(sdist.format, SdistFormat::TarGz | SdistFormat::Zip))
+        .map(|sdist| sdist.version)
+}
+
+/// Read the wanted members from a gzipped tarball, stopping once all were found
+async fn read_tar_gz(reader: impl AsyncRead + Unpin, collect: &mut impl Collect) -> Result<()> {
+    let mut archive = tokio_tar::Archive::new(GzipDecoder::new(BufReader::new(reader)));
+    let mut entries = archive.entries()?;
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let Some(name) = top_level(&path).filter(|name| collect.wants(name)) else {
+            continue;
+        };
+        let mut buf = String::new();
+        entry
+            .read_to_string(&mut buf)
+            .await
+            .with_context(|| format!("failed to read {path}"))?;
+        collect.store(name, buf);
+        if collect.is_complete() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_member_from_path() {
+        let from_path = |path| top_level(path).and_then(Member::from_name);
+        assert_eq!(from_path("foo-1.0/PKG-INFO"), Some(Member::PkgInfo));
+        assert_eq!(from_path("foo-1.0/pyproject.toml"), Some(Member::PyProject));
+        assert_eq!(from_path("foo-1.0/foo.egg-info/PKG-INFO"), None);
+        assert_eq!(from_path("PKG-INFO"), None);
+    }
+
+    #[tokio::test]
+    async fn test_dynamic() {
+        let members = Members {
+            pkg_info: Some(
+                "Metadata-Version: 2.2\nName: foo\nVersion: 1.0\nDynamic: Requires-Dist\n"
+                    .to_owned(),
+            ),
+            pyproject: None,
+        };
+        let (info, warnings) = warnings::collect(async { members.into_info() }).await;
+        assert_eq!(info.unwrap().dynamic, ["Requires-Dist"]);
+        assert_eq!(
+            warnings,
+            [Warning::DynamicMetadata {
+                fields: vec!["Requires-Dist".to_owned()]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_sdist_version() {
+        assert_eq!(
+            sdist_version("foo_bar-1.0.post1.tar.gz"),
+            Some("1.0.post1".parse().unwrap())
+        );
+        assert_eq!(sdist_version("foo-2.0.zip"), Some("2.0".parse().unwrap()));
+        assert_eq!(sdist_version("foo-1.0-py3-none-any.whl"), None);
+    }
+
+    async fn tar_gz(files: &[(&str, &str)]) -> Vec<u8> {
+        use async_compression::tokio::bufread::GzipEncoder;
+
+        let mut builder = tokio_tar::Builder::new(Vec::new());
+        for (path, content) in files {
+            let mut header = tokio_tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .await
+                .unwrap();
+        }
+        let tar = builder.into_inner().await.unwrap();
+        let mut tar_gz = Vec::new();
+        GzipEncoder::new(tar.as_slice())
+            .read_to_end(&mut tar_gz)
+            .await
+            .unwrap();
+        tar_gz
+    }
+
+    #[tokio::test]
+    async fn test_read_tar_gz() {
+        let tar_gz = tar_gz(&[
+            (
+                "foo-1.0/pyproject.toml",
+                "[build-system]\nrequires = [\"hatchling\"]\nbuild-backend = \"hatchling.build\"\n",
+            ),
+            ("foo-1.0/src/foo/__init__.py", ""),
+            (
+                "foo-1.0/PKG-INFO",
+                "Metadata-Version: 2.1\nName: foo\nVersion: 1.0\n",
+            ),
+        ])
+        .await;
+
+        let mut members = Members::default();
+        read_tar_gz(tar_gz.as_slice(), &mut members).await.unwrap();
+        let info = members.into_info().unwrap();
+        assert_eq!(info.metadata.unwrap().get("Name"), Some("foo"));
+        assert_eq!(info.build_backend.as_deref(), Some("hatchling.build"));
+        assert_eq!(info.build_requires, ["hatchling"]);
+    }
+
+    #[tokio::test]
+    async fn test_read_tar_gz_changelog() {
+        let tar_gz = tar_gz(&[
+            ("foo-1.0/docs/changelog.md", "# nested\n"),
+            ("foo-1.0/NEWS.rst", "news\n"),
+            ("foo-1.0/CHANGES.md", "# 1.0\n"),
+            ("foo-1.0/README.md", "# foo\n"),
+            (
+                "foo-1.0/PKG-INFO",
+                "Metadata-Version: 2.1\nName: foo\nVersion: 1.0\n",
+            ),
+            ("foo-1.0/HISTORY.txt", "history\n"),
+        ])
+        .await;
+
+        let mut members = ChangelogMembers::default();
+        read_tar_gz(tar_gz.as_slice(), &mut members).await.unwrap();
+        assert!(members.pkg_info.is_some());
+        assert_eq!(members.changelog, Some((1, "# 1.0\n".to_owned())));
+    }
+}
@@ -0,0 +1,188 @@
+//! Resuming lazy reads whose pre-signed storage URLs expired.
+//!
+//! Private registries list or redirect to storage URLs that are only valid for a while.
+//! When reading a file takes longer, further range requests fail with `403 Forbidden`.
+//! Then the file’s URL is resolved again, from its index if it was listed by one,
+//! and the failed and later requests go to the fresh URL.
+
+use std::sync::{Mutex, PoisonError};
+
+use http::Extensions;
+use reqwest::header::RANGE;
+use reqwest::{Request, Response, StatusCode, Url};
+use reqwest_middleware::{Error, Middleware, Next};
+
+use crate::LazyZipClient;
+
+/// Middleware for one lazily read file, fetched from `url` before redirects
+pub(crate) struct RefreshExpired {
+    client: LazyZipClient,
+    url: Url,
+    /// The URL requests go to instead, once it was resolved again
+    fresh: Mutex<Option<Url>>,
+}
+
+impl RefreshExpired {
+    pub(crate) fn new(client: LazyZipClient, url: Url) -> Self {
+        Self {
+            client,
+            url,
+            fresh: Mutex::new(None),
+        }
+    }
+
+    /// Look the file up again, and follow redirects to where its ranges can be read from
+    async fn resolve(&self) -> eyre::Result<Url> {
+        let url = self.client.refresh_file_url(&self.url).await?;
+        let response = self
+            .client
+            .http()
+            .get(url)
+            .header(RANGE, "bytes=0-0")
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.url().clone())
+    }
+
+    /// Send `req`’s range request to `url` with the full client, so it’s authenticated for that URL
+    async fn send(&self, url: Url, req: &Request) -> reqwest_middleware::Result<Response> {
+        let mut builder = self.client.http().get(url);
+        if let Some(range) = req.headers().get(RANGE) {
+            builder = builder.header(RANGE, range);
+        }
+        builder.send().await
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for RefreshExpired {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let fresh = self
+            .fresh
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let response = match fresh {
+            Some(url) => self.send(url, &req).await?,
+            None => match req.try_clone() {
+                Some(attempt) => next.run(attempt, extensions).await?,
+                None => return next.run(req, extensions).await,
+            },
+        };
+        if response.status() != StatusCode::FORBIDDEN {
+            return Ok(response);
+        }
+        tracing::info!(url = %self.url, "URL expired, resolving it again");
+        let url = self
+            .resolve()
+            .await
+            .map_err(|e| Error::middleware(std::io::Error::other(e)))?;
+        *self.fresh.lock().unwrap_or_else(PoisonError::into_inner) = Some(url.clone());
+        self.send(url, &req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use bytes::Bytes;
+    use http_body_util::Full;
+    use hyper::header::CONTENT_TYPE;
+
+    use super::*;
+    use crate::test_util::{
+        assert_far_entry, far_entry_wheel, file_response, open_far_entry_wheel, serve, status,
+    };
+
+    const FILENAME: &str = "foo-1.0-py3-none-any.whl";
+
+    /// Serve `wheel` under URLs signed with the number of times the project page was served,
+    /// so only the last one listed is valid
+    fn signing_index(
+        wheel: &Bytes,
+        addr: SocketAddr,
+        pages: &AtomicUsize,
+        req: &hyper::Request<hyper::body::Incoming>,
+    ) -> hyper::Response<Full<Bytes>> {
+        if req.uri().path() == "/simple/foo/" {
+            let signature = pages.fetch_add(1, Ordering::SeqCst) + 1;
+            let page = serde_json::json!({
+                "meta": {"api-version": "1.0"},
+                "name": "foo",
+                "files": [{
+                    "filename": FILENAME,
+                    "url": format!("http://{addr}/files/{FILENAME}?signature={signature}"),
+                    "hashes": {},
+                }],
+            });
+            return hyper::Response::builder()
+                .header(CONTENT_TYPE, "application/vnd.pypi.simple.v1+json")
+                .body(Full::new(Bytes::from(page.to_string())))
+                .unwrap();
+        }
+        let valid = format!("signature={}", pages.load(Ordering::SeqCst));
+        if req.uri().query() != Some(valid.as_str()) {
+            return status(StatusCode::FORBIDDEN);
+        }
+        file_response(wheel, req)
+    }
+
+    #[tokio::test]
+    async fn test_refresh_expired() {
+        let wheel = far_entry_wheel();
+        let pages = Arc::new(AtomicUsize::new(0));
+        let server = serve({
+            let pages = pages.clone();
+            move |addr, req| signing_index(&wheel, addr, &pages, req)
+        })
+        .await
+        .unwrap();
+        let client = LazyZipClient::new(
+            reqwest::Client::new(),
+            [format!("http://{}/simple/", server.addr()).parse().unwrap()],
+        );
+
+        let mut zip = open_far_entry_wheel(&client).await;
+        // Some time later, the listed URL expires
+        pages.fetch_add(1, Ordering::SeqCst);
+        assert_far_entry(&mut zip).await;
+        assert_eq!(pages.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_expired_with_host_limit() {
+        let wheel = far_entry_wheel();
+        let pages = Arc::new(AtomicUsize::new(0));
+        let server = serve({
+            let pages = pages.clone();
+            move |addr, req| signing_index(&wheel, addr, &pages, req)
+        })
+        .await
+        .unwrap();
+        // The index is on the same host as the file, whose reader holds the only permit
+        let client = LazyZipClient::new(
+            reqwest::Client::new(),
+            [format!("http://{}/simple/", server.addr()).parse().unwrap()],
+        )
+        .with_max_connections_per_host(1);
+
+        let mut zip = open_far_entry_wheel(&client).await;
+        pages.fetch_add(1, Ordering::SeqCst);
+        tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            assert_far_entry(&mut zip),
+        )
+        .await
+        .expect("refreshing the URL waited for the reader’s own permit");
+        assert_eq!(pages.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,145 @@
+//! Adding signatures to outgoing requests, for artifact gateways requiring e.g. AWS `SigV4` or custom HMAC headers.
+//!
+//! Implement [`RequestSigner`] and add it with [`LazyZipClient::with_signer`](crate::LazyZipClient::with_signer),
+//! or use [`CommandSigner`] to delegate signing to an external program.
+
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+
+use eyre::{Context as _, ContextCompat as _, Result, bail};
+use http::Extensions;
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Error, Middleware, Next};
+
+/// Adds headers authenticating a request right before it’s sent
+#[async_trait::async_trait]
+pub trait RequestSigner: Send + Sync + 'static {
+    async fn sign(&self, req: &mut Request) -> Result<()>;
+}
+
+/// Middleware signing every request with a [`RequestSigner`]
+pub(crate) struct Signing<S>(pub S);
+
+#[async_trait::async_trait]
+impl<S: RequestSigner> Middleware for Signing<S> {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        self.0
+            .sign(&mut req)
+            .await
+            .map_err(|e| Error::middleware(std::io::Error::other(e)))?;
+        next.run(req, extensions).await
+    }
+}
+
+/// Signs requests by running a shell command for each.
+///
+/// The command gets the request’s method and URL in `PYPI_LAZYZIP_METHOD` and `PYPI_LAZYZIP_URL`,
+/// and its headers on stdin as `Name: value` lines.
+/// Every `Name: value` line it prints is added as a header.
+#[derive(Debug, Clone)]
+pub struct CommandSigner {
+    command: String,
+}
+
+impl CommandSigner {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+        }
+    }
+
+    fn run(&self, method: &str, url: &str, headers: &str) -> Result<String> {
+        let (shell, flag) = if cfg!(windows) {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+        let mut child = Command::new(shell)
+            .args([flag, &self.command])
+            .env("PYPI_LAZYZIP_METHOD", method)
+            .env("PYPI_LAZYZIP_URL", url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run signing command {:?}", self.command))?;
+        // A command ignoring its input may exit before reading it
+        let _ = child
+            .stdin
+            .take()
+            .context("stdin is piped")?
+            .write_all(headers.as_bytes());
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            bail!(
+                "Signing command failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        String::from_utf8(output.stdout).context("Signing command printed invalid UTF-8")
+    }
+}
+
+#[async_trait::async_trait]
+impl RequestSigner for CommandSigner {
+    async fn sign(&self, req: &mut Request) -> Result<()> {
+        let method = req.method().to_string();
+        let url = req.url().to_string();
+        let headers = req
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some(format!("{name}: {}\n", value.to_str().ok()?)))
+            .collect::<String>();
+        let signer = self.clone();
+        let output =
+            tokio::task::spawn_blocking(move || signer.run(&method, &url, &headers)).await??;
+        for line in output.lines().filter(|line| !line.trim().is_empty()) {
+            let (name, value) = line
+                .split_once(':')
+                .with_context(|| format!("Signing command printed {line:?}, not a header"))?;
+            req.headers_mut().insert(
+                HeaderName::from_bytes(name.trim().as_bytes())?,
+                HeaderValue::from_str(value.trim())?,
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use reqwest::header::RANGE;
+    use reqwest::{Method, Url};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_command_signer() {
+        let signer = CommandSigner::new(
+            r#"echo "X-Signature: $PYPI_LAZYZIP_METHOD $PYPI_LAZYZIP_URL"; echo "X-Signed: $(cat)""#,
+        );
+        let url = Url::parse("https://example.com/foo.whl").unwrap();
+        let mut req = Request::new(Method::GET, url);
+        req.headers_mut()
+            .insert(RANGE, HeaderValue::from_static("bytes=-10"));
+        signer.sign(&mut req).await.unwrap();
+        assert_eq!(
+            req.headers()["x-signature"],
+            "GET https://example.com/foo.whl"
+        );
+        assert_eq!(req.headers()["x-signed"], "range: bytes=-10");
+
+        let err = CommandSigner::new("exit 3")
+            .sign(&mut req)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Signing command failed"), "{err}");
+    }
+}
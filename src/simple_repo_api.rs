@@ -1,19 +1,272 @@
-use color_eyre::eyre::{Context as _, Error};
+use std::collections::HashMap;
 
+use eyre::{Context as _, Error, Result, bail};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Response, StatusCode, Url};
+use reqwest_middleware::ClientWithMiddleware;
+
+use crate::error::Failure;
 use crate::python_pkg::PackageName;
 
+mod html;
 mod spec;
 
 pub use spec::*;
 
-pub async fn fetch_project(client: &reqwest::Client, name: &PackageName) -> Result<Project, Error> {
-    client
-        .get(format!("https://pypi.org/simple/{name}/"))
-        .header("Accept", "application/vnd.pypi.simple.v1+json")
+/// The simple API of the Python Package Index
+pub const PYPI: &str = "https://pypi.org/simple/";
+
+/// The media type of JSON pages of the simple API, which only carries the major version
+pub const ACCEPT: &str = "application/vnd.pypi.simple.v1+json";
+
+/// Preferring JSON pages, but taking HTML ones from indexes that only have those, like GitLab’s
+pub const ACCEPT_WITH_HTML: &str = "application/vnd.pypi.simple.v1+json, application/vnd.pypi.simple.v1+html;q=0.2, text/html;q=0.01";
+
+/// Only HTML pages, e.g. as TUF metadata signs those (PEP 458)
+pub const ACCEPT_HTML: &str = "application/vnd.pypi.simple.v1+html, text/html;q=0.01";
+
+/// Minimum versions of the simple API, named by the fields they added that we use
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiVersion {
+    /// JSON pages ([PEP 691](https://peps.python.org/pep-0691/))
+    #[cfg_attr(feature = "cli", value(name = "1.0"))]
+    V1_0,
+    /// File sizes ([PEP 700](https://peps.python.org/pep-0700/))
+    #[cfg_attr(feature = "cli", value(name = "1.1"))]
+    V1_1,
+    /// Provenance of files ([PEP 740](https://peps.python.org/pep-0740/))
+    #[cfg_attr(feature = "cli", value(name = "1.3"))]
+    V1_3,
+}
+
+impl ApiVersion {
+    fn minor(self) -> u32 {
+        match self {
+            ApiVersion::V1_0 => 0,
+            ApiVersion::V1_1 => 1,
+            ApiVersion::V1_3 => 3,
+        }
+    }
+}
+
+impl std::fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "1.{}", self.minor())
+    }
+}
+
+/// Fetch a project from the index at `index`, or `None` if the index doesn’t have it.
+/// Fails if the page’s API version isn’t 1.x, or older than `required`.
+///
+/// Pages are at the normalized name, as PEP 503 requires.
+/// Some registries’ URLs are case-sensitive though, so if that’s not found, the name as spelled is tried.
+pub async fn fetch_project(
+    client: &ClientWithMiddleware,
+    index: &Url,
+    name: &PackageName,
+    required: Option<ApiVersion>,
+) -> Result<Option<Project>, Error> {
+    let Some(response) = get_project_page(client, index, name, ACCEPT_WITH_HTML).await? else {
+        return Ok(None);
+    };
+    let project = parse_project(response, name)
+        .await
+        .wrap_err_with(|| Failure::index(format!("Unusable page for {name} on {index}")))?;
+    check_api_version(&project.meta.api_version, required)
+        .wrap_err_with(|| Failure::index(format!("Unusable page for {name} on {index}")))?;
+    Ok(Some(project))
+}
+
+/// Request a project’s page as [`fetch_project`] does, or `None` if the index doesn’t have it
+pub(crate) async fn get_project_page(
+    client: &ClientWithMiddleware,
+    index: &Url,
+    name: &PackageName,
+    accept: &str,
+) -> Result<Option<Response>, Error> {
+    let mut response = client
+        .get(index.join(&format!("{name}/"))?)
+        .header("Accept", accept)
+        .send()
+        .await?;
+    if response.status() == StatusCode::NOT_FOUND && name.original() != name.as_normalized() {
+        tracing::debug!(%name, original = name.original(), "not found, trying the original spelling");
+        response = client
+            .get(index.join(&format!("{}/", name.original()))?)
+            .header("Accept", accept)
+            .send()
+            .await?;
+    }
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(response.error_for_status()?))
+}
+
+/// Parse an HTML project page at `page`, checking its API version like [`fetch_project`]
+#[cfg(feature = "tuf")]
+pub(crate) fn parse_project_html(
+    html: &str,
+    page: &Url,
+    name: &PackageName,
+    required: Option<ApiVersion>,
+) -> Result<Project> {
+    let project = html::parse_project(html, page, &name.to_string())?;
+    check_api_version(&project.meta.api_version, required)?;
+    Ok(project)
+}
+
+/// Fetch the names of all projects on the index at `index`
+pub async fn fetch_root(client: &ClientWithMiddleware, index: &Url) -> Result<Vec<String>, Error> {
+    let response = client
+        .get(index.clone())
+        .header("Accept", ACCEPT_WITH_HTML)
         .send()
         .await?
-        .error_for_status()?
-        .json()
+        .error_for_status()?;
+    let list = if is_html(&response) {
+        response
+            .text()
+            .await
+            .map(|page| html::parse_project_list(&page))
+    } else {
+        response.json::<ProjectList>().await
+    }
+    .context("Failed to parse page")
+    .wrap_err_with(|| Failure::index(format!("Unusable project list on {index}")))?;
+    check_api_version(&list.meta.api_version, None)
+        .wrap_err_with(|| Failure::index(format!("Unusable project list on {index}")))?;
+    Ok(list.projects.into_iter().map(|p| p.name).collect())
+}
+
+/// Parse a project page, which is JSON or HTML depending on its `Content-Type`
+pub(crate) async fn parse_project(response: Response, name: &PackageName) -> Result<Project> {
+    if is_html(&response) {
+        let page = response.url().clone();
+        html::parse_project(&response.text().await?, &page, &name.to_string())
+    } else {
+        response.json().await.context("Failed to parse JSON")
+    }
+}
+
+fn is_html(response: &Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.contains("html"))
+}
+
+/// Check that a page’s `api_version` has the major version we understand
+/// and is at least `required`
+fn check_api_version(api_version: &str, required: Option<ApiVersion>) -> Result<()> {
+    let Some((major, minor)) = api_version
+        .split_once('.')
+        .and_then(|(major, minor)| Some((major.parse::<u32>().ok()?, minor.parse::<u32>().ok()?)))
+    else {
+        bail!("Invalid simple API version {api_version:?}");
+    };
+    if major != 1 {
+        bail!("Simple API version {api_version} is unsupported, only 1.x is");
+    }
+    if let Some(required) = required {
+        if minor < required.minor() {
+            bail!("Simple API version {api_version} is older than the required {required}");
+        }
+        if minor > required.minor() {
+            tracing::debug!(
+                "Simple API version {api_version} is newer than the required {required}"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Hash names allowed in URL fragments, see [the spec](https://packaging.python.org/en/latest/specifications/simple-repository-api/#base-html-api)
+const HASH_NAMES: &[&str] = &[
+    "md5", "sha1", "sha224", "sha256", "sha384", "sha512", "blake2b", "blake2s",
+];
+
+/// Split a `#<hashname>=<hashvalue>` fragment off a file URL,
+/// as used by simple HTML indexes and direct URLs.
+/// Other fragments like `#egg=…` are left alone.
+pub fn split_hash_fragment(mut url: Url) -> (Url, HashMap<String, String>) {
+    let hashes = url
+        .fragment()
+        .and_then(|fragment| fragment.split_once('='))
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.to_owned()))
+        .filter(|(name, _)| HASH_NAMES.contains(&name.as_str()))
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    if !hashes.is_empty() {
+        url.set_fragment(None);
+    }
+    (url, hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_api_version() {
+        assert!(check_api_version("1.0", None).is_ok());
+        assert!(check_api_version("1.4", Some(ApiVersion::V1_3)).is_ok());
+        let err = check_api_version("1.1", Some(ApiVersion::V1_3)).unwrap_err();
+        assert!(
+            err.to_string().contains("older than the required 1.3"),
+            "{err}"
+        );
+        assert!(check_api_version("2.0", None).is_err());
+        assert!(check_api_version("latest", None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_project_original_spelling() {
+        use std::str::FromStr as _;
+
+        use hyper::StatusCode;
+
+        use crate::test_util::{serve, status};
+
+        // A registry only serving the page at the name as uploaded
+        let server = serve(|_, req| {
+            if req.uri().path() != "/simple/Foo_Bar/" {
+                return status(StatusCode::NOT_FOUND);
+            }
+            let page = r#"{"meta": {"api-version": "1.0"}, "name": "Foo_Bar", "files": []}"#;
+            hyper::Response::builder()
+                .header(CONTENT_TYPE, "application/vnd.pypi.simple.v1+json")
+                .body(page.into())
+                .unwrap()
+        })
         .await
-        .context("Failed to parse JSON")
+        .unwrap();
+        let index = Url::parse(&format!("http://{}/simple/", server.addr())).unwrap();
+        let http = reqwest_middleware::ClientBuilder::new(reqwest::Client::new()).build();
+        let fetch = |name: &str| {
+            let name = PackageName::from_str(name).unwrap();
+            let (http, index) = (http.clone(), index.clone());
+            async move { fetch_project(&http, &index, &name, None).await.unwrap() }
+        };
+        assert!(fetch("Foo_Bar").await.is_some());
+        assert!(fetch("foo-bar").await.is_none());
+    }
+
+    #[test]
+    fn test_split_hash_fragment() {
+        let url = Url::parse("https://example.com/foo-1.0-py3-none-any.whl#sha256=abc").unwrap();
+        let (url, hashes) = split_hash_fragment(url);
+        assert_eq!(url.as_str(), "https://example.com/foo-1.0-py3-none-any.whl");
+        assert_eq!(
+            hashes,
+            HashMap::from([("sha256".to_owned(), "abc".to_owned())])
+        );
+
+        let url = Url::parse("https://example.com/foo.whl#egg=foo").unwrap();
+        let (url, hashes) = split_hash_fragment(url);
+        assert_eq!(url.fragment(), Some("egg=foo"));
+        assert!(hashes.is_empty());
+    }
 }
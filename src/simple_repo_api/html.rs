@@ -0,0 +1,230 @@
+//! HTML pages of the simple API ([PEP 503](https://peps.python.org/pep-0503/)),
+//! which some registries, like GitLab’s, serve instead of JSON ones.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use eyre::{Result, WrapErr as _};
+use regex::Regex;
+use reqwest::Url;
+
+use super::{
+    CoreMetadata, File, Meta, Project, ProjectList, ProjectListEntry, ProjectStatus, Status,
+    Yanking, split_hash_fragment,
+};
+
+static ANCHOR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<a\s([^>]*)>(.*?)</a\s*>").unwrap());
+static META_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<meta\s([^>]*)>").unwrap());
+static ATTRIBUTE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"([\w:-]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#).unwrap()
+});
+
+/// Parse a project page fetched from `page`, against which relative links are resolved
+pub(crate) fn parse_project(html: &str, page: &Url, name: &str) -> Result<Project> {
+    let metas = metas(html);
+    let files = anchors(html)
+        .map(|(attributes, text)| {
+            let href = attributes.get("href").map_or("", String::as_str);
+            let url = page
+                .join(href)
+                .wrap_err_with(|| format!("Invalid link {href:?}"))?;
+            let (url, hashes) = split_hash_fragment(url);
+            let yanked = match attributes.get("data-yanked") {
+                Some(reason) if reason.is_empty() => Yanking::Yanked(None),
+                Some(reason) => Yanking::Yanked(Some(reason.clone())),
+                None => Yanking::NotYanked,
+            };
+            // The metadata’s hashes aren’t used, only whether it’s there
+            let core_metadata = match attributes
+                .get("data-core-metadata")
+                .or_else(|| attributes.get("data-dist-info-metadata"))
+            {
+                Some(value) if value != "false" => CoreMetadata::Present(Box::default()),
+                _ => CoreMetadata::Absent,
+            };
+            Ok(File {
+                filename: text,
+                url,
+                hashes,
+                requires_python: attributes.get("data-requires-python").cloned(),
+                core_metadata,
+                gpg_sig: attributes
+                    .get("data-gpg-sig")
+                    .is_some_and(|sig| sig == "true"),
+                yanked,
+                size: None,
+                upload_time: None,
+                index: None,
+            })
+        })
+        .collect::<Result<_>>()?;
+    let status = match metas.get("pypi:project-status").map(String::as_str) {
+        Some("archived") => Status::Archived,
+        Some("quarantined") => Status::Quarantined,
+        Some("deprecated") => Status::Deprecated,
+        _ => Status::Active,
+    };
+    Ok(Project {
+        meta: meta(&metas),
+        name: name.to_owned(),
+        files,
+        project_status: ProjectStatus {
+            status,
+            reason: metas.get("pypi:project-status-reason").cloned(),
+        },
+    })
+}
+
+/// Parse the root page listing all projects by name
+pub(crate) fn parse_project_list(html: &str) -> ProjectList {
+    ProjectList {
+        meta: meta(&metas(html)),
+        projects: anchors(html)
+            .map(|(_, name)| ProjectListEntry { name })
+            .collect(),
+    }
+}
+
+/// Pages without a version are from before it was added, i.e. 1.0
+fn meta(metas: &HashMap<String, String>) -> Meta {
+    Meta {
+        api_version: metas
+            .get("pypi:repository-version")
+            .cloned()
+            .unwrap_or_else(|| "1.0".to_owned()),
+    }
+}
+
+/// `<meta name="…" content="…">` tags
+fn metas(html: &str) -> HashMap<String, String> {
+    META_RE
+        .captures_iter(html)
+        .filter_map(|captures| {
+            let mut attributes = attributes(&captures[1]);
+            Some((attributes.remove("name")?, attributes.remove("content")?))
+        })
+        .collect()
+}
+
+/// Attributes and text of links
+fn anchors(html: &str) -> impl Iterator<Item = (HashMap<String, String>, String)> + '_ {
+    ANCHOR_RE.captures_iter(html).map(|captures| {
+        (
+            attributes(&captures[1]),
+            unescape(captures[2].trim()).into_owned(),
+        )
+    })
+}
+
+/// Attributes of a tag by lowercase name. Attributes without a value, like `data-yanked`, are empty.
+fn attributes(tag: &str) -> HashMap<String, String> {
+    ATTRIBUTE_RE
+        .captures_iter(tag)
+        .map(|captures| {
+            let value = (2..=4)
+                .find_map(|i| captures.get(i))
+                .map_or("", |value| value.as_str());
+            (
+                captures[1].to_ascii_lowercase(),
+                unescape(value).into_owned(),
+            )
+        })
+        .collect()
+}
+
+/// Decode character references, which only the ones for markup and numeric ones are likely to be
+fn unescape(text: &str) -> std::borrow::Cow<'_, str> {
+    if !text.contains('&') {
+        return text.into();
+    }
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                reference => {
+                    let number = reference.strip_prefix('#')?;
+                    let code = match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end + 1))
+        });
+        let (c, len) = decoded.unwrap_or(('&', 1));
+        unescaped.push(c);
+        rest = &rest[len..];
+    }
+    unescaped.push_str(rest);
+    unescaped.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_project() {
+        // Like GitLab’s, with links to a download endpoint on the same host
+        let html = r#"<!DOCTYPE html>
+<html><head><title>Links for foo</title></head><body>
+<h1>Links for foo</h1>
+<a href="../../files/abc/foo-1.0-py3-none-any.whl#sha256=abc" data-requires-python="&gt;=3.9">foo-1.0-py3-none-any.whl</a><br>
+<a href='https://files.example.com/foo-1.1.tar.gz?token=a&amp;b' data-yanked="broken" data-dist-info-metadata="sha256=def">foo-1.1.tar.gz</a><br>
+<A HREF="foo-1.2-py3-none-any.whl" data-yanked data-core-metadata=true>
+  foo-1.2-py3-none-any.whl
+</A>
+</body></html>"#;
+        let page =
+            Url::parse("https://gitlab.example.com/api/v4/projects/1/packages/pypi/simple/foo/")
+                .unwrap();
+        let project = parse_project(html, &page, "foo").unwrap();
+        assert_eq!(project.meta.api_version, "1.0");
+        assert_eq!(project.project_status.status, Status::Active);
+        let [whl, sdist, yanked] = &project.files[..] else {
+            panic!("{:?}", project.files);
+        };
+        assert_eq!(whl.filename, "foo-1.0-py3-none-any.whl");
+        assert_eq!(
+            whl.url.as_str(),
+            "https://gitlab.example.com/api/v4/projects/1/packages/pypi/files/abc/foo-1.0-py3-none-any.whl"
+        );
+        assert_eq!(whl.hashes["sha256"], "abc");
+        assert_eq!(whl.requires_python.as_deref(), Some(">=3.9"));
+        assert_eq!(whl.yanked, Yanking::NotYanked);
+        assert_eq!(whl.core_metadata, CoreMetadata::Absent);
+        assert_eq!(
+            sdist.url.as_str(),
+            "https://files.example.com/foo-1.1.tar.gz?token=a&b"
+        );
+        assert_eq!(sdist.yanked, Yanking::Yanked(Some("broken".to_owned())));
+        assert_ne!(sdist.core_metadata, CoreMetadata::Absent);
+        assert_eq!(yanked.filename, "foo-1.2-py3-none-any.whl");
+        assert_eq!(yanked.yanked, Yanking::Yanked(None));
+    }
+
+    #[test]
+    fn test_parse_project_list() {
+        let html = r#"<html><head><meta name="pypi:repository-version" content="1.1"></head>
+<body><a href="/simple/foo/">foo</a><a href="/simple/bar-baz/">bar-baz</a></body></html>"#;
+        let list = parse_project_list(html);
+        assert_eq!(list.meta.api_version, "1.1");
+        let names = list
+            .projects
+            .into_iter()
+            .map(|p| p.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["foo", "bar-baz"]);
+        assert_eq!(unescape("a &amp;&#62;&#x3C; &b"), "a &>< &b");
+    }
+}
@@ -1,25 +1,77 @@
 use std::collections::HashMap;
 
-use color_eyre::eyre::Result;
 use either::Either;
+use eyre::Result;
 use reqwest::Url;
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_with::{DisplayFromStr, serde_as};
 
 /// A project on the simple API.
 /// See [spec](https://packaging.python.org/en/latest/specifications/simple-repository-api/#project-detail).
 #[allow(dead_code)]
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct Project {
     pub meta: Meta,
     pub name: String,
     pub files: Vec<File>,
+    /// From API version 1.4 on ([PEP 792](https://peps.python.org/pep-0792/))
+    #[serde(default)]
+    pub project_status: ProjectStatus,
+}
+
+/// A project’s status and why it was set.
+/// See [spec](https://packaging.python.org/en/latest/specifications/project-status-markers/).
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProjectStatus {
+    #[serde(default)]
+    pub status: Status,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// Won’t receive updates
+    Archived,
+    /// Considered unsafe, e.g. malware, and its files aren’t offered
+    Quarantined,
+    /// Shouldn’t be used anymore
+    Deprecated,
+    /// Unknown statuses are treated as active, as the spec requires
+    #[default]
+    #[serde(other)]
+    Active,
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Status::Archived => "archived",
+            Status::Quarantined => "quarantined",
+            Status::Deprecated => "deprecated",
+            Status::Active => "active",
+        })
+    }
+}
+
+/// The root listing of all projects on the simple API.
+/// See [spec](https://packaging.python.org/en/latest/specifications/simple-repository-api/#json-serialization).
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone)]
+pub struct ProjectList {
+    pub meta: Meta,
+    pub projects: Vec<ProjectListEntry>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ProjectListEntry {
+    pub name: String,
 }
 
 /// Project metadata on the simple API.
 #[allow(dead_code)]
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct Meta {
     pub api_version: String,
@@ -28,7 +80,7 @@ pub struct Meta {
 /// A file on the simple API.
 #[allow(dead_code)]
 #[serde_as]
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct File {
     pub filename: String,
@@ -42,6 +94,15 @@ pub struct File {
     pub gpg_sig: bool,
     #[serde(default)]
     pub yanked: Yanking,
+    /// Size in bytes, from API version 1.1 on ([PEP 700](https://peps.python.org/pep-0700/))
+    pub size: Option<u64>,
+    /// When the file was uploaded, from API version 1.1 on ([PEP 700](https://peps.python.org/pep-0700/))
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub upload_time: Option<jiff::Timestamp>,
+    /// The index that listed the file
+    #[serde(skip)]
+    pub index: Option<Url>,
 }
 
 /// Indicator if the (wheel) file has core metadata.
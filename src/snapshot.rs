@@ -0,0 +1,116 @@
+//! Crawling many projects to produce bulk datasets, e.g. the top-level modules of all popular projects.
+//!
+//! Projects are started at a limited rate to be polite to the index,
+//! and a failing query is recorded in the project’s results instead of aborting the crawl.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use eyre::Result;
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tokio::time::{Interval, MissedTickBehavior};
+
+use crate::python_pkg::{Dependency, PackageName};
+use crate::{LazyZipClient, PkgLoc, wheel};
+
+/// What to find out about every project
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Query {
+    /// Top-level modules from `top_level.txt`
+    TopLevel,
+    /// Core metadata
+    Metadata,
+    /// Requirements (`Requires-Dist`)
+    Requires,
+    /// Files in the wheel
+    List,
+}
+
+/// Limits how many projects are started per second
+#[derive(Debug)]
+pub struct Pacer {
+    interval: Mutex<Interval>,
+}
+
+impl Pacer {
+    pub fn new(per_second: f64) -> Self {
+        let mut interval = tokio::time::interval(Duration::from_secs_f64(1. / per_second));
+        // Don’t catch up after a slow stretch with a burst
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Self {
+            interval: Mutex::new(interval),
+        }
+    }
+
+    /// Wait for the next turn
+    pub async fn wait(&self) {
+        self.interval.lock().await.tick().await;
+    }
+}
+
+/// Run all `queries` for a project, sharing the fetched parts of its wheel.
+/// A failed query’s result is `{"error": "..."}`.
+pub async fn query(
+    client: &LazyZipClient,
+    name: PackageName,
+    queries: &[Query],
+) -> (PackageName, BTreeMap<Query, serde_json::Value>) {
+    let pkg_loc = PkgLoc::Dependency(Dependency::new(name.clone(), None));
+    let name_ref = &name;
+    // Run concurrently, so later queries reuse the zip reader of the first
+    let results = futures::future::join_all(queries.iter().map(|&query| {
+        let pkg_loc = pkg_loc.clone();
+        async move {
+            let result = run(client, pkg_loc, query).await.unwrap_or_else(|e| {
+                tracing::warn!(name = %name_ref, ?query, "query failed: {e:#}");
+                serde_json::json!({ "error": format!("{e:#}") })
+            });
+            (query, result)
+        }
+    }))
+    .await;
+    (name, results.into_iter().collect())
+}
+
+async fn run(client: &LazyZipClient, pkg_loc: PkgLoc, query: Query) -> Result<serde_json::Value> {
+    Ok(match query {
+        Query::TopLevel => {
+            let (_, content) = wheel::extract(client, pkg_loc, wheel::is_top_level).await?;
+            serde_json::to_value(wheel::top_level_names(
+                content.as_deref().unwrap_or_default(),
+            ))?
+        }
+        Query::Metadata => serde_json::to_value(wheel::pkg_metadata(client, pkg_loc).await?.1)?,
+        Query::Requires => {
+            let (_, metadata) = wheel::pkg_metadata_headers(client, pkg_loc).await?;
+            serde_json::to_value(metadata.get_all("Requires-Dist").collect::<Vec<_>>())?
+        }
+        Query::List => serde_json::to_value(wheel::list_entries(client, pkg_loc).await?.1)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockIndex, MockWheel};
+
+    #[tokio::test]
+    async fn test_query() {
+        let index = MockIndex::start([MockWheel::new("foo", "1.0")
+            .requires("bar")
+            .file("foo-1.0.dist-info/top_level.txt", "foo\n")])
+        .await
+        .unwrap();
+        let client = index.client();
+        let queries = [Query::TopLevel, Query::Requires];
+        let (_, results) = query(&client, "foo".parse().unwrap(), &queries).await;
+        assert_eq!(results[&Query::TopLevel], serde_json::json!(["foo"]));
+        assert_eq!(results[&Query::Requires], serde_json::json!(["bar"]));
+
+        let (_, results) = query(&client, "missing".parse().unwrap(), &queries).await;
+        assert!(results[&Query::TopLevel].get("error").is_some());
+    }
+}
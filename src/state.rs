@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead as _, Write as _};
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{Context as _, Result, bail};
+use pypi_lazyzip::PkgLoc;
+use pypi_lazyzip::blob_store::write_atomic;
+use serde::{Deserialize, Serialize};
+
+/// Progress of a batch run, appended to after every completed package so it can be resumed.
+///
+/// The file holds JSON lines: a [`Header`] naming the command, then one [`Entry`] per completed package.
+/// Appending keeps saving cheap for thousands of packages.
+/// Resuming compacts the file, dropping a last line that was cut off by an interrupted run.
+#[derive(Debug)]
+pub struct RunState {
+    file: File,
+    path: PathBuf,
+    /// Results by package location as given on the command line
+    completed: BTreeMap<String, Completed>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Completed {
+    pub name: String,
+    pub result: serde_json::Value,
+}
+
+/// The first line of a state file
+#[derive(Serialize, Deserialize)]
+struct Header {
+    /// The subcommand producing the results
+    command: String,
+    /// Results in state files from before they were appended as lines
+    #[serde(default, skip_serializing)]
+    completed: BTreeMap<String, Completed>,
+}
+
+/// A line recording a completed package
+#[derive(Deserialize)]
+struct Entry {
+    pkg_loc: String,
+    #[serde(flatten)]
+    completed: Completed,
+}
+
+impl RunState {
+    /// Start a new run, or continue the one recorded in `path` if `resume` is set.
+    /// Fails instead of replacing the state of an earlier run without `resume`.
+    pub fn open(path: &Path, command: &str, resume: bool) -> Result<Self> {
+        let exists = path.exists();
+        if exists && !resume {
+            bail!(
+                "State file {} exists, pass `--resume` to continue its run or remove it to start over",
+                path.display(),
+            );
+        }
+        let completed = if exists {
+            Self::load(path, command)?
+        } else {
+            BTreeMap::new()
+        };
+        if !completed.is_empty() {
+            tracing::info!(completed = completed.len(), "resuming run");
+        }
+        let mut content = serde_json::to_vec(&Header {
+            command: command.to_owned(),
+            completed: BTreeMap::new(),
+        })?;
+        content.push(b'\n');
+        for (pkg_loc, completed) in &completed {
+            serde_json::to_writer(&mut content, &EntryRef { pkg_loc, completed })?;
+            content.push(b'\n');
+        }
+        write_atomic(path, &content).context("Failed to save state")?;
+        let file = File::options()
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open state file {}", path.display()))?;
+        Ok(Self {
+            file,
+            path: path.to_owned(),
+            completed,
+        })
+    }
+
+    /// Read the completed packages of an earlier run of `command`
+    fn load(path: &Path, command: &str) -> Result<BTreeMap<String, Completed>> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open state file {}", path.display()))?;
+        let mut lines = std::io::BufReader::new(file).lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+        let header = serde_json::from_str::<Header>(&header)
+            .with_context(|| format!("Failed to parse state file {}", path.display()))?;
+        if header.command != command {
+            bail!(
+                "State file {} is from a `{}` run, not `{command}`",
+                path.display(),
+                header.command,
+            );
+        }
+        let mut completed = header.completed;
+        let mut lines = lines.peekable();
+        while let Some(line) = lines.next() {
+            let line = line?;
+            match serde_json::from_str::<Entry>(&line) {
+                Ok(entry) => {
+                    completed.insert(entry.pkg_loc, entry.completed);
+                }
+                // The run was interrupted while appending it
+                Err(e) if lines.peek().is_none() => {
+                    tracing::warn!("Ignoring incomplete last line of {}: {e}", path.display());
+                }
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to parse state file {}", path.display()));
+                }
+            }
+        }
+        Ok(completed)
+    }
+
+    pub fn is_completed(&self, pkg_loc: &PkgLoc) -> bool {
+        self.completed.contains_key(&pkg_loc.to_string())
+    }
+
+    /// Record a result, appending it to the state file
+    pub fn complete(&mut self, pkg_loc: String, completed: Completed) -> Result<()> {
+        let mut line = serde_json::to_vec(&EntryRef {
+            pkg_loc: &pkg_loc,
+            completed: &completed,
+        })?;
+        line.push(b'\n');
+        self.file
+            .write_all(&line)
+            .with_context(|| format!("Failed to save state to {}", self.path.display()))?;
+        self.completed.insert(pkg_loc, completed);
+        Ok(())
+    }
+
+    pub fn into_results(self) -> impl Iterator<Item = Completed> {
+        self.completed.into_values()
+    }
+}
+
+/// An [`Entry`] to write, without copying the result
+#[derive(Serialize)]
+struct EntryRef<'a> {
+    pkg_loc: &'a str,
+    #[serde(flatten)]
+    completed: &'a Completed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "pypi-lazyzip-state-{name}-{}.jsonl",
+            std::process::id()
+        ))
+    }
+
+    fn completed(name: &str) -> Completed {
+        Completed {
+            name: name.to_owned(),
+            result: serde_json::json!([name]),
+        }
+    }
+
+    fn names(state: RunState) -> Vec<String> {
+        state.into_results().map(|c| c.name).collect()
+    }
+
+    #[test]
+    fn test_resume() {
+        let path = state_path("resume");
+        let mut state = RunState::open(&path, "top-level", false).unwrap();
+        state.complete("foo".to_owned(), completed("foo")).unwrap();
+        state.complete("bar".to_owned(), completed("bar")).unwrap();
+        state.complete("foo".to_owned(), completed("foo")).unwrap();
+        drop(state);
+        let err = RunState::open(&path, "top-level", false).unwrap_err();
+        assert!(err.to_string().contains("--resume"), "{err}");
+
+        // An interrupted run leaves a truncated last line
+        let mut file = File::options().append(true).open(&path).unwrap();
+        file.write_all(br#"{"pkg_loc": "baz", "na"#).unwrap();
+        drop(file);
+        let state = RunState::open(&path, "top-level", true).unwrap();
+        assert!(state.is_completed(&PkgLoc::Dependency("foo".parse().unwrap())));
+        assert!(!state.is_completed(&PkgLoc::Dependency("baz".parse().unwrap())));
+        assert_eq!(names(state), ["bar", "foo"]);
+        // Resuming compacts the file to the header and one line per package
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 3, "{content}");
+        assert!(content.ends_with('\n'));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_other_command() {
+        let path = state_path("other-command");
+        RunState::open(&path, "metadata", false).unwrap();
+        let err = RunState::open(&path, "top-level", true).unwrap_err();
+        assert!(err.to_string().contains("from a `metadata` run"), "{err}");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_line() {
+        let path = state_path("corrupt");
+        let content = [
+            r#"{"command": "top-level"}"#,
+            r#"{"pkg_loc": "foo", "na"#,
+            r#"{"pkg_loc": "bar", "name": "bar", "result": ["bar"]}"#,
+        ];
+        std::fs::write(&path, content.join("\n")).unwrap();
+        let err = RunState::open(&path, "top-level", true).unwrap_err();
+        assert!(err.to_string().contains("Failed to parse"), "{err}");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_legacy_header() {
+        let path = state_path("legacy");
+        let header = serde_json::json!({
+            "command": "top-level",
+            "completed": {"foo": {"name": "foo", "result": ["foo"]}},
+        });
+        let entry = r#"{"pkg_loc": "bar", "name": "bar", "result": ["bar"]}"#;
+        std::fs::write(&path, format!("{header}\n{entry}\n")).unwrap();
+        let state = RunState::open(&path, "top-level", true).unwrap();
+        assert_eq!(names(state), ["bar", "foo"]);
+        // The results move from the header to lines of their own
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3, "{content}");
+        assert_eq!(lines[0], r#"{"command":"top-level"}"#);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,199 @@
+//! Suggesting existing project names when a project isn’t found, from the indexes’ root listings.
+//!
+//! Root listings are large (hundreds of thousands of names on the Python Package Index),
+//! so they’re fetched at most once per run and kept in the cache directory for a day.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+
+use eyre::{Context as _, Result};
+use reqwest::Url;
+use reqwest_middleware::ClientWithMiddleware;
+use sha2::{Digest as _, Sha256};
+use tokio::sync::OnceCell;
+
+use crate::cache_integrity::{read_checked, write_checked};
+use crate::hashes::hex;
+use crate::python_pkg::PackageName;
+use crate::result_cache::user_cache_dir;
+use crate::simple_repo_api;
+
+/// How long cached root listings are used before they’re fetched again
+const MAX_AGE: Duration = Duration::from_hours(24);
+
+/// How many names to suggest at most
+const MAX_SUGGESTIONS: usize = 3;
+
+#[derive(Debug, Default)]
+pub struct NameSuggester {
+    /// Where to cache root listings, not cached if unset
+    dir: Option<PathBuf>,
+    /// The normalized names of indexes’ projects, fetched once per index
+    roots: Mutex<HashMap<Url, Arc<OnceCell<Vec<String>>>>>,
+}
+
+impl NameSuggester {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            dir,
+            roots: Mutex::default(),
+        }
+    }
+
+    /// Caching root listings in `index-roots` in the user’s cache directory, if there is one
+    pub fn in_user_cache() -> Self {
+        Self::new(user_cache_dir().map(|dir| dir.join("index-roots")))
+    }
+
+    /// Names of projects on `indexes` closest to `name`, best first.
+    /// Failures to get root listings are logged and yield no suggestions.
+    pub async fn suggest(
+        &self,
+        http: &ClientWithMiddleware,
+        indexes: &[Url],
+        name: &PackageName,
+    ) -> Vec<String> {
+        let mut names = Vec::new();
+        for index in indexes {
+            let cell = self.listing(http, index).await;
+            let root = cell.get().map_or(&[][..], Vec::as_slice);
+            for candidate in closest(root, name) {
+                if !names.contains(&candidate) {
+                    names.push(candidate);
+                }
+            }
+        }
+        names.truncate(MAX_SUGGESTIONS);
+        names
+    }
+
+    /// Names of projects on `index` only differing from `name` in separators, like `foobar` for `foo-bar`.
+    /// They’re easily confused with `name`, e.g. when an extra index offers one.
+    pub async fn near_misses(
+        &self,
+        http: &ClientWithMiddleware,
+        index: &Url,
+        name: &PackageName,
+    ) -> Vec<String> {
+        let cell = self.listing(http, index).await;
+        let root = cell.get().map_or(&[][..], Vec::as_slice);
+        let wanted = name.to_string();
+        root.iter()
+            .filter(|candidate| **candidate != wanted && squash(candidate) == squash(&wanted))
+            .cloned()
+            .collect()
+    }
+
+    /// The root listing of `index`, fetched once. Failures are logged and yield no names.
+    async fn listing(
+        &self,
+        http: &ClientWithMiddleware,
+        index: &Url,
+    ) -> Arc<OnceCell<Vec<String>>> {
+        let cell = self
+            .roots
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(index.clone())
+            .or_default()
+            .clone();
+        cell.get_or_init(|| async {
+            self.root(http, index).await.unwrap_or_else(|e| {
+                tracing::warn!("Failed to list projects on {index}: {e:#}");
+                Vec::new()
+            })
+        })
+        .await;
+        cell
+    }
+
+    /// The normalized project names on `index`, from the cache if recent enough
+    async fn root(&self, http: &ClientWithMiddleware, index: &Url) -> Result<Vec<String>> {
+        let path = self.dir.as_ref().map(|dir| {
+            dir.join(hex(&Sha256::digest(index.as_str())))
+                .with_extension("json")
+        });
+        if let (Some(dir), Some(path)) = (&self.dir, &path)
+            && let Ok(modified) = std::fs::metadata(path).and_then(|m| m.modified())
+            && SystemTime::now()
+                .duration_since(modified)
+                .is_ok_and(|age| age < MAX_AGE)
+            && let Some(cached) = read_checked(dir, path)
+            && let Ok(names) = serde_json::from_slice(&cached)
+        {
+            return Ok(names);
+        }
+        let names = simple_repo_api::fetch_root(http, index)
+            .await?
+            .into_iter()
+            .filter_map(|name| name.parse::<PackageName>().ok())
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        if let Some(path) = &path {
+            write_checked(path, &serde_json::to_vec(&names)?)
+                .context("Failed to cache the project listing")?;
+        }
+        Ok(names)
+    }
+}
+
+/// The names closest to `name`: ones only differing in separators first, then ones with few typos
+fn closest(names: &[String], name: &PackageName) -> Vec<String> {
+    let wanted = squash(&name.to_string());
+    let max_distance = if wanted.len() < 5 { 1 } else { 2 };
+    let mut candidates = names
+        .iter()
+        .map(|candidate| (levenshtein(&squash(candidate), &wanted), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect::<Vec<_>>();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+/// A normalized name without separators
+fn squash(name: &str) -> String {
+    name.replace('-', "")
+}
+
+/// The number of single character edits turning `a` into `b`
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closest() {
+        let names = ["foo-bar", "foobaz", "requests", "unrelated"].map(str::to_owned);
+        let name = |n: &str| n.parse().unwrap();
+        assert_eq!(closest(&names, &name("foobar")), ["foo-bar", "foobaz"]);
+        assert_eq!(closest(&names, &name("reqeusts")), ["requests"]);
+        assert_eq!(closest(&names, &name("xyz")), [] as [&str; 0]);
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("abc", "abc"), 0);
+    }
+}
@@ -0,0 +1,226 @@
+//! Minimal templates for rendering JSON results as text, for `--template`.
+//!
+//! `{{ path | filter | … }}` is replaced by the value at `path`, e.g. `name` or `urls.0`,
+//! after passing it through the filters. Missing values render as nothing.
+//!
+//! Filters are `join("sep")`, `lower`, `upper`, `length`, `first`, `last`, and `default("text")`.
+//! Strings render as is, `null` as nothing, lists as comma-separated items, and objects as JSON.
+
+use std::fmt;
+use std::str::FromStr;
+
+use eyre::{Context as _, ContextCompat as _, Error, Result, bail};
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Expr {
+        path: Vec<String>,
+        filters: Vec<Filter>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Filter {
+    Join(String),
+    Lower,
+    Upper,
+    Length,
+    First,
+    Last,
+    Default(String),
+}
+
+impl FromStr for Template {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_owned()));
+            }
+            let end = rest[start..].find("}}").with_context(|| {
+                format!("unclosed `{{{{` at byte {}", s.len() - rest.len() + start)
+            })?;
+            let expr = &rest[start + 2..start + end];
+            parts.push(parse_expr(expr).with_context(|| format!("invalid expression `{expr}`"))?);
+            rest = &rest[start + end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_owned()));
+        }
+        Ok(Self { parts })
+    }
+}
+
+fn parse_expr(expr: &str) -> Result<Part> {
+    let mut segments = expr.split('|').map(str::trim);
+    let path = segments.next().unwrap_or_default();
+    if path.is_empty() {
+        bail!("missing field name");
+    }
+    let path = path.split('.').map(ToOwned::to_owned).collect();
+    let filters = segments.map(parse_filter).collect::<Result<_>>()?;
+    Ok(Part::Expr { path, filters })
+}
+
+fn parse_filter(filter: &str) -> Result<Filter> {
+    let (name, arg) = match filter.split_once('(') {
+        Some((name, arg)) => {
+            let arg = arg
+                .strip_suffix(')')
+                .context("expected `)` after filter argument")?
+                .trim();
+            (name.trim(), Some(parse_string(arg)?))
+        }
+        None => (filter, None),
+    };
+    Ok(match (name, arg) {
+        ("join", Some(sep)) => Filter::Join(sep),
+        ("join", None) => Filter::Join(", ".to_owned()),
+        ("default", Some(text)) => Filter::Default(text),
+        ("lower", None) => Filter::Lower,
+        ("upper", None) => Filter::Upper,
+        ("length", None) => Filter::Length,
+        ("first", None) => Filter::First,
+        ("last", None) => Filter::Last,
+        (name, _) => bail!("unknown filter `{filter}` or wrong arguments for `{name}`"),
+    })
+}
+
+/// A string literal in single or double quotes, without escapes
+fn parse_string(arg: &str) -> Result<String> {
+    let quote = arg.chars().next().filter(|c| matches!(c, '"' | '\''));
+    quote
+        .and_then(|q| arg[1..].strip_suffix(q))
+        .map(ToOwned::to_owned)
+        .with_context(|| format!("expected a quoted string, got `{arg}`"))
+}
+
+impl Template {
+    /// Render the template with the fields of `context`
+    pub fn render(&self, context: &Value) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Expr { path, filters } => {
+                    let value = path
+                        .iter()
+                        .try_fold(context, |value, key| match value {
+                            Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+                            value => value.get(key),
+                        })
+                        .cloned()
+                        .unwrap_or_default();
+                    let value = filters.iter().fold(value, |value, f| f.apply(value));
+                    out.push_str(&Display(&value).to_string());
+                }
+            }
+        }
+        out
+    }
+}
+
+impl Filter {
+    fn apply(&self, value: Value) -> Value {
+        match (self, value) {
+            (Self::Join(sep), Value::Array(items)) => Value::String(
+                items
+                    .iter()
+                    .map(|v| Display(v).to_string())
+                    .collect::<Vec<_>>()
+                    .join(sep),
+            ),
+            (Self::Lower, Value::String(s)) => Value::String(s.to_lowercase()),
+            (Self::Upper, Value::String(s)) => Value::String(s.to_uppercase()),
+            (Self::Length, Value::Array(items)) => items.len().into(),
+            (Self::Length, Value::String(s)) => s.chars().count().into(),
+            (Self::Length, Value::Object(map)) => map.len().into(),
+            (Self::First, Value::Array(items)) => items.into_iter().next().unwrap_or_default(),
+            (Self::Last, Value::Array(items)) => items.into_iter().next_back().unwrap_or_default(),
+            (Self::Default(text), Value::Null) => Value::String(text.clone()),
+            (Self::Default(text), Value::String(s)) if s.is_empty() => Value::String(text.clone()),
+            (Self::Default(text), Value::Array(items)) if items.is_empty() => {
+                Value::String(text.clone())
+            }
+            (_, value) => value,
+        }
+    }
+}
+
+/// Renders a value as text instead of JSON
+struct Display<'a>(&'a Value);
+
+impl fmt::Display for Display<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Value::Null => Ok(()),
+            Value::String(s) => f.write_str(s),
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    Display(item).fmt(f)?;
+                }
+                Ok(())
+            }
+            value => write!(f, "{value}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let template: Template = r#"{{name}}=={{ version }} -> {{top_level | join(",")}}"#
+            .parse()
+            .unwrap();
+        let context = json!({"name": "foo", "version": "1.0", "top_level": ["foo", "_foo"]});
+        assert_eq!(template.render(&context), "foo==1.0 -> foo,_foo");
+    }
+
+    #[test]
+    fn test_filters() {
+        let context = json!({"name": "Foo", "urls": ["a", "b"], "summary": null});
+        for (template, expected) in [
+            ("{{name | lower}}", "foo"),
+            ("{{name | upper}}", "FOO"),
+            ("{{urls | length}}", "2"),
+            ("{{urls | last}}", "b"),
+            ("{{urls.0}}", "a"),
+            ("{{urls}}", "a, b"),
+            ("{{summary | default('-')}}", "-"),
+            ("{{missing.field}}", ""),
+        ] {
+            let template: Template = template.parse().unwrap();
+            assert_eq!(template.render(&context), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for template in [
+            "{{name",
+            "{{}}",
+            "{{name | frobnicate}}",
+            "{{urls | join(,)}}",
+        ] {
+            assert!(template.parse::<Template>().is_err(), "{template}");
+        }
+    }
+}
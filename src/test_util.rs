@@ -0,0 +1,568 @@
+//! An in-process package index serving synthetic wheels, for testing code using this crate.
+//!
+//! [`MockIndex`] serves the JSON simple API under `/simple/`, including the root listing, and wheels under `/files/`,
+//! supporting `HEAD` and range requests like real file hosts do.
+//! [`serve`] runs a server with a custom handler, e.g. for registries with redirects or authentication.
+//!
+//! ```no_run
+//! # async fn example() -> std::io::Result<()> {
+//! use pypi_lazyzip::test_util::{MockIndex, MockWheel};
+//!
+//! let index = MockIndex::start([MockWheel::new("foo", "1.0").requires("bar>=1")]).await?;
+//! let client = index.client();
+//! # Ok(())
+//! # }
+//! ```
+
+// Only ever panics on invalid URLs, which the server never produces
+#![allow(clippy::missing_panics_doc)]
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Write as _;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use hyper::{Method, StatusCode};
+use hyper_util::rt::TokioIo;
+use reqwest::Url;
+use sha2::{Digest as _, Sha256};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::LazyZipClient;
+use crate::hashes::hex;
+use crate::python_pkg::normalize;
+use crate::simple_repo_api::Yanking;
+
+/// A synthetic wheel containing a package directory and `METADATA`
+#[derive(Debug, Clone)]
+pub struct MockWheel {
+    name: String,
+    version: String,
+    requires_dist: Vec<String>,
+    requires_python: Option<String>,
+    yanked: Yanking,
+    project_status: Option<String>,
+    upload_time: Option<String>,
+    tag: String,
+    corrupt: bool,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl MockWheel {
+    pub fn new(name: &str, version: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            version: version.to_owned(),
+            requires_dist: Vec::new(),
+            requires_python: None,
+            yanked: Yanking::NotYanked,
+            project_status: None,
+            upload_time: None,
+            tag: "py3-none-any".to_owned(),
+            corrupt: false,
+            files: vec![(format!("{}/__init__.py", module_name(name)), Vec::new())],
+        }
+    }
+
+    /// Add a `Requires-Dist` entry
+    #[must_use]
+    pub fn requires(mut self, requirement: &str) -> Self {
+        self.requires_dist.push(requirement.to_owned());
+        self
+    }
+
+    /// Set `Requires-Python`, both in `METADATA` and on the index
+    #[must_use]
+    pub fn requires_python(mut self, specifiers: &str) -> Self {
+        self.requires_python = Some(specifiers.to_owned());
+        self
+    }
+
+    /// Mark the wheel as yanked on the index
+    #[must_use]
+    pub fn yanked(mut self, reason: Option<&str>) -> Self {
+        self.yanked = Yanking::Yanked(reason.map(ToOwned::to_owned));
+        self
+    }
+
+    /// Mark the wheel’s project with a status like `quarantined` on the index (PEP 792)
+    #[must_use]
+    pub fn project_status(mut self, status: &str) -> Self {
+        self.project_status = Some(status.to_owned());
+        self
+    }
+
+    /// Set the upload time on the index, like `2023-06-01T12:00:00Z` (PEP 700)
+    #[must_use]
+    pub fn uploaded(mut self, time: &str) -> Self {
+        self.upload_time = Some(time.to_owned());
+        self
+    }
+
+    /// Set the wheel’s compatibility tag instead of `py3-none-any`, e.g. `cp312-cp312-manylinux_2_17_x86_64`
+    #[must_use]
+    pub fn tag(mut self, tag: &str) -> Self {
+        tag.clone_into(&mut self.tag);
+        self
+    }
+
+    /// Serve a truncated archive, like a broken upload or mirror copy
+    #[must_use]
+    pub fn corrupt(mut self) -> Self {
+        self.corrupt = true;
+        self
+    }
+
+    /// Add a file to the wheel
+    #[must_use]
+    pub fn file(mut self, path: &str, content: impl Into<Vec<u8>>) -> Self {
+        self.files.push((path.to_owned(), content.into()));
+        self
+    }
+
+    pub fn filename(&self) -> String {
+        format!(
+            "{}-{}-{}.whl",
+            module_name(&self.name),
+            self.version,
+            self.tag
+        )
+    }
+
+    fn metadata(&self) -> String {
+        let mut metadata = format!(
+            "Metadata-Version: 2.1\nName: {}\nVersion: {}\n",
+            self.name, self.version
+        );
+        if let Some(requires_python) = &self.requires_python {
+            let _ = writeln!(metadata, "Requires-Python: {requires_python}");
+        }
+        for requirement in &self.requires_dist {
+            let _ = writeln!(metadata, "Requires-Dist: {requirement}");
+        }
+        metadata
+    }
+
+    /// Build the wheel’s zip archive, with entries stored uncompressed
+    pub fn build(&self) -> Vec<u8> {
+        let dist_info = format!("{}-{}.dist-info", module_name(&self.name), self.version);
+        let mut entries = self.files.clone();
+        entries.push((
+            format!("{dist_info}/METADATA"),
+            self.metadata().into_bytes(),
+        ));
+        entries.push((
+            format!("{dist_info}/WHEEL"),
+            format!(
+                "Wheel-Version: 1.0\nRoot-Is-Purelib: {}\nTag: {}\n",
+                self.tag.ends_with("-any"),
+                self.tag
+            )
+            .into_bytes(),
+        ));
+        let mut zip = build_zip(&entries);
+        if self.corrupt {
+            zip.truncate(zip.len() / 2);
+        }
+        zip
+    }
+}
+
+/// A running mock index. The server stops when this is dropped.
+#[derive(Debug)]
+pub struct MockIndex {
+    server: MockServer,
+}
+
+/// A running server answering requests with a handler. The server stops when this is dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+/// Serve `handler`’s responses on a random local port, e.g. to mock registries with quirks [`MockIndex`] lacks.
+/// The handler gets the server’s address, to link to itself.
+pub async fn serve<F>(handler: F) -> io::Result<MockServer>
+where
+    F: Fn(SocketAddr, &hyper::Request<Incoming>) -> hyper::Response<Full<Bytes>>
+        + Send
+        + Sync
+        + 'static,
+{
+    let handler = Arc::new(handler);
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let task = tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                let service = hyper::service::service_fn(move |req| {
+                    let response = handler(addr, &req);
+                    async move { Ok::<_, Infallible>(response) }
+                });
+                let _ = hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+    Ok(MockServer { addr, task })
+}
+
+impl MockServer {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Wheels by file name, and file names by normalized project name
+#[derive(Debug, Default)]
+struct State {
+    files: BTreeMap<String, (MockWheel, Bytes)>,
+    projects: BTreeMap<String, Vec<String>>,
+}
+
+impl MockIndex {
+    /// Serve `wheels` on a random local port
+    pub async fn start(wheels: impl IntoIterator<Item = MockWheel>) -> io::Result<Self> {
+        let mut state = State::default();
+        for wheel in wheels {
+            let project = normalize(&wheel.name).map_err(io::Error::other)?;
+            let filename = wheel.filename();
+            state
+                .projects
+                .entry(project)
+                .or_default()
+                .push(filename.clone());
+            let content = Bytes::from(wheel.build());
+            state.files.insert(filename, (wheel, content));
+        }
+        let server = serve(move |addr, req| handle(&state, addr, req)).await?;
+        Ok(Self { server })
+    }
+
+    /// Base URL of the simple API
+    pub fn index_url(&self) -> Url {
+        base_url(self.server.addr)
+            .join("simple/")
+            .expect("valid URL")
+    }
+
+    /// URL of a wheel file
+    pub fn file_url(&self, filename: &str) -> Url {
+        file_url(self.server.addr, filename)
+    }
+
+    /// A client using only this index
+    pub fn client(&self) -> LazyZipClient {
+        LazyZipClient::new(reqwest::Client::new(), [self.index_url()])
+    }
+}
+
+fn base_url(addr: SocketAddr) -> Url {
+    Url::parse(&format!("http://{addr}/")).expect("valid URL")
+}
+
+fn file_url(addr: SocketAddr, filename: &str) -> Url {
+    base_url(addr)
+        .join(&format!("files/{filename}"))
+        .expect("valid URL")
+}
+
+fn handle(
+    state: &State,
+    addr: SocketAddr,
+    req: &hyper::Request<Incoming>,
+) -> hyper::Response<Full<Bytes>> {
+    let path = req.uri().path();
+    if path == "/simple/" {
+        let projects = state
+            .projects
+            .keys()
+            .map(|name| serde_json::json!({"name": name}))
+            .collect::<Vec<_>>();
+        let list = serde_json::json!({"meta": {"api-version": "1.1"}, "projects": projects});
+        return hyper::Response::builder()
+            .header(CONTENT_TYPE, "application/vnd.pypi.simple.v1+json")
+            .body(Full::new(Bytes::from(list.to_string())))
+            .expect("valid response");
+    }
+    if let Some(project) = path
+        .strip_prefix("/simple/")
+        .and_then(|p| p.strip_suffix('/'))
+    {
+        let Some(filenames) = normalize(project)
+            .ok()
+            .and_then(|project| state.projects.get(&project))
+        else {
+            return status(StatusCode::NOT_FOUND);
+        };
+        let files = filenames
+            .iter()
+            .map(|filename| {
+                let (wheel, content) = &state.files[filename];
+                serde_json::json!({
+                    "filename": filename,
+                    "url": file_url(addr, filename),
+                    "hashes": {"sha256": hex(&Sha256::digest(content))},
+                    "size": content.len(),
+                    "requires-python": wheel.requires_python,
+                    "upload-time": wheel.upload_time,
+                    "yanked": match &wheel.yanked {
+                        Yanking::NotYanked => serde_json::Value::Bool(false),
+                        Yanking::Yanked(None) => serde_json::Value::Bool(true),
+                        Yanking::Yanked(Some(reason)) => reason.as_str().into(),
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut project = serde_json::json!({
+            "meta": {"api-version": "1.1"},
+            "name": project,
+            "files": files,
+        });
+        if let Some(status) = filenames
+            .iter()
+            .find_map(|filename| state.files[filename].0.project_status.as_ref())
+        {
+            project["meta"]["api-version"] = "1.4".into();
+            project["project-status"] = serde_json::json!({"status": status, "reason": "test"});
+        }
+        return hyper::Response::builder()
+            .header(CONTENT_TYPE, "application/vnd.pypi.simple.v1+json")
+            .body(Full::new(Bytes::from(project.to_string())))
+            .expect("valid response");
+    }
+    let Some((_, content)) = path
+        .strip_prefix("/files/")
+        .and_then(|filename| state.files.get(filename))
+    else {
+        return status(StatusCode::NOT_FOUND);
+    };
+    file_response(content, req)
+}
+
+/// Respond with a file like file hosts do, supporting `HEAD` and range requests
+pub fn file_response(
+    content: &Bytes,
+    req: &hyper::Request<Incoming>,
+) -> hyper::Response<Full<Bytes>> {
+    let builder = hyper::Response::builder().header(ACCEPT_RANGES, "bytes");
+    if req.method() == Method::HEAD {
+        return builder
+            .header(CONTENT_LENGTH, content.len())
+            .body(Full::default())
+            .expect("valid response");
+    }
+    let Some(range) = req.headers().get(RANGE) else {
+        return builder
+            .body(Full::new(content.clone()))
+            .expect("valid response");
+    };
+    let Some((start, end)) = range
+        .to_str()
+        .ok()
+        .and_then(|range| parse_range(range, content.len()))
+    else {
+        return status(StatusCode::RANGE_NOT_SATISFIABLE);
+    };
+    builder
+        .status(StatusCode::PARTIAL_CONTENT)
+        .header(
+            CONTENT_RANGE,
+            format!("bytes {start}-{end}/{}", content.len()),
+        )
+        .body(Full::new(content.slice(start..=end)))
+        .expect("valid response")
+}
+
+/// An empty response with `status`
+pub fn status(status: StatusCode) -> hyper::Response<Full<Bytes>> {
+    hyper::Response::builder()
+        .status(status)
+        .body(Full::default())
+        .expect("valid response")
+}
+
+/// A wheel of `foo` whose first entry, `foo/first.txt`, is far from the central directory,
+/// so reading it lazily takes a range request besides the initial one
+#[cfg(test)]
+pub(crate) fn far_entry_wheel() -> Bytes {
+    Bytes::from(
+        MockWheel::new("foo", "1.0")
+            .file("foo/first.txt", "first")
+            .file("foo/data.bin", vec![0; 300_000])
+            .build(),
+    )
+}
+
+/// Lazily open [`far_entry_wheel`] as found on `client`’s indexes
+#[cfg(test)]
+pub(crate) async fn open_far_entry_wheel(client: &LazyZipClient) -> crate::wheel::PkgZipReader {
+    let (_, reader) = crate::wheel::pkg_reader(client, "foo".parse().unwrap())
+        .await
+        .unwrap();
+    crate::wheel::zip_reader(reader).await.unwrap()
+}
+
+/// Read `foo/first.txt` of [`far_entry_wheel`], which takes another range request
+#[cfg(test)]
+pub(crate) async fn assert_far_entry(zip: &mut crate::wheel::PkgZipReader) {
+    let idx = crate::wheel::find_entry(zip, |e| {
+        e.filename()
+            .as_str()
+            .is_ok_and(|name| name == "foo/first.txt")
+    })
+    .unwrap();
+    let mut content = String::new();
+    crate::wheel::read_entry(zip, idx, &mut content)
+        .await
+        .unwrap();
+    assert_eq!(content, "first");
+}
+
+/// Parse a single-part `Range` header into an inclusive range of byte positions
+fn parse_range(range: &str, len: usize) -> Option<(usize, usize)> {
+    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = if start.is_empty() {
+        let suffix = end.parse::<usize>().ok()?.min(len);
+        (len - suffix, len.checked_sub(1)?)
+    } else {
+        let end = match end {
+            "" => len.checked_sub(1)?,
+            end => end.parse::<usize>().ok()?.min(len.checked_sub(1)?),
+        };
+        (start.parse().ok()?, end)
+    };
+    (start <= end).then_some((start, end))
+}
+
+/// Build a zip archive with uncompressed entries
+pub(crate) fn build_zip(entries: &[(String, Vec<u8>)]) -> Vec<u8> {
+    // MS-DOS date for 1980-01-01, the earliest one possible
+    const DATE: u16 = 0x21;
+    let mut archive = Vec::new();
+    let mut central_directory = Vec::new();
+    for (name, content) in entries {
+        let offset = u32::try_from(archive.len()).expect("archive too large");
+        let crc = crc32fast::hash(content);
+        let size = u32::try_from(content.len()).expect("entry too large");
+        let name_len = u16::try_from(name.len()).expect("name too long");
+
+        // Local file header: signature, version needed, flags, method (stored), time, date
+        archive.extend(0x0403_4b50_u32.to_le_bytes());
+        archive.extend([20, 0, 0, 0, 0, 0, 0, 0]);
+        archive.extend(DATE.to_le_bytes());
+        for field in [crc, size, size] {
+            archive.extend(field.to_le_bytes());
+        }
+        archive.extend(name_len.to_le_bytes());
+        archive.extend(0_u16.to_le_bytes());
+        archive.extend(name.as_bytes());
+        archive.extend(content);
+
+        // Central directory header: signature, version made by, version needed, flags, method, time, date
+        central_directory.extend(0x0201_4b50_u32.to_le_bytes());
+        central_directory.extend([20, 0, 20, 0, 0, 0, 0, 0, 0, 0]);
+        central_directory.extend(DATE.to_le_bytes());
+        for field in [crc, size, size] {
+            central_directory.extend(field.to_le_bytes());
+        }
+        central_directory.extend(name_len.to_le_bytes());
+        // Extra field and comment lengths, disk number, internal and external attributes
+        central_directory.extend([0; 12]);
+        central_directory.extend(offset.to_le_bytes());
+        central_directory.extend(name.as_bytes());
+    }
+    let cd_offset = u32::try_from(archive.len()).expect("archive too large");
+    let cd_size = u32::try_from(central_directory.len()).expect("archive too large");
+    let count = u16::try_from(entries.len()).expect("too many entries");
+    archive.extend(central_directory);
+
+    // End of central directory record
+    archive.extend(0x0605_4b50_u32.to_le_bytes());
+    archive.extend([0; 4]);
+    archive.extend(count.to_le_bytes());
+    archive.extend(count.to_le_bytes());
+    archive.extend(cd_size.to_le_bytes());
+    archive.extend(cd_offset.to_le_bytes());
+    archive.extend(0_u16.to_le_bytes());
+    archive
+}
+
+/// The name of the module and dist-info directory, e.g. `foo_bar` for `foo-bar`
+fn module_name(name: &str) -> String {
+    name.replace(['-', '.'], "_").to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr as _;
+
+    use super::*;
+    use crate::PkgLoc;
+    use crate::warnings::{self, Warning};
+    use crate::wheel::{find_wheel, list_entries, pkg_metadata};
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-9", 100), Some((0, 9)));
+        assert_eq!(parse_range("bytes=90-", 100), Some((90, 99)));
+        assert_eq!(parse_range("bytes=-10", 100), Some((90, 99)));
+        assert_eq!(parse_range("bytes=-200", 100), Some((0, 99)));
+        assert_eq!(parse_range("bytes=50-10", 100), None);
+    }
+
+    #[tokio::test]
+    async fn test_mock_index() {
+        let index = MockIndex::start([
+            MockWheel::new("foo", "1.0").requires("bar>=1"),
+            MockWheel::new("foo", "2.0").yanked(Some("broken")),
+        ])
+        .await
+        .unwrap();
+        let client = index.client();
+
+        let (whl, warnings) = warnings::collect(async {
+            find_wheel(&client, &"foo".parse().unwrap()).await.unwrap()
+        })
+        .await;
+        assert_eq!(whl.filename, "foo-1.0-py3-none-any.whl");
+        assert_eq!(
+            warnings,
+            [Warning::YankedSkipped {
+                filename: "foo-2.0-py3-none-any.whl".to_owned(),
+                reason: Some("broken".to_owned()),
+            }]
+        );
+
+        let (name, metadata) = pkg_metadata(&client, PkgLoc::from_str("foo").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(name.to_string(), "foo");
+        assert_eq!(
+            metadata.get_all("Requires-Dist").collect::<Vec<_>>(),
+            ["bar>=1"]
+        );
+
+        let url = index.file_url("foo-1.0-py3-none-any.whl");
+        let (_, entries) = list_entries(&client, PkgLoc::Url(url)).await.unwrap();
+        assert!(entries.contains(&"foo/__init__.py".to_owned()));
+    }
+}
@@ -0,0 +1,99 @@
+//! The most downloaded projects, from the
+//! [top-pypi-packages](https://hugovk.github.io/top-pypi-packages/) dataset, for bulk analyses.
+
+use eyre::{Context as _, Result};
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::LazyZipClient;
+use crate::python_pkg::PackageName;
+
+/// The dataset of the last 30 days, updated monthly
+pub const TOP_PACKAGES_URL: &str =
+    "https://hugovk.github.io/top-pypi-packages/top-pypi-packages.min.json";
+
+#[derive(Deserialize, Debug)]
+struct TopPackages {
+    /// Projects by descending download count
+    rows: Vec<Row>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Row {
+    project: String,
+}
+
+/// Load the names of the `limit` most downloaded projects, most downloaded first,
+/// from a dump at an HTTP(S) URL or in a local file
+pub async fn load(
+    client: &LazyZipClient,
+    source: &str,
+    limit: Option<usize>,
+) -> Result<Vec<PackageName>> {
+    let top: TopPackages = match Url::parse(source) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => client
+            .http()
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .with_context(|| format!("invalid top packages list at {source}"))?,
+        _ => {
+            let content = tokio::fs::read(source)
+                .await
+                .with_context(|| format!("Failed to read {source}"))?;
+            serde_json::from_slice(&content)
+                .with_context(|| format!("invalid top packages list in {source}"))?
+        }
+    };
+    parse_rows(top, limit)
+}
+
+fn parse_rows(top: TopPackages, limit: Option<usize>) -> Result<Vec<PackageName>> {
+    top.rows
+        .into_iter()
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|row| row.project.parse())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load_file() {
+        let path = std::env::temp_dir().join(format!("top-packages-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"rows": [{"download_count": 1, "project": "foo"}]}"#,
+        )
+        .unwrap();
+        let client = LazyZipClient::default();
+        let names = load(&client, path.to_str().unwrap(), None).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(names, ["foo".parse().unwrap()]);
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let top: TopPackages = serde_json::from_str(
+            r#"{
+                "last_update": "2025-01-01 00:00:00",
+                "rows": [
+                    {"download_count": 3, "project": "boto3"},
+                    {"download_count": 2, "project": "Requests"},
+                    {"download_count": 1, "project": "urllib3"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let names = parse_rows(top, Some(2)).unwrap();
+        assert_eq!(
+            names,
+            ["boto3".parse().unwrap(), "requests".parse().unwrap()]
+        );
+    }
+}
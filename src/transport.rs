@@ -0,0 +1,253 @@
+//! Answering the client’s requests with something other than `reqwest`’s network stack,
+//! e.g. in-memory mocks in tests, or another HTTP client with custom TLS.
+//!
+//! Implement [`HttpTransport`] and add it with [`LazyZipClient::with_transport`](crate::LazyZipClient::with_transport).
+//! Requests pass through middleware added before it, like retries, credentials and signing.
+
+use bytes::Bytes;
+use eyre::{Result, bail};
+use http::Extensions;
+use reqwest::header::{ACCEPT, CONTENT_LENGTH, CONTENT_RANGE, HeaderMap, HeaderValue, RANGE};
+use reqwest::{Method, Request, Response, ResponseBuilderExt as _, StatusCode, Url};
+use reqwest_middleware::{Error, Middleware, Next};
+
+/// Bytes of a file to fetch, like in a `Range` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// From the first to the last offset, inclusive
+    Between(u64, u64),
+    /// From an offset to the end
+    From(u64),
+    /// This many bytes at the end, e.g. a zip’s central directory
+    Suffix(u64),
+}
+
+impl ByteRange {
+    /// Parse a `Range` header value with a single range, like `bytes=-65536`
+    pub fn parse(header: &str) -> Option<Self> {
+        let (start, end) = header.strip_prefix("bytes=")?.split_once('-')?;
+        match (start, end) {
+            ("", suffix) => Some(Self::Suffix(suffix.parse().ok()?)),
+            (start, "") => Some(Self::From(start.parse().ok()?)),
+            (start, end) => Some(Self::Between(start.parse().ok()?, end.parse().ok()?)),
+        }
+    }
+
+    /// The first and last offsets in a file of `len` bytes, if any are in it
+    pub fn resolve(self, len: u64) -> Option<(u64, u64)> {
+        let (start, end) = match self {
+            Self::Between(start, end) => (start, end.min(len.checked_sub(1)?)),
+            Self::From(start) => (start, len.checked_sub(1)?),
+            Self::Suffix(suffix) => (len.saturating_sub(suffix), len.checked_sub(1)?),
+        };
+        (start <= end).then_some((start, end))
+    }
+}
+
+/// A transport’s answer to a request
+#[derive(Debug, Clone)]
+pub struct TransportResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl TransportResponse {
+    pub fn new(status: StatusCode, body: impl Into<Bytes>) -> Self {
+        Self {
+            status,
+            headers: HeaderMap::new(),
+            body: body.into(),
+        }
+    }
+
+    /// `range` of `file` with `206 Partial Content`, or `416` if it’s outside of the file
+    pub fn partial(file: &Bytes, range: ByteRange) -> Self {
+        let len = file.len() as u64;
+        let Some((start, end)) = range.resolve(len) else {
+            return Self::new(StatusCode::RANGE_NOT_SATISFIABLE, Bytes::new());
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        let body = file.slice(start as usize..=end as usize);
+        Self::new(StatusCode::PARTIAL_CONTENT, body)
+            .with_header(CONTENT_RANGE, &format!("bytes {start}-{end}/{len}"))
+    }
+
+    /// Add a header, ignoring invalid values
+    #[must_use]
+    pub fn with_header(mut self, name: reqwest::header::HeaderName, value: &str) -> Self {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            self.headers.insert(name, value);
+        }
+        self
+    }
+}
+
+/// Sends the client’s requests. Each gets the request’s headers, e.g. `Accept` or `Authorization`.
+#[async_trait::async_trait]
+pub trait HttpTransport: Send + Sync + 'static {
+    /// Fetch a JSON document, i.e. an index page
+    async fn get_json(&self, url: &Url, headers: &HeaderMap) -> Result<TransportResponse>;
+
+    /// Fetch part of a file. Transports that can’t fetch ranges answer with the whole file and `200 OK`.
+    async fn get_range(
+        &self,
+        url: &Url,
+        range: ByteRange,
+        headers: &HeaderMap,
+    ) -> Result<TransportResponse>;
+
+    /// Fetch a file’s headers, e.g. its `Content-Length`
+    async fn head(&self, url: &Url, headers: &HeaderMap) -> Result<TransportResponse>;
+
+    /// Fetch a whole file, by default as a range from its start
+    async fn get(&self, url: &Url, headers: &HeaderMap) -> Result<TransportResponse> {
+        let mut response = self.get_range(url, ByteRange::From(0), headers).await?;
+        if response.status == StatusCode::PARTIAL_CONTENT {
+            response.status = StatusCode::OK;
+            response.headers.remove(CONTENT_RANGE);
+        }
+        Ok(response)
+    }
+}
+
+/// Middleware answering every request with an [`HttpTransport`], never calling the next one
+pub(crate) struct Transported<T>(pub T);
+
+impl<T: HttpTransport> Transported<T> {
+    async fn send(&self, req: &Request) -> Result<TransportResponse> {
+        let (url, headers) = (req.url(), req.headers());
+        let range = headers.get(RANGE).and_then(|range| range.to_str().ok());
+        match *req.method() {
+            Method::HEAD => self.0.head(url, headers).await,
+            Method::GET => match range {
+                Some(range) => {
+                    let Some(range) = ByteRange::parse(range) else {
+                        bail!("unsupported range {range:?}");
+                    };
+                    self.0.get_range(url, range, headers).await
+                }
+                None if is_json(headers) => self.0.get_json(url, headers).await,
+                None => self.0.get(url, headers).await,
+            },
+            ref method => bail!("unsupported method {method}"),
+        }
+    }
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("json"))
+}
+
+#[async_trait::async_trait]
+impl<T: HttpTransport> Middleware for Transported<T> {
+    async fn handle(
+        &self,
+        req: Request,
+        _extensions: &mut Extensions,
+        _next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let response = self
+            .send(&req)
+            .await
+            .map_err(|e| Error::middleware(std::io::Error::other(e)))?;
+        let mut builder = http::Response::builder()
+            .status(response.status)
+            .url(req.url().clone());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers;
+            if !headers.contains_key(CONTENT_LENGTH) {
+                headers.insert(CONTENT_LENGTH, response.body.len().into());
+            }
+        }
+        Ok(builder
+            .body(response.body)
+            .map_err(Error::middleware)?
+            .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LazyZipClient;
+    use crate::test_util::MockWheel;
+
+    /// Serves one wheel from memory
+    struct InMemory {
+        wheel: Bytes,
+    }
+
+    const WHEEL_URL: &str = "https://files.example.com/foo-1.0-py3-none-any.whl";
+
+    #[async_trait::async_trait]
+    impl HttpTransport for InMemory {
+        async fn get_json(&self, url: &Url, _headers: &HeaderMap) -> Result<TransportResponse> {
+            if url.path() != "/simple/foo/" {
+                return Ok(TransportResponse::new(StatusCode::NOT_FOUND, Bytes::new()));
+            }
+            let page = serde_json::json!({
+                "meta": {"api-version": "1.1"},
+                "name": "foo",
+                "files": [{
+                    "filename": "foo-1.0-py3-none-any.whl",
+                    "url": WHEEL_URL,
+                    "hashes": {},
+                    "size": self.wheel.len(),
+                }],
+            });
+            Ok(TransportResponse::new(StatusCode::OK, page.to_string()))
+        }
+
+        async fn get_range(
+            &self,
+            _url: &Url,
+            range: ByteRange,
+            _headers: &HeaderMap,
+        ) -> Result<TransportResponse> {
+            Ok(TransportResponse::partial(&self.wheel, range))
+        }
+
+        async fn head(&self, _url: &Url, _headers: &HeaderMap) -> Result<TransportResponse> {
+            Ok(TransportResponse::new(StatusCode::OK, Bytes::new())
+                .with_header(CONTENT_LENGTH, &self.wheel.len().to_string()))
+        }
+    }
+
+    #[test]
+    fn test_byte_range() {
+        assert_eq!(ByteRange::parse("bytes=-100"), Some(ByteRange::Suffix(100)));
+        assert_eq!(ByteRange::parse("bytes=5-"), Some(ByteRange::From(5)));
+        assert_eq!(
+            ByteRange::parse("bytes=5-9"),
+            Some(ByteRange::Between(5, 9))
+        );
+        assert_eq!(ByteRange::Suffix(100).resolve(10), Some((0, 9)));
+        assert_eq!(ByteRange::Between(5, 20).resolve(10), Some((5, 9)));
+        assert_eq!(ByteRange::From(10).resolve(10), None);
+    }
+
+    #[tokio::test]
+    async fn test_transport() {
+        let wheel = Bytes::from(MockWheel::new("foo", "1.0").requires("bar>=1").build());
+        let client = LazyZipClient::new(
+            reqwest::Client::new(),
+            ["https://pkgs.example.com/simple/".parse().unwrap()],
+        )
+        .with_transport(InMemory { wheel });
+        let (_, metadata) = crate::wheel::pkg_metadata(&client, "foo".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            metadata.get_all("Requires-Dist").collect::<Vec<_>>(),
+            ["bar>=1"]
+        );
+        let err = crate::wheel::pkg_metadata(&client, "bar".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not found"), "{err}");
+    }
+}
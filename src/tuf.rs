@@ -0,0 +1,798 @@
+//! Verification of files against [TUF](https://theupdateframework.io/) metadata,
+//! as proposed for Python package indexes in [PEP 458](https://peps.python.org/pep-0458/).
+//!
+//! Starting from a trusted root, the `timestamp`, `snapshot`, and `targets` roles are fetched and verified,
+//! and delegated targets roles (like PEP 458’s hashed bins) are followed on demand.
+//! The versions of the newest timestamp and snapshot metadata are kept across runs,
+//! so a repository serving older ones, e.g. to hide a new release, is rejected.
+//! Only `ed25519` keys are supported.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::str::FromStr as _;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use ed25519_dalek::{Signature, Verifier as _, VerifyingKey};
+use eyre::{Context as _, ContextCompat as _, Result, bail, ensure};
+use reqwest::{StatusCode, Url};
+use reqwest_middleware::ClientWithMiddleware;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::blob_store::write_atomic;
+use crate::glob::glob_matches;
+use crate::hashes::hex;
+use crate::result_cache::user_cache_dir;
+
+/// Delegations nested deeper than this are assumed to be cyclic
+const MAX_DELEGATION_DEPTH: usize = 32;
+
+/// Signed metadata, with the signed part kept as is to verify signatures over its canonical form
+#[derive(Deserialize)]
+struct Envelope {
+    signed: serde_json::Value,
+    signatures: Vec<KeySignature>,
+}
+
+#[derive(Deserialize)]
+struct KeySignature {
+    keyid: String,
+    sig: String,
+}
+
+/// Fields every role’s metadata has
+#[derive(Deserialize)]
+struct Common {
+    #[serde(rename = "_type")]
+    type_: String,
+    version: u64,
+    expires: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Key {
+    keytype: String,
+    keyval: KeyVal,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct KeyVal {
+    public: String,
+}
+
+/// The keys allowed to sign a role’s metadata, and how many signatures are needed
+#[derive(Deserialize, Debug, Clone)]
+struct RoleKeys {
+    keyids: Vec<String>,
+    threshold: usize,
+}
+
+#[derive(Deserialize, Debug)]
+struct Root {
+    version: u64,
+    #[serde(default)]
+    consistent_snapshot: bool,
+    keys: HashMap<String, Key>,
+    roles: HashMap<String, RoleKeys>,
+}
+
+/// A metadata file as listed in timestamp or snapshot metadata
+#[derive(Deserialize, Debug)]
+struct MetaFile {
+    version: u64,
+    length: Option<usize>,
+    #[serde(default)]
+    hashes: HashMap<String, String>,
+}
+
+/// Timestamp or snapshot metadata
+#[derive(Deserialize, Debug)]
+struct Meta {
+    meta: HashMap<String, MetaFile>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Targets {
+    targets: HashMap<String, Target>,
+    delegations: Option<Delegations>,
+}
+
+/// A signed file length and its hashes
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub length: u64,
+    pub hashes: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Delegations {
+    keys: HashMap<String, Key>,
+    roles: Vec<DelegatedRole>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DelegatedRole {
+    name: String,
+    #[serde(flatten)]
+    keys: RoleKeys,
+    #[serde(default)]
+    terminating: bool,
+    paths: Option<Vec<String>>,
+    path_hash_prefixes: Option<Vec<String>>,
+}
+
+impl DelegatedRole {
+    fn is_responsible_for(&self, path: &str) -> bool {
+        if let Some(patterns) = &self.paths {
+            return patterns.iter().any(|pattern| glob_matches(pattern, path));
+        }
+        if let Some(prefixes) = &self.path_hash_prefixes {
+            let hash = hex(&Sha256::digest(path.as_bytes()));
+            return prefixes.iter().any(|prefix| hash.starts_with(prefix));
+        }
+        false
+    }
+}
+
+impl Root {
+    fn role(&self, role: &str) -> Result<&RoleKeys> {
+        self.roles
+            .get(role)
+            .with_context(|| format!("root metadata has no {role} role"))
+    }
+
+    /// Verify metadata of a top-level role
+    fn verify_role<T: DeserializeOwned>(&self, raw: &[u8], role: &str) -> Result<T> {
+        verify(raw, role, &self.keys, self.role(role)?, true)
+    }
+}
+
+/// Versions of the newest timestamp and snapshot metadata verified so far
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+struct TrustedVersions {
+    timestamp: u64,
+    snapshot: u64,
+}
+
+impl TrustedVersions {
+    fn load(path: &Path) -> Result<Self> {
+        match std::fs::read(path) {
+            Ok(raw) => serde_json::from_slice(&raw).with_context(|| {
+                format!("Failed to parse trusted TUF versions {}", path.display())
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e)
+                .with_context(|| format!("Failed to read trusted TUF versions {}", path.display())),
+        }
+    }
+}
+
+/// Where to keep the versions of the metadata at `metadata_url` verified so far, in the user’s cache directory
+pub fn versions_path(metadata_url: &Url) -> Option<PathBuf> {
+    let key = hex(&Sha256::digest(metadata_url.as_str()));
+    Some(
+        user_cache_dir()?
+            .join("tuf-versions")
+            .join(format!("{key}.json")),
+    )
+}
+
+/// Verified TUF metadata of a repository
+#[derive(Debug)]
+pub struct TufRepository {
+    http: ClientWithMiddleware,
+    metadata_url: Url,
+    consistent_snapshot: bool,
+    snapshot: Meta,
+    targets: Targets,
+    /// Verified delegated targets roles by name
+    delegated: Mutex<HashMap<String, Arc<Targets>>>,
+}
+
+impl TufRepository {
+    /// Fetch and verify the repository’s metadata, starting from a trusted `root.json`.
+    /// If `versions` is given, metadata older than the versions recorded there by earlier runs is rejected,
+    /// and the new versions are recorded.
+    #[tracing::instrument(skip(http, trusted_root))]
+    pub async fn load(
+        http: ClientWithMiddleware,
+        metadata_url: Url,
+        trusted_root: &[u8],
+        versions: Option<&Path>,
+    ) -> Result<Self> {
+        let trusted = versions
+            .map(TrustedVersions::load)
+            .transpose()?
+            .unwrap_or_default();
+        let root = update_root(&http, &metadata_url, trusted_root).await?;
+
+        let raw = fetch(&http, &metadata_url, "timestamp.json")
+            .await?
+            .context("Repository has no timestamp metadata")?;
+        let timestamp = root.verify_role::<Meta>(&raw, "timestamp")?;
+        let timestamp_version = parse_unverified::<Common>(&raw)?.version;
+        check_rollback("timestamp", timestamp_version, trusted.timestamp)?;
+
+        let snapshot_file = timestamp
+            .meta
+            .get("snapshot.json")
+            .context("timestamp metadata doesn’t list snapshot.json")?;
+        check_rollback("snapshot", snapshot_file.version, trusted.snapshot)?;
+        let raw = fetch_meta(
+            &http,
+            &metadata_url,
+            "snapshot",
+            snapshot_file,
+            root.consistent_snapshot,
+        )
+        .await?;
+        let snapshot = root.verify_role::<Meta>(&raw, "snapshot")?;
+        check_version(&raw, snapshot_file.version, "snapshot")?;
+
+        let targets_file = snapshot
+            .meta
+            .get("targets.json")
+            .context("snapshot metadata doesn’t list targets.json")?;
+        let raw = fetch_meta(
+            &http,
+            &metadata_url,
+            "targets",
+            targets_file,
+            root.consistent_snapshot,
+        )
+        .await?;
+        let targets = root.verify_role::<Targets>(&raw, "targets")?;
+        check_version(&raw, targets_file.version, "targets")?;
+
+        let verified = TrustedVersions {
+            timestamp: timestamp_version,
+            snapshot: snapshot_file.version,
+        };
+        if let Some(path) = versions
+            && verified != trusted
+            && let Err(e) = write_atomic(path, &serde_json::to_vec(&verified)?)
+        {
+            tracing::warn!("Failed to record trusted TUF versions: {e:#}");
+        }
+
+        Ok(Self {
+            http,
+            metadata_url,
+            consistent_snapshot: root.consistent_snapshot,
+            snapshot,
+            targets,
+            delegated: Mutex::default(),
+        })
+    }
+
+    /// Find the signed length and hashes of a target path, following delegations
+    #[tracing::instrument(skip(self))]
+    pub async fn target(&self, path: &str) -> Result<Option<Target>> {
+        self.find_target(&self.targets, path, 0).await
+    }
+
+    /// Check that `content` is the target at `path`, with the signed length and SHA-256
+    pub async fn check_target(&self, path: &str, content: &[u8]) -> Result<()> {
+        let Some(target) = self.target(path).await? else {
+            bail!("{path} is not signed in TUF metadata");
+        };
+        let Some(signed) = target.hashes.get("sha256") else {
+            bail!("TUF metadata has no SHA-256 hash for {path}");
+        };
+        ensure!(
+            target.length == content.len() as u64
+                && signed.eq_ignore_ascii_case(&hex(&Sha256::digest(content))),
+            "{path} differs from the one signed in TUF metadata",
+        );
+        Ok(())
+    }
+
+    async fn find_target(
+        &self,
+        targets: &Targets,
+        path: &str,
+        depth: usize,
+    ) -> Result<Option<Target>> {
+        if let Some(target) = targets.targets.get(path) {
+            return Ok(Some(target.clone()));
+        }
+        let Some(delegations) = &targets.delegations else {
+            return Ok(None);
+        };
+        ensure!(
+            depth < MAX_DELEGATION_DEPTH,
+            "TUF delegations are nested too deeply"
+        );
+        for role in &delegations.roles {
+            if !role.is_responsible_for(path) {
+                continue;
+            }
+            let delegated = self.delegated(role, &delegations.keys).await?;
+            if let Some(target) = Box::pin(self.find_target(&delegated, path, depth + 1)).await? {
+                return Ok(Some(target));
+            }
+            if role.terminating {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Fetch and verify a delegated targets role, once
+    async fn delegated(
+        &self,
+        role: &DelegatedRole,
+        keys: &HashMap<String, Key>,
+    ) -> Result<Arc<Targets>> {
+        let cached = self
+            .delegated
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&role.name)
+            .cloned();
+        if let Some(targets) = cached {
+            return Ok(targets);
+        }
+        let file = self
+            .snapshot
+            .meta
+            .get(&format!("{}.json", role.name))
+            .with_context(|| format!("snapshot metadata doesn’t list {}.json", role.name))?;
+        let raw = fetch_meta(
+            &self.http,
+            &self.metadata_url,
+            &role.name,
+            file,
+            self.consistent_snapshot,
+        )
+        .await?;
+        let targets = Arc::new(verify::<Targets>(&raw, "targets", keys, &role.keys, true)?);
+        check_version(&raw, file.version, &role.name)?;
+        self.delegated
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(role.name.clone(), targets.clone());
+        Ok(targets)
+    }
+}
+
+/// Update a trusted root to the newest one.
+/// Every root has to be signed by its own keys, newer ones also by the previous root’s keys.
+async fn update_root(
+    http: &ClientWithMiddleware,
+    metadata_url: &Url,
+    trusted_root: &[u8],
+) -> Result<Root> {
+    let self_signed = |raw: &[u8]| {
+        let unverified = parse_unverified::<Root>(raw)?;
+        verify::<Root>(
+            raw,
+            "root",
+            &unverified.keys,
+            unverified.role("root")?,
+            false,
+        )
+    };
+    let mut root = self_signed(trusted_root)?;
+    let mut root_raw = trusted_root.to_vec();
+    loop {
+        let name = format!("{}.root.json", root.version + 1);
+        let Some(raw) = fetch(http, metadata_url, &name).await? else {
+            break;
+        };
+        verify::<Root>(&raw, "root", &root.keys, root.role("root")?, false)?;
+        let new_root = self_signed(&raw)?;
+        ensure!(
+            new_root.version == root.version + 1,
+            "unexpected version in {name}"
+        );
+        root = new_root;
+        root_raw = raw;
+    }
+    // Only the newest root has to be current
+    root.verify_role::<Root>(&root_raw, "root")
+}
+
+/// Fetch a metadata file, or `None` if it doesn’t exist
+async fn fetch(
+    http: &ClientWithMiddleware,
+    metadata_url: &Url,
+    name: &str,
+) -> Result<Option<Vec<u8>>> {
+    let response = http.get(metadata_url.join(name)?).send().await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let bytes = response
+        .error_for_status()?
+        .bytes()
+        .await
+        .with_context(|| format!("Failed to fetch TUF metadata {name}"))?;
+    Ok(Some(bytes.to_vec()))
+}
+
+/// Fetch a role’s metadata as listed in timestamp or snapshot metadata, checking its length and hash
+async fn fetch_meta(
+    http: &ClientWithMiddleware,
+    metadata_url: &Url,
+    role: &str,
+    file: &MetaFile,
+    consistent_snapshot: bool,
+) -> Result<Vec<u8>> {
+    let name = if consistent_snapshot {
+        format!("{}.{role}.json", file.version)
+    } else {
+        format!("{role}.json")
+    };
+    let raw = fetch(http, metadata_url, &name)
+        .await?
+        .with_context(|| format!("TUF metadata {name} not found"))?;
+    if let Some(length) = file.length {
+        ensure!(raw.len() == length, "unexpected length of {name}");
+    }
+    if let Some(sha256) = file.hashes.get("sha256") {
+        ensure!(
+            hex(&Sha256::digest(&raw)) == *sha256,
+            "unexpected hash of {name}"
+        );
+    }
+    Ok(raw)
+}
+
+/// Check signatures, type, and expiry of metadata, and parse it
+fn verify<T: DeserializeOwned>(
+    raw: &[u8],
+    role: &str,
+    keys: &HashMap<String, Key>,
+    role_keys: &RoleKeys,
+    check_expiry: bool,
+) -> Result<T> {
+    let envelope = serde_json::from_slice::<Envelope>(raw)
+        .with_context(|| format!("Failed to parse {role} metadata"))?;
+    let mut canonical = Vec::new();
+    let mut serializer = serde_json::Serializer::with_formatter(
+        &mut canonical,
+        olpc_cjson::CanonicalFormatter::new(),
+    );
+    serde::Serialize::serialize(&envelope.signed, &mut serializer)?;
+
+    // Distinct public keys, so neither repeated keyids nor keyids of the same key count twice
+    let valid = role_keys
+        .keyids
+        .iter()
+        .filter_map(|keyid| keys.get(keyid).map(|key| (keyid, key)))
+        .filter(|(keyid, key)| {
+            envelope
+                .signatures
+                .iter()
+                .any(|s| &s.keyid == *keyid && verify_signature(key, &canonical, &s.sig))
+        })
+        .map(|(_, key)| key.keyval.public.to_ascii_lowercase())
+        .collect::<BTreeSet<_>>();
+    ensure!(
+        valid.len() >= role_keys.threshold.max(1),
+        "{role} metadata has {} valid signatures, {} needed",
+        valid.len(),
+        role_keys.threshold,
+    );
+
+    let common = serde_json::from_value::<Common>(envelope.signed.clone())
+        .with_context(|| format!("Failed to parse {role} metadata"))?;
+    ensure!(
+        common.type_ == role,
+        "expected {role} metadata, got {}",
+        common.type_
+    );
+    if check_expiry {
+        let expires = jiff::Timestamp::from_str(&common.expires)
+            .with_context(|| format!("invalid expiry date in {role} metadata"))?;
+        ensure!(
+            expires > jiff::Timestamp::now(),
+            "{role} metadata expired at {expires}"
+        );
+    }
+    serde_json::from_value(envelope.signed)
+        .with_context(|| format!("Failed to parse {role} metadata"))
+}
+
+/// Parse metadata without verifying it, e.g. to get the keys a root is signed with
+fn parse_unverified<T: DeserializeOwned>(raw: &[u8]) -> Result<T> {
+    let envelope = serde_json::from_slice::<Envelope>(raw).context("Failed to parse metadata")?;
+    serde_json::from_value(envelope.signed).context("Failed to parse metadata")
+}
+
+/// Check that a role’s metadata isn’t older than the newest verified in earlier runs
+fn check_rollback(role: &str, version: u64, trusted: u64) -> Result<()> {
+    ensure!(
+        version >= trusted,
+        "version {version} of {role} metadata is older than the trusted version {trusted}, the repository was rolled back",
+    );
+    Ok(())
+}
+
+/// Check that metadata has the version the timestamp or snapshot metadata announced
+fn check_version(raw: &[u8], expected: u64, role: &str) -> Result<()> {
+    let common = parse_unverified::<Common>(raw)?;
+    if common.version != expected {
+        bail!(
+            "expected version {expected} of {role} metadata, got {}",
+            common.version
+        );
+    }
+    Ok(())
+}
+
+fn verify_signature(key: &Key, message: &[u8], sig: &str) -> bool {
+    if key.keytype != "ed25519" {
+        return false;
+    }
+    let Some(public) = unhex(&key.keyval.public).and_then(|b| <[u8; 32]>::try_from(b).ok()) else {
+        return false;
+    };
+    let Some(sig) = unhex(sig).and_then(|b| <[u8; 64]>::try_from(b).ok()) else {
+        return false;
+    };
+    VerifyingKey::from_bytes(&public)
+        .is_ok_and(|key| key.verify(message, &Signature::from_bytes(&sig)).is_ok())
+}
+
+fn unhex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer as _, SigningKey};
+    use serde_json::json;
+
+    use super::*;
+
+    fn sign(signed: &serde_json::Value, keys: &[(&str, &SigningKey)]) -> Vec<u8> {
+        let mut canonical = Vec::new();
+        let mut serializer = serde_json::Serializer::with_formatter(
+            &mut canonical,
+            olpc_cjson::CanonicalFormatter::new(),
+        );
+        serde::Serialize::serialize(signed, &mut serializer).unwrap();
+        let signatures = keys
+            .iter()
+            .map(|(keyid, key)| json!({"keyid": keyid, "sig": hex(&key.sign(&canonical).to_bytes())}))
+            .collect::<Vec<_>>();
+        serde_json::to_vec(&json!({"signed": signed, "signatures": signatures})).unwrap()
+    }
+
+    #[test]
+    fn test_verify_threshold() {
+        let (a, b) = (
+            SigningKey::from_bytes(&[1; 32]),
+            SigningKey::from_bytes(&[2; 32]),
+        );
+        let keys = [("a", &a), ("b", &b)]
+            .into_iter()
+            .map(|(id, key)| {
+                let key = Key {
+                    keytype: "ed25519".to_owned(),
+                    keyval: KeyVal {
+                        public: hex(key.verifying_key().as_bytes()),
+                    },
+                };
+                (id.to_owned(), key)
+            })
+            .collect::<HashMap<_, _>>();
+        let role_keys = RoleKeys {
+            keyids: vec!["a".to_owned(), "b".to_owned()],
+            threshold: 2,
+        };
+        let signed = json!({
+            "_type": "timestamp",
+            "version": 1,
+            "expires": "2100-01-01T00:00:00Z",
+            "meta": {"snapshot.json": {"version": 1}},
+        });
+
+        let raw = sign(&signed, &[("a", &a), ("b", &b)]);
+        let meta = verify::<Meta>(&raw, "timestamp", &keys, &role_keys, true).unwrap();
+        assert_eq!(meta.meta["snapshot.json"].version, 1);
+        // Signing twice with the same key doesn’t count
+        let raw = sign(&signed, &[("a", &a), ("a", &a)]);
+        assert!(verify::<Meta>(&raw, "timestamp", &keys, &role_keys, true).is_err());
+        // Signatures have to be made by the key they claim
+        let raw = sign(&signed, &[("a", &a), ("b", &a)]);
+        assert!(verify::<Meta>(&raw, "timestamp", &keys, &role_keys, true).is_err());
+        // Repeating a keyid in the role doesn’t count it twice
+        let repeated = RoleKeys {
+            keyids: vec!["a".to_owned(), "b".to_owned(), "a".to_owned()],
+            threshold: 3,
+        };
+        let raw = sign(&signed, &[("a", &a), ("b", &b)]);
+        assert!(verify::<Meta>(&raw, "timestamp", &keys, &repeated, true).is_err());
+        // Neither do two keyids for the same key
+        let mut aliased = keys.clone();
+        aliased.insert("c".to_owned(), keys["a"].clone());
+        let role_keys_aliased = RoleKeys {
+            keyids: vec!["a".to_owned(), "c".to_owned()],
+            threshold: 2,
+        };
+        let raw = sign(&signed, &[("a", &a), ("c", &a)]);
+        assert!(verify::<Meta>(&raw, "timestamp", &aliased, &role_keys_aliased, true).is_err());
+        // Metadata of another role is rejected
+        let raw = sign(&signed, &[("a", &a), ("b", &b)]);
+        assert!(verify::<Meta>(&raw, "snapshot", &keys, &role_keys, true).is_err());
+
+        let expired = json!({
+            "_type": "timestamp",
+            "version": 1,
+            "expires": "2000-01-01T00:00:00Z",
+            "meta": {},
+        });
+        let raw = sign(&expired, &[("a", &a), ("b", &b)]);
+        assert!(verify::<Meta>(&raw, "timestamp", &keys, &role_keys, true).is_err());
+        assert!(verify::<Meta>(&raw, "timestamp", &keys, &role_keys, false).is_ok());
+    }
+
+    #[test]
+    fn test_delegated_role() {
+        let role = |paths: Option<&[&str]>, prefixes: Option<&[&str]>| DelegatedRole {
+            name: "bins".to_owned(),
+            keys: RoleKeys {
+                keyids: Vec::new(),
+                threshold: 1,
+            },
+            terminating: false,
+            paths: paths.map(|p| p.iter().map(|&p| p.to_owned()).collect()),
+            path_hash_prefixes: prefixes.map(|p| p.iter().map(|&p| p.to_owned()).collect()),
+        };
+        let by_path = role(Some(&["packages/*/foo-*.whl"]), None);
+        assert!(by_path.is_responsible_for("packages/ab/foo-1.0-py3-none-any.whl"));
+        assert!(!by_path.is_responsible_for("packages/ab/cd/foo-1.0-py3-none-any.whl"));
+        assert!(!by_path.is_responsible_for("packages/ab/bar-1.0-py3-none-any.whl"));
+
+        let path = "packages/ab/foo-1.0-py3-none-any.whl";
+        let prefix = &hex(&Sha256::digest(path.as_bytes()))[..2];
+        assert!(role(None, Some(&[prefix])).is_responsible_for(path));
+        assert!(!role(None, Some(&["xx"])).is_responsible_for(path));
+    }
+
+    /// Signed metadata of `role`, with `fields` besides the common ones
+    fn signed_meta(
+        key: &SigningKey,
+        role: &str,
+        version: u64,
+        fields: serde_json::Value,
+    ) -> hyper::body::Bytes {
+        let mut signed =
+            json!({"_type": role, "version": version, "expires": "2100-01-01T00:00:00Z"});
+        let serde_json::Value::Object(fields) = fields else {
+            panic!("fields have to be an object");
+        };
+        signed.as_object_mut().unwrap().extend(fields);
+        sign(&signed, &[("k", key)]).into()
+    }
+
+    /// A root trusting `key` for all roles, and timestamp, snapshot and targets metadata signing `targets`
+    fn signed_repo(
+        key: &SigningKey,
+        targets: &serde_json::Value,
+    ) -> (Vec<u8>, Vec<(String, hyper::body::Bytes)>) {
+        let roles = ["root", "timestamp", "snapshot", "targets"]
+            .map(|role| (role.to_owned(), json!({"keyids": ["k"], "threshold": 1})));
+        let root = signed_meta(
+            key,
+            "root",
+            1,
+            json!({
+                "keys": {"k": {"keytype": "ed25519", "keyval": {"public": hex(key.verifying_key().as_bytes())}}},
+                "roles": serde_json::Map::from_iter(roles),
+            }),
+        );
+        let files = [
+            ("timestamp.json", signed_timestamp(key, 2)),
+            (
+                "snapshot.json",
+                signed_meta(
+                    key,
+                    "snapshot",
+                    2,
+                    json!({"meta": {"targets.json": {"version": 1}}}),
+                ),
+            ),
+            (
+                "targets.json",
+                signed_meta(key, "targets", 1, json!({"targets": targets})),
+            ),
+        ];
+        let files = files.map(|(name, raw)| (format!("/tuf/{name}"), raw));
+        (root.to_vec(), files.into())
+    }
+
+    fn signed_timestamp(key: &SigningKey, version: u64) -> hyper::body::Bytes {
+        let meta = json!({"meta": {"snapshot.json": {"version": version}}});
+        signed_meta(key, "timestamp", version, meta)
+    }
+
+    #[tokio::test]
+    async fn test_pages_and_rollback() {
+        use std::collections::BTreeMap;
+
+        use hyper::StatusCode;
+        use hyper::body::Bytes;
+
+        use crate::python_pkg::PackageName;
+        use crate::test_util;
+
+        let key = SigningKey::from_bytes(&[3; 32]);
+        let page = "<a href=\"../../files/foo-1.0-py3-none-any.whl\">foo-1.0-py3-none-any.whl</a>";
+        let (root, metadata) = signed_repo(
+            &key,
+            &json!({"simple/foo/index.html": {
+                "length": page.len(),
+                "hashes": {"sha256": hex(&Sha256::digest(page))},
+            }}),
+        );
+        let mut files = BTreeMap::from_iter(metadata);
+        files.insert("/simple/foo/".to_owned(), Bytes::from(page));
+        files.insert("/simple/bar/".to_owned(), Bytes::from(page));
+        let files = Arc::new(Mutex::new(files));
+        let server = test_util::serve({
+            let files = files.clone();
+            move |_, req| {
+                let files = files.lock().unwrap();
+                match files.get(req.uri().path()) {
+                    Some(content) => test_util::file_response(content, req),
+                    None => test_util::status(StatusCode::NOT_FOUND),
+                }
+            }
+        })
+        .await
+        .unwrap();
+        let base = Url::parse(&format!("http://{}/", server.addr())).unwrap();
+        let http = ClientWithMiddleware::from(reqwest::Client::new());
+        let dir = std::env::temp_dir().join(format!("pypi-lazyzip-tuf-{}", std::process::id()));
+        let versions = dir.join("versions.json");
+
+        let repo = TufRepository::load(
+            http.clone(),
+            base.join("tuf/").unwrap(),
+            &root,
+            Some(&versions),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            TrustedVersions::load(&versions).unwrap(),
+            TrustedVersions {
+                timestamp: 2,
+                snapshot: 2
+            }
+        );
+
+        // Pages are only used if they’re signed and unchanged
+        let client = crate::LazyZipClient::new(http.clone(), [base.join("simple/").unwrap()])
+            .with_tuf(repo, base.clone());
+        let foo = PackageName::new_unchecked("foo");
+        assert_eq!(client.find_project(&foo).await.unwrap().files.len(), 1);
+        let bar = PackageName::new_unchecked("bar");
+        assert!(client.find_project(&bar).await.is_err());
+        files.lock().unwrap().insert(
+            "/simple/foo/".to_owned(),
+            Bytes::from(page.replace("1.0", "6.6")),
+        );
+        client.forget_project(&foo);
+        assert!(client.find_project(&foo).await.is_err());
+
+        // Older metadata than seen before is a rollback
+        files
+            .lock()
+            .unwrap()
+            .insert("/tuf/timestamp.json".to_owned(), signed_timestamp(&key, 1));
+        let error = TufRepository::load(http, base.join("tuf/").unwrap(), &root, Some(&versions))
+            .await
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("rolled back"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::BTreeSet;
+use std::str::FromStr as _;
+
+use color_eyre::eyre::Result;
+use pep508_rs::Requirement;
+use pypi_lazyzip::python_pkg::{Dependency, Metadata};
+use pypi_lazyzip::wheel::{self, SharedZipReader};
+use pypi_lazyzip::{LazyZipClient, PkgLoc};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+/// Entries larger than this aren’t previewed
+const MAX_PREVIEW_SIZE: u64 = 1024 * 1024;
+
+const HELP: &str =
+    "↑↓ select · Enter preview/open · Tab switch view · PgUp/PgDn scroll · Backspace back · q quit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum View {
+    Files,
+    Metadata,
+    Requirements,
+}
+
+/// A row in the file tree: a directory, or a file with its entry index
+struct Row {
+    label: String,
+    entry: Option<usize>,
+}
+
+/// A package being explored
+struct Pkg {
+    name: String,
+    zip_reader: SharedZipReader,
+    rows: Vec<Row>,
+    metadata: Option<Metadata>,
+    requirements: Vec<Requirement>,
+    view: View,
+    files: ListState,
+    reqs: ListState,
+    preview: String,
+    scroll: u16,
+}
+
+impl Pkg {
+    async fn open(client: &LazyZipClient, pkg_loc: PkgLoc) -> Result<Self> {
+        let (name, mut zip_reader) = wheel::open_zip(client, pkg_loc).await?;
+        let mut paths = zip_reader
+            .file()
+            .entries()
+            .iter()
+            .enumerate()
+            .map(|(i, e)| Ok((e.filename().as_str()?.to_owned(), i)))
+            .collect::<Result<Vec<_>>>()?;
+        paths.sort();
+        let metadata = match wheel::find_entry(&mut zip_reader, wheel::is_metadata) {
+            Some(idx) => {
+                let mut buf = String::new();
+                wheel::read_entry(&mut zip_reader, idx, &mut buf).await?;
+                Some(Metadata::from_str(&buf)?)
+            }
+            None => None,
+        };
+        let requirements = metadata
+            .as_ref()
+            .map(Metadata::requires_dist)
+            .transpose()?
+            .unwrap_or_default();
+        let name = metadata
+            .as_ref()
+            .and_then(|m| Some(format!("{} {}", m.get("Name")?, m.get("Version")?)))
+            .unwrap_or_else(|| name.to_string());
+        Ok(Pkg {
+            name,
+            zip_reader,
+            rows: tree_rows(&paths),
+            metadata,
+            requirements,
+            view: View::Files,
+            files: ListState::default().with_selected(Some(0)),
+            reqs: ListState::default().with_selected(Some(0)),
+            preview: String::new(),
+            scroll: 0,
+        })
+    }
+
+    async fn load_preview(&mut self) {
+        let Some(entry) = self.files.selected().and_then(|i| self.rows[i].entry) else {
+            return;
+        };
+        self.scroll = 0;
+        let size = self.zip_reader.file().entries()[entry].uncompressed_size();
+        if size > MAX_PREVIEW_SIZE {
+            self.preview = format!("({size} bytes, too large to preview)");
+            return;
+        }
+        let mut buf = String::new();
+        self.preview = match wheel::read_entry(&mut self.zip_reader, entry, &mut buf).await {
+            Ok(_) => buf,
+            Err(e) => format!("(can’t preview: {e})"),
+        };
+    }
+
+    fn selected_requirement(&self) -> Option<&Requirement> {
+        self.reqs.selected().and_then(|i| self.requirements.get(i))
+    }
+}
+
+/// Turn sorted paths into an indented tree with a row for each directory
+fn tree_rows(paths: &[(String, usize)]) -> Vec<Row> {
+    let mut dirs = BTreeSet::new();
+    let mut rows = Vec::new();
+    for (path, entry) in paths {
+        let parts = path.split('/').collect::<Vec<_>>();
+        for depth in 1..parts.len() {
+            let dir = parts[..depth].join("/");
+            if dirs.insert(dir) {
+                let label = format!("{}{}/", "  ".repeat(depth - 1), parts[depth - 1]);
+                rows.push(Row { label, entry: None });
+            }
+        }
+        if let Some(file) = parts.last().filter(|f| !f.is_empty()) {
+            let label = format!("{}{file}", "  ".repeat(parts.len() - 1));
+            rows.push(Row {
+                label,
+                entry: Some(*entry),
+            });
+        }
+    }
+    rows
+}
+
+/// Explore a package interactively
+pub async fn run(client: &LazyZipClient, pkg_loc: PkgLoc) -> Result<()> {
+    let pkg = Pkg::open(client, pkg_loc).await?;
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, client, vec![pkg]).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    client: &LazyZipClient,
+    mut stack: Vec<Pkg>,
+) -> Result<()> {
+    let mut status = String::from(HELP);
+    loop {
+        let breadcrumb = stack
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<_>>()
+            .join(" → ");
+        let depth = stack.len();
+        let Some(pkg) = stack.last_mut() else {
+            return Ok(());
+        };
+        terminal.draw(|frame| draw(frame, pkg, &breadcrumb, &status))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        status = String::from(HELP);
+        let list = match pkg.view {
+            View::Requirements => &mut pkg.reqs,
+            View::Files | View::Metadata => &mut pkg.files,
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Backspace if depth > 1 => {
+                stack.pop();
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Tab => {
+                pkg.view = match pkg.view {
+                    View::Files => View::Metadata,
+                    View::Metadata => View::Requirements,
+                    View::Requirements => View::Files,
+                };
+                pkg.scroll = 0;
+            }
+            KeyCode::Down | KeyCode::Char('j') if pkg.view != View::Metadata => list.select_next(),
+            KeyCode::Up | KeyCode::Char('k') if pkg.view != View::Metadata => {
+                list.select_previous();
+            }
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::PageDown => {
+                pkg.scroll = pkg.scroll.saturating_add(10);
+            }
+            KeyCode::Up | KeyCode::Char('k') | KeyCode::PageUp => {
+                pkg.scroll = pkg.scroll.saturating_sub(10);
+            }
+            KeyCode::Enter if pkg.view == View::Files => pkg.load_preview().await,
+            KeyCode::Enter if pkg.view == View::Requirements => {
+                let Some(req) = pkg.selected_requirement() else {
+                    continue;
+                };
+                let pkg_loc = PkgLoc::Dependency(Dependency::from_requirement(req)?);
+                status = format!("Loading {pkg_loc}…");
+                terminal.draw(|frame| draw(frame, pkg, &breadcrumb, &status))?;
+                match Pkg::open(client, pkg_loc).await {
+                    Ok(dep) => {
+                        stack.push(dep);
+                        status = String::from(HELP);
+                    }
+                    Err(e) => status = format!("{e:#}"),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, pkg: &mut Pkg, breadcrumb: &str, status: &str) {
+    let [header, body, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let highlight = Style::default().add_modifier(Modifier::REVERSED);
+    let tabs = [
+        (View::Files, "Files"),
+        (View::Metadata, "Metadata"),
+        (View::Requirements, "Requirements"),
+    ]
+    .map(|(view, title)| {
+        if view == pkg.view {
+            format!("[{title}]")
+        } else {
+            format!(" {title} ")
+        }
+    })
+    .join("");
+    frame.render_widget(Line::from(format!("{breadcrumb}  {tabs}")), header);
+    frame.render_widget(Line::from(status), footer);
+
+    match pkg.view {
+        View::Files => {
+            let [tree, preview] =
+                Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                    .areas(body);
+            let list = List::new(pkg.rows.iter().map(|r| r.label.as_str()))
+                .block(Block::bordered().title("Files"))
+                .highlight_style(highlight);
+            frame.render_stateful_widget(list, tree, &mut pkg.files);
+            let paragraph = Paragraph::new(pkg.preview.as_str())
+                .block(Block::bordered().title("Preview"))
+                .scroll((pkg.scroll, 0));
+            frame.render_widget(paragraph, preview);
+        }
+        View::Metadata => {
+            let text = match &pkg.metadata {
+                Some(metadata) => serde_json::to_string_pretty(metadata).unwrap_or_default(),
+                None => String::from("(no METADATA)"),
+            };
+            let paragraph = Paragraph::new(text)
+                .block(Block::bordered().title("Metadata"))
+                .wrap(Wrap { trim: false })
+                .scroll((pkg.scroll, 0));
+            frame.render_widget(paragraph, body);
+        }
+        View::Requirements => {
+            let list = List::new(pkg.requirements.iter().map(ToString::to_string))
+                .block(Block::bordered().title("Requirements (Enter to open)"))
+                .highlight_style(highlight);
+            frame.render_stateful_widget(list, body, &mut pkg.reqs);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Finding dependencies a wheel bundles instead of requiring, for `vendored-scan`.
+//!
+//! Vulnerability scanners match advisories against installed distributions,
+//! so vendored copies are invisible to them unless someone knows where to look.
+//! This is a heuristic over the file listing: modules in vendoring directories
+//! and well-known packages nested inside other packages.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::python_pkg::normalize;
+
+/// Directory names conventionally holding vendored code, like `pip/_vendor`
+const VENDOR_DIRS: &[&str] = &[
+    "_vendor",
+    "vendor",
+    "_vendored",
+    "vendored",
+    "_extern",
+    "extern",
+    "_third_party",
+    "third_party",
+];
+
+/// Import names of popular packages that are commonly vendored
+const WELL_KNOWN: &[&str] = &[
+    "attr",
+    "cachecontrol",
+    "certifi",
+    "chardet",
+    "charset_normalizer",
+    "colorama",
+    "dateutil",
+    "distlib",
+    "distro",
+    "idna",
+    "importlib_metadata",
+    "jaraco",
+    "more_itertools",
+    "msgpack",
+    "packaging",
+    "pkg_resources",
+    "platformdirs",
+    "pygments",
+    "pyparsing",
+    "requests",
+    "rich",
+    "simplejson",
+    "six",
+    "toml",
+    "tomli",
+    "tomli_w",
+    "truststore",
+    "typing_extensions",
+    "urllib3",
+    "yaml",
+    "zipp",
+];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Evidence {
+    /// A module in a vendoring directory
+    VendorDir,
+    /// A well-known package nested inside another package
+    WellKnown,
+}
+
+/// A bundled copy of a dependency
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Vendored {
+    /// The import name
+    pub name: String,
+    /// Where it is in the wheel
+    pub path: String,
+    /// From a `*.dist-info` directory next to it, if it was vendored with one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub evidence: Evidence,
+}
+
+/// Find vendored dependencies in a wheel’s file listing, sorted by path
+pub fn scan<'a>(paths: impl IntoIterator<Item = &'a str>) -> Vec<Vendored> {
+    let mut found = BTreeMap::<String, Vendored>::new();
+    // Versions by containing directory and normalized name
+    let mut versions = BTreeMap::<(String, String), String>::new();
+    for path in paths {
+        let parts = path.split('/').collect::<Vec<_>>();
+        // The last part is a file name, or empty for directory entries
+        let dirs = &parts[..parts.len() - 1];
+        if let Some(i) = dirs.iter().position(|d| VENDOR_DIRS.contains(d)) {
+            let parent = parts[..=i].join("/");
+            let Some(&child) = parts.get(i + 1) else {
+                continue;
+            };
+            if let Some(dist_info) = child.strip_suffix(".dist-info") {
+                if let Some((name, version)) = dist_info.rsplit_once('-')
+                    && let Ok(name) = normalize(name)
+                {
+                    versions.insert((parent, name), version.to_owned());
+                }
+                continue;
+            }
+            let name = if i + 1 < dirs.len() {
+                child
+            } else if let Some(module) = child.strip_suffix(".py")
+                && module != "__init__"
+            {
+                module
+            } else {
+                continue;
+            };
+            let path = format!("{parent}/{child}");
+            found.entry(path.clone()).or_insert_with(|| Vendored {
+                name: name.to_owned(),
+                path,
+                version: None,
+                evidence: Evidence::VendorDir,
+            });
+            continue;
+        }
+        // Well-known packages anywhere but at the top level, where they’re the wheel’s own
+        for (j, dir) in dirs.iter().enumerate().skip(1) {
+            if WELL_KNOWN.contains(dir) && parts.get(j + 1) == Some(&"__init__.py") {
+                let path = parts[..=j].join("/");
+                found.entry(path.clone()).or_insert_with(|| Vendored {
+                    name: (*dir).to_owned(),
+                    path,
+                    version: None,
+                    evidence: Evidence::WellKnown,
+                });
+            }
+        }
+    }
+    for vendored in found.values_mut() {
+        let parent = vendored
+            .path
+            .rsplit_once('/')
+            .map_or("", |(parent, _)| parent);
+        if let Ok(name) = normalize(&vendored.name) {
+            vendored.version = versions.get(&(parent.to_owned(), name)).cloned();
+        }
+    }
+    found.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        let paths = [
+            "pip/__init__.py",
+            "pip/_vendor/__init__.py",
+            "pip/_vendor/vendor.txt",
+            "pip/_vendor/six.py",
+            "pip/_vendor/urllib3/__init__.py",
+            "pip/_vendor/urllib3/util/retry.py",
+            "pip/_vendor/typing_extensions-4.12.2.dist-info/METADATA",
+            "pip/_vendor/typing_extensions.py",
+            "botocore/vendored/requests/__init__.py",
+            "foo/compat/yaml/__init__.py",
+            "packaging/__init__.py",
+            "pip-24.0.dist-info/METADATA",
+        ];
+        let found = scan(paths)
+            .into_iter()
+            .map(|v| (v.path, v.name, v.version, v.evidence))
+            .collect::<Vec<_>>();
+        let entry = |path: &str, name: &str, version: Option<&str>, evidence| {
+            (
+                path.to_owned(),
+                name.to_owned(),
+                version.map(ToOwned::to_owned),
+                evidence,
+            )
+        };
+        assert_eq!(
+            found,
+            [
+                entry(
+                    "botocore/vendored/requests",
+                    "requests",
+                    None,
+                    Evidence::VendorDir
+                ),
+                entry("foo/compat/yaml", "yaml", None, Evidence::WellKnown),
+                entry("pip/_vendor/six.py", "six", None, Evidence::VendorDir),
+                entry(
+                    "pip/_vendor/typing_extensions.py",
+                    "typing_extensions",
+                    Some("4.12.2"),
+                    Evidence::VendorDir
+                ),
+                entry("pip/_vendor/urllib3", "urllib3", None, Evidence::VendorDir),
+            ]
+        );
+    }
+}
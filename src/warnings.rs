@@ -0,0 +1,170 @@
+//! Caveats about results that don’t warrant failing.
+//!
+//! Library code reports them via [`warn`].
+//! Callers can [`collect`] the ones reported while computing a result;
+//! warnings outside of [`collect`] are only logged.
+
+use std::cell::RefCell;
+use std::fmt;
+
+use reqwest::Url;
+use serde::Serialize;
+
+use crate::simple_repo_api::Status;
+
+tokio::task_local! {
+    static COLLECTED: RefCell<Vec<Warning>>;
+}
+
+/// Something that didn’t prevent a result, but might make it differ from what’s expected
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Warning {
+    /// A newer matching release was skipped because it’s yanked
+    YankedSkipped {
+        filename: String,
+        reason: Option<String>,
+    },
+    /// The server doesn’t support range requests, so the whole file was downloaded
+    RangesUnsupported { url: Url },
+    /// The wheel has no `METADATA` file
+    MetadataMissing,
+    /// A version constraint was ignored for a file
+    ConstraintIgnored { location: String },
+    /// A file’s hash differs from the one pinned when it was first used
+    PinnedHashChanged {
+        filename: String,
+        pinned: String,
+        actual: String,
+    },
+    /// An sdist’s `PKG-INFO` marks fields as computed at build time,
+    /// so their values in it, e.g. dependencies, may be missing or incomplete
+    DynamicMetadata { fields: Vec<String> },
+    /// The project is marked archived, deprecated or quarantined by the index (PEP 792)
+    ProjectStatus {
+        project: String,
+        status: Status,
+        reason: Option<String>,
+    },
+    /// Indexes offer different files with the same name, so the one from the preferred index is used
+    IndexConflict {
+        filename: String,
+        preferred: Url,
+        ignored: Url,
+    },
+    /// A file couldn’t be read as an archive, e.g. a truncated upload,
+    /// so another file of the same version was used
+    FileSubstituted {
+        filename: String,
+        substitute: String,
+        error: String,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::YankedSkipped {
+                filename,
+                reason: Some(reason),
+            } => write!(f, "skipped yanked {filename}: {reason}"),
+            Warning::YankedSkipped {
+                filename,
+                reason: None,
+            } => write!(f, "skipped yanked {filename}"),
+            Warning::RangesUnsupported { url } => {
+                write!(
+                    f,
+                    "{url} doesn’t support range requests, downloaded it fully"
+                )
+            }
+            Warning::MetadataMissing => f.write_str("wheel has no METADATA"),
+            Warning::ConstraintIgnored { location } => {
+                write!(f, "ignoring version constraint for file {location}")
+            }
+            Warning::PinnedHashChanged {
+                filename,
+                pinned,
+                actual,
+            } => write!(
+                f,
+                "hash of {filename} changed from pinned {pinned} to {actual}"
+            ),
+            Warning::IndexConflict {
+                filename,
+                preferred,
+                ignored,
+            } => write!(
+                f,
+                "{filename} differs between {preferred} and {ignored}, using the former"
+            ),
+            Warning::ProjectStatus {
+                project,
+                status,
+                reason,
+            } => {
+                write!(f, "{project} is {status}")?;
+                match reason {
+                    Some(reason) => write!(f, ": {reason}"),
+                    None => Ok(()),
+                }
+            }
+            Warning::FileSubstituted {
+                filename,
+                substitute,
+                error,
+            } => write!(
+                f,
+                "{filename} is unreadable ({error}), used {substitute} instead"
+            ),
+            Warning::DynamicMetadata { fields } => write!(
+                f,
+                "metadata fields are computed at build time: {}",
+                fields.join(", ")
+            ),
+        }
+    }
+}
+
+/// Report a warning, logging it and adding it to the surrounding [`collect`] call, if any
+pub fn warn(warning: Warning) {
+    tracing::warn!(%warning);
+    // Outside of `collect`, there’s nowhere to add it to
+    let _ = COLLECTED.try_with(|collected| collected.borrow_mut().push(warning));
+}
+
+/// Run `future`, collecting the warnings reported while it runs
+pub async fn collect<F: Future>(future: F) -> (F::Output, Vec<Warning>) {
+    COLLECTED
+        .scope(RefCell::default(), async {
+            let output = future.await;
+            (output, COLLECTED.with(RefCell::take))
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_collect_nested() {
+        let (((), inner), outer) = collect(async {
+            warn(Warning::MetadataMissing);
+            collect(async {
+                warn(Warning::ConstraintIgnored {
+                    location: "a".into(),
+                });
+            })
+            .await
+        })
+        .await;
+        assert_eq!(
+            inner,
+            [Warning::ConstraintIgnored {
+                location: "a".into()
+            }]
+        );
+        assert_eq!(outer, [Warning::MetadataMissing]);
+    }
+}
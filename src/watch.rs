@@ -0,0 +1,150 @@
+//! Polling a project’s file list and reporting changes as JSON Patch ([RFC 6902](https://www.rfc-editor.org/rfc/rfc6902)), for `watch`.
+//!
+//! The watched document maps file names to what the index says about them,
+//! so patches show exactly which files were added, removed, yanked or changed.
+
+use std::time::Duration;
+
+use eyre::Result;
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use tokio::time::MissedTickBehavior;
+
+use crate::LazyZipClient;
+use crate::python_pkg::PackageName;
+use crate::simple_repo_api::{Project, Yanking};
+
+/// One operation of a JSON Patch
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+}
+
+/// Poll `name` every `interval`, passing the changes to `emit`.
+/// The first patch adds all files to an empty document, later ones are skipped if nothing changed.
+pub async fn watch(
+    client: &LazyZipClient,
+    name: &PackageName,
+    interval: Duration,
+    mut emit: impl FnMut(Vec<Operation>) -> Result<()>,
+) -> Result<()> {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut previous = Value::Object(Map::new());
+    loop {
+        ticks.tick().await;
+        client.forget_project(name);
+        let current = match client.find_project(name).await {
+            Ok(project) => file_list(&project),
+            Err(e) => {
+                tracing::warn!("Failed to poll {name}: {e:#}");
+                continue;
+            }
+        };
+        let patch = diff(&previous, &current);
+        if !patch.is_empty() {
+            emit(patch)?;
+        }
+        previous = current;
+    }
+}
+
+/// The watched document of a project, mapping file names to their details
+fn file_list(project: &Project) -> Value {
+    let files = project.files.iter().map(|file| {
+        let yanked = match &file.yanked {
+            Yanking::NotYanked => Value::Bool(false),
+            Yanking::Yanked(None) => Value::Bool(true),
+            Yanking::Yanked(Some(reason)) => reason.as_str().into(),
+        };
+        let details = json!({
+            "url": file.url,
+            "hashes": file.hashes,
+            "size": file.size,
+            "upload-time": file.upload_time.map(|t| t.to_string()),
+            "requires-python": file.requires_python,
+            "yanked": yanked,
+        });
+        (file.filename.clone(), details)
+    });
+    Value::Object(files.collect())
+}
+
+/// The operations turning `old` into `new`, recursing into objects and replacing everything else whole
+pub fn diff(old: &Value, new: &Value) -> Vec<Operation> {
+    let mut ops = Vec::new();
+    diff_at(&mut String::new(), old, new, &mut ops);
+    ops
+}
+
+fn diff_at(path: &mut String, old: &Value, new: &Value, ops: &mut Vec<Operation>) {
+    let (Value::Object(old), Value::Object(new)) = (old, new) else {
+        if old != new {
+            ops.push(Operation::Replace {
+                path: path.clone(),
+                value: new.clone(),
+            });
+        }
+        return;
+    };
+    let len = path.len();
+    for (key, old_value) in old {
+        push_token(path, key);
+        match new.get(key) {
+            Some(new_value) => diff_at(path, old_value, new_value, ops),
+            None => ops.push(Operation::Remove { path: path.clone() }),
+        }
+        path.truncate(len);
+    }
+    for (key, new_value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+        push_token(path, key);
+        ops.push(Operation::Add {
+            path: path.clone(),
+            value: new_value.clone(),
+        });
+        path.truncate(len);
+    }
+}
+
+/// Append a JSON Pointer reference token, escaping `~` and `/` as RFC 6901 requires
+fn push_token(path: &mut String, key: &str) {
+    path.push('/');
+    path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let old = json!({
+            "foo-1.0.tar.gz": {"size": 10, "yanked": false},
+            "foo-1.1.tar.gz": {"size": 11, "yanked": false},
+        });
+        let new = json!({
+            "foo-1.0.tar.gz": {"size": 10, "yanked": "broken"},
+            "foo-2.0/weird~name.tar.gz": {"size": 20},
+        });
+        assert_eq!(
+            diff(&old, &new),
+            [
+                Operation::Replace {
+                    path: "/foo-1.0.tar.gz/yanked".to_owned(),
+                    value: "broken".into(),
+                },
+                Operation::Remove {
+                    path: "/foo-1.1.tar.gz".to_owned(),
+                },
+                Operation::Add {
+                    path: "/foo-2.0~1weird~0name.tar.gz".to_owned(),
+                    value: json!({"size": 20}),
+                },
+            ]
+        );
+        assert_eq!(diff(&new, &new), []);
+    }
+}
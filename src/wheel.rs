@@ -0,0 +1,1278 @@
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::str::FromStr as _;
+use std::sync::{Arc, PoisonError};
+use std::task::{Context, Poll};
+
+use async_http_range_reader::AsyncHttpRangeReader;
+use async_zip::StoredZipEntry;
+use async_zip::base::read::seek::ZipFileReader;
+use async_zip::error::ZipError;
+use either::Either;
+use eyre::{Context as _, ContextCompat, Result, bail};
+use futures::io::BufReader;
+use futures::{AsyncBufRead, AsyncBufReadExt as _, AsyncRead, AsyncSeek, StreamExt as _};
+use percent_encoding::percent_decode_str;
+use reqwest::header::HeaderMap;
+use reqwest::{StatusCode, Url};
+use tokio::sync::{Mutex, OnceCell, OwnedMutexGuard, OwnedSemaphorePermit};
+use tokio_util::compat::TokioAsyncReadCompatExt as _;
+use tracing::instrument::Instrument as _;
+
+use crate::build_backend::WheelBuild;
+use crate::client::WheelPreference;
+use crate::error::Failure;
+use crate::hashes::{Hashes, StreamHasher};
+use crate::package_report::FileInfo;
+use crate::parallel_ranges::{RegionReader, Regions};
+use crate::python_pkg::{
+    Dependency, LegacyFilename, Metadata, PackageName, WheelFilename, normalize,
+};
+use crate::signed_urls::RefreshExpired;
+use crate::simple_repo_api::{self, Yanking};
+use crate::warnings::{self, Warning};
+use crate::{LazyZipClient, PkgLoc};
+
+pub async fn pkg_metadata(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+) -> Result<(PackageName, Metadata)> {
+    read_pkg_metadata(client, pkg_loc, false).await
+}
+
+/// Like [`pkg_metadata`], but stops reading `METADATA` where the long description starts.
+/// For packages embedding huge READMEs, this saves fetching and decompressing them.
+#[tracing::instrument(skip(client), fields(pkg_loc = %pkg_loc))]
+pub async fn pkg_metadata_headers(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+) -> Result<(PackageName, Metadata)> {
+    read_pkg_metadata(client, pkg_loc, true).await
+}
+
+async fn read_pkg_metadata(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+    headers_only: bool,
+) -> Result<(PackageName, Metadata)> {
+    let (name, mut zip_reader) = match open_zip(client, pkg_loc).await {
+        Ok(opened) => opened,
+        Err(error) => {
+            if let Some(unreadable) = error.downcast_ref::<Unreadable>().cloned() {
+                let name = unreadable.name.clone();
+                // Reading sdists takes a lot of state, so keep it out of the common path’s future
+                if let Some(metadata) = Box::pin(sdist_metadata(client, unreadable)).await {
+                    return Ok((name, metadata));
+                }
+            }
+            return Err(error);
+        }
+    };
+    // Prefer the project’s own `METADATA` if a wheel has multiple `*.dist-info` directories
+    let idx = find_entry(&mut zip_reader, dist_info_file(&name, "METADATA"))
+        .or_else(|| find_entry(&mut zip_reader, is_metadata));
+    if let Some(idx) = idx {
+        let mut buf = String::new();
+        if !headers_only {
+            read_entry_stored(client, &mut zip_reader, idx, &mut buf).await?;
+        } else if let Some(content) = stored_entry(client, &zip_reader, idx) {
+            buf.push_str(headers(&content));
+        } else {
+            read_entry_headers(&mut zip_reader, idx, &mut buf).await?;
+        }
+        return Ok((name, Metadata::from_str(&buf)?));
+    }
+    if client.legacy_formats()
+        && let Some(metadata) = crate::legacy::metadata(&mut zip_reader).await?
+    {
+        return Ok((name, metadata));
+    }
+    bail!(Failure::entry_missing("wheel has no METADATA"))
+}
+
+pub fn is_metadata(e: &StoredZipEntry) -> bool {
+    e.filename().as_str().is_ok_and(|n| {
+        n.split_once('/')
+            .is_some_and(|(dir, file)| dir.ends_with(".dist-info") && file == "METADATA")
+    })
+}
+
+/// The name and version of a `{name}-{version}.dist-info` directory
+pub fn split_dist_info(dir: &str) -> Option<(&str, &str)> {
+    dir.strip_suffix(".dist-info")?.rsplit_once('-')
+}
+
+/// Whether `dir` is the `*.dist-info` directory of the project `name`.
+/// Names are compared in their normalized forms, as directories spell them like the project,
+/// e.g. `Pillow-10.0.0.dist-info`, or escaped, e.g. `zope_interface-7.0.dist-info`.
+pub fn is_dist_info_of(dir: &str, name: &PackageName) -> bool {
+    let Some((dir_name, _)) = split_dist_info(dir) else {
+        return false;
+    };
+    match (normalize(dir_name), normalize(name.original())) {
+        (Ok(dir_name), Ok(name)) => dir_name == name,
+        _ => PackageName::new_unchecked(dir_name) == *name,
+    }
+}
+
+/// Match `file` in the `*.dist-info` directory of the project `name`, like `METADATA`
+pub fn dist_info_file<'a>(
+    name: &'a PackageName,
+    file: &'a str,
+) -> impl Fn(&StoredZipEntry) -> bool + 'a {
+    move |e| {
+        e.filename().as_str().is_ok_and(|path| {
+            path.split_once('/')
+                .is_some_and(|(dir, f)| f == file && is_dist_info_of(dir, name))
+        })
+    }
+}
+
+/// `*.dist-info/WHEEL`, with the wheel format version, tags and the tool that built it
+pub fn is_wheel_file(e: &StoredZipEntry) -> bool {
+    e.filename()
+        .as_str()
+        .is_ok_and(|n| n.ends_with(".dist-info/WHEEL"))
+}
+
+/// `*.dist-info/top_level.txt`, listing a wheel’s importable top-level modules
+pub fn is_top_level(e: &StoredZipEntry) -> bool {
+    e.filename()
+        .as_str()
+        .is_ok_and(|n| n.ends_with("/top_level.txt"))
+}
+
+/// `*.dist-info/entry_points.txt`, declaring a wheel’s scripts and plugins
+pub fn is_entry_points(e: &StoredZipEntry) -> bool {
+    e.filename()
+        .as_str()
+        .is_ok_and(|n| n.ends_with(".dist-info/entry_points.txt"))
+}
+
+/// The module names in `top_level.txt`
+pub fn top_level_names(content: &str) -> Vec<String> {
+    content
+        .split_terminator('\n')
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+#[tracing::instrument(skip(client), fields(pkg_loc = %pkg_loc))]
+pub async fn list_entries(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+) -> Result<(PackageName, Vec<String>)> {
+    let (name, zip_reader) = open_zip(client, pkg_loc).await?;
+    let filenames = zip_reader
+        .file()
+        .entries()
+        .iter()
+        .map(|e| e.filename().as_str().map(ToOwned::to_owned))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((name, filenames))
+}
+
+/// Read the first entry matching `predicate`, if any.
+/// See [`find_entry`] for what the predicate can check.
+#[tracing::instrument(skip(client, predicate), fields(pkg_loc = %pkg_loc))]
+pub async fn extract(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+    predicate: impl Fn(&StoredZipEntry) -> bool,
+) -> Result<(PackageName, Option<String>)> {
+    let (name, mut zip_reader) = open_zip(client, pkg_loc).await?;
+    let Some(idx_entry) = find_entry(&mut zip_reader, predicate) else {
+        return Ok((name, None));
+    };
+    let mut buf = String::new();
+    zip_reader.prefetch_entry(idx_entry).await?;
+    read_entry_stored(client, &mut zip_reader, idx_entry, &mut buf).await?;
+    Ok((name, Some(buf)))
+}
+
+pub type PkgZipReader = ZipFileReader<BufReader<Box<dyn AsyncRS>>>;
+
+/// A remote zip, opened by the first task reading it
+pub(crate) type ZipCell = OnceCell<(Arc<Mutex<PkgZipReader>>, Option<Arc<Regions>>)>;
+
+/// A zip reader that other tasks reading the same remote file share.
+/// It’s locked while this exists, so its central directory and fetched ranges are reused afterwards.
+pub struct SharedZipReader {
+    reader: OwnedMutexGuard<PkgZipReader>,
+    /// Where large entries are fetched in parallel, if the client does that
+    regions: Option<Arc<Regions>>,
+    /// The file’s SHA-256 from the index or URL, which its entries are stored in the blob store by
+    sha256: Option<String>,
+    /// Keeps the reader cached for other tasks while in use
+    _cell: Option<Arc<ZipCell>>,
+}
+
+impl SharedZipReader {
+    /// Fetch entry `idx` before reading it fully, in parallel parts if it’s large
+    /// and the client allows it (see [`LazyZipClient::with_parallel_ranges`]).
+    /// Does nothing for local files or if the server doesn’t support range requests.
+    pub async fn prefetch_entry(&self, idx: usize) -> Result<()> {
+        let (Some(regions), Some(entry)) = (&self.regions, self.file().entries().get(idx)) else {
+            return Ok(());
+        };
+        let start = entry.header_offset();
+        // The local header’s extra field can be longer than the central directory’s
+        let end = start + entry.header_size() + u64::from(u16::MAX) + entry.compressed_size();
+        regions.fetch(start..end).await
+    }
+}
+
+impl Deref for SharedZipReader {
+    type Target = PkgZipReader;
+
+    fn deref(&self) -> &Self::Target {
+        &self.reader
+    }
+}
+
+impl DerefMut for SharedZipReader {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.reader
+    }
+}
+
+/// Open a package as a zip. Tasks opening the same remote file concurrently share one reader.
+/// If the file chosen from an index is corrupt, another one of the same version is used.
+pub async fn open_zip(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+) -> Result<(PackageName, SharedZipReader)> {
+    let dep = match &pkg_loc {
+        PkgLoc::Dependency(dep) => Some(dep.clone()),
+        PkgLoc::Url(_) | PkgLoc::Path(_) => None,
+    };
+    let (name, file) = locate(client, pkg_loc).await?;
+    let reader = match file {
+        Either::Left(remote) => match (open_remote_zip(client, &remote).await, dep) {
+            (Err(error), Some(dep)) if is_corrupt(&error) => {
+                open_alternate(client, &dep, &remote, error).await?
+            }
+            (reader, _) => reader?,
+        },
+        Either::Right(path) => open_local_zip(path).await?,
+    };
+    Ok((name, reader))
+}
+
+/// Whether an error opening a zip means that its bytes are broken, rather than that they couldn’t be fetched
+fn is_corrupt(error: &eyre::Report) -> bool {
+    match error.downcast_ref::<ZipError>() {
+        Some(ZipError::UpstreamReadError(error)) => {
+            error.kind() == std::io::ErrorKind::UnexpectedEof
+        }
+        Some(_) => true,
+        None => false,
+    }
+}
+
+/// The index’s files of a version were all unreadable
+#[derive(Debug, Clone)]
+struct Unreadable {
+    name: PackageName,
+    /// The file chosen first
+    filename: String,
+    version: pep440_rs::Version,
+    /// Why it was unreadable
+    reason: String,
+}
+
+impl std::fmt::Display for Unreadable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no readable file of {} {}", self.name, self.version)
+    }
+}
+
+/// Open another file of the version `corrupt` belongs to, reporting the substitution
+async fn open_alternate(
+    client: &LazyZipClient,
+    dep: &Dependency,
+    corrupt: &RemoteFile,
+    error: eyre::Report,
+) -> Result<SharedZipReader> {
+    let version_of = |filename: &str| {
+        WheelFilename::from_str(filename)
+            .map(|whl| whl.version)
+            .ok()
+            .or_else(|| {
+                client
+                    .legacy_formats()
+                    .then(|| LegacyFilename::from_str(filename).ok().map(|f| f.version))
+                    .flatten()
+            })
+    };
+    let files = client.find_project(dep.name()).await?.files;
+    let Some((filename, version)) = files
+        .iter()
+        .find(|f| f.url == corrupt.url)
+        .and_then(|f| Some((f.filename.clone(), version_of(&f.filename)?)))
+    else {
+        return Err(error);
+    };
+    let reason = format!("{error:#}");
+    let alternates = files.into_iter().filter(|f| {
+        f.url != corrupt.url && !&f.yanked && version_of(&f.filename).as_ref() == Some(&version)
+    });
+    for file in alternates {
+        let substitute = file.filename.clone();
+        #[cfg(feature = "tuf")]
+        let Ok(file) = client.verify_tuf_target(file).await else {
+            continue;
+        };
+        match open_remote_zip(client, &RemoteFile::from_index(client, file)).await {
+            Ok(reader) => {
+                warnings::warn(Warning::FileSubstituted {
+                    filename,
+                    substitute,
+                    error: reason,
+                });
+                return Ok(reader);
+            }
+            Err(err) => tracing::info!(filename = substitute, %err, "alternate is unreadable too"),
+        }
+    }
+    Err(error.wrap_err(Unreadable {
+        name: dep.name().clone(),
+        filename,
+        version,
+        reason,
+    }))
+}
+
+/// Read the metadata of a version whose wheels are all unreadable from its sdist instead
+async fn sdist_metadata(client: &LazyZipClient, unreadable: Unreadable) -> Option<Metadata> {
+    let spec = pep440_rs::VersionSpecifier::equals_version(unreadable.version);
+    let pinned = Dependency::new(unreadable.name, Some(spec.into()));
+    let sdist = crate::sdist::find_sdist(client, &pinned).await.ok()?;
+    let (_, info) = crate::sdist::inspect(client, PkgLoc::Dependency(pinned))
+        .await
+        .ok()?;
+    let metadata = info.metadata?;
+    warnings::warn(Warning::FileSubstituted {
+        filename: unreadable.filename,
+        substitute: sdist.filename,
+        error: unreadable.reason,
+    });
+    Some(metadata)
+}
+
+/// Open a local zip. Local files are cheap to open, so they’re not shared.
+pub(crate) async fn open_local_zip(path: PathBuf) -> Result<SharedZipReader> {
+    let reader = zip_reader(open_path(path).await?).await?;
+    Ok(SharedZipReader {
+        reader: Arc::new(Mutex::new(reader)).lock_owned().await,
+        regions: None,
+        sha256: None,
+        _cell: None,
+    })
+}
+
+/// Open a remote zip, sharing it with other tasks reading the same file
+pub(crate) async fn open_remote_zip(
+    client: &LazyZipClient,
+    file: &RemoteFile,
+) -> Result<SharedZipReader> {
+    let cell = client.zip_cell(&file.url);
+    let (shared, regions) = cell
+        .get_or_try_init(|| async {
+            let url = file.url.clone();
+            let (reader, regions, len) = lazy_reader(client, url, &file.hashes, file.size).await?;
+            let mut reader = zip_reader(reader).await?;
+            if let Some(len) = len {
+                record_prefetch_hint(client, &file.url, &mut reader, len).await;
+            }
+            Ok::<_, eyre::Error>((Arc::new(Mutex::new(reader)), regions))
+        })
+        .await?;
+    Ok(SharedZipReader {
+        reader: shared.clone().lock_owned().await,
+        regions: regions.clone(),
+        sha256: file.hashes.sha256.clone(),
+        _cell: Some(cell),
+    })
+}
+
+pub async fn zip_reader(reader: Box<dyn AsyncRS>) -> Result<PkgZipReader> {
+    let buf_reader = BufReader::new(reader);
+    ZipFileReader::new(buf_reader)
+        .instrument(tracing::info_span!("create_zip_reader"))
+        .await
+        .context("Failed to read zip archive")
+}
+
+pub trait AsyncRS: AsyncRead + AsyncSeek + Unpin + Send {}
+
+impl<R> AsyncRS for R where R: AsyncRead + AsyncSeek + Unpin + Send {}
+
+/// A remote file and what the index or URL tells about it
+pub(crate) struct RemoteFile {
+    pub url: Url,
+    pub hashes: Hashes,
+    pub size: Option<u64>,
+}
+
+impl RemoteFile {
+    /// A file picked from an index, checking its hash pin
+    pub(crate) fn from_index(client: &LazyZipClient, mut file: simple_repo_api::File) -> Self {
+        let hashes = Hashes::take_from(&mut file.hashes);
+        if let Some(sha256) = &hashes.sha256 {
+            client.check_hash_pin(&file.filename, sha256);
+        }
+        Self {
+            url: file.url,
+            hashes,
+            size: file.size,
+        }
+    }
+}
+
+impl RemoteFile {
+    /// Its SHA-256 if known, else its URL
+    pub(crate) fn fingerprint(&self) -> String {
+        match &self.hashes.sha256 {
+            Some(sha256) => format!("sha256:{sha256}"),
+            None => self.url.to_string(),
+        }
+    }
+}
+
+/// What identifies the file a package resolves to, to tell whether it changed since an earlier run.
+/// Local files have none, as they can change in place.
+pub async fn fingerprint(client: &LazyZipClient, pkg_loc: PkgLoc) -> Result<Option<String>> {
+    let (_, file) = locate(client, pkg_loc).await?;
+    Ok(file.left().map(|file| file.fingerprint()))
+}
+
+/// The file a package resolves to
+pub async fn file_info(client: &LazyZipClient, pkg_loc: PkgLoc) -> Result<FileInfo> {
+    let (_, file) = locate(client, pkg_loc).await?;
+    Ok(FileInfo::from_located(file))
+}
+
+/// Find where a package’s file is, checking its hash pin if it’s remote
+pub(crate) async fn locate(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+) -> Result<(PackageName, Either<RemoteFile, PathBuf>)> {
+    match pkg_loc {
+        PkgLoc::Dependency(dep) => {
+            let whl = find_wheel(client, &dep)
+                .instrument(tracing::info_span!("find_wheel"))
+                .await?;
+            let file = RemoteFile::from_index(client, whl);
+            Ok((dep.into_name(), Either::Left(file)))
+        }
+        PkgLoc::Url(url) => {
+            let file_name = url
+                .path_segments()
+                .and_then(Iterator::last)
+                .filter(|s| !s.is_empty())
+                .context("URL without file name")?;
+            let file_name = percent_decode_str(file_name).decode_utf8()?.into_owned();
+            let name = PackageName::from_str(&file_name)?;
+            let (url, mut hashes) = simple_repo_api::split_hash_fragment(url);
+            let hashes = Hashes::take_from(&mut hashes);
+            if let Some(sha256) = &hashes.sha256 {
+                client.check_hash_pin(&file_name, sha256);
+            }
+            let file = RemoteFile {
+                url,
+                hashes,
+                size: None,
+            };
+            Ok((name, Either::Left(file)))
+        }
+        PkgLoc::Path(path) => {
+            let name = PackageName::from_str(
+                path.file_name()
+                    .context("file without name")?
+                    .to_str()
+                    .context("file name not UTF-8")?,
+            )?;
+            Ok((name, Either::Right(path)))
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn pkg_reader(
+    client: &LazyZipClient,
+    pkg_loc: PkgLoc,
+) -> Result<(PackageName, Box<dyn AsyncRS>)> {
+    let (name, file) = locate(client, pkg_loc).await?;
+    let reader = match file {
+        Either::Left(file) => url_reader(client, file.url, &file.hashes, file.size).await?,
+        Either::Right(path) => open_path(path).await?,
+    };
+    Ok((name, reader))
+}
+
+async fn open_path(path: PathBuf) -> Result<Box<dyn AsyncRS>> {
+    let reader = tokio::fs::File::open(path).await?;
+    Ok(Box::new(reader.compat()))
+}
+
+/// Bytes to fetch from the end of a wheel of unknown size
+const DEFAULT_TAIL_SIZE: u64 = 64 * 1024;
+/// Wheels up to this size are fetched whole
+const SMALL_WHEEL_SIZE: u64 = 128 * 1024;
+/// Bytes at the end of a wheel that likely contain `*.dist-info`, fetched speculatively
+const SPECULATIVE_TAIL_SIZE: u64 = 256 * 1024;
+
+/// Bytes to fetch from the end of a wheel in the first request.
+///
+/// The central directory is at the end, and wheels store `*.dist-info` right before it,
+/// so for typical wheels this covers both it and `METADATA`.
+/// For huge archives whose central directory doesn’t fit, the rest is fetched in a second request.
+fn initial_tail_size(size: Option<u64>) -> u64 {
+    match size {
+        None => DEFAULT_TAIL_SIZE,
+        Some(size) if size <= SMALL_WHEEL_SIZE => size.max(1),
+        // Central directories take up a few percent of typical wheels
+        Some(size) => (size / 20).clamp(DEFAULT_TAIL_SIZE, 1024 * 1024),
+    }
+}
+
+/// Lazily read a remote file using range requests,
+/// or download it fully if the server doesn’t support them.
+/// The client’s URL rewrites are applied first.
+///
+/// The first request fetches the end of the file, sized by `size` if known (e.g. from the index).
+/// If it’s redirected, e.g. to a signed storage URL, further ones go to where it was redirected to.
+/// Should they fail with `403 Forbidden` as that URL expired, it’s resolved again.
+/// For wheels, the rest of the last [`SPECULATIVE_TAIL_SIZE`] bytes is requested right after it.
+/// Files can only be verified against their `hashes` when downloaded fully.
+#[tracing::instrument(skip(client))]
+pub async fn url_reader(
+    client: &LazyZipClient,
+    url: Url,
+    hashes: &Hashes,
+    size: Option<u64>,
+) -> Result<Box<dyn AsyncRS>> {
+    let (reader, _, _) = lazy_reader(client, url, hashes, size).await?;
+    Ok(reader)
+}
+
+/// Like [`url_reader`], also returning where regions are fetched in parallel for the reader,
+/// if it reads lazily and the client splits ranges, and the file’s length if it reads lazily
+async fn lazy_reader(
+    client: &LazyZipClient,
+    url: Url,
+    hashes: &Hashes,
+    size: Option<u64>,
+) -> Result<(Box<dyn AsyncRS>, Option<Arc<Regions>>, Option<u64>)> {
+    let url = client.rewrite_url(url)?;
+    let whl = url
+        .path_segments()
+        .and_then(Iterator::last)
+        .and_then(|file_name| WheelFilename::from_str(file_name).ok());
+    // Wheels of a package are laid out alike, so earlier ones tell how much of the end is needed
+    let hinted = client
+        .prefetch_hints()
+        .zip(whl.as_ref())
+        .and_then(|(hints, whl)| {
+            let hinted = hints
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .tail_size(&whl.name)?;
+            Some(size.map_or(hinted, |size| hinted.min(size)))
+        });
+    let tail_size = initial_tail_size(size).max(hinted.unwrap_or_default());
+    let permit = client.host_permit(&url).await;
+    let http = client.http();
+    let response = AsyncHttpRangeReader::initial_tail_request(
+        http.clone(),
+        url.clone(),
+        tail_size,
+        HeaderMap::new(),
+    )
+    .instrument(tracing::info_span!("fetch_tail"))
+    .await?;
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        // The server ignored the range and sent the whole file
+        warnings::warn(Warning::RangesUnsupported { url: url.clone() });
+        let digests = StreamHasher::new(hashes);
+        let mut content = Vec::new();
+        let mut chunks = response.bytes_stream();
+        async {
+            while let Some(chunk) = chunks.next().await {
+                let chunk = chunk?;
+                digests.update(&chunk).await;
+                content.extend_from_slice(&chunk);
+            }
+            Ok::<_, reqwest::Error>(())
+        }
+        .instrument(tracing::info_span!("download"))
+        .await?;
+        digests.verify(&url).await?;
+        return Ok((Box::new(futures::io::Cursor::new(content)), None, None));
+    }
+    // Read the rest from where a redirect led, so signed storage URLs are resolved once,
+    // and the credentials for `url`’s host stay there
+    let range_url = response.url().clone();
+    let http = reqwest_middleware::ClientBuilder::from_client(http.clone())
+        .with(RefreshExpired::new(client.clone(), url))
+        .build();
+    let mut reader = AsyncHttpRangeReader::from_range_response(
+        http.clone(),
+        response,
+        range_url.clone(),
+        HeaderMap::new(),
+    )
+    .instrument(tracing::info_span!("create_range_reader"))
+    .await?;
+    let len = reader.len();
+    // `*.dist-info` is usually right before the central directory, so without a hint,
+    // request it in the background while the central directory is parsed
+    if whl.is_some() && hinted.is_none() {
+        let start = len.saturating_sub(SPECULATIVE_TAIL_SIZE);
+        reader.prefetch(start..len.saturating_sub(tail_size)).await;
+    }
+    let parts = client.parallel_ranges();
+    let regions =
+        (parts > 1).then(|| Arc::new(Regions::new(client.clone(), http, range_url, len, parts)));
+    let reader: Box<dyn AsyncRS> = match &regions {
+        Some(regions) => Box::new(RegionReader {
+            inner: reader.compat(),
+            regions: regions.clone(),
+            pos: 0,
+            synced: false,
+        }),
+        None => Box::new(reader.compat()),
+    };
+    let reader: Box<dyn AsyncRS> = match permit {
+        Some(permit) => Box::new(WithPermit {
+            reader,
+            _permit: permit,
+        }),
+        None => reader,
+    };
+    Ok((reader, regions, Some(len)))
+}
+
+/// Record how much of the end of a wheel its `*.dist-info` and the central directory take up,
+/// and the tool that built it, as prefetch hint for other versions.
+/// Only this layout counts, not what’s read, so reading large entries doesn’t inflate hints.
+async fn record_prefetch_hint(
+    client: &LazyZipClient,
+    url: &Url,
+    reader: &mut PkgZipReader,
+    len: u64,
+) {
+    let Some(hints) = client.prefetch_hints() else {
+        return;
+    };
+    let Some(whl) = url
+        .path_segments()
+        .and_then(Iterator::last)
+        .and_then(|file_name| WheelFilename::from_str(file_name).ok())
+    else {
+        return;
+    };
+    let Some(start) = reader
+        .file()
+        .entries()
+        .iter()
+        .filter(|e| {
+            e.filename().as_str().is_ok_and(|n| {
+                n.split_once('/')
+                    .is_some_and(|(dir, _)| dir.ends_with(".dist-info"))
+            })
+        })
+        .map(StoredZipEntry::header_offset)
+        .min()
+    else {
+        return;
+    };
+    // `WHEEL` is in `*.dist-info`, which is usually fetched with the central directory
+    let mut wheel = String::new();
+    let backend = match find_entry(reader, is_wheel_file) {
+        Some(idx) => read_entry(reader, idx, &mut wheel)
+            .await
+            .ok()
+            .and_then(|_| Metadata::from_str(&wheel).ok())
+            .and_then(|wheel| wheel.get("Generator").map(|g| WheelBuild::parse(g).tool)),
+        None => None,
+    };
+    hints.lock().unwrap_or_else(PoisonError::into_inner).record(
+        &whl.name,
+        backend.as_deref(),
+        len.saturating_sub(start),
+    );
+}
+
+/// A lazy reader holding a connection permit for as long as it’s used
+struct WithPermit<R> {
+    reader: R,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for WithPermit<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.reader).poll_read(cx, buf)
+    }
+}
+
+impl<R: AsyncSeek + Unpin> AsyncSeek for WithPermit<R> {
+    fn poll_seek(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: std::io::SeekFrom,
+    ) -> Poll<std::io::Result<u64>> {
+        Pin::new(&mut self.reader).poll_seek(cx, pos)
+    }
+}
+
+/// Find the newest wheel matching `dep`, skipping yanked ones and ones the client’s target can’t install.
+/// If the client allows legacy formats, `.egg` and `.zip` files are used if there’s no wheel.
+pub async fn find_wheel(client: &LazyZipClient, dep: &Dependency) -> Result<simple_repo_api::File> {
+    let wheel = find_newest(client, dep, "wheel", |filename| {
+        let whl = WheelFilename::from_str(filename).ok()?;
+        if let Some(target) = client.target() {
+            target.supports(&whl.tags().ok()?).then_some(())?;
+        }
+        Some(whl.version)
+    })
+    .await;
+    if wheel.is_err() && client.legacy_formats() {
+        let legacy = find_newest(client, dep, "wheel, egg, or zip", |filename| {
+            Some(LegacyFilename::from_str(filename).ok()?.version)
+        })
+        .await;
+        if let Ok(file) = legacy {
+            tracing::info!(filename = file.filename, "no wheel, using legacy format");
+            return Ok(file);
+        }
+    }
+    wheel
+}
+
+/// Find the newest file matching `dep` among those `version` understands, skipping yanked ones
+pub(crate) async fn find_newest(
+    client: &LazyZipClient,
+    dep: &Dependency,
+    kind: &str,
+    version: impl Fn(&str) -> Option<pep440_rs::Version>,
+) -> Result<simple_repo_api::File> {
+    let by_version = |(l, _): &(pep440_rs::Version, _), (r, _): &(pep440_rs::Version, _)| l.cmp(r);
+    let preference = client.wheel_preference();
+    let by_preference = |l: &(pep440_rs::Version, simple_repo_api::File),
+                         r: &(pep440_rs::Version, simple_repo_api::File)| {
+        by_version(l, r).then_with(|| {
+            preference_cost(preference, &r.1.filename, r.1.size).cmp(&preference_cost(
+                preference,
+                &l.1.filename,
+                l.1.size,
+            ))
+        })
+    };
+    let dep = &client.constrain(dep);
+    let (available, yanked): (Vec<_>, Vec<_>) = client
+        .find_project(dep.name())
+        .await?
+        .files
+        .into_iter()
+        .filter_map(|p| {
+            let v = version(&p.filename)?;
+            let is_valid = dep
+                .version_spec()
+                .is_none_or(|version_spec| version_spec.contains(&v));
+            is_valid.then_some((v, p))
+        })
+        .partition(|(_, p)| !&p.yanked);
+    let candidates = available.len();
+    let (newest, file) = available
+        .into_iter()
+        .max_by(by_preference)
+        .ok_or_else(|| Failure::no_compatible_file(format!("No {kind} found for {dep}")))?;
+    tracing::info!(
+        target: "pypi_lazyzip::explain",
+        %dep,
+        filename = file.filename,
+        index = file.index.as_ref().map(tracing::field::display),
+        candidates,
+        "chose {kind}",
+    );
+    let newest_yanked = yanked
+        .into_iter()
+        .filter(|(v, _)| *v > newest)
+        .max_by(by_version);
+    if let Some((
+        _,
+        simple_repo_api::File {
+            filename,
+            yanked: Yanking::Yanked(reason),
+            ..
+        },
+    )) = newest_yanked
+    {
+        warnings::warn(Warning::YankedSkipped { filename, reason });
+    }
+    #[cfg(feature = "tuf")]
+    let file = client.verify_tuf_target(file).await?;
+    Ok(file)
+}
+
+/// How much `preference` disfavours a file, lower is better.
+/// Files of unknown size are the last choice for [`WheelPreference::Smallest`].
+fn preference_cost(preference: Option<WheelPreference>, filename: &str, size: Option<u64>) -> u64 {
+    let is_pure = || {
+        WheelFilename::from_str(filename)
+            .and_then(|whl| whl.tags())
+            .ok()
+            .map(|tags| tags.platform.iter().all(|platform| platform == "any"))
+    };
+    match preference {
+        None => 0,
+        Some(WheelPreference::Pure) => u64::from(is_pure() == Some(false)),
+        Some(WheelPreference::Platform) => u64::from(is_pure() == Some(true)),
+        Some(WheelPreference::Smallest) => size.unwrap_or(u64::MAX),
+    }
+}
+
+/// The index of the first entry matching `predicate`.
+/// Besides its name, the predicate can check an entry’s sizes, compression and other central directory fields,
+/// and it can be a closure, e.g. matching names against a user-provided glob.
+pub fn find_entry<R>(
+    reader: &mut ZipFileReader<R>,
+    predicate: impl Fn(&StoredZipEntry) -> bool,
+) -> Option<usize>
+where
+    R: AsyncBufRead + AsyncSeek + Unpin,
+{
+    reader
+        .file()
+        .entries()
+        .iter()
+        .enumerate()
+        .find(|(_, e)| predicate(e))
+        .map(|(i, _)| i)
+}
+
+#[tracing::instrument(skip(reader, buf))]
+pub async fn read_entry<R>(
+    reader: &mut ZipFileReader<R>,
+    idx: usize,
+    buf: &mut String,
+) -> Result<usize>
+where
+    R: AsyncBufRead + AsyncSeek + Unpin,
+{
+    reader
+        .reader_with_entry(idx)
+        .instrument(tracing::info_span!("create_entry_reader"))
+        .await?
+        .read_to_string_checked(buf)
+        .instrument(tracing::info_span!("read_to_string"))
+        .await
+        .context("Failed to read entry")
+}
+
+/// Like [`read_entry`], for binary files
+#[tracing::instrument(skip(reader, buf))]
+pub async fn read_entry_bytes<R>(
+    reader: &mut ZipFileReader<R>,
+    idx: usize,
+    buf: &mut Vec<u8>,
+) -> Result<usize>
+where
+    R: AsyncBufRead + AsyncSeek + Unpin,
+{
+    reader
+        .reader_with_entry(idx)
+        .instrument(tracing::info_span!("create_entry_reader"))
+        .await?
+        .read_to_end_checked(buf)
+        .instrument(tracing::info_span!("read_to_end"))
+        .await
+        .context("Failed to read entry")
+}
+
+/// Like [`read_entry`], but taking the content from the client’s blob store
+/// if the entry was read from the same file before, and adding it otherwise
+pub async fn read_entry_stored(
+    client: &LazyZipClient,
+    reader: &mut SharedZipReader,
+    idx: usize,
+    buf: &mut String,
+) -> Result<usize> {
+    if let Some(content) = stored_entry(client, reader, idx) {
+        buf.push_str(&content);
+        return Ok(content.len());
+    }
+    let start = buf.len();
+    let len = read_entry(reader, idx, buf).await?;
+    if let Some(store) = client.blob_store()
+        && let Some(sha256) = &reader.sha256
+        && let Some(path) = reader
+            .file()
+            .entries()
+            .get(idx)
+            .and_then(|e| e.filename().as_str().ok())
+        && let Err(e) = store.put(sha256, path, &buf.as_bytes()[start..])
+    {
+        tracing::warn!("Failed to store entry: {e:#}");
+    }
+    Ok(len)
+}
+
+/// An entry’s content from the client’s blob store, if it has one and the entry is stored
+fn stored_entry(client: &LazyZipClient, reader: &SharedZipReader, idx: usize) -> Option<String> {
+    let entry = reader.file().entries().get(idx)?;
+    let content = client.blob_store()?.get(
+        reader.sha256.as_deref()?,
+        entry.filename().as_str().ok()?,
+        entry.crc32(),
+        entry.uncompressed_size(),
+    );
+    client.record_cache_lookup("blob_store", content.is_some());
+    tracing::debug!(hit = content.is_some(), "Looked up entry in blob store");
+    String::from_utf8(content?).ok()
+}
+
+/// Stream an entry’s decompressed content, e.g. to pipe large or binary files without buffering them.
+/// Unlike [`read_entry`], this doesn’t verify the entry’s CRC.
+pub async fn entry_reader<R>(
+    reader: &mut ZipFileReader<R>,
+    idx: usize,
+) -> Result<impl AsyncRead + Unpin + '_>
+where
+    R: AsyncBufRead + AsyncSeek + Unpin,
+{
+    reader
+        .reader_with_entry(idx)
+        .instrument(tracing::info_span!("create_entry_reader"))
+        .await
+        .context("Failed to read entry")
+}
+
+/// Read an entry up to and including its first blank line, i.e. the headers of an email-like file.
+/// The rest isn’t decompressed, and for lazy remote files not fetched if it isn’t yet.
+/// Unlike [`read_entry`], this can’t verify the entry’s CRC.
+pub async fn read_entry_headers<R>(
+    reader: &mut ZipFileReader<R>,
+    idx: usize,
+    buf: &mut String,
+) -> Result<usize>
+where
+    R: AsyncBufRead + AsyncSeek + Unpin,
+{
+    let entry = reader
+        .reader_with_entry(idx)
+        .instrument(tracing::info_span!("create_entry_reader"))
+        .await?;
+    let mut lines = BufReader::new(entry);
+    let start = buf.len();
+    async {
+        loop {
+            let line_start = buf.len();
+            if lines.read_line(buf).await? == 0 || is_blank(&buf[line_start..]) {
+                return Ok::<_, std::io::Error>(());
+            }
+        }
+    }
+    .instrument(tracing::info_span!("read_headers"))
+    .await
+    .context("Failed to read entry")?;
+    Ok(buf.len() - start)
+}
+
+/// The headers of a `METADATA` file, up to and including the blank line ending them,
+/// as [`read_entry_headers`] reads them
+fn headers(content: &str) -> &str {
+    let mut end = 0;
+    for line in content.split_inclusive('\n') {
+        end += line.len();
+        if is_blank(line) {
+            break;
+        }
+    }
+    &content[..end]
+}
+
+/// Whether a line ends the headers, e.g. `\r\n`
+fn is_blank(line: &str) -> bool {
+    line.trim_end().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::RequestStats;
+    use crate::prefetch_hints::PrefetchHints;
+    use crate::test_util::{MockIndex, MockWheel};
+
+    #[test]
+    fn test_initial_tail_size() {
+        assert_eq!(initial_tail_size(None), DEFAULT_TAIL_SIZE);
+        assert_eq!(initial_tail_size(Some(1000)), 1000);
+        assert_eq!(initial_tail_size(Some(200 * 1024)), DEFAULT_TAIL_SIZE);
+        assert_eq!(initial_tail_size(Some(10 * 1024 * 1024)), 512 * 1024);
+        assert_eq!(initial_tail_size(Some(1024 * 1024 * 1024)), 1024 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_speculative_tail() {
+        // `METADATA` is before a central directory larger than the default tail
+        let whl = (0..1200).fold(
+            MockWheel::new("foo", "1.0").file("foo/data.bin", vec![0; 300 * 1024]),
+            |whl, i| whl.file(&format!("foo/module_with_a_long_name_{i:04}.py"), ""),
+        );
+        let index = MockIndex::start([whl]).await.unwrap();
+        let stats = RequestStats::default();
+        let client = index.client().with_middleware(stats.clone());
+        let whl = find_wheel(&client, &"foo".parse().unwrap()).await.unwrap();
+        let before = stats.requests();
+        pkg_metadata(&client, PkgLoc::Url(whl.url)).await.unwrap();
+        // The tail and the speculative request, but none after parsing the central directory
+        assert_eq!(stats.requests() - before, 2);
+    }
+
+    #[test]
+    fn test_is_dist_info_of() {
+        let pillow = "pillow".parse().unwrap();
+        assert!(is_dist_info_of("Pillow-10.0.0.dist-info", &pillow));
+        assert!(!is_dist_info_of("Pillow-10.0.0.data", &pillow));
+        assert!(!is_dist_info_of("pillow_heif-0.1.dist-info", &pillow));
+        let zope = "zope.interface".parse().unwrap();
+        assert!(is_dist_info_of("zope_interface-7.0.dist-info", &zope));
+        assert_eq!(
+            split_dist_info("zope_interface-7.0.dist-info"),
+            Some(("zope_interface", "7.0"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_wheel_substituted() {
+        let index = MockIndex::start([
+            MockWheel::new("foo", "1.0").tag("cp312-cp312-manylinux_2_17_x86_64"),
+            MockWheel::new("foo", "1.0").corrupt(),
+        ])
+        .await
+        .unwrap();
+        let client = index.client();
+        let (metadata, warnings) =
+            warnings::collect(pkg_metadata(&client, "foo".parse().unwrap())).await;
+        let (_, metadata) = metadata.unwrap();
+        assert_eq!(metadata.get("Version"), Some("1.0"));
+        let [
+            Warning::FileSubstituted {
+                filename,
+                substitute,
+                ..
+            },
+        ] = warnings.as_slice()
+        else {
+            panic!("expected a substitution, got {warnings:?}");
+        };
+        assert_eq!(filename, "foo-1.0-py3-none-any.whl");
+        assert_eq!(substitute, "foo-1.0-cp312-cp312-manylinux_2_17_x86_64.whl");
+
+        let index = MockIndex::start([MockWheel::new("foo", "1.0").corrupt()])
+            .await
+            .unwrap();
+        let err = pkg_metadata(&index.client(), "foo".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("no readable file of foo 1.0"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_preference_cost() {
+        let pure = "numpy-2.0-py3-none-any.whl";
+        let platform = "numpy-2.0-cp312-cp312-manylinux_2_17_x86_64.manylinux2014_x86_64.whl";
+        let cost = |preference, filename, size| preference_cost(Some(preference), filename, size);
+        assert!(
+            cost(WheelPreference::Pure, pure, None) < cost(WheelPreference::Pure, platform, None)
+        );
+        assert!(
+            cost(WheelPreference::Platform, platform, None)
+                < cost(WheelPreference::Platform, pure, None)
+        );
+        assert!(
+            cost(WheelPreference::Smallest, platform, Some(10))
+                < cost(WheelPreference::Smallest, pure, Some(20))
+        );
+        assert!(
+            cost(WheelPreference::Smallest, pure, Some(20))
+                < cost(WheelPreference::Smallest, platform, None)
+        );
+        assert_eq!(
+            preference_cost(None, pure, Some(20)),
+            preference_cost(None, platform, Some(10))
+        );
+        assert_eq!(cost(WheelPreference::Pure, "numpy-2.0.tar.gz", None), 0);
+    }
+
+    #[tokio::test]
+    async fn test_extract_closure() {
+        let whl = MockWheel::new("foo", "1.0")
+            .file("foo/empty.txt", "")
+            .file("foo/data.txt", "data");
+        let index = MockIndex::start([whl]).await.unwrap();
+        let client = index.client();
+        let suffix = ".txt".to_owned();
+        let (_, content) = extract(&client, "foo".parse().unwrap(), |e| {
+            e.uncompressed_size() > 0
+                && e.filename()
+                    .as_str()
+                    .is_ok_and(|n| n.starts_with("foo/") && n.ends_with(&suffix))
+        })
+        .await
+        .unwrap();
+        assert_eq!(content.as_deref(), Some("data"));
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_hints() {
+        // A central directory larger than the default tail, so reading it takes two requests
+        let wheel = |version| {
+            (0..2000).fold(
+                MockWheel::new("foo", version).file("foo/data.bin", vec![0; 200 * 1024]),
+                |whl, i| whl.file(&format!("foo/module_with_a_long_name_{i:04}.py"), ""),
+            )
+        };
+        let index = MockIndex::start([wheel("1.0"), wheel("2.0")])
+            .await
+            .unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "pypi-lazyzip-prefetch-test-{}.json",
+            std::process::id()
+        ));
+        let stats = RequestStats::default();
+        let client = index
+            .client()
+            .with_prefetch_hints(PrefetchHints::load(&path))
+            .with_middleware(stats.clone());
+        let mut requests = Vec::new();
+        for version in ["1.0", "2.0"] {
+            let dep = Dependency::from_str(&format!("foo=={version}")).unwrap();
+            let whl = find_wheel(&client, &dep).await.unwrap();
+            let before = stats.requests();
+            pkg_metadata(&client, PkgLoc::Url(whl.url)).await.unwrap();
+            requests.push(stats.requests() - before);
+        }
+        assert!(requests[0] > 1);
+        assert_eq!(requests[1], 1);
+        client.save_prefetch_hints().unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_hint_ignores_entry_reads() {
+        let whl = MockWheel::new("foo", "1.0")
+            .file("foo/big.bin", vec![0; 2 * 1024 * 1024])
+            .file("foo/__init__.py", "");
+        let index = MockIndex::start([whl]).await.unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "pypi-lazyzip-prefetch-entry-test-{}.json",
+            std::process::id()
+        ));
+        let client = index
+            .client()
+            .with_prefetch_hints(PrefetchHints::load(&path));
+        let (name, mut zip) = open_zip(&client, PkgLoc::Dependency("foo".parse().unwrap()))
+            .await
+            .unwrap();
+        let idx = find_entry(&mut zip, |e| {
+            e.filename()
+                .as_str()
+                .is_ok_and(|name| name == "foo/big.bin")
+        })
+        .unwrap();
+        read_entry_bytes(&mut zip, idx, &mut Vec::new())
+            .await
+            .unwrap();
+        drop(zip);
+        let tail = client
+            .prefetch_hints()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .tail_size(&name)
+            .unwrap();
+        assert!(tail < 64 * 1024, "hint of {tail} bytes");
+    }
+
+    #[tokio::test]
+    async fn test_small_wheel_single_request() {
+        let index = MockIndex::start([MockWheel::new("foo", "1.0")])
+            .await
+            .unwrap();
+        let stats = RequestStats::default();
+        let client = index.client().with_middleware(stats.clone());
+        let whl = find_wheel(&client, &"foo".parse().unwrap()).await.unwrap();
+        let before = stats.requests();
+        pkg_metadata(&client, PkgLoc::Url(whl.url)).await.unwrap();
+        assert_eq!(stats.requests() - before, 1);
+    }
+
+    #[tokio::test]
+    async fn test_entry_reader() {
+        use futures::AsyncReadExt as _;
+
+        let content = vec![0xff_u8, 0, 0xfe];
+        let zip = crate::test_util::build_zip(&[("foo/data.bin".to_owned(), content.clone())]);
+        let mut reader = ZipFileReader::new(futures::io::Cursor::new(zip))
+            .await
+            .unwrap();
+        let mut buf = Vec::new();
+        entry_reader(&mut reader, 0)
+            .await
+            .unwrap()
+            .read_to_end(&mut buf)
+            .await
+            .unwrap();
+        assert_eq!(buf, content);
+    }
+
+    #[tokio::test]
+    async fn test_read_entry_headers() {
+        let content = "Name: foo\r\nVersion: 1.0\r\n\r\n# Foo\n\nA long description\n";
+        let zip = crate::test_util::build_zip(&[(
+            "foo-1.0.dist-info/METADATA".to_owned(),
+            content.as_bytes().to_vec(),
+        )]);
+        let mut reader = ZipFileReader::new(futures::io::Cursor::new(zip))
+            .await
+            .unwrap();
+        let mut buf = String::new();
+        read_entry_headers(&mut reader, 0, &mut buf).await.unwrap();
+        assert_eq!(buf, "Name: foo\r\nVersion: 1.0\r\n\r\n");
+        let metadata = Metadata::from_str(&buf).unwrap();
+        assert_eq!(metadata.get("Version"), Some("1.0"));
+        assert_eq!(metadata.body(), None);
+        // Stored entries are cut the same way
+        assert_eq!(headers(content), buf);
+    }
+
+    #[tokio::test]
+    async fn test_shared_zip() {
+        let index = MockIndex::start([MockWheel::new("foo", "1.0")])
+            .await
+            .unwrap();
+        let stats = RequestStats::default();
+        let client = index.client().with_middleware(stats.clone());
+        let url = index.file_url("foo-1.0-py3-none-any.whl");
+        let (by_name, by_url) = tokio::join!(
+            pkg_metadata(&client, "foo".parse().unwrap()),
+            list_entries(&client, PkgLoc::Url(url)),
+        );
+        assert_eq!(by_name.unwrap().1.get("Version"), Some("1.0"));
+        assert!(!by_url.unwrap().1.is_empty());
+        // The index page and one range request
+        assert_eq!(stats.requests(), 2);
+    }
+}
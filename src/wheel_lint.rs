@@ -0,0 +1,337 @@
+//! Checking that a wheel conforms to the [binary distribution format](https://packaging.python.org/en/latest/specifications/binary-distribution-format/), for `lint-wheel`.
+//!
+//! Only the central directory and the small `*.dist-info` files are read, so this is cheap for remote wheels.
+//! `RECORD` is checked against the entries and their sizes, but hashes aren’t verified.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr as _;
+
+use either::Either;
+use eyre::{OptionExt as _, Result};
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+
+use crate::python_pkg::{Metadata, PackageName, WheelFilename};
+use crate::wheel::{self, SharedZipReader, is_dist_info_of, split_dist_info};
+use crate::{LazyZipClient, PkgLoc};
+
+/// The problems found in a wheel
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct WheelLint {
+    pub filename: String,
+    pub problems: Vec<Problem>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Problem {
+    /// There’s no `{name}-{version}.dist-info` directory matching the filename
+    DistInfoMissing { expected: String },
+    /// A required file in `*.dist-info` is missing
+    FileMissing { path: String },
+    /// `Wheel-Version` is missing or has a major version other than 1
+    UnsupportedWheelVersion { version: Option<String> },
+    /// The tags in `WHEEL` differ from the ones in the filename
+    TagMismatch {
+        filename: Vec<String>,
+        wheel: Vec<String>,
+    },
+    /// `Root-Is-Purelib` contradicts the platform tags
+    PurelibMismatch {
+        root_is_purelib: Option<String>,
+        platforms: Vec<String>,
+    },
+    /// `Name` or `Version` in `METADATA` differs from the filename
+    MetadataMismatch {
+        field: &'static str,
+        filename: String,
+        metadata: Option<String>,
+    },
+    /// An entry in the wheel isn’t listed in `RECORD`
+    NotInRecord { path: String },
+    /// A `RECORD` line lists a file that isn’t in the wheel
+    NotInWheel { path: String },
+    /// A `RECORD` line’s size differs from the entry’s
+    SizeMismatch {
+        path: String,
+        record: u64,
+        wheel: u64,
+    },
+    /// A `RECORD` line can’t be parsed
+    InvalidRecord { line: String },
+}
+
+/// Check a wheel’s `*.dist-info` files and `RECORD` against its filename and entries
+pub async fn lint(client: &LazyZipClient, pkg_loc: PkgLoc) -> Result<WheelLint> {
+    let (_, file) = wheel::locate(client, pkg_loc).await?;
+    let filename = match &file {
+        Either::Left(remote) => remote
+            .url
+            .path_segments()
+            .and_then(Iterator::last)
+            .map(|name| percent_decode_str(name).decode_utf8_lossy().into_owned()),
+        Either::Right(path) => path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(ToOwned::to_owned),
+    }
+    .ok_or_eyre("file without name")?;
+    let wheel_filename = WheelFilename::from_str(&filename)?;
+    let mut reader = match file {
+        Either::Left(remote) => wheel::open_remote_zip(client, &remote).await?,
+        Either::Right(path) => wheel::open_local_zip(path).await?,
+    };
+    let problems = Linter {
+        client,
+        reader: &mut reader,
+        filename: &wheel_filename,
+        problems: Vec::new(),
+    }
+    .run()
+    .await?;
+    Ok(WheelLint { filename, problems })
+}
+
+struct Linter<'a> {
+    client: &'a LazyZipClient,
+    reader: &'a mut SharedZipReader,
+    filename: &'a WheelFilename,
+    problems: Vec<Problem>,
+}
+
+impl Linter<'_> {
+    async fn run(mut self) -> Result<Vec<Problem>> {
+        let entries = self
+            .reader
+            .file()
+            .entries()
+            .iter()
+            .map(|e| Ok((e.filename().as_str()?.to_owned(), e.uncompressed_size())))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        let dist_info = entries.keys().find_map(|path| {
+            let (dir, _) = path.split_once('/')?;
+            let (_, version) = split_dist_info(dir)?;
+            let matches = is_dist_info_of(dir, &self.filename.name)
+                && pep440_rs::Version::from_str(version).is_ok_and(|v| v == self.filename.version);
+            matches.then(|| dir.to_owned())
+        });
+        let Some(dist_info) = dist_info else {
+            self.problems.push(Problem::DistInfoMissing {
+                expected: format!(
+                    "{}-{}.dist-info",
+                    self.filename.name.as_normalized().replace('-', "_"),
+                    self.filename.version
+                ),
+            });
+            return Ok(self.problems);
+        };
+        if let Some(wheel) = self.read(&format!("{dist_info}/WHEEL")).await? {
+            self.check_wheel(&Metadata::from_str(&wheel)?)?;
+        }
+        if let Some(metadata) = self.read(&format!("{dist_info}/METADATA")).await? {
+            self.check_metadata(&Metadata::from_str(&metadata)?);
+        }
+        let record_path = format!("{dist_info}/RECORD");
+        if let Some(record) = self.read(&record_path).await? {
+            self.check_record(&entries, &record_path, &record);
+        }
+        Ok(self.problems)
+    }
+
+    /// Read a file, recording it as missing if it’s not in the wheel
+    async fn read(&mut self, path: &str) -> Result<Option<String>> {
+        let idx = self
+            .reader
+            .file()
+            .entries()
+            .iter()
+            .position(|e| e.filename().as_str().is_ok_and(|p| p == path));
+        let Some(idx) = idx else {
+            self.problems.push(Problem::FileMissing {
+                path: path.to_owned(),
+            });
+            return Ok(None);
+        };
+        let mut buf = String::new();
+        wheel::read_entry_stored(self.client, self.reader, idx, &mut buf).await?;
+        Ok(Some(buf))
+    }
+
+    fn check_wheel(&mut self, wheel: &Metadata) -> Result<()> {
+        let version = wheel.get("Wheel-Version");
+        if version.is_none_or(|v| v.split('.').next() != Some("1")) {
+            self.problems.push(Problem::UnsupportedWheelVersion {
+                version: version.map(ToOwned::to_owned),
+            });
+        }
+        let tags = self.filename.tags()?;
+        let mut expected = BTreeSet::new();
+        for python in &tags.python {
+            for abi in &tags.abi {
+                for platform in &tags.platform {
+                    expected.insert(format!("{python}-{abi}-{platform}"));
+                }
+            }
+        }
+        let listed = wheel
+            .get_all("Tag")
+            .map(ToOwned::to_owned)
+            .collect::<BTreeSet<_>>();
+        if listed != expected {
+            self.problems.push(Problem::TagMismatch {
+                filename: expected.into_iter().collect(),
+                wheel: listed.into_iter().collect(),
+            });
+        }
+        // Pure wheels work on any platform, platform-specific ones can’t be pure
+        let root_is_purelib = wheel.get("Root-Is-Purelib");
+        let is_any = tags.platform.iter().all(|p| p == "any");
+        let consistent = match root_is_purelib.map(str::to_ascii_lowercase).as_deref() {
+            Some("true") => is_any,
+            Some("false") => true,
+            _ => false,
+        };
+        if !consistent {
+            self.problems.push(Problem::PurelibMismatch {
+                root_is_purelib: root_is_purelib.map(ToOwned::to_owned),
+                platforms: tags.platform,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_metadata(&mut self, metadata: &Metadata) {
+        let name = metadata.get("Name");
+        if name.and_then(|n| PackageName::from_str(n).ok()).as_ref() != Some(&self.filename.name) {
+            self.problems.push(Problem::MetadataMismatch {
+                field: "Name",
+                filename: self.filename.name.to_string(),
+                metadata: name.map(ToOwned::to_owned),
+            });
+        }
+        let version = metadata.get("Version");
+        if version
+            .and_then(|v| pep440_rs::Version::from_str(v).ok())
+            .as_ref()
+            != Some(&self.filename.version)
+        {
+            self.problems.push(Problem::MetadataMismatch {
+                field: "Version",
+                filename: self.filename.version.to_string(),
+                metadata: version.map(ToOwned::to_owned),
+            });
+        }
+    }
+
+    fn check_record(&mut self, entries: &BTreeMap<String, u64>, record_path: &str, record: &str) {
+        let mut recorded = BTreeSet::new();
+        for line in record.lines().filter(|line| !line.trim().is_empty()) {
+            let Some([path, _hash, size]) = parse_record_line(line) else {
+                self.problems.push(Problem::InvalidRecord {
+                    line: line.to_owned(),
+                });
+                continue;
+            };
+            match (entries.get(&path), size.parse::<u64>()) {
+                (None, _) => self
+                    .problems
+                    .push(Problem::NotInWheel { path: path.clone() }),
+                (Some(&wheel), Ok(record)) if wheel != record => {
+                    self.problems.push(Problem::SizeMismatch {
+                        path: path.clone(),
+                        record,
+                        wheel,
+                    });
+                }
+                _ => {}
+            }
+            recorded.insert(path);
+        }
+        // Directories and signatures of `RECORD` aren’t listed
+        let signatures = [".jws", ".p7s"].map(|ext| format!("{record_path}{ext}"));
+        for path in entries.keys() {
+            if !path.ends_with('/') && !signatures.contains(path) && !recorded.contains(path) {
+                self.problems
+                    .push(Problem::NotInRecord { path: path.clone() });
+            }
+        }
+    }
+}
+
+/// Split a CSV line into path, hash and size, unquoting fields
+fn parse_record_line(line: &str) -> Option<[String; 3]> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockIndex, MockWheel};
+
+    #[test]
+    fn test_parse_record_line() {
+        assert_eq!(
+            parse_record_line(r#""foo/a,""b"".py",sha256=abc,12"#),
+            Some(["foo/a,\"b\".py", "sha256=abc", "12"].map(String::from))
+        );
+        assert_eq!(parse_record_line("foo.py,"), None);
+    }
+
+    #[tokio::test]
+    async fn test_lint() {
+        let record = "foo/__init__.py,sha256=abc,5\n\
+                      foo/gone.py,,\n\
+                      foo-1.0.dist-info/METADATA,,\n\
+                      foo-1.0.dist-info/RECORD,,\n";
+        let index = MockIndex::start([
+            MockWheel::new("foo", "1.0"),
+            MockWheel::new("foo", "2.0")
+                .file("foo-2.0.dist-info/RECORD", record.replace("1.0", "2.0")),
+        ])
+        .await
+        .unwrap();
+        let client = index.client();
+        let lint_version = |version: &str| {
+            let pkg_loc = PkgLoc::Dependency(format!("foo=={version}").parse().unwrap());
+            lint(&client, pkg_loc)
+        };
+        assert_eq!(
+            lint_version("1.0").await.unwrap().problems,
+            [Problem::FileMissing {
+                path: "foo-1.0.dist-info/RECORD".to_owned()
+            }]
+        );
+        assert_eq!(
+            lint_version("2.0").await.unwrap().problems,
+            [
+                Problem::SizeMismatch {
+                    path: "foo/__init__.py".to_owned(),
+                    record: 5,
+                    wheel: 0,
+                },
+                Problem::NotInWheel {
+                    path: "foo/gone.py".to_owned()
+                },
+                Problem::NotInRecord {
+                    path: "foo-2.0.dist-info/WHEEL".to_owned()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Which releases ship wheels for which Python versions and platforms, for `wheel-matrix`.
+//!
+//! Derived from the tags in wheel filenames, so no files need to be read.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::str::FromStr as _;
+
+use eyre::Result;
+use serde::Serialize;
+
+use crate::LazyZipClient;
+use crate::python_pkg::{Dependency, WheelFilename};
+use crate::simple_repo_api::Yanking;
+
+/// Output format for wheel matrices.
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatrixFormat {
+    /// Releases as rows, platforms as columns, listing the Python versions with wheels
+    #[default]
+    Table,
+    Json,
+}
+
+/// Releases with wheels, newest first
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct WheelMatrix {
+    releases: Vec<Release>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+struct Release {
+    version: String,
+    /// Python tags with wheels by platform tag
+    wheels: BTreeMap<String, Vec<String>>,
+}
+
+impl WheelMatrix {
+    /// Collect the wheels of the releases matching `dep` and the constraints, skipping yanked ones
+    pub async fn build(client: &LazyZipClient, dep: &Dependency) -> Result<Self> {
+        let project = client.find_project(dep.name()).await?;
+        let filenames = project
+            .files
+            .iter()
+            .filter(|file| matches!(file.yanked, Yanking::NotYanked))
+            .map(|file| file.filename.as_str());
+        Ok(Self::from_filenames(filenames, &client.constrain(dep)))
+    }
+
+    fn from_filenames<'a>(filenames: impl IntoIterator<Item = &'a str>, dep: &Dependency) -> Self {
+        let mut releases = BTreeMap::<_, BTreeMap<_, BTreeSet<_>>>::new();
+        for filename in filenames {
+            let Ok(wheel) = WheelFilename::from_str(filename) else {
+                continue;
+            };
+            if !dep
+                .version_spec()
+                .is_none_or(|version_spec| version_spec.contains(&wheel.version))
+            {
+                continue;
+            }
+            let Ok(tags) = wheel.tags() else {
+                continue;
+            };
+            let platforms = releases.entry(wheel.version).or_default();
+            // Skip aliases from before PEP 600, like `manylinux2014_x86_64` for `manylinux_2_17_x86_64`
+            let is_alias = |platform: &str| {
+                ["manylinux1_", "manylinux2010_", "manylinux2014_"]
+                    .iter()
+                    .any(|alias| platform.starts_with(alias))
+            };
+            let has_current = tags.platform.iter().any(|p| !is_alias(p));
+            for platform in tags
+                .platform
+                .iter()
+                .filter(|p| !(has_current && is_alias(p)))
+            {
+                let pythons = platforms.entry(platform.clone()).or_default();
+                for python in &tags.python {
+                    for abi in &tags.abi {
+                        pythons.insert(PythonTag::new(python, abi));
+                    }
+                }
+            }
+        }
+        let releases = releases
+            .into_iter()
+            .rev()
+            .map(|(version, platforms)| Release {
+                version: version.to_string(),
+                wheels: platforms
+                    .into_iter()
+                    .map(|(platform, pythons)| {
+                        (platform, pythons.into_iter().map(|p| p.label).collect())
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self { releases }
+    }
+
+    /// Render as a table with a column per platform, `any` last
+    pub fn render_table(&self) -> String {
+        let mut platforms = self
+            .releases
+            .iter()
+            .flat_map(|release| release.wheels.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        if let Some(i) = platforms.iter().position(|p| *p == "any") {
+            let any = platforms.remove(i);
+            platforms.push(any);
+        }
+        let header = std::iter::once("version".to_owned())
+            .chain(platforms.iter().map(|p| (*p).clone()))
+            .collect::<Vec<_>>();
+        let rows = self.releases.iter().map(|release| {
+            std::iter::once(release.version.clone())
+                .chain(platforms.iter().map(|platform| {
+                    release
+                        .wheels
+                        .get(*platform)
+                        .map(|pythons| pythons.join(" "))
+                        .unwrap_or_default()
+                }))
+                .collect::<Vec<_>>()
+        });
+        let rows = std::iter::once(header).chain(rows).collect::<Vec<_>>();
+        let widths = (0..=platforms.len())
+            .map(|col| rows.iter().map(|row| row[col].chars().count()).max())
+            .map(Option::unwrap_or_default)
+            .collect::<Vec<_>>();
+        let mut table = String::new();
+        for row in rows {
+            let mut line = String::new();
+            for (cell, width) in row.iter().zip(&widths) {
+                let _ = write!(line, "{cell:width$}  ");
+            }
+            table.push_str(line.trim_end());
+            table.push('\n');
+        }
+        table
+    }
+}
+
+/// A Python tag sorted by version, e.g. `cp39` before `cp310`.
+/// Free-threaded and stable ABI wheels get their own tags, like `cp313t` and `cp39-abi3`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct PythonTag {
+    key: (String, u64, u64),
+    label: String,
+}
+
+impl PythonTag {
+    fn new(python: &str, abi: &str) -> Self {
+        let label = match abi {
+            "abi3" => format!("{python}-abi3"),
+            abi if abi.ends_with('t') && abi.starts_with("cp") => format!("{python}t"),
+            _ => python.to_owned(),
+        };
+        let prefix = python.trim_end_matches(|c: char| c.is_ascii_digit());
+        let digits = &python[prefix.len()..];
+        let major = digits.get(..1).and_then(|d| d.parse().ok()).unwrap_or(0);
+        let minor = digits.get(1..).and_then(|d| d.parse().ok()).unwrap_or(0);
+        Self {
+            key: (prefix.to_owned(), major, minor),
+            label,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILENAMES: &[&str] = &[
+        "foo-1.0-cp310-cp310-manylinux_2_17_x86_64.manylinux2014_x86_64.whl",
+        "foo-1.0-cp39-cp39-manylinux_2_17_x86_64.manylinux2014_x86_64.whl",
+        "foo-1.0.tar.gz",
+        "foo-2.0-cp313-cp313t-win_amd64.whl",
+        "foo-2.0-cp39-abi3-win_amd64.whl",
+        "foo-2.0-py3-none-any.whl",
+    ];
+
+    #[test]
+    fn test_from_filenames() {
+        let matrix =
+            WheelMatrix::from_filenames(FILENAMES.iter().copied(), &"foo".parse().unwrap());
+        assert_eq!(
+            serde_json::to_value(&matrix).unwrap(),
+            serde_json::json!([
+                {
+                    "version": "2.0",
+                    "wheels": {"any": ["py3"], "win_amd64": ["cp39-abi3", "cp313t"]},
+                },
+                {
+                    "version": "1.0",
+                    "wheels": {"manylinux_2_17_x86_64": ["cp39", "cp310"]},
+                },
+            ])
+        );
+        let matrix =
+            WheelMatrix::from_filenames(FILENAMES.iter().copied(), &"foo<2".parse().unwrap());
+        assert_eq!(matrix.releases.len(), 1);
+    }
+
+    #[test]
+    fn test_render_table() {
+        let filenames = [
+            "foo-1.0-cp39-cp39-win_amd64.whl",
+            "foo-2.0-py3-none-any.whl",
+            "foo-2.0-cp39-cp39-manylinux1_x86_64.whl",
+        ];
+        let matrix = WheelMatrix::from_filenames(filenames, &"foo".parse().unwrap());
+        assert_eq!(
+            matrix.render_table(),
+            "version  manylinux1_x86_64  win_amd64  any\n\
+             2.0      cp39                          py3\n\
+             1.0                         cp39\n"
+        );
+    }
+}